@@ -1,3 +1,5 @@
+use alloc::vec;
+use alloc::vec::Vec;
 use core::ops::{Add, Mul, Sub};
 
 use p3_field::{ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra};
@@ -12,6 +14,30 @@ pub trait BaseAir<F>: Sync {
     fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
         None
     }
+
+    /// The number of preprocessed columns in this AIR.
+    ///
+    /// The default implementation generates the preprocessed trace, so AIRs with expensive
+    /// preprocessed traces may want to override it.
+    fn preprocessed_width(&self) -> usize {
+        self.preprocessed_trace().map_or(0, |trace| trace.width)
+    }
+
+    /// The number of consecutive rows of the main and preprocessed traces which constraints can
+    /// access, starting at the current row. Must be at least 2.
+    fn window_size(&self) -> usize {
+        2
+    }
+
+    /// Columns whose values repeat cyclically down the trace, such as round constants, and which
+    /// constraints access through `PeriodicAirBuilder::periodic_values`.
+    ///
+    /// Row `i` of a column `c` has the value `c[i % c.len()]`. Each column's length must be a
+    /// power of two dividing the trace height. Periodic columns aren't committed to: the verifier
+    /// evaluates their interpolants itself.
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
 }
 
 ///  An AIR with 0 or more public values.
@@ -21,6 +47,26 @@ pub trait BaseAirWithPublicValues<F>: BaseAir<F> {
     }
 }
 
+/// An AIR with a second, "after-challenge" trace over an extension field, which is generated
+/// only after the main trace has been committed to and some random challenges have been sampled.
+///
+/// Constraints access the after-challenge trace through `PermutationAirBuilder::permutation`,
+/// and the challenges through `PermutationAirBuilder::permutation_randomness`.
+pub trait MultiPhaseBaseAir<F: Field>: BaseAir<F> {
+    /// The number of extension field challenges sampled after the main trace is committed to.
+    fn num_challenges(&self) -> usize;
+
+    /// The number of extension field columns in the after-challenge trace.
+    fn after_challenge_width(&self) -> usize;
+
+    /// Generate the after-challenge trace, which must have the same height as `main`.
+    fn generate_after_challenge_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> RowMajorMatrix<EF>;
+}
+
 /// An AIR that works with a particular `AirBuilder`.
 pub trait Air<AB: AirBuilder>: BaseAir<AB::F> {
     fn eval(&self, builder: &mut AB);
@@ -63,6 +109,19 @@ pub trait AirBuilder: Sized {
     }
     fn is_transition_window(&self, size: usize) -> Self::Expr;
 
+    /// A selector which is nonzero only on the row with index `row`, for boundary constraints at
+    /// arbitrary rows. The row must be less than the trace height.
+    ///
+    /// By default only the first row is supported, as `is_first_row`. Builders which support
+    /// boundary constraints at other rows override this.
+    fn is_row(&self, row: usize) -> Self::Expr {
+        assert_eq!(
+            row, 0,
+            "this builder only supports boundary constraints on the first row"
+        );
+        self.is_first_row()
+    }
+
     /// Returns a sub-builder whose constraints are enforced only when `condition` is nonzero.
     fn when<I: Into<Self::Expr>>(&mut self, condition: I) -> FilteredAirBuilder<'_, Self> {
         FilteredAirBuilder {
@@ -90,6 +149,11 @@ pub trait AirBuilder: Sized {
         self.when(self.is_last_row())
     }
 
+    /// Returns a sub-builder whose constraints are enforced only on the row with index `row`.
+    fn when_row(&mut self, row: usize) -> FilteredAirBuilder<'_, Self> {
+        self.when(self.is_row(row))
+    }
+
     /// Returns a sub-builder whose constraints are enforced on all rows except the last.
     fn when_transition(&mut self) -> FilteredAirBuilder<'_, Self> {
         self.when(self.is_transition())
@@ -102,6 +166,12 @@ pub trait AirBuilder: Sized {
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I);
 
+    /// Like `assert_zero`, but attaches a human-readable name to the constraint, which debugging
+    /// builders can show when it fails. Other builders ignore the name.
+    fn assert_zero_named<I: Into<Self::Expr>>(&mut self, _name: &str, x: I) {
+        self.assert_zero(x);
+    }
+
     fn assert_one<I: Into<Self::Expr>>(&mut self, x: I) {
         self.assert_zero(x.into() - Self::Expr::ONE);
     }
@@ -110,6 +180,16 @@ pub trait AirBuilder: Sized {
         self.assert_zero(x.into() - y.into());
     }
 
+    /// Like `assert_eq`, but attaches a name to the constraint; see `assert_zero_named`.
+    fn assert_eq_named<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(
+        &mut self,
+        name: &str,
+        x: I1,
+        y: I2,
+    ) {
+        self.assert_zero_named(name, x.into() - y.into());
+    }
+
     /// Assert that `x` is a boolean, i.e. either 0 or 1.
     fn assert_bool<I: Into<Self::Expr>>(&mut self, x: I) {
         let x = x.into();
@@ -129,6 +209,14 @@ pub trait AirBuilderWithPublicValues: AirBuilder {
     fn public_values(&self) -> &[Self::PublicVar];
 }
 
+/// A builder whose constraints can access the current row of the AIR's periodic columns, see
+/// `BaseAir::periodic_columns`.
+pub trait PeriodicAirBuilder: AirBuilder {
+    type PeriodicVar: Into<Self::Expr> + Copy;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar];
+}
+
 pub trait PairBuilder: AirBuilder {
     fn preprocessed(&self) -> Self::M;
 }
@@ -204,9 +292,18 @@ impl<AB: AirBuilder> AirBuilder for FilteredAirBuilder<'_, AB> {
         self.inner.is_transition_window(size)
     }
 
+    fn is_row(&self, row: usize) -> Self::Expr {
+        self.inner.is_row(row)
+    }
+
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.inner.assert_zero(self.condition() * x.into());
     }
+
+    fn assert_zero_named<I: Into<Self::Expr>>(&mut self, name: &str, x: I) {
+        self.inner
+            .assert_zero_named(name, self.condition() * x.into());
+    }
 }
 
 impl<AB: ExtensionBuilder> ExtensionBuilder for FilteredAirBuilder<'_, AB> {
@@ -0,0 +1,194 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use p3_field::{
+    batch_multiplicative_inverse, ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::{BaseAir, ExtensionBuilder, PairBuilder, PermutationAirBuilder, VirtualPairCol};
+
+/// A tuple of values sent to or received from a bus, `multiplicity` times.
+///
+/// Every value sent to a bus, across all tables, must be received from it the same number of
+/// times, which is checked with a LogUp argument.
+#[derive(Clone, Debug)]
+pub struct Interaction<F: Field> {
+    pub values: Vec<VirtualPairCol<F>>,
+    pub multiplicity: VirtualPairCol<F>,
+    pub bus: usize,
+}
+
+/// An AIR which sends values to, or receives values from, buses shared with other AIRs.
+pub trait InteractionAir<F: Field>: BaseAir<F> {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        vec![]
+    }
+
+    fn receives(&self) -> Vec<Interaction<F>> {
+        vec![]
+    }
+
+    /// The number of extension field columns in the permutation trace: one per interaction,
+    /// followed by a running sum, or none at all if there are no interactions.
+    fn permutation_width(&self) -> usize {
+        let num_interactions = self.sends().len() + self.receives().len();
+        if num_interactions == 0 {
+            0
+        } else {
+            num_interactions + 1
+        }
+    }
+}
+
+/// A builder for the LogUp constraints of an `InteractionAir`.
+///
+/// The permutation randomness is `[alpha, beta]`, where `alpha` combines the values of an
+/// interaction and `beta` shifts the combination away from zero.
+pub trait InteractionAirBuilder: PermutationAirBuilder + PairBuilder {
+    /// The claimed value of the running sum column in the last row.
+    fn cumulative_sum(&self) -> Self::RandomVar;
+}
+
+/// Compute `beta + bus + sum_i alpha^(i + 1) values[i]`, the denominator of an interaction's
+/// LogUp term.
+fn fingerprint<Expr, ExprEF>(
+    alpha: ExprEF,
+    beta: ExprEF,
+    bus: usize,
+    values: impl Iterator<Item = Expr>,
+) -> ExprEF
+where
+    Expr: FieldAlgebra,
+    ExprEF: FieldExtensionAlgebra<Expr>,
+{
+    let mut result = beta;
+    let mut power = ExprEF::ONE;
+    for value in iter::once(Expr::from_canonical_usize(bus)).chain(values) {
+        result += power.clone() * value;
+        power *= alpha.clone();
+    }
+    result
+}
+
+/// Evaluate the LogUp constraints for the given interactions.
+///
+/// Column `i` of the permutation trace must hold `multiplicity_i / fingerprint_i`, and the last
+/// column must hold a running sum of these terms, added for sends and subtracted for receives,
+/// which ends in the builder's cumulative sum.
+pub fn eval_interactions<AB: InteractionAirBuilder>(
+    builder: &mut AB,
+    sends: &[Interaction<AB::F>],
+    receives: &[Interaction<AB::F>],
+) {
+    let num_interactions = sends.len() + receives.len();
+    if num_interactions == 0 {
+        return;
+    }
+
+    let preprocessed = builder.preprocessed();
+    let main = builder.main();
+    let permutation = builder.permutation();
+    let (preprocessed_local, main_local) = (preprocessed.row_slice(0), main.row_slice(0));
+    let (permutation_local, permutation_next) =
+        (permutation.row_slice(0), permutation.row_slice(1));
+
+    let randomness = builder.permutation_randomness();
+    let (alpha, beta): (AB::ExprEF, AB::ExprEF) = (randomness[0].into(), randomness[1].into());
+    let cumulative_sum: AB::ExprEF = builder.cumulative_sum().into();
+
+    let mut phi_local = AB::ExprEF::ZERO;
+    let mut phi_next = AB::ExprEF::ZERO;
+    for (i, interaction) in sends.iter().chain(receives).enumerate() {
+        let values = interaction
+            .values
+            .iter()
+            .map(|value| value.apply::<AB::Expr, AB::Var>(&preprocessed_local, &main_local));
+        let fingerprint = fingerprint(alpha.clone(), beta.clone(), interaction.bus, values);
+        let multiplicity = interaction
+            .multiplicity
+            .apply::<AB::Expr, AB::Var>(&preprocessed_local, &main_local);
+        builder.assert_eq_ext(fingerprint * permutation_local[i].into(), multiplicity);
+
+        if i < sends.len() {
+            phi_local += permutation_local[i].into();
+            phi_next += permutation_next[i].into();
+        } else {
+            phi_local -= permutation_local[i].into();
+            phi_next -= permutation_next[i].into();
+        }
+    }
+
+    let running_sum_local: AB::ExprEF = permutation_local[num_interactions].into();
+    let running_sum_next: AB::ExprEF = permutation_next[num_interactions].into();
+    builder
+        .when_first_row()
+        .assert_eq_ext(running_sum_local.clone(), phi_local);
+    builder
+        .when_transition()
+        .assert_eq_ext(running_sum_next, running_sum_local.clone() + phi_next);
+    builder
+        .when_last_row()
+        .assert_eq_ext(running_sum_local, cumulative_sum);
+}
+
+/// Generate the permutation trace checked by `eval_interactions`, given the randomness
+/// `[alpha, beta]`, or `None` if there are no interactions.
+///
+/// The cumulative sum is the last entry of the returned trace.
+pub fn generate_permutation_trace<F: Field, EF: ExtensionField<F>>(
+    sends: &[Interaction<F>],
+    receives: &[Interaction<F>],
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    main: &RowMajorMatrix<F>,
+    randomness: &[EF],
+) -> Option<RowMajorMatrix<EF>> {
+    let num_interactions = sends.len() + receives.len();
+    if num_interactions == 0 {
+        return None;
+    }
+    let (alpha, beta) = (randomness[0], randomness[1]);
+
+    let height = main.height();
+    let mut fingerprints = Vec::with_capacity(height * num_interactions);
+    let mut multiplicities = Vec::with_capacity(height * num_interactions);
+    for r in 0..height {
+        let preprocessed_row = preprocessed.map_or_else(Vec::new, |m| m.row(r).collect());
+        let main_row = main.row_slice(r);
+        for interaction in sends.iter().chain(receives) {
+            let values = interaction
+                .values
+                .iter()
+                .map(|value| value.apply::<F, F>(&preprocessed_row, &main_row));
+            fingerprints.push(fingerprint(alpha, beta, interaction.bus, values));
+            multiplicities.push(
+                interaction
+                    .multiplicity
+                    .apply::<F, F>(&preprocessed_row, &main_row),
+            );
+        }
+    }
+    let inverses = batch_multiplicative_inverse(&fingerprints);
+
+    let width = num_interactions + 1;
+    let mut values = Vec::with_capacity(height * width);
+    let mut running_sum = EF::ZERO;
+    for (inverses, multiplicities) in inverses
+        .chunks_exact(num_interactions)
+        .zip(multiplicities.chunks_exact(num_interactions))
+    {
+        for (i, (&inverse, &multiplicity)) in inverses.iter().zip(multiplicities).enumerate() {
+            let term = inverse * multiplicity;
+            values.push(term);
+            if i < sends.len() {
+                running_sum += term;
+            } else {
+                running_sum -= term;
+            }
+        }
+        values.push(running_sum);
+    }
+    Some(RowMajorMatrix::new(values, width))
+}
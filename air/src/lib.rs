@@ -5,8 +5,12 @@
 extern crate alloc;
 
 mod air;
+mod interaction;
+mod padding;
 pub mod utils;
 mod virtual_column;
 
 pub use air::*;
+pub use interaction::*;
+pub use padding::*;
 pub use virtual_column::*;
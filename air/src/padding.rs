@@ -0,0 +1,69 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::BaseAir;
+
+/// How to pad a trace whose height isn't a power of two.
+///
+/// Whichever rule is used, the AIR's constraints must hold on the padding rows.
+pub enum PaddingRule<F> {
+    /// Repeat the last row of the trace.
+    RepeatLastRow,
+    /// Append the rows produced by the given function, which is called with the index of each
+    /// padding row within the padded trace.
+    DummyRows(Box<dyn Fn(usize) -> Vec<F> + Send + Sync>),
+    /// Append rows of zeros. The column `is_real` must be one on every row of the unpadded trace,
+    /// so the AIR can guard its constraints with it.
+    ZeroRows { is_real: usize },
+}
+
+impl<F: Field> PaddingRule<F> {
+    /// Pad `trace` to the next power of two, which must be nonempty.
+    pub fn pad(&self, trace: &mut RowMajorMatrix<F>) {
+        let height = trace.height();
+        assert!(height > 0, "cannot pad an empty trace");
+        let padded_height = height.next_power_of_two();
+        let width = trace.width();
+        match self {
+            Self::RepeatLastRow => {
+                let last_row = trace.row_slice(height - 1).to_vec();
+                trace.values.reserve((padded_height - height) * width);
+                for _ in height..padded_height {
+                    trace.values.extend_from_slice(&last_row);
+                }
+            }
+            Self::DummyRows(generate) => {
+                trace.values.reserve((padded_height - height) * width);
+                for i in height..padded_height {
+                    let row = generate(i);
+                    assert_eq!(
+                        row.len(),
+                        width,
+                        "dummy row width must match the trace width"
+                    );
+                    trace.values.extend(row);
+                }
+            }
+            Self::ZeroRows { is_real } => {
+                assert!(*is_real < width, "the is_real column is out of bounds");
+                debug_assert!(
+                    trace
+                        .rows()
+                        .all(|mut row| row.nth(*is_real) == Some(F::ONE)),
+                    "the is_real column must be one on every row of the unpadded trace"
+                );
+                trace.pad_to_height(padded_height, F::ZERO);
+            }
+        }
+    }
+}
+
+/// An AIR whose traces may have any nonzero height, and are padded to a power of two according to
+/// its padding rule.
+pub trait PaddedBaseAir<F>: BaseAir<F> {
+    fn padding_rule(&self) -> PaddingRule<F>;
+}
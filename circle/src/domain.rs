@@ -11,6 +11,7 @@ use p3_util::{log2_ceil_usize, log2_strict_usize};
 use tracing::instrument;
 
 use crate::point::Point;
+use crate::CircleEvaluations;
 
 /// A twin-coset of the circle group on F. It has a power-of-two size and an arbitrary shift.
 ///
@@ -91,6 +92,29 @@ impl<F: ComplexExtendable> CircleDomain<F> {
     pub(crate) fn s_p_normalized<EF: ExtensionField<F>>(&self, p: Point<F>, at: Point<EF>) -> EF {
         self.zeroifier(at) / (p.v_tilde_p(at) * p.s_p_at_p(self.log_n))
     }
+
+    /// Evaluate the interpolant of a periodic column over this domain at `at`.
+    ///
+    /// Moving `values.len()` rows along a standard position domain rotates by a point of order
+    /// `n / values.len()`, so the interpolant is that of `values` over the standard position
+    /// domain of size `values.len()`, after doubling `at` until it's on the circle that domain
+    /// lives on.
+    fn periodic_column_at<EF: ExtensionField<F>>(&self, values: &[F], at: Point<EF>) -> EF {
+        assert!(
+            self.is_standard(),
+            "periodic columns are only supported in standard position"
+        );
+        let log_period = log2_strict_usize(values.len());
+        if log_period == 0 {
+            return EF::from_base(values[0]);
+        }
+        let at = (log_period..self.log_n).fold(at, |at, _| at.double());
+        CircleEvaluations::from_natural_order(
+            Self::standard(log_period),
+            RowMajorMatrix::new_col(values.to_vec()),
+        )
+        .evaluate_at_point(at)[0]
+    }
 }
 
 impl<F: ComplexExtendable> PolynomialSpace for CircleDomain<F> {
@@ -184,6 +208,21 @@ impl<F: ComplexExtendable> PolynomialSpace for CircleDomain<F> {
         }
     }
 
+    fn periodic_column_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        values: &[Self::Val],
+        point: Ext,
+    ) -> Ext {
+        self.periodic_column_at(values, Point::from_projective_line(point))
+    }
+
+    fn periodic_column_on_coset(&self, values: &[Self::Val], coset: Self) -> Vec<Self::Val> {
+        coset
+            .points()
+            .map(|p| self.periodic_column_at(values, p))
+            .collect()
+    }
+
     /*
     chunks=2:
 
@@ -235,10 +274,9 @@ mod tests {
     use itertools::izip;
     use p3_field::{batch_multiplicative_inverse, FieldAlgebra};
     use p3_mersenne_31::Mersenne31;
-    use rand::thread_rng;
+    use rand::{thread_rng, Rng};
 
     use super::*;
-    use crate::CircleEvaluations;
 
     fn assert_is_twin_coset<F: ComplexExtendable>(d: CircleDomain<F>) {
         let pts = d.points().collect_vec();
@@ -396,6 +434,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn periodic_columns() {
+        type F = Mersenne31;
+        let log_n = 6;
+        let n = 1 << log_n;
+
+        let d = CircleDomain::<F>::standard(log_n);
+        let coset = d.create_disjoint_domain(n);
+        let values: Vec<F> = (0..8).map(|_| thread_rng().gen()).collect();
+        let on_coset = d.periodic_column_on_coset(&values, coset);
+
+        // periodic_column_on_coset matches periodic_column_at_point
+        for (pt, &value) in coset.points().zip(&on_coset) {
+            let pt = pt.to_projective_line().unwrap();
+            assert_eq!(d.periodic_column_at_point(&values, pt), value);
+        }
+
+        // The interpolant is in the domain's polynomial space, and repeats the column on it.
+        let coeffs =
+            CircleEvaluations::from_natural_order(coset, RowMajorMatrix::new_col(on_coset))
+                .interpolate();
+        let (lo, hi) = coeffs.split_rows(n);
+        assert_eq!(hi.values, vec![F::ZERO; n]);
+        let on_d = CircleEvaluations::evaluate(d, lo.to_row_major_matrix())
+            .to_natural_order()
+            .to_row_major_matrix()
+            .values;
+        assert_eq!(on_d, (0..n).map(|i| values[i % values.len()]).collect_vec());
+    }
+
     #[test]
     fn test_circle_domain() {
         do_test_circle_domain(4, 8);
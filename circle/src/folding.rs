@@ -37,9 +37,11 @@ impl<F: ComplexExtendable, EF: ExtensionField<F>, InputProof, InputError: Debug>
         &self,
         index: usize,
         log_folded_height: usize,
+        log_arity: usize,
         beta: EF,
         evals: impl Iterator<Item = EF>,
     ) -> EF {
+        assert_eq!(log_arity, 1, "circle FRI only supports arity 2");
         fold_x_row(index, log_folded_height, beta, evals)
     }
 
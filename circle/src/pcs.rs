@@ -9,7 +9,7 @@ use p3_commit::{Mmcs, OpenedValues, Pcs, PolynomialSpace};
 use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::verifier::FriError;
-use p3_fri::FriConfig;
+use p3_fri::{claimed_widths, FriConfig};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::{Dimensions, Matrix};
 use p3_maybe_rayon::prelude::*;
@@ -27,16 +27,44 @@ use crate::{cfft_permute_index, CfftPermutable, CircleEvaluations, CircleFriProo
 
 #[derive(Debug)]
 pub struct CirclePcs<Val: Field, InputMmcs, FriMmcs> {
-    pub mmcs: InputMmcs,
-    pub fri_config: FriConfig<FriMmcs>,
-    pub _phantom: PhantomData<Val>,
+    mmcs: InputMmcs,
+    fri_config: FriConfig<FriMmcs>,
+    _phantom: PhantomData<Val>,
+}
+
+impl<Val: Field, InputMmcs, FriMmcs> CirclePcs<Val, InputMmcs, FriMmcs> {
+    /// Create a PCS committing to matrices with `mmcs` and proving their low degree with circle FRI.
+    ///
+    /// Circle FRI only folds by 2, so `fri_config.max_log_arity` must be 1.
+    pub fn new(mmcs: InputMmcs, fri_config: FriConfig<FriMmcs>) -> Self {
+        assert_eq!(
+            fri_config.max_log_arity, 1,
+            "circle FRI only supports folding by 2"
+        );
+        Self {
+            mmcs,
+            fri_config,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The log of the final polynomial's length, for FRI inputs of log height at least
+    /// `log_min_height`. That's the configured length, unless an input is shorter than the final
+    /// codeword it would give, in which case the final codeword is shortened to that input's height
+    /// so it can still be folded in.
+    fn log_final_poly_len(&self, log_min_height: usize) -> usize {
+        self.fri_config
+            .log_final_poly_len
+            .min(log_min_height - self.fri_config.log_blowup)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
 pub struct BatchOpening<Val: Field, InputMmcs: Mmcs<Val>> {
-    pub(crate) opened_values: Vec<Vec<Val>>,
-    pub(crate) opening_proof: <InputMmcs as Mmcs<Val>>::Proof,
+    /// For each query, the opened rows of each matrix in the batch.
+    pub(crate) opened_values: Vec<Vec<Vec<Val>>>,
+    pub(crate) opening_proof: <InputMmcs as Mmcs<Val>>::MultiProof,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -48,8 +76,9 @@ pub struct CircleInputProof<
     FriMmcs: Mmcs<Challenge>,
 > {
     input_openings: Vec<BatchOpening<Val, InputMmcs>>,
-    first_layer_siblings: Vec<Challenge>,
-    first_layer_proof: FriMmcs::Proof,
+    /// For each query, the first layer sibling of each reduced opening.
+    first_layer_siblings: Vec<Vec<Challenge>>,
+    first_layer_proof: FriMmcs::MultiProof,
 }
 
 #[derive(Debug)]
@@ -95,6 +124,10 @@ where
     type Proof = CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Challenger::Witness>;
     type Error = FriError<FriMmcs::Error, InputError<InputMmcs::Error, FriMmcs::Error>>;
 
+    fn parameters(&self) -> Vec<usize> {
+        self.fri_config.parameters()
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         CircleDomain::standard(log2_strict_usize(degree))
     }
@@ -239,6 +272,9 @@ where
             })
             .collect();
         let log_max_height = log_heights.iter().max().copied().unwrap();
+        // The first layer fold halves each height.
+        let log_final_poly_len =
+            self.log_final_poly_len(log_heights.iter().min().copied().unwrap() - 1);
 
         // Commit to reduced openings at each log_height, so we can challenge a global
         // folding factor for all first layers, which we use for a "manual" (not part of p3-fri) fold.
@@ -265,43 +301,60 @@ where
         let g: CircleFriConfig<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriGenericConfig(PhantomData);
 
-        let fri_proof = prove(&g, &self.fri_config, fri_input, challenger, |index| {
-            // CircleFriFolder asks for an extra query index bit, so we use that here to index
-            // the first layer fold.
-
-            // Open the input (big opening, lots of columns) at the full index...
-            let input_openings = rounds
-                .iter()
-                .map(|(data, _)| {
-                    let log_max_batch_height = log2_strict_usize(self.mmcs.get_max_height(data));
-                    let reduced_index = index >> (log_max_height - log_max_batch_height);
-                    let (opened_values, opening_proof) = self.mmcs.open_batch(reduced_index, data);
-                    BatchOpening {
-                        opened_values,
-                        opening_proof,
-                    }
-                })
-                .collect();
-
-            // We committed to first_layer in pairs, so open the reduced index and include the sibling
-            // as part of the input proof.
-            let (first_layer_values, first_layer_proof) = self
-                .fri_config
-                .mmcs
-                .open_batch(index >> 1, &first_layer_data);
-            let first_layer_siblings = izip!(&first_layer_values, &log_heights)
-                .map(|(v, log_height)| {
-                    let reduced_index = index >> (log_max_height - log_height);
-                    let sibling_index = (reduced_index & 1) ^ 1;
-                    v[sibling_index]
-                })
-                .collect();
-            CircleInputProof {
-                input_openings,
-                first_layer_siblings,
-                first_layer_proof,
-            }
-        });
+        let fri_proof = prove(
+            &g,
+            &self.fri_config,
+            log_final_poly_len,
+            fri_input,
+            challenger,
+            |indices| {
+                // CircleFriFolder asks for an extra query index bit, so we use that here to index
+                // the first layer fold.
+
+                // Open the input (big opening, lots of columns) at the full indices...
+                let input_openings = rounds
+                    .iter()
+                    .map(|(data, _)| {
+                        let log_max_batch_height =
+                            log2_strict_usize(self.mmcs.get_max_height(data));
+                        let reduced_indices = indices
+                            .iter()
+                            .map(|index| index >> (log_max_height - log_max_batch_height))
+                            .collect_vec();
+                        let (opened_values, opening_proof) =
+                            self.mmcs.open_multi_batch(&reduced_indices, data);
+                        BatchOpening {
+                            opened_values,
+                            opening_proof,
+                        }
+                    })
+                    .collect();
+
+                // We committed to first_layer in pairs, so open the reduced indices and include the
+                // siblings as part of the input proof.
+                let (first_layer_values, first_layer_proof) =
+                    self.fri_config.mmcs.open_multi_batch(
+                        &indices.iter().map(|index| index >> 1).collect_vec(),
+                        &first_layer_data,
+                    );
+                let first_layer_siblings = izip!(indices, &first_layer_values)
+                    .map(|(index, values)| {
+                        izip!(values, &log_heights)
+                            .map(|(v, log_height)| {
+                                let reduced_index = index >> (log_max_height - log_height);
+                                let sibling_index = (reduced_index & 1) ^ 1;
+                                v[sibling_index]
+                            })
+                            .collect()
+                    })
+                    .collect();
+                CircleInputProof {
+                    input_openings,
+                    first_layer_siblings,
+                    first_layer_proof,
+                }
+            },
+        );
 
         (
             values,
@@ -334,14 +387,29 @@ where
         proof: &Self::Proof,
         challenger: &mut Challenger,
     ) -> Result<(), Self::Error> {
+        let widths = rounds
+            .iter()
+            .map(|(_, mats)| claimed_widths(mats))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(FriError::InvalidProofShape)?;
+
         // Batch combination challenge
         let alpha: Challenge = challenger.sample_ext_element();
         challenger.observe(proof.first_layer_commitment.clone());
         let bivariate_beta: Challenge = challenger.sample_ext_element();
 
-        // +1 to account for first layer
-        let log_global_max_height =
-            proof.fri_proof.commit_phase_commits.len() + self.fri_config.log_blowup + 1;
+        let log_heights = rounds
+            .iter()
+            .flat_map(|(_, mats)| mats)
+            .map(|(domain, _)| domain.log_n + self.fri_config.log_blowup)
+            .collect_vec();
+        let (Some(&log_global_max_height), Some(&log_min_height)) =
+            (log_heights.iter().max(), log_heights.iter().min())
+        else {
+            return Err(FriError::InvalidProofShape);
+        };
+        // The first layer fold halves each height.
+        let log_final_poly_len = self.log_final_poly_len(log_min_height - 1);
 
         let g: CircleFriConfig<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriGenericConfig(PhantomData);
@@ -349,11 +417,14 @@ where
         verify(
             &g,
             &self.fri_config,
+            log_global_max_height - 1,
+            log_final_poly_len,
             &proof.fri_proof,
             challenger,
-            |index, input_proof| {
-                // log_height -> (alpha_offset, ro)
-                let mut reduced_openings = BTreeMap::<usize, (Challenge, Challenge)>::new();
+            |indices, input_proof| {
+                // For each query, log_height -> (alpha_offset, ro)
+                let mut reduced_openings =
+                    vec![BTreeMap::<usize, (Challenge, Challenge)>::new(); indices.len()];
 
                 let CircleInputProof {
                     input_openings,
@@ -361,111 +432,136 @@ where
                     first_layer_proof,
                 } = input_proof;
 
-                for (batch_opening, (batch_commit, mats)) in izip!(input_openings, &rounds) {
+                for (batch_opening, (batch_commit, mats), widths) in
+                    izip!(input_openings, &rounds, &widths)
+                {
                     let batch_heights: Vec<usize> = mats
                         .iter()
                         .map(|(domain, _)| (domain.size() << self.fri_config.log_blowup))
                         .collect_vec();
-                    let batch_dims: Vec<Dimensions> = batch_heights
-                        .iter()
-                        // todo: mmcs doesn't really need width
-                        .map(|&height| Dimensions { width: 0, height })
+                    let batch_dims: Vec<Dimensions> = izip!(widths, &batch_heights)
+                        .map(|(&width, &height)| Dimensions { width, height })
                         .collect_vec();
 
                     let log_batch_max_height =
                         log2_strict_usize(batch_heights.iter().max().copied().unwrap());
+                    let reduced_indices = indices
+                        .iter()
+                        .map(|index| index >> (log_global_max_height - log_batch_max_height))
+                        .collect_vec();
 
                     self.mmcs
-                        .verify_batch(
+                        .verify_multi_batch(
                             batch_commit,
                             &batch_dims,
-                            index >> (log_global_max_height - log_batch_max_height),
+                            &reduced_indices,
                             &batch_opening.opened_values,
                             &batch_opening.opening_proof,
                         )
                         .map_err(InputError::InputMmcsError)?;
 
-                    for (ps_at_x, (mat_domain, mat_points_and_values)) in
-                        izip!(&batch_opening.opened_values, mats)
+                    for (&index, query_opening, reduced_openings) in
+                        izip!(indices, &batch_opening.opened_values, &mut reduced_openings)
                     {
-                        let log_height = mat_domain.log_n + self.fri_config.log_blowup;
-                        let bits_reduced = log_global_max_height - log_height;
-                        let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
+                        for (ps_at_x, (mat_domain, mat_points_and_values)) in
+                            izip!(query_opening, mats)
+                        {
+                            let log_height = mat_domain.log_n + self.fri_config.log_blowup;
+                            let bits_reduced = log_global_max_height - log_height;
+                            let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
 
-                        let committed_domain = CircleDomain::standard(log_height);
-                        let x = committed_domain.nth_point(orig_idx);
+                            let committed_domain = CircleDomain::standard(log_height);
+                            let x = committed_domain.nth_point(orig_idx);
 
-                        let (alpha_offset, ro) = reduced_openings
-                            .entry(log_height)
-                            .or_insert((Challenge::ONE, Challenge::ZERO));
-                        let alpha_pow_width_2 = alpha.exp_u64(ps_at_x.len() as u64).square();
+                            let (alpha_offset, ro) = reduced_openings
+                                .entry(log_height)
+                                .or_insert((Challenge::ONE, Challenge::ZERO));
+                            let alpha_pow_width_2 = alpha.exp_u64(ps_at_x.len() as u64).square();
 
-                        for (zeta_uni, ps_at_zeta) in mat_points_and_values {
-                            let zeta = Point::from_projective_line(*zeta_uni);
+                            for (zeta_uni, ps_at_zeta) in mat_points_and_values {
+                                let zeta = Point::from_projective_line(*zeta_uni);
 
-                            *ro += *alpha_offset
-                                * deep_quotient_reduce_row(alpha, x, zeta, ps_at_x, ps_at_zeta);
+                                *ro += *alpha_offset
+                                    * deep_quotient_reduce_row(alpha, x, zeta, ps_at_x, ps_at_zeta);
 
-                            *alpha_offset *= alpha_pow_width_2;
+                                *alpha_offset *= alpha_pow_width_2;
+                            }
                         }
                     }
                 }
 
                 // Verify bivariate fold and lambda correction
 
-                let (mut fri_input, fl_dims, fl_leaves): (Vec<_>, Vec<_>, Vec<_>) =
-                    izip!(reduced_openings, first_layer_siblings, &proof.lambdas)
-                        .map(|((log_height, (_, ro)), &fl_sib, &lambda)| {
-                            assert!(log_height > 0);
-
-                            let orig_size = log_height - self.fri_config.log_blowup;
-                            let bits_reduced = log_global_max_height - log_height;
-                            let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
-
-                            let lde_domain = CircleDomain::standard(log_height);
-                            let p: Point<Val> = lde_domain.nth_point(orig_idx);
-
-                            let lambda_corrected = ro - lambda * p.v_n(orig_size);
-
-                            let mut fl_values = vec![lambda_corrected; 2];
-                            fl_values[((index >> bits_reduced) & 1) ^ 1] = fl_sib;
-
-                            let fri_input = (
-                                // - 1 here is because we have already folded a layer.
-                                log_height - 1,
-                                fold_y_row(
-                                    index >> (bits_reduced + 1),
-                                    // - 1 here is log_arity.
-                                    log_height - 1,
-                                    bivariate_beta,
-                                    fl_values.iter().cloned(),
-                                ),
-                            );
-
-                            let fl_dims = Dimensions {
-                                width: 0,
-                                height: 1 << (log_height - 1),
-                            };
-
-                            (fri_input, fl_dims, fl_values)
+                // The first layer holds one matrix for each log_height, in ascending order, with
+                // each row holding a pair of siblings.
+                let fl_dims = rounds
+                    .iter()
+                    .flat_map(|(_, mats)| mats)
+                    .map(|(domain, _)| domain.log_n + self.fri_config.log_blowup)
+                    .sorted()
+                    .dedup()
+                    .map(|log_height| Dimensions {
+                        width: 2,
+                        height: 1 << (log_height - 1),
+                    })
+                    .collect_vec();
+
+                let (fri_inputs, fl_leaves): (Vec<_>, Vec<_>) =
+                    izip!(indices, reduced_openings, first_layer_siblings)
+                        .map(|(&index, reduced_openings, first_layer_siblings)| {
+                            let (mut fri_input, fl_leaves): (Vec<_>, Vec<_>) =
+                                izip!(reduced_openings, first_layer_siblings, &proof.lambdas)
+                                    .map(|((log_height, (_, ro)), &fl_sib, &lambda)| {
+                                        assert!(log_height > 0);
+
+                                        let orig_size = log_height - self.fri_config.log_blowup;
+                                        let bits_reduced = log_global_max_height - log_height;
+                                        let orig_idx =
+                                            cfft_permute_index(index >> bits_reduced, log_height);
+
+                                        let lde_domain = CircleDomain::standard(log_height);
+                                        let p: Point<Val> = lde_domain.nth_point(orig_idx);
+
+                                        let lambda_corrected = ro - lambda * p.v_n(orig_size);
+
+                                        let mut fl_values = vec![lambda_corrected; 2];
+                                        fl_values[((index >> bits_reduced) & 1) ^ 1] = fl_sib;
+
+                                        let fri_input = (
+                                            // - 1 here is because we have already folded a layer.
+                                            log_height - 1,
+                                            fold_y_row(
+                                                index >> (bits_reduced + 1),
+                                                // - 1 here is log_arity.
+                                                log_height - 1,
+                                                bivariate_beta,
+                                                fl_values.iter().cloned(),
+                                            ),
+                                        );
+
+                                        (fri_input, fl_values)
+                                    })
+                                    .unzip();
+
+                            // sort descending
+                            fri_input.reverse();
+
+                            (fri_input, fl_leaves)
                         })
-                        .multiunzip();
-
-                // sort descending
-                fri_input.reverse();
+                        .unzip();
 
                 self.fri_config
                     .mmcs
-                    .verify_batch(
+                    .verify_multi_batch(
                         &proof.first_layer_commitment,
                         &fl_dims,
-                        index >> 1,
+                        &indices.iter().map(|index| index >> 1).collect_vec(),
                         &fl_leaves,
                         first_layer_proof,
                     )
                     .map_err(InputError::FirstLayerMmcsError)?;
 
-                Ok(fri_input)
+                Ok(fri_inputs)
             },
         )
     }
@@ -489,7 +585,43 @@ mod tests {
     #[test]
     fn circle_pcs() {
         // Very simple pcs test. More rigorous tests in p3_fri/tests/pcs.
+        do_test_circle_pcs(&[10], 0);
+    }
+
+    #[test]
+    fn circle_pcs_early_stopping() {
+        do_test_circle_pcs(&[10], 3);
+        do_test_circle_pcs(&[10, 8, 5], 2);
+    }
+
+    #[test]
+    fn circle_pcs_early_stopping_small_inputs() {
+        // Inputs shorter than the final codeword shorten it rather than being left out.
+        do_test_circle_pcs(&[10, 2], 3);
+        do_test_circle_pcs(&[3], 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "circle FRI only supports folding by 2")]
+    fn circle_pcs_rejects_higher_arity() {
+        type Val = Mersenne31;
+        type Challenge = BinomialExtensionField<Mersenne31, 3>;
+        type ByteHash = Keccak256Hash;
+        type FieldHash = SerializingHasher32<ByteHash>;
+        type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+        type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+        type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+        let byte_hash = ByteHash {};
+        let val_mmcs = ValMmcs::new(FieldHash::new(byte_hash), MyCompress::new(byte_hash));
+        let fri_config = FriConfig {
+            max_log_arity: 2,
+            ..create_test_fri_config(ChallengeMmcs::new(val_mmcs.clone()))
+        };
+        CirclePcs::<Val, ValMmcs, ChallengeMmcs>::new(val_mmcs, fri_config);
+    }
 
+    fn do_test_circle_pcs(log_ns: &[usize], log_final_poly_len: usize) {
         let mut rng = ChaCha8Rng::from_seed([0; 32]);
 
         type Val = Mersenne31;
@@ -511,38 +643,38 @@ mod tests {
 
         type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
 
-        let fri_config = create_test_fri_config(challenge_mmcs);
-
-        type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
-        let pcs = Pcs {
-            mmcs: val_mmcs,
-            fri_config,
-            _phantom: PhantomData,
+        let fri_config = FriConfig {
+            log_final_poly_len,
+            ..create_test_fri_config(challenge_mmcs)
         };
 
-        let log_n = 10;
-
-        let d = <Pcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
-            &pcs,
-            1 << log_n,
-        );
+        type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+        let pcs = Pcs::new(val_mmcs, fri_config);
 
-        let evals = RowMajorMatrix::rand(&mut rng, 1 << log_n, 1);
+        let domains_and_evals = log_ns
+            .iter()
+            .map(|&log_n| {
+                let d = <Pcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
+                    &pcs,
+                    1 << log_n,
+                );
+                (d, RowMajorMatrix::rand(&mut rng, 1 << log_n, 1))
+            })
+            .collect_vec();
 
         let (comm, data) =
-            <Pcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, vec![(d, evals)]);
+            <Pcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, domains_and_evals.clone());
 
         let zeta: Challenge = rng.gen();
 
         let mut chal = Challenger::from_hasher(vec![], byte_hash);
-        let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut chal);
+        let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]; log_ns.len()])], &mut chal);
 
+        let claims = izip!(&domains_and_evals, &values[0])
+            .map(|((d, _), mat_values)| (*d, vec![(zeta, mat_values[0].clone())]))
+            .collect();
         let mut chal = Challenger::from_hasher(vec![], byte_hash);
-        pcs.verify(
-            vec![(comm, vec![(d, vec![(zeta, values[0][0][0].clone())])])],
-            &proof,
-            &mut chal,
-        )
-        .expect("verify err");
+        pcs.verify(vec![(comm, claims)], &proof, &mut chal)
+            .expect("verify err");
     }
 }
@@ -11,32 +11,25 @@ use serde::{Deserialize, Serialize};
 ))]
 pub struct CircleFriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
-    pub query_proofs: Vec<CircleQueryProof<F, M, InputProof>>,
-    // This could become Vec<FC::Challenge> if this library was generalized to support non-constant
-    // final polynomials.
-    pub final_poly: F,
-    pub pow_witness: Witness,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(bound(
-    serialize = "InputProof: Serialize",
-    deserialize = "InputProof: Deserialize<'de>",
-))]
-pub struct CircleQueryProof<F: Field, M: Mmcs<F>, InputProof> {
+    /// Openings of the inputs at every query index.
     pub input_proof: InputProof,
-    /// For each commit phase commitment, this contains openings of a commit phase codeword at the
-    /// queried location, along with an opening proof.
-    pub commit_phase_openings: Vec<CircleCommitPhaseProofStep<F, M>>,
+    /// For each commit phase commitment, openings of its codeword at every query index.
+    pub commit_phase_openings: Vec<CircleCommitPhaseOpenings<F, M>>,
+    /// The coefficients of the final polynomial in the circle basis: the coefficient at index `b`
+    /// multiplies `prod_i pi^i(x)^{b_i}`, where `b_i` is the `i`-th most significant bit of `b`
+    /// and `pi(x) = 2x^2 - 1` maps the x-coordinate of a point to that of its double.
+    pub final_poly: Vec<F>,
+    pub pow_witness: Witness,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct CircleCommitPhaseProofStep<F: Field, M: Mmcs<F>> {
-    /// The opening of the commit phase codeword at the sibling location.
-    // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
-    // folding arities besides 2, meaning that there can be multiple siblings.
-    pub sibling_value: F,
+pub struct CircleCommitPhaseOpenings<F: Field, M: Mmcs<F>> {
+    /// For each query, the opening of the commit phase codeword at the sibling location.
+    // This may change to Vec<Vec<FC::Challenge>> if the library is generalized to support other
+    // FRI folding arities besides 2, meaning that there can be multiple siblings.
+    pub sibling_values: Vec<F>,
 
-    pub opening_proof: M::Proof,
+    /// A single proof for the pairs of every query.
+    pub opening_proof: M::MultiProof,
 }
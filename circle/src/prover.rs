@@ -11,15 +11,18 @@ use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_strict_usize;
 use tracing::{info_span, instrument};
 
-use crate::{CircleCommitPhaseProofStep, CircleFriProof, CircleQueryProof};
+use crate::{CircleCommitPhaseOpenings, CircleFriProof};
 
+/// Prove that `inputs` are close to low degree codewords, folding down to a final polynomial of
+/// length `2^log_final_poly_len`, which may be shorter than the configured one.
 #[instrument(name = "FRI prover", skip_all)]
 pub fn prove<G, Val, Challenge, M, Challenger>(
     g: &G,
     config: &FriConfig<M>,
+    log_final_poly_len: usize,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize) -> G::InputProof,
+    open_input: impl Fn(&[usize]) -> G::InputProof,
 ) -> CircleFriProof<Challenge, M, Challenger::Witness, G::InputProof>
 where
     Val: Field,
@@ -35,28 +38,38 @@ where
         .all(|(l, r)| l.len() >= r.len()));
 
     let log_max_height = log2_strict_usize(inputs[0].len());
+    let log_min_height = log2_strict_usize(inputs.last().unwrap().len());
+    // A shorter input would never be folded in.
+    assert!(
+        log_min_height >= log_final_poly_len + config.log_blowup,
+        "inputs must be at least as tall as the final codeword"
+    );
 
-    let commit_phase_result = commit_phase(g, config, inputs, challenger);
+    let commit_phase_result = commit_phase(g, config, log_final_poly_len, inputs, challenger);
 
     let pow_witness = challenger.grind(config.proof_of_work_bits);
 
-    let query_proofs = info_span!("query phase").in_scope(|| {
-        iter::repeat_with(|| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
-            .take(config.num_queries)
-            .map(|index| CircleQueryProof {
-                input_proof: open_input(index),
-                commit_phase_openings: answer_query(
-                    config,
-                    &commit_phase_result.data,
-                    index >> g.extra_query_index_bits(),
-                ),
-            })
-            .collect()
+    let (input_proof, commit_phase_openings) = info_span!("query phase").in_scope(|| {
+        let indices = iter::repeat_with(|| {
+            challenger.sample_bits(log_max_height + g.extra_query_index_bits())
+        })
+        .take(config.num_queries)
+        .collect_vec();
+        let commit_phase_openings = answer_queries(
+            config,
+            &commit_phase_result.data,
+            &indices
+                .iter()
+                .map(|index| index >> g.extra_query_index_bits())
+                .collect_vec(),
+        );
+        (open_input(&indices), commit_phase_openings)
     });
 
     CircleFriProof {
         commit_phase_commits: commit_phase_result.commits,
-        query_proofs,
+        input_proof,
+        commit_phase_openings,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
     }
@@ -65,13 +78,14 @@ where
 struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
     commits: Vec<M::Commitment>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
-    final_poly: F,
+    final_poly: Vec<F>,
 }
 
 #[instrument(name = "commit phase", skip_all)]
 fn commit_phase<G, Val, Challenge, M, Challenger>(
     g: &G,
     config: &FriConfig<M>,
+    log_final_poly_len: usize,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
 ) -> CommitPhaseResult<Challenge, M>
@@ -86,8 +100,9 @@ where
     let mut folded = inputs_iter.next().unwrap();
     let mut commits = vec![];
     let mut data = vec![];
+    let final_height = config.blowup() << log_final_poly_len;
 
-    while folded.len() > config.blowup() {
+    while folded.len() > final_height {
         let leaves = RowMajorMatrix::new(folded, 2);
         let (commit, prover_data) = config.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
@@ -105,13 +120,41 @@ where
         }
     }
 
-    // We should be left with `blowup` evaluations of a constant polynomial.
-    assert_eq!(folded.len(), config.blowup());
-    let final_poly = folded[0];
-    for x in folded {
-        assert_eq!(x, final_poly);
+    // We should be left with `blowup` evaluations of a polynomial of length `final_poly_len`.
+    assert_eq!(folded.len(), final_height);
+
+    // Find its coefficients in the circle basis by splitting it into even and odd parts, as
+    // folding does, `log_final_poly_len` times. Folding at zero gives the even part, and folding
+    // at one gives the sum of both parts.
+    let mut parts = vec![folded];
+    for _ in 0..log_final_poly_len {
+        parts = parts
+            .into_iter()
+            .flat_map(|evals| {
+                let evals = RowMajorMatrix::new(evals, 2);
+                let even = g.fold_matrix(Challenge::ZERO, evals.as_view());
+                let odd = izip!(g.fold_matrix(Challenge::ONE, evals.as_view()), &even)
+                    .map(|(sum, &even)| sum - even)
+                    .collect();
+                [even, odd]
+            })
+            .collect();
+    }
+
+    // Each part should be `blowup` evaluations of a constant, its coefficient.
+    let final_poly = parts
+        .into_iter()
+        .map(|evals| {
+            let coeff = evals[0];
+            for x in evals {
+                assert_eq!(x, coeff);
+            }
+            coeff
+        })
+        .collect_vec();
+    for &x in &final_poly {
+        challenger.observe_ext_element(x);
     }
-    challenger.observe_ext_element(final_poly);
 
     CommitPhaseResult {
         commits,
@@ -120,11 +163,11 @@ where
     }
 }
 
-fn answer_query<F, M>(
+fn answer_queries<F, M>(
     config: &FriConfig<M>,
     commit_phase_commits: &[M::ProverData<RowMajorMatrix<F>>],
-    index: usize,
-) -> Vec<CircleCommitPhaseProofStep<F, M>>
+    indices: &[usize],
+) -> Vec<CircleCommitPhaseOpenings<F, M>>
 where
     F: Field,
     M: Mmcs<F>,
@@ -133,18 +176,22 @@ where
         .iter()
         .enumerate()
         .map(|(i, commit)| {
-            let index_i = index >> i;
-            let index_i_sibling = index_i ^ 1;
-            let index_pair = index_i >> 1;
-
-            let (mut opened_rows, opening_proof) = config.mmcs.open_batch(index_pair, commit);
-            assert_eq!(opened_rows.len(), 1);
-            let opened_row = opened_rows.pop().unwrap();
-            assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
-            let sibling_value = opened_row[index_i_sibling % 2];
-
-            CircleCommitPhaseProofStep {
-                sibling_value,
+            let indices_i = indices.iter().map(|index| index >> i).collect_vec();
+            let index_pairs = indices_i.iter().map(|index_i| index_i >> 1).collect_vec();
+
+            let (opened_rows, opening_proof) = config.mmcs.open_multi_batch(&index_pairs, commit);
+            let sibling_values = izip!(opened_rows, indices_i)
+                .map(|(mut opened_rows, index_i)| {
+                    assert_eq!(opened_rows.len(), 1);
+                    let opened_row = opened_rows.pop().unwrap();
+                    assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
+                    let index_i_sibling = index_i ^ 1;
+                    opened_row[index_i_sibling % 2]
+                })
+                .collect();
+
+            CircleCommitPhaseOpenings {
+                sibling_values,
                 opening_proof,
             }
         })
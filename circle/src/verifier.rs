@@ -1,25 +1,33 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
 use p3_commit::Mmcs;
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::verifier::FriError;
 use p3_fri::{FriConfig, FriGenericConfig};
 use p3_matrix::Dimensions;
+use p3_util::{log2_strict_usize, reverse_bits_len};
 
-use crate::{CircleCommitPhaseProofStep, CircleFriProof};
+use crate::domain::CircleDomain;
+use crate::{CircleCommitPhaseOpenings, CircleFriProof};
 
+/// Verify a FRI proof for inputs whose largest log height is `log_max_height`, which folds down to
+/// a final polynomial of length `2^log_final_poly_len`.
 pub fn verify<G, Val, Challenge, M, Challenger>(
     g: &G,
     config: &FriConfig<M>,
+    log_max_height: usize,
+    log_final_poly_len: usize,
     proof: &CircleFriProof<Challenge, M, Challenger::Witness, G::InputProof>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+    open_input: impl Fn(&[usize], &G::InputProof) -> Result<Vec<Vec<(usize, Challenge)>>, G::InputError>,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
@@ -33,9 +41,16 @@ where
             challenger.sample_ext_element()
         })
         .collect();
-    challenger.observe_ext_element(proof.final_poly);
+    for &x in &proof.final_poly {
+        challenger.observe_ext_element(x);
+    }
 
-    if proof.query_proofs.len() != config.num_queries {
+    let log_final_height = config.log_blowup + log_final_poly_len;
+    // Each round folds by 2, from the largest input down to the final codeword.
+    if proof.commit_phase_commits.len() + log_final_height != log_max_height
+        || proof.commit_phase_openings.len() != proof.commit_phase_commits.len()
+        || proof.final_poly.len() != 1 << log_final_poly_len
+    {
         return Err(FriError::InvalidProofShape);
     }
 
@@ -44,31 +59,44 @@ where
         return Err(FriError::InvalidPowWitness);
     }
 
-    let log_max_height = proof.commit_phase_commits.len() + config.log_blowup;
-
-    for qp in &proof.query_proofs {
-        let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
-        let ro = open_input(index, &qp.input_proof).map_err(FriError::InputError)?;
-
-        debug_assert!(
-            ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
-            "reduced openings sorted by height descending"
-        );
-
-        let folded_eval = verify_query(
-            g,
-            config,
-            index >> g.extra_query_index_bits(),
-            izip!(
-                &betas,
-                &proof.commit_phase_commits,
-                &qp.commit_phase_openings
-            ),
-            ro,
-            log_max_height,
-        )?;
-
-        if folded_eval != proof.final_poly {
+    let indices = (0..config.num_queries)
+        .map(|_| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
+        .collect_vec();
+    let reduced_openings =
+        open_input(&indices, &proof.input_proof).map_err(FriError::InputError)?;
+    if reduced_openings.len() != indices.len() {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    debug_assert!(
+        reduced_openings
+            .iter()
+            .all(|ro| ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r)),
+        "reduced openings sorted by height descending"
+    );
+
+    let folded_evals = verify_queries(
+        g,
+        config,
+        indices
+            .iter()
+            .map(|index| index >> g.extra_query_index_bits())
+            .collect(),
+        izip!(
+            &betas,
+            &proof.commit_phase_commits,
+            &proof.commit_phase_openings
+        ),
+        reduced_openings,
+        log_max_height,
+        log_final_height,
+    )?;
+
+    for (index, folded_eval) in izip!(indices, folded_evals) {
+        let final_index = index >> (g.extra_query_index_bits() + log_max_height - log_final_height);
+        if eval_final_poly::<Val, _>(&proof.final_poly, log_final_height, final_index)
+            != folded_eval
+        {
             return Err(FriError::FinalPolyMismatch);
         }
     }
@@ -79,35 +107,53 @@ where
 type CommitStep<'a, F, M> = (
     &'a F,
     &'a <M as Mmcs<F>>::Commitment,
-    &'a CircleCommitPhaseProofStep<F, M>,
+    &'a CircleCommitPhaseOpenings<F, M>,
 );
 
-fn verify_query<'a, G, F, M>(
+fn verify_queries<'a, G, F, M>(
     g: &G,
     config: &FriConfig<M>,
-    mut index: usize,
+    mut indices: Vec<usize>,
     steps: impl Iterator<Item = CommitStep<'a, F, M>>,
-    reduced_openings: Vec<(usize, F)>,
+    reduced_openings: Vec<Vec<(usize, F)>>,
     log_max_height: usize,
-) -> Result<F, FriError<M::Error, G::InputError>>
+    log_final_height: usize,
+) -> Result<Vec<F>, FriError<M::Error, G::InputError>>
 where
     F: Field,
     M: Mmcs<F> + 'a,
     G: FriGenericConfig<F>,
 {
-    let mut folded_eval = F::ZERO;
-    let mut ro_iter = reduced_openings.into_iter().peekable();
-
-    for (log_folded_height, (&beta, comm, opening)) in izip!((0..log_max_height).rev(), steps) {
-        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
-            folded_eval += ro;
+    let mut folded_evals = vec![F::ZERO; indices.len()];
+    let mut ro_iters = reduced_openings
+        .into_iter()
+        .map(|ro| ro.into_iter().peekable())
+        .collect_vec();
+
+    for (log_folded_height, (&beta, comm, openings)) in izip!((0..log_max_height).rev(), steps) {
+        if openings.sibling_values.len() != indices.len() {
+            return Err(FriError::InvalidProofShape);
         }
 
-        let index_sibling = index ^ 1;
-        let index_pair = index >> 1;
+        let rows = izip!(
+            &mut folded_evals,
+            &mut ro_iters,
+            &indices,
+            &openings.sibling_values
+        )
+        .map(|(folded_eval, ro_iter, &index, &sibling_value)| {
+            if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
+                *folded_eval += ro;
+            }
+
+            let index_sibling = index ^ 1;
+            let mut evals = vec![*folded_eval; 2];
+            evals[index_sibling % 2] = sibling_value;
+            vec![evals]
+        })
+        .collect_vec();
 
-        let mut evals = vec![folded_eval; 2];
-        evals[index_sibling % 2] = opening.sibling_value;
+        let index_pairs = indices.iter().map(|index| index >> 1).collect_vec();
 
         let dims = &[Dimensions {
             width: 2,
@@ -115,25 +161,60 @@ where
         }];
         config
             .mmcs
-            .verify_batch(
-                comm,
-                dims,
-                index_pair,
-                &[evals.clone()],
-                &opening.opening_proof,
-            )
+            .verify_multi_batch(comm, dims, &index_pairs, &rows, &openings.opening_proof)
             .map_err(FriError::CommitPhaseMmcsError)?;
 
-        index = index_pair;
+        indices = index_pairs;
 
-        folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
+        for (folded_eval, &index, mut row) in izip!(&mut folded_evals, &indices, rows) {
+            *folded_eval = g.fold_row(
+                index,
+                log_folded_height,
+                1,
+                beta,
+                row.pop().unwrap().into_iter(),
+            );
+        }
     }
 
-    debug_assert!(index < config.blowup(), "index was {}", index);
     debug_assert!(
-        ro_iter.next().is_none(),
+        indices.iter().all(|&index| index < 1 << log_final_height),
+        "indices were {:?}",
+        indices
+    );
+    debug_assert!(
+        ro_iters.iter_mut().all(|ro_iter| ro_iter.next().is_none()),
         "verifier reduced_openings were not in descending order?"
     );
 
-    Ok(folded_eval)
+    Ok(folded_evals)
+}
+
+/// Evaluate the final polynomial, in the circle basis, at the point of the final codeword of height
+/// `2^log_final_height` with the given index.
+fn eval_final_poly<Val: ComplexExtendable, Challenge: ExtensionField<Val>>(
+    final_poly: &[Challenge],
+    log_final_height: usize,
+    index: usize,
+) -> Challenge {
+    if final_poly.len() == 1 {
+        return final_poly[0];
+    }
+
+    // The codeword pairs each point with its negation, as folding expects.
+    let x = CircleDomain::<Val>::standard(log_final_height + 1)
+        .nth_x_twiddle(reverse_bits_len(index >> 1, log_final_height - 1));
+    let x = if index & 1 == 0 { x } else { -x };
+
+    let xs = iter::successors(Some(x), |&x| Some(x.square().double() - Val::ONE))
+        .take(log2_strict_usize(final_poly.len()))
+        .collect_vec();
+    let mut evals = final_poly.to_vec();
+    for &x in xs.iter().rev() {
+        evals = evals
+            .chunks_exact(2)
+            .map(|coeffs| coeffs[0] + coeffs[1] * x)
+            .collect();
+    }
+    evals[0]
 }
@@ -1,3 +1,4 @@
+use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::Itertools;
@@ -52,6 +53,72 @@ pub trait PolynomialSpace: Copy {
 
     // Unnormalized
     fn selectors_on_coset(&self, coset: Self) -> LagrangeSelectors<Vec<Self::Val>>;
+
+    /// The unnormalized selector for windows of `window_size` rows, which vanishes on the last
+    /// `window_size - 1` points of this domain, evaluated at `point`.
+    ///
+    /// A window of 2 rows gives `is_transition`; larger windows need not be supported.
+    fn transition_window_selector_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        window_size: usize,
+        point: Ext,
+    ) -> Ext {
+        assert_eq!(window_size, 2, "only windows of 2 rows are supported");
+        self.selectors_at_point(point).is_transition
+    }
+
+    /// Like `transition_window_selector_at_point`, evaluated over `coset`.
+    fn transition_window_selector_on_coset(
+        &self,
+        window_size: usize,
+        coset: Self,
+    ) -> Vec<Self::Val> {
+        assert_eq!(window_size, 2, "only windows of 2 rows are supported");
+        self.selectors_on_coset(coset).is_transition
+    }
+
+    /// The unnormalized selector for the `row`-th point of this domain, which vanishes on every
+    /// other point, evaluated at `point`.
+    ///
+    /// The first row gives `is_first_row`; other rows need not be supported.
+    fn row_selector_at_point<Ext: ExtensionField<Self::Val>>(&self, row: usize, point: Ext) -> Ext {
+        assert_eq!(row, 0, "only first-row selectors are supported");
+        self.selectors_at_point(point).is_first_row
+    }
+
+    /// Like `row_selector_at_point`, evaluated over `coset`.
+    fn row_selector_on_coset(&self, row: usize, coset: Self) -> Vec<Self::Val> {
+        assert_eq!(row, 0, "only first-row selectors are supported");
+        self.selectors_on_coset(coset).is_first_row
+    }
+
+    /// Evaluate, at `point`, the polynomial of degree less than this domain's size whose value at
+    /// the `i`-th point of this domain is `values[i % values.len()]`.
+    ///
+    /// `values.len()` must be a power of two dividing this domain's size. Constant columns are
+    /// always supported; longer periods need not be.
+    fn periodic_column_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        values: &[Self::Val],
+        _point: Ext,
+    ) -> Ext {
+        assert_eq!(
+            values.len(),
+            1,
+            "only constant periodic columns are supported"
+        );
+        Ext::from_base(values[0])
+    }
+
+    /// Like `periodic_column_at_point`, evaluated over `coset`.
+    fn periodic_column_on_coset(&self, values: &[Self::Val], coset: Self) -> Vec<Self::Val> {
+        assert_eq!(
+            values.len(),
+            1,
+            "only constant periodic columns are supported"
+        );
+        vec![values[0]; coset.size()]
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -168,4 +235,128 @@ impl<Val: TwoAdicField> PolynomialSpace for TwoAdicMultiplicativeCoset<Val> {
                 .collect(),
         }
     }
+
+    fn transition_window_selector_at_point<Ext: ExtensionField<Val>>(
+        &self,
+        window_size: usize,
+        point: Ext,
+    ) -> Ext {
+        let unshifted_point = point * self.shift.inverse();
+        self.gen()
+            .inverse()
+            .powers()
+            .skip(1)
+            .take(window_size - 1)
+            .map(|last_point| unshifted_point - last_point)
+            .product()
+    }
+
+    fn transition_window_selector_on_coset(&self, window_size: usize, coset: Self) -> Vec<Val> {
+        assert_eq!(self.shift, Val::ONE);
+        let last_points = self
+            .gen()
+            .inverse()
+            .powers()
+            .skip(1)
+            .take(window_size - 1)
+            .collect_vec();
+        cyclic_subgroup_coset_known_order(coset.gen(), coset.shift, 1 << coset.log_n)
+            .map(|x| {
+                last_points
+                    .iter()
+                    .map(|&last_point| x - last_point)
+                    .product()
+            })
+            .collect()
+    }
+
+    fn row_selector_at_point<Ext: ExtensionField<Val>>(&self, row: usize, point: Ext) -> Ext {
+        assert!(row < self.size(), "row is outside the domain");
+        let unshifted_point = point * self.shift.inverse();
+        let z_h = unshifted_point.exp_power_of_2(self.log_n) - Ext::ONE;
+        z_h / (unshifted_point - self.gen().exp_u64(row as u64))
+    }
+
+    fn row_selector_on_coset(&self, row: usize, coset: Self) -> Vec<Val> {
+        assert!(row < self.size(), "row is outside the domain");
+        let row_point = self.gen().exp_u64(row as u64);
+        let unshifted_points = cyclic_subgroup_coset_known_order(
+            coset.gen(),
+            coset.shift * self.shift.inverse(),
+            coset.size(),
+        )
+        .collect_vec();
+        let denoms = unshifted_points
+            .iter()
+            .map(|&x| x - row_point)
+            .collect_vec();
+        unshifted_points
+            .into_iter()
+            .zip(batch_multiplicative_inverse(&denoms))
+            .map(|(x, inv)| (x.exp_power_of_2(self.log_n) - Val::ONE) * inv)
+            .collect()
+    }
+
+    fn periodic_column_at_point<Ext: ExtensionField<Val>>(
+        &self,
+        values: &[Val],
+        point: Ext,
+    ) -> Ext {
+        let log_period = log2_strict_usize(values.len());
+        assert!(
+            log_period <= self.log_n,
+            "periodic column is longer than the domain"
+        );
+        // The column is Q(x^(n / p)), where Q interpolates the values over the subgroup of order p.
+        let y = (point * self.shift.inverse()).exp_power_of_2(self.log_n - log_period);
+        subgroup_interpolant_coeffs(values)
+            .iter()
+            .rev()
+            .fold(Ext::ZERO, |acc, &coeff| acc * y + coeff)
+    }
+
+    fn periodic_column_on_coset(&self, values: &[Val], coset: Self) -> Vec<Val> {
+        let log_period = log2_strict_usize(values.len());
+        assert!(
+            log_period <= self.log_n,
+            "periodic column is longer than the domain"
+        );
+        let log_stride = self.log_n - log_period;
+        // The coset's points raised to the power n / p cycle through a coset of a subgroup of
+        // order at most p, so we only evaluate Q on one cycle.
+        let coeffs = subgroup_interpolant_coeffs(values);
+        let y_shift = (coset.shift * self.shift.inverse()).exp_power_of_2(log_stride);
+        let y_gen = coset.gen().exp_power_of_2(log_stride);
+        let cycle = y_gen
+            .shifted_powers(y_shift)
+            .take(1 << coset.log_n.saturating_sub(log_stride))
+            .map(|y| {
+                coeffs
+                    .iter()
+                    .rev()
+                    .fold(Val::ZERO, |acc, &coeff| acc * y + coeff)
+            })
+            .collect_vec();
+        cycle.into_iter().cycle().take(coset.size()).collect()
+    }
+}
+
+/// The coefficients of the polynomial of degree less than `values.len()` whose value at the `i`-th
+/// power of the canonical generator of the subgroup of that order is `values[i]`.
+fn subgroup_interpolant_coeffs<Val: TwoAdicField>(values: &[Val]) -> Vec<Val> {
+    // An inverse DFT; periodic columns are short, so the quadratic cost doesn't matter.
+    let n = values.len();
+    let gen_inv = Val::two_adic_generator(log2_strict_usize(n)).inverse();
+    let n_inv = Val::from_canonical_usize(n).inverse();
+    gen_inv
+        .powers()
+        .take(n)
+        .map(|root| {
+            root.powers()
+                .zip(values)
+                .map(|(power, &value)| power * value)
+                .sum::<Val>()
+                * n_inv
+        })
+        .collect()
 }
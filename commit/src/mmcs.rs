@@ -20,6 +20,9 @@ pub trait Mmcs<T: Send + Sync>: Clone {
     type ProverData<M>;
     type Commitment: Clone + Serialize + DeserializeOwned;
     type Proof: Clone + Serialize + DeserializeOwned;
+    /// A proof of openings at several indices at once, which shares whatever the proofs of the
+    /// individual openings have in common.
+    type MultiProof: Clone + Serialize + DeserializeOwned;
     type Error: Debug;
 
     fn commit<M: Matrix<T>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>);
@@ -45,6 +48,16 @@ pub trait Mmcs<T: Send + Sync>: Clone {
         prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<T>>, Self::Proof);
 
+    /// Opens a batch of rows from committed matrices at each of several indices.
+    /// returns `(openings, proof)`
+    /// where `openings[q]` is the batch of rows `open_batch` would open at `indices[q]`. Indices
+    /// may repeat, and the proof only contains what's needed to check all openings together.
+    fn open_multi_batch<M: Matrix<T>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<T>>>, Self::MultiProof);
+
     /// Get the matrices that were committed to.
     fn get_matrices<'a, M: Matrix<T>>(&self, prover_data: &'a Self::ProverData<M>) -> Vec<&'a M>;
 
@@ -76,4 +89,16 @@ pub trait Mmcs<T: Send + Sync>: Clone {
         opened_values: &[Vec<T>],
         proof: &Self::Proof,
     ) -> Result<(), Self::Error>;
+
+    /// Verify a batch opening at several indices, as produced by `open_multi_batch`.
+    /// `opened_values[q]` is the batch of rows opened at `indices[q]`, and `dimensions` is as in
+    /// `verify_batch`.
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<T>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error>;
 }
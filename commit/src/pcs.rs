@@ -9,7 +9,7 @@ use p3_matrix::Matrix;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::PolynomialSpace;
+use crate::{PcsSoundness, PolynomialSpace, ProximityRegime};
 
 pub type Val<D> = <D as PolynomialSpace>::Val;
 
@@ -32,6 +32,30 @@ where
 
     type Error: Debug;
 
+    /// Whether this PCS is hiding. A hiding PCS randomizes each polynomial it commits to, doubling
+    /// its degree, so a STARK using it must account for that in its quotient degree.
+    const ZK: bool = false;
+
+    /// Parameters of this PCS which affect the proofs it produces, such as FRI's blowup factor and
+    /// number of queries. STARKs bind these into their transcripts, so that a proof made under one
+    /// configuration can't be replayed under another.
+    fn parameters(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Estimate the proven soundness of opening proofs which batch `num_polys` polynomial
+    /// evaluations, of polynomials with degree less than `2^log_max_degree`, using challenges from
+    /// a field of at least `2^challenge_bits` elements. Returns `None` if this PCS has no estimate.
+    fn proven_soundness(
+        &self,
+        _regime: ProximityRegime,
+        _challenge_bits: f64,
+        _log_max_degree: usize,
+        _num_polys: usize,
+    ) -> Option<PcsSoundness> {
+        None
+    }
+
     /// This should return a coset domain (s.t. Domain::next_point returns Some)
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain;
 
@@ -41,6 +65,27 @@ where
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
     ) -> (Self::Commitment, Self::ProverData);
 
+    /// Commit to the chunks of one or more quotient polynomials. Each is given by its evaluations
+    /// over a quotient domain, and is split into `num_chunks` chunks over the domains given by
+    /// `quotient_domain.split_domains(num_chunks)`.
+    ///
+    /// A hiding PCS may randomize the chunks, so long as they still recombine to the quotient.
+    #[allow(clippy::type_complexity)]
+    fn commit_quotients(
+        &self,
+        quotients: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>, usize)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let chunks = quotients
+            .into_iter()
+            .flat_map(|(quotient_domain, evaluations, num_chunks)| {
+                let chunk_domains = quotient_domain.split_domains(num_chunks);
+                let chunk_evaluations = quotient_domain.split_evals(num_chunks, evaluations);
+                chunk_domains.into_iter().zip(chunk_evaluations)
+            })
+            .collect();
+        self.commit(chunks)
+    }
+
     fn get_evaluations_on_domain<'a>(
         &self,
         prover_data: &'a Self::ProverData,
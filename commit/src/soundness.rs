@@ -0,0 +1,64 @@
+use p3_util::log2_f64;
+
+/// How far from the code a committed word may be while a proximity test still accepts it, which
+/// determines how soundness errors are bounded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProximityRegime {
+    /// Proximity up to the unique decoding radius `(1 - rho) / 2`, where `rho` is the code rate,
+    /// so that a committed word is close to at most one codeword.
+    UniqueDecoding,
+    /// Proximity up to the Johnson bound `1 - sqrt(rho) (1 + 1 / (2m))`, for a proximity parameter
+    /// `m >= 3`. This allows more queries to be dropped, but a committed word may be close to up
+    /// to `(m + 1/2) / sqrt(rho)` codewords.
+    Johnson { m: usize },
+}
+
+impl ProximityRegime {
+    /// The log of the maximum number of codewords close to a committed word, for a code of rate
+    /// `2^-log_blowup`.
+    pub fn log_list_size(&self, log_blowup: usize) -> f64 {
+        match *self {
+            Self::UniqueDecoding => 0.0,
+            Self::Johnson { m } => {
+                assert!(
+                    m >= 3,
+                    "the Johnson bound needs a proximity parameter of at least 3"
+                );
+                log2_f64(m as f64 + 0.5) + log_blowup as f64 / 2.0
+            }
+        }
+    }
+}
+
+/// A breakdown of the proven soundness of a PCS's opening proofs, as bits of security (the
+/// negated log of each error).
+#[derive(Copy, Clone, Debug)]
+pub struct PcsSoundness {
+    /// The log of the maximum number of polynomials consistent with each commitment. Any check of
+    /// the committed polynomials at a random point errs this many times as often.
+    pub log_list_size: f64,
+    /// Batching the polynomials being opened into a single low-degree test.
+    pub batching_bits: f64,
+    /// The commit phase of the low-degree test, i.e. its folding rounds.
+    pub commit_phase_bits: f64,
+    /// The query phase of the low-degree test, including proof of work.
+    pub query_phase_bits: f64,
+}
+
+impl PcsSoundness {
+    /// The bits of security of the opening proof as a whole.
+    pub fn bits(&self) -> f64 {
+        security_bits(&[
+            self.batching_bits,
+            self.commit_phase_bits,
+            self.query_phase_bits,
+        ])
+    }
+}
+
+/// Combine the bits of security of the steps of a protocol. The total error is the sum of the
+/// errors of each step, which is at most their number times the largest error.
+pub fn security_bits(step_bits: &[f64]) -> f64 {
+    let weakest = step_bits.iter().copied().fold(f64::INFINITY, f64::min);
+    weakest - log2_f64(step_bits.len() as f64)
+}
@@ -52,6 +52,10 @@ where
     type Proof = ();
     type Error = ();
 
+    fn parameters(&self) -> Vec<usize> {
+        vec![self.log_n]
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         TwoAdicMultiplicativeCoset {
             log_n: log2_strict_usize(degree),
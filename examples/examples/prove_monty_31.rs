@@ -6,10 +6,12 @@ use p3_blake3_air::Blake3Air;
 use p3_dft::Radix2DitParallel;
 use p3_examples::airs::ProofObjective;
 use p3_examples::dfts::DftChoice;
-use p3_examples::parsers::{DftOptions, FieldOptions, MerkleHashOptions, ProofOptions};
+use p3_examples::parsers::{
+    DftOptions, FieldOptions, LowDegreeTestOptions, MerkleHashOptions, ProofOptions,
+};
 use p3_examples::proofs::{prove_hashes_keccak, prove_hashes_poseidon2, report_result};
 use p3_field::extension::BinomialExtensionField;
-use p3_keccak_air::KeccakAir;
+use p3_keccak_air::{KeccakAir, NUM_PADDED_ROUNDS};
 use p3_koala_bear::{GenericPoseidon2LinearLayersKoalaBear, KoalaBear, Poseidon2KoalaBear};
 use p3_monty_31::dft::RecursiveDft;
 use p3_poseidon2_air::{RoundConstants, VectorizedPoseidon2Air};
@@ -47,6 +49,16 @@ struct Args {
     /// The hash function to use when assembling the Merkle tree.
     #[arg(short, long, ignore_case = true, value_enum)]
     merkle_hash: MerkleHashOptions,
+
+    /// The low-degree test used by the polynomial commitment scheme.
+    #[arg(
+        short = 't',
+        long,
+        ignore_case = true,
+        value_enum,
+        default_value = "fri"
+    )]
+    low_degree_test: LowDegreeTestOptions,
 }
 
 fn main() {
@@ -77,7 +89,7 @@ fn main() {
             trace_height << 3
         }
         ProofOptions::KeccakFPermutations => {
-            let num_hashes = trace_height / 24;
+            let num_hashes = trace_height / NUM_PADDED_ROUNDS;
             println!("Proving {num_hashes} Keccak-F permutations");
             num_hashes
         }
@@ -121,8 +133,13 @@ fn main() {
 
             match args.merkle_hash {
                 MerkleHashOptions::KeccakF => {
-                    let result =
-                        prove_hashes_keccak(proof_goal, dft, num_hashes, PhantomData::<EF>);
+                    let result = prove_hashes_keccak(
+                        proof_goal,
+                        dft,
+                        args.low_degree_test,
+                        num_hashes,
+                        PhantomData::<EF>,
+                    );
                     report_result(result);
                 }
                 MerkleHashOptions::Poseidon2 => {
@@ -131,6 +148,7 @@ fn main() {
                     let result = prove_hashes_poseidon2(
                         proof_goal,
                         dft,
+                        args.low_degree_test,
                         num_hashes,
                         perm16,
                         perm24,
@@ -177,8 +195,13 @@ fn main() {
 
             match args.merkle_hash {
                 MerkleHashOptions::KeccakF => {
-                    let result =
-                        prove_hashes_keccak(proof_goal, dft, num_hashes, PhantomData::<EF>);
+                    let result = prove_hashes_keccak(
+                        proof_goal,
+                        dft,
+                        args.low_degree_test,
+                        num_hashes,
+                        PhantomData::<EF>,
+                    );
                     report_result(result);
                 }
                 MerkleHashOptions::Poseidon2 => {
@@ -187,6 +210,7 @@ fn main() {
                     let result = prove_hashes_poseidon2(
                         proof_goal,
                         dft,
+                        args.low_degree_test,
                         num_hashes,
                         perm16,
                         perm24,
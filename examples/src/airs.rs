@@ -1,4 +1,4 @@
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, BaseAir, PeriodicAirBuilder};
 use p3_blake3_air::Blake3Air;
 use p3_field::{Field, PrimeField64};
 use p3_keccak_air::KeccakAir;
@@ -100,10 +100,19 @@ impl<
             ProofObjective::Keccak(k_air) => <KeccakAir as BaseAir<F>>::width(k_air),
         }
     }
+
+    #[inline]
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        match self {
+            ProofObjective::Blake3(b3_air) => <Blake3Air as BaseAir<F>>::periodic_columns(b3_air),
+            ProofObjective::Poseidon2(p2_air) => p2_air.periodic_columns(),
+            ProofObjective::Keccak(k_air) => <KeccakAir as BaseAir<F>>::periodic_columns(k_air),
+        }
+    }
 }
 
 impl<
-        AB: AirBuilder,
+        AB: PeriodicAirBuilder,
         LinearLayers: GenericPoseidon2LinearLayers<AB::Expr, WIDTH>,
         const WIDTH: usize,
         const SBOX_DEGREE: u64,
@@ -33,6 +33,12 @@ pub enum MerkleHashOptions {
     Poseidon2,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LowDegreeTestOptions {
+    Fri,
+    Stir,
+}
+
 /// Produce a collection of PossibleValue's for an Enum variant.
 ///
 /// We allow any prefix of the full name which uniquely determines the variant.
@@ -144,3 +150,16 @@ impl ValueEnum for MerkleHashOptions {
         })
     }
 }
+
+impl ValueEnum for LowDegreeTestOptions {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[LowDegreeTestOptions::Fri, LowDegreeTestOptions::Stir]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            LowDegreeTestOptions::Fri => get_aliases("fri", 1, None),
+            LowDegreeTestOptions::Stir => get_aliases("stir", 1, None),
+        })
+    }
+}
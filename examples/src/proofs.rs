@@ -1,23 +1,33 @@
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
+use p3_air::Air;
 use p3_challenger::{DuplexChallenger, SerializingChallenger32};
 use p3_commit::ExtensionMmcs;
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::{ExtensionField, PrimeField32, TwoAdicField};
 use p3_fri::{create_benchmark_fri_config, TwoAdicFriPcs};
 use p3_keccak::{Keccak256Hash, KeccakF};
+use p3_matrix::dense::RowMajorMatrix;
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_poseidon2::GenericPoseidon2LinearLayers;
+use p3_stir::{create_benchmark_stir_config, StirPcs};
 use p3_symmetric::{
     CompressionFunctionFromHasher, CryptographicPermutation, PaddingFreeSponge,
     SerializingHasher32To64, TruncatedPermutation,
 };
-use p3_uni_stark::{prove, verify, StarkConfig, SymbolicExpression};
+#[cfg(debug_assertions)]
+use p3_uni_stark::DebugConstraintBuilder;
+use p3_uni_stark::{
+    prove, verify, PcsError, StarkConfig, StarkGenericConfig, SymbolicAirBuilder,
+    SymbolicExpression, Val, VerificationError, VerifierConstraintFolder,
+};
 use rand::distributions::Standard;
 use rand::prelude::Distribution;
+use tracing::info;
 
 use crate::airs::ProofObjective;
+use crate::parsers::LowDegreeTestOptions;
 
 /// Prove the given ProofGoal using the Keccak hash function to build the merkle tree.
 ///
@@ -25,6 +35,7 @@ use crate::airs::ProofObjective;
 /// - The Field
 /// - The Proof Goal (Choice of Hash function and number of hashes to prove)
 /// - The DFT
+/// - The low-degree test used by the PCS
 #[inline]
 pub fn prove_hashes_keccak<
     F: PrimeField32 + TwoAdicField,
@@ -52,9 +63,10 @@ pub fn prove_hashes_keccak<
         P2_VECTOR_LEN,
     >,
     dft: DFT,
+    low_degree_test: LowDegreeTestOptions,
     num_hashes: usize,
     _ef: PhantomData<EF>, // A simple workaround allowing the compiler to determine all generic parameters
-) -> Result<(), impl Debug>
+) -> Result<(), Box<dyn Debug>>
 where
     Standard: Distribution<F>,
 {
@@ -73,17 +85,22 @@ where
 
     let trace = proof_goal.generate_trace_rows(num_hashes);
 
-    let fri_config = create_benchmark_fri_config(challenge_mmcs);
-
-    let pcs = TwoAdicFriPcs::new(dft, val_mmcs, fri_config);
-
-    let config = StarkConfig::new(pcs);
-
-    let mut proof_challenger = SerializingChallenger32::from_hasher(vec![], byte_hash);
-    let mut verif_challenger = SerializingChallenger32::from_hasher(vec![], byte_hash);
-
-    let proof = prove(&config, &proof_goal, &mut proof_challenger, trace, &vec![]);
-    verify(&config, &proof_goal, &mut verif_challenger, &proof, &vec![])
+    let challenger = SerializingChallenger32::from_hasher(vec![], byte_hash);
+
+    match low_degree_test {
+        LowDegreeTestOptions::Fri => {
+            let fri_config = create_benchmark_fri_config(challenge_mmcs);
+            let pcs = TwoAdicFriPcs::new(dft, val_mmcs, fri_config);
+            prove_and_verify(&StarkConfig::new(pcs), &proof_goal, trace, challenger)
+                .map_err(|err| Box::new(err) as Box<dyn Debug>)
+        }
+        LowDegreeTestOptions::Stir => {
+            let stir_config = create_benchmark_stir_config(challenge_mmcs);
+            let pcs = StirPcs::new(dft, val_mmcs, stir_config);
+            prove_and_verify(&StarkConfig::new(pcs), &proof_goal, trace, challenger)
+                .map_err(|err| Box::new(err) as Box<dyn Debug>)
+        }
+    }
 }
 
 /// Prove the given ProofGoal using the Poseidon2 hash function to build the merkle tree.
@@ -92,6 +109,7 @@ where
 /// - The Field
 /// - The Proof Goal (Choice of Hash function and number of hashes to prove)
 /// - The DFT
+/// - The low-degree test used by the PCS
 #[inline]
 pub fn prove_hashes_poseidon2<
     F: PrimeField32 + TwoAdicField,
@@ -121,11 +139,12 @@ pub fn prove_hashes_poseidon2<
         P2_VECTOR_LEN,
     >,
     dft: DFT,
+    low_degree_test: LowDegreeTestOptions,
     num_hashes: usize,
     perm16: Perm16,
     perm24: Perm24,
     _ef: PhantomData<EF>, // A simple workaround allowing the compiler to determine all generic parameters
-) -> Result<(), impl Debug>
+) -> Result<(), Box<dyn Debug>>
 where
     Standard: Distribution<F>,
 {
@@ -139,17 +158,50 @@ where
 
     let trace = proof_goal.generate_trace_rows(num_hashes);
 
-    let fri_config = create_benchmark_fri_config(challenge_mmcs);
-
-    let pcs = TwoAdicFriPcs::new(dft, val_mmcs, fri_config);
+    let challenger = DuplexChallenger::<_, _, 24, 16>::new(perm24.clone());
+
+    match low_degree_test {
+        LowDegreeTestOptions::Fri => {
+            let fri_config = create_benchmark_fri_config(challenge_mmcs);
+            let pcs = TwoAdicFriPcs::new(dft, val_mmcs, fri_config);
+            prove_and_verify(&StarkConfig::new(pcs), &proof_goal, trace, challenger)
+                .map_err(|err| Box::new(err) as Box<dyn Debug>)
+        }
+        LowDegreeTestOptions::Stir => {
+            let stir_config = create_benchmark_stir_config(challenge_mmcs);
+            let pcs = StirPcs::new(dft, val_mmcs, stir_config);
+            prove_and_verify(&StarkConfig::new(pcs), &proof_goal, trace, challenger)
+                .map_err(|err| Box::new(err) as Box<dyn Debug>)
+        }
+    }
+}
 
-    let config = StarkConfig::new(pcs);
+/// Prove and verify the given AIR under the given config, logging the size of the proof.
+#[inline]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+fn prove_and_verify<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    trace: RowMajorMatrix<Val<SC>>,
+    challenger: SC::Challenger,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    SC::Challenger: Clone,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let mut proof_challenger = challenger.clone();
+    let mut verif_challenger = challenger;
 
-    let mut proof_challenger = DuplexChallenger::<_, _, 24, 16>::new(perm24.clone());
-    let mut verif_challenger = DuplexChallenger::new(perm24.clone());
+    let proof = prove(config, air, &mut proof_challenger, trace, &vec![]);
+    let proof_bytes = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    info!("Proof size: {} bytes", proof_bytes.len());
 
-    let proof = prove(&config, &proof_goal, &mut proof_challenger, trace, &vec![]);
-    verify(&config, &proof_goal, &mut verif_challenger, &proof, &vec![])
+    verify(config, air, &mut verif_challenger, &proof, &vec![])
 }
 
 #[inline]
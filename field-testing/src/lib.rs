@@ -8,13 +8,15 @@ pub mod bench_func;
 pub mod dft_testing;
 pub mod packedfield_testing;
 
+use alloc::vec::Vec;
+
 pub use bench_func::*;
 pub use dft_testing::*;
 use num_bigint::BigUint;
 use num_traits::identities::One;
 use p3_field::{
     cyclic_subgroup_coset_known_order, cyclic_subgroup_known_order, two_adic_coset_zerofier,
-    two_adic_subgroup_zerofier, ExtensionField, Field, TwoAdicField,
+    two_adic_subgroup_zerofier, ExtensionField, Field, FieldAlgebra, PackedValue, TwoAdicField,
 };
 pub use packedfield_testing::*;
 use rand::distributions::{Distribution, Standard};
@@ -133,6 +135,32 @@ pub fn test_ef_two_adic_generator_consistency<
     );
 }
 
+pub fn test_packed_extension<F: Field, EF: ExtensionField<F>>()
+where
+    Standard: Distribution<EF>,
+{
+    let mut rng = rand::thread_rng();
+    let width = F::Packing::WIDTH;
+    let xs = (0..width).map(|_| rng.gen::<EF>()).collect::<Vec<_>>();
+    let ys = (0..width).map(|_| rng.gen::<EF>()).collect::<Vec<_>>();
+    let z = rng.gen::<EF>();
+
+    let packed_xs = EF::pack_ext_slice(&xs);
+    let packed_ys = EF::pack_ext_slice(&ys);
+    assert_eq!(EF::unpack_ext(packed_xs).collect::<Vec<_>>(), xs);
+
+    let products = EF::unpack_ext(packed_xs * packed_ys + EF::ExtensionPacking::from_f(z));
+    for ((x, y), product) in xs.iter().zip(&ys).zip(products) {
+        assert_eq!(*x * *y + z, product);
+    }
+
+    let powers = z.ext_powers_packed().take(2).flat_map(EF::unpack_ext);
+    assert!(z
+        .powers()
+        .zip(powers)
+        .all(|(power, packed)| power == packed));
+}
+
 #[macro_export]
 macro_rules! test_field {
     ($field:ty) => {
@@ -190,6 +218,11 @@ macro_rules! test_two_adic_extension_field {
             fn test_ef_two_adic_generator_consistency() {
                 $crate::test_ef_two_adic_generator_consistency::<$field, $ef>();
             }
+
+            #[test]
+            fn test_packed_extension() {
+                $crate::test_packed_extension::<$field, $ef>();
+            }
         }
     };
 }
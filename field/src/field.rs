@@ -487,20 +487,33 @@ pub trait ExtensionField<Base: Field>: Field + FieldExtensionAlgebra<Base> {
         }
     }
 
+    /// Pack `Base::Packing::WIDTH` extension elements into an `ExtensionPacking` element, with
+    /// the `i`-th element in the `i`-th lane.
+    #[inline]
+    fn pack_ext_slice(ext_slice: &[Self]) -> Self::ExtensionPacking {
+        assert_eq!(ext_slice.len(), Base::Packing::WIDTH);
+        // Transpose the extension elements into one packed element per coefficient.
+        Self::ExtensionPacking::from_base_fn(|i| {
+            Base::Packing::from_fn(|j| ext_slice[j].as_base_slice()[i])
+        })
+    }
+
+    /// Unpack an `ExtensionPacking` element into the extension elements of its lanes, in order.
+    #[inline]
+    fn unpack_ext(packed: Self::ExtensionPacking) -> impl Iterator<Item = Self> {
+        (0..Base::Packing::WIDTH)
+            .map(move |j| Self::from_base_fn(|i| packed.as_base_slice()[i].as_slice()[j]))
+    }
+
     /// Construct an iterator which returns powers of `self` packed into `ExtensionPacking` elements.
     ///
     /// E.g. if `PACKING::WIDTH = 4` this returns the elements:
     /// `[self^0, self^1, self^2, self^3], [self^4, self^5, self^6, self^7], ...`.
     fn ext_powers_packed(&self) -> Powers<Self::ExtensionPacking> {
         let powers = self.powers().take(Base::Packing::WIDTH + 1).collect_vec();
-        // Transpose first WIDTH powers
-        let current = Self::ExtensionPacking::from_base_fn(|i| {
-            Base::Packing::from_fn(|j| powers[j].as_base_slice()[i])
-        });
+        let current = Self::pack_ext_slice(&powers[..Base::Packing::WIDTH]);
         // Broadcast self^WIDTH
-        let multiplier = Self::ExtensionPacking::from_base_fn(|i| {
-            Base::Packing::from(powers[Base::Packing::WIDTH].as_base_slice()[i])
-        });
+        let multiplier = Self::ExtensionPacking::from_f(powers[Base::Packing::WIDTH]);
 
         Powers {
             base: multiplier,
@@ -0,0 +1,75 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, FieldChallenger};
+use p3_commit::{ExtensionMmcs, Pcs};
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::Field;
+use p3_fri::{create_benchmark_fri_config, FriConfig, TwoAdicFriPcs};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+type Val = BabyBear;
+type Challenge = BinomialExtensionField<Val, 4>;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+
+/// Opens a committed trace with each folding arity, reporting the proof size alongside the time.
+fn bench_fri_arity(c: &mut Criterion) {
+    let log_height = 16;
+    let width = 16;
+
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let trace = RowMajorMatrix::<Val>::rand(&mut rng, 1 << log_height, width);
+
+    let mut group = c.benchmark_group("fri_arity");
+    group.sample_size(10);
+
+    for max_log_arity in [1, 2, 3, 4] {
+        let val_mmcs = ValMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm.clone()));
+        let fri_config = FriConfig {
+            max_log_arity,
+            ..create_benchmark_fri_config(ChallengeMmcs::new(val_mmcs.clone()))
+        };
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, fri_config);
+
+        let domain =
+            <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 1 << log_height);
+        let (_commit, data) =
+            <MyPcs as Pcs<Challenge, Challenger>>::commit(&pcs, vec![(domain, trace.clone())]);
+
+        let open = || {
+            let mut challenger = Challenger::new(perm.clone());
+            let zeta: Challenge = challenger.sample_ext_element();
+            pcs.open(vec![(&data, vec![vec![zeta]])], &mut challenger)
+        };
+
+        let (_opened_values, proof) = open();
+        let proof_size = postcard::to_allocvec(&proof)
+            .expect("unable to serialize proof")
+            .len();
+        println!(
+            "arity {}: {} commit phase rounds, proof size {proof_size} bytes",
+            1 << max_log_arity,
+            proof.commit_phase_commits.len(),
+        );
+
+        group.bench_function(BenchmarkId::from_parameter(1 << max_log_arity), |b| {
+            b.iter(open)
+        });
+    }
+}
+
+criterion_group!(benches, bench_fri_arity);
+criterion_main!(benches);
@@ -1,14 +1,22 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
+use p3_commit::{PcsSoundness, ProximityRegime};
 use p3_field::Field;
 use p3_matrix::Matrix;
+use p3_util::log2_f64;
 
 #[derive(Debug)]
 pub struct FriConfig<M> {
     pub log_blowup: usize,
-    // TODO: This parameter and FRI early stopping are not yet implemented in `CirclePcs`.
     pub log_final_poly_len: usize,
+    /// The log of the largest folding arity. Each commit phase round folds by up to
+    /// `2^max_log_arity`, and by less where that would skip past the height of an input or of the
+    /// final polynomial; see `log_arities`. It must be at least 1.
+    ///
+    /// `CirclePcs` only supports folding by 2, i.e. `max_log_arity = 1`.
+    pub max_log_arity: usize,
     pub num_queries: usize,
     pub proof_of_work_bits: usize,
     pub mmcs: M,
@@ -23,14 +31,122 @@ impl<M> FriConfig<M> {
         1 << self.log_final_poly_len
     }
 
+    pub const fn max_arity(&self) -> usize {
+        1 << self.max_log_arity
+    }
+
+    /// The log of the folding arity of each commit phase round, for inputs whose log heights are
+    /// `log_heights`, in descending order.
+    ///
+    /// Each round folds by `max_arity`, except where that would skip past the height of the next
+    /// input or of the final polynomial. The prover and verifier both derive the schedule this
+    /// way, so it's fixed by the configuration and the inputs rather than chosen by the prover.
+    pub fn log_arities(&self, log_heights: &[usize]) -> Vec<usize> {
+        assert!(self.max_log_arity >= 1, "FRI must fold by at least 2");
+        let log_final_height = self.log_blowup + self.log_final_poly_len;
+        let mut log_arities = vec![];
+        let Some(&log_max_height) = log_heights.first() else {
+            return log_arities;
+        };
+        let mut log_height = log_max_height;
+        while log_height > log_final_height {
+            let log_stop_height = log_heights
+                .iter()
+                .find(|&&log_next_height| log_next_height < log_height)
+                .map_or(log_final_height, |&log_next_height| {
+                    log_next_height.max(log_final_height)
+                });
+            let log_arity = (log_height - log_stop_height).min(self.max_log_arity);
+            log_arities.push(log_arity);
+            log_height -= log_arity;
+        }
+        log_arities
+    }
+
+    /// The parameters which determine the shape and soundness of FRI proofs, for binding into a
+    /// transcript.
+    pub fn parameters(&self) -> Vec<usize> {
+        vec![
+            self.log_blowup,
+            self.log_final_poly_len,
+            self.max_log_arity,
+            self.num_queries,
+            self.proof_of_work_bits,
+        ]
+    }
+
     /// Returns the soundness bits of this FRI instance based on the
     /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture.
     ///
-    /// Certain users may instead want to look at proven soundness, a more complex calculation which
-    /// isn't currently supported by this crate.
+    /// Certain users may instead want to look at proven soundness; see `proven_soundness`.
     pub fn conjectured_soundness_bits(&self) -> usize {
         self.log_blowup * self.num_queries + self.proof_of_work_bits
     }
+
+    /// Returns the proven soundness of this FRI instance, when testing a batch of `num_polys`
+    /// codewords of height at most `2^log_max_height` with challenges from a field of at least
+    /// `2^challenge_bits` elements.
+    ///
+    /// This follows Theorem 2 of the ethSTARK documentation, i.e. the bounds of
+    /// [BCIKS20](https://eprint.iacr.org/2020/654) in the Johnson regime and of
+    /// [BKS18](https://eprint.iacr.org/2018/828) in the unique decoding regime, with each round's
+    /// error scaled by its folding arity less one.
+    pub fn proven_soundness(
+        &self,
+        regime: ProximityRegime,
+        challenge_bits: f64,
+        log_max_height: usize,
+        num_polys: usize,
+    ) -> PcsSoundness {
+        let log_blowup = self.log_blowup as f64;
+        let log_height = log_max_height as f64;
+
+        // The arities of the folding rounds, folding by the maximum arity until the final height.
+        let folding_factors = self
+            .log_arities(&[log_max_height])
+            .into_iter()
+            .map(|log_arity| (1 << log_arity) - 1)
+            .sum::<usize>();
+
+        // The log of the error of one batching or folding challenge, for each coefficient in the
+        // random linear combination beyond the first, and the log of the chance that one query
+        // accepts a word far from the code.
+        let (log_batching_error, log_folding_error, log_query_error) = match regime {
+            ProximityRegime::UniqueDecoding => (
+                log_height - challenge_bits,
+                log_height - challenge_bits,
+                log2_f64(1.0 + 1.0 / self.blowup() as f64) - 1.0,
+            ),
+            ProximityRegime::Johnson { m } => {
+                let m_plus_half = log2_f64(m as f64 + 0.5);
+                (
+                    7.0 * m_plus_half - log2_f64(3.0) + 1.5 * log_blowup + 2.0 * log_height
+                        - challenge_bits,
+                    log2_f64(2.0 * m as f64 + 1.0)
+                        + log2_f64((1u64 << log_max_height) as f64 + 1.0)
+                        + log_blowup / 2.0
+                        - challenge_bits,
+                    log2_f64(1.0 + 1.0 / (2.0 * m as f64)) - log_blowup / 2.0,
+                )
+            }
+        };
+
+        PcsSoundness {
+            log_list_size: regime.log_list_size(self.log_blowup),
+            batching_bits: if num_polys > 1 {
+                -log_batching_error - log2_f64((num_polys - 1) as f64)
+            } else {
+                f64::INFINITY
+            },
+            commit_phase_bits: if folding_factors > 0 {
+                -log_folding_error - log2_f64(folding_factors as f64)
+            } else {
+                f64::INFINITY
+            },
+            query_phase_bits: -log_query_error * self.num_queries as f64
+                + self.proof_of_work_bits as f64,
+        }
+    }
 }
 
 /// Whereas `FriConfig` encompasses parameters the end user can set, `FriGenericConfig` is
@@ -44,17 +160,18 @@ pub trait FriGenericConfig<F: Field> {
     fn extra_query_index_bits(&self) -> usize;
 
     /// Fold a row, returning a single column.
-    /// Right now the input row will always be 2 columns wide,
-    /// but we may support higher folding arity in the future.
+    /// The input row is `2^log_arity` columns wide, and `log_height` is the log of the number of
+    /// rows, i.e. the height of the folded codeword.
     fn fold_row(
         &self,
         index: usize,
         log_height: usize,
+        log_arity: usize,
         beta: F,
         evals: impl Iterator<Item = F>,
     ) -> F;
 
-    /// Same as applying fold_row to every row, possibly faster.
+    /// Same as applying fold_row to every row, possibly faster. The arity is the width of `m`.
     fn fold_matrix<M: Matrix<F>>(&self, beta: F, m: M) -> Vec<F>;
 }
 
@@ -64,6 +181,7 @@ pub fn create_test_fri_config<Mmcs>(mmcs: Mmcs) -> FriConfig<Mmcs> {
     FriConfig {
         log_blowup: 1,
         log_final_poly_len: 0,
+        max_log_arity: 1,
         num_queries: 2,
         proof_of_work_bits: 1,
         mmcs,
@@ -76,6 +194,7 @@ pub fn create_benchmark_fri_config<Mmcs>(mmcs: Mmcs) -> FriConfig<Mmcs> {
     FriConfig {
         log_blowup: 1,
         log_final_poly_len: 0,
+        max_log_arity: 1,
         num_queries: 100,
         proof_of_work_bits: 16,
         mmcs,
@@ -1,7 +1,7 @@
 use alloc::vec::Vec;
 
-use itertools::Itertools;
-use p3_field::TwoAdicField;
+use itertools::{izip, Itertools};
+use p3_field::{ExtensionField, FieldAlgebra, PackedValue, TwoAdicField};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
@@ -19,6 +19,26 @@ use tracing::instrument;
 /// Expects input to be bit-reversed evaluations.
 #[instrument(skip_all, level = "debug")]
 pub fn fold_even_odd<F: TwoAdicField>(poly: Vec<F>, beta: F) -> Vec<F> {
+    fold_halves::<F, F, _>(beta, RowMajorMatrix::new(poly, 2))
+}
+
+/// Like `fold_even_odd`, for evaluations in an extension `EF` of the two-adic field `F`, folding
+/// several points at once with packed extension arithmetic.
+#[instrument(skip_all, level = "debug")]
+pub fn fold_even_odd_ext<F: TwoAdicField, EF: ExtensionField<F>>(
+    poly: Vec<EF>,
+    beta: EF,
+) -> Vec<EF> {
+    fold_halves::<F, EF, _>(beta, RowMajorMatrix::new(poly, 2))
+}
+
+/// Fold a bit-reversed codeword, given as a matrix of pairs `p(x), p(-x)`, by arity 2.
+pub(crate) fn fold_halves<F, EF, M>(beta: EF, m: M) -> Vec<EF>
+where
+    F: TwoAdicField,
+    EF: ExtensionField<F>,
+    M: Matrix<EF>,
+{
     // We use the fact that
     //     p_e(x^2) = (p(x) + p(-x)) / 2
     //     p_o(x^2) = (p(x) - p(-x)) / (2 x)
@@ -27,29 +47,52 @@ pub fn fold_even_odd<F: TwoAdicField>(poly: Vec<F>, beta: F) -> Vec<F> {
     //     p_o(g^(2i)) = (p(g^i) - p(g^(n/2 + i))) / (2 g^i)
     // so
     //     result(g^(2i)) = p_e(g^(2i)) + beta p_o(g^(2i))
-    //                    = (1/2 + beta/2 g_inv^i) p(g^i)
-    //                    + (1/2 - beta/2 g_inv^i) p(g^(n/2 + i))
-    let m = RowMajorMatrix::new(poly, 2);
-    let g_inv = F::two_adic_generator(log2_strict_usize(m.height()) + 1).inverse();
-    let one_half = F::TWO.inverse();
+    //                    = (p(g^i) + p(g^(n/2 + i))) / 2
+    //                    + beta/2 g_inv^i (p(g^i) - p(g^(n/2 + i)))
+    let height = m.height();
+    let g_inv = F::two_adic_generator(log2_strict_usize(height) + 1).inverse();
+    let one_half = F::ONE.halve();
     let half_beta = beta * one_half;
 
-    // TODO: vectorize this (after we have packed extension fields)
-
-    // beta/2 times successive powers of g_inv
-    let mut powers = g_inv
-        .shifted_powers(half_beta)
-        .take(m.height())
-        .collect_vec();
+    // Successive powers of g_inv, which are in the base field, so that the only extension
+    // multiplication is by beta/2.
+    let mut powers = g_inv.powers().take(height).collect_vec();
     reverse_slice_index_bits(&mut powers);
 
-    m.par_rows()
-        .zip(powers)
-        .map(|(mut row, power)| {
-            let (r0, r1) = row.next_tuple().unwrap();
-            (one_half + power) * r0 + (one_half - power) * r1
-        })
-        .collect()
+    let width = F::Packing::WIDTH;
+    if height % width != 0 {
+        return m
+            .par_rows()
+            .zip(powers)
+            .map(|(mut row, power)| {
+                let (lo, hi) = row.next_tuple().unwrap();
+                (lo + hi) * one_half + half_beta * ((lo - hi) * power)
+            })
+            .collect();
+    }
+
+    // Fold `width` rows at a time with packed extension arithmetic, packing each column of the
+    // rows straight from the matrix.
+    let packed_one_half = F::Packing::from(one_half);
+    let packed_half_beta = EF::ExtensionPacking::from_f(half_beta);
+    let mut folded = EF::zero_vec(height);
+    folded
+        .par_chunks_exact_mut(width)
+        .zip(powers.par_chunks_exact(width))
+        .enumerate()
+        .for_each(|(chunk, (folded, powers))| {
+            let r = chunk * width;
+            let pack_column = |c| {
+                EF::ExtensionPacking::from_base_fn(|i| {
+                    F::Packing::from_fn(|j| m.get(r + j, c).as_base_slice()[i])
+                })
+            };
+            let (lo, hi) = (pack_column(0), pack_column(1));
+            let powers = *F::Packing::from_slice(powers);
+            let result = (lo + hi) * packed_one_half + packed_half_beta * ((lo - hi) * powers);
+            izip!(folded, EF::unpack_ext(result)).for_each(|(folded, result)| *folded = result);
+        });
+    folded
 }
 
 #[cfg(test)]
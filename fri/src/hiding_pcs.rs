@@ -1,9 +1,14 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 use core::fmt::Debug;
 
+use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs, TwoAdicMultiplicativeCoset};
+use p3_commit::{
+    Mmcs, OpenedValues, Pcs, PcsSoundness, PolynomialSpace, ProximityRegime,
+    TwoAdicMultiplicativeCoset,
+};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::dense::RowMajorMatrix;
@@ -14,10 +19,15 @@ use rand::Rng;
 use tracing::instrument;
 
 use crate::verifier::FriError;
-use crate::{BatchOpening, FriConfig, FriProof, TwoAdicFriPcs};
+use crate::{BatchOpening, BatchOpeningError, FriConfig, FriProof, TwoAdicFriPcs};
 
 /// A hiding FRI PCS. Both MMCSs must also be hiding; this is not enforced at compile time so it's
 /// the user's responsibility to configure.
+///
+/// Each committed polynomial `p`, given by its evaluations over a domain `D`, is randomized to
+/// `p + Z_D * r` for a random `r` of degree less than `|D|`. It still agrees with `p` on `D`, but
+/// its openings elsewhere reveal nothing about `p`, so long as there are fewer than `|D|` of them.
+/// The randomized polynomial is committed over a domain of twice the size of `D`.
 #[derive(Debug)]
 pub struct HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R> {
     inner: TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs>,
@@ -26,6 +36,7 @@ pub struct HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R> {
 }
 
 impl<Val, Dft, InputMmcs, FriMmcs, R> HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R> {
+    /// Panics if `fri.max_log_arity` is 0, as FRI must fold by at least 2.
     pub fn new(
         dft: Dft,
         mmcs: InputMmcs,
@@ -42,6 +53,44 @@ impl<Val, Dft, InputMmcs, FriMmcs, R> HidingFriPcs<Val, Dft, InputMmcs, FriMmcs,
     }
 }
 
+impl<Val, Dft, InputMmcs, FriMmcs, R> HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R>
+where
+    Val: TwoAdicField,
+    Standard: Distribution<Val>,
+    Dft: TwoAdicSubgroupDft<Val>,
+    R: Rng + Send + Sync,
+{
+    /// Given the coefficients of `p(s X)` and `r(s X)`, where `s` is the first point of `domain`,
+    /// return the evaluations of `p + Z_D * r` over the domain of twice the size, along with that
+    /// domain. Random codewords are appended, as the inner PCS's FRI batch masks.
+    fn randomize(
+        &self,
+        domain: TwoAdicMultiplicativeCoset<Val>,
+        coeffs: RowMajorMatrix<Val>,
+        mask: RowMajorMatrix<Val>,
+    ) -> (TwoAdicMultiplicativeCoset<Val>, RowMajorMatrix<Val>) {
+        // `Z_D(s X) = X^n - 1`, so `p + Z_D * r` has coefficients `p - r` followed by `r`.
+        let mut values = coeffs.values;
+        for (v, &r) in values.iter_mut().zip(&mask.values) {
+            *v -= r;
+        }
+        values.extend(mask.values);
+        let coeffs = RowMajorMatrix::new(values, mask.width);
+        let evals = self.inner.dft.dft_batch(coeffs).to_row_major_matrix();
+
+        let randomized_domain = TwoAdicMultiplicativeCoset {
+            log_n: domain.log_n + 1,
+            shift: domain.shift,
+        };
+        let evals = add_random_cols(
+            evals,
+            self.num_random_codewords,
+            &mut *self.rng.borrow_mut(),
+        );
+        (randomized_domain, evals)
+    }
+}
+
 impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger, R> Pcs<Challenge, Challenger>
     for HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R>
 where
@@ -64,7 +113,35 @@ where
         OpenedValues<Challenge>,
         FriProof<Challenge, FriMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>,
     );
-    type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
+    type Error = FriError<FriMmcs::Error, BatchOpeningError<InputMmcs::Error>>;
+
+    const ZK: bool = true;
+
+    fn parameters(&self) -> Vec<usize> {
+        let mut parameters = <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<
+            Challenge,
+            Challenger,
+        >>::parameters(&self.inner);
+        parameters.push(self.num_random_codewords);
+        parameters
+    }
+
+    fn proven_soundness(
+        &self,
+        regime: ProximityRegime,
+        challenge_bits: f64,
+        log_max_degree: usize,
+        num_polys: usize,
+    ) -> Option<PcsSoundness> {
+        // The random codewords are batched into the low-degree test along with everything else.
+        <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::proven_soundness(
+            &self.inner,
+            regime,
+            challenge_bits,
+            log_max_degree,
+            num_polys + self.num_random_codewords,
+        )
+    }
 
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::natural_domain_for_degree(
@@ -78,10 +155,10 @@ where
         let randomized_evaluations = evaluations
             .into_iter()
             .map(|(domain, mat)| {
-                (
-                    domain,
-                    add_random_cols(mat, self.num_random_codewords, &mut *self.rng.borrow_mut()),
-                )
+                let coeffs = self.inner.dft.idft_batch(mat);
+                let mask =
+                    random_matrix(coeffs.height(), coeffs.width(), &mut *self.rng.borrow_mut());
+                self.randomize(domain, coeffs, mask)
             })
             .collect();
         <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::commit(
@@ -90,6 +167,64 @@ where
         )
     }
 
+    fn commit_quotients(
+        &self,
+        quotients: Vec<(Self::Domain, RowMajorMatrix<Val>, usize)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let mut randomized_chunks = vec![];
+        for (quotient_domain, evaluations, num_chunks) in quotients {
+            assert!(
+                num_chunks > 1,
+                "a hiding PCS needs at least two quotient chunks to randomize"
+            );
+            let chunk_domains = quotient_domain.split_domains(num_chunks);
+            let chunks = quotient_domain.split_evals(num_chunks, evaluations);
+
+            // The verifier recombines the chunks as `sum_i L_i(X) q_i(X)`, where
+            // `L_i = prod_{j != i} Z_{D_j}(X) / k_i` and `k_i = prod_{j != i} Z_{D_j}(s_i)`, with
+            // `s_i` the first point of `D_i`. Adding `k_i Z_{D_i} u_i` to each chunk adds
+            // `Z_D sum_i u_i` to the recombined quotient, which is zero if the `u_i` sum to zero.
+            let k = chunk_domains
+                .iter()
+                .enumerate()
+                .map(|(i, domain)| {
+                    chunk_domains
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, other)| other.zp_at_point(domain.first_point()))
+                        .product::<Val>()
+                })
+                .collect_vec();
+            let height = chunks[0].height();
+            let width = chunks[0].width();
+            let mut masks = (1..num_chunks)
+                .map(|_| random_matrix(height, width, &mut *self.rng.borrow_mut()))
+                .collect_vec();
+            let mut last_mask = RowMajorMatrix::new(Val::zero_vec(height * width), width);
+            for mask in &masks {
+                for (last, &v) in last_mask.values.iter_mut().zip(&mask.values) {
+                    *last -= v;
+                }
+            }
+            masks.push(last_mask);
+
+            for (domain, chunk, mut mask, k) in izip!(chunk_domains, chunks, masks, k) {
+                // We work with polynomials over the unshifted subgroup, i.e. `p(s_i X)`, so each
+                // coefficient of `u_i` is scaled by a power of `s_i`, as well as by `k_i`.
+                for (row, power) in mask.rows_mut().zip(domain.first_point().shifted_powers(k)) {
+                    row.iter_mut().for_each(|v| *v *= power);
+                }
+                let coeffs = self.inner.dft.idft_batch(chunk);
+                randomized_chunks.push(self.randomize(domain, coeffs, mask));
+            }
+        }
+        <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::commit(
+            &self.inner,
+            randomized_chunks,
+        )
+    }
+
     fn get_evaluations_on_domain<'a>(
         &self,
         prover_data: &'a Self::ProverData,
@@ -168,6 +303,12 @@ where
         challenger: &mut Challenger,
     ) -> Result<(), Self::Error> {
         let (opened_values_for_rand_cws, inner_proof) = proof;
+        // Every polynomial was committed over a domain of twice the size of its original domain.
+        for (_, mats) in rounds.iter_mut() {
+            for (domain, _) in mats.iter_mut() {
+                domain.log_n += 1;
+            }
+        }
         // Now we merge `opened_values_for_rand_cws` into the opened values in `rounds`, undoing
         // the split that we did in `open`, to get a complete set of opened values for the inner PCS
         // to check.
@@ -182,6 +323,15 @@ where
     }
 }
 
+fn random_matrix<Val, R>(height: usize, width: usize, rng: &mut R) -> RowMajorMatrix<Val>
+where
+    Val: Field,
+    R: Rng,
+    Standard: Distribution<Val>,
+{
+    RowMajorMatrix::new((0..height * width).map(|_| rng.gen()).collect(), width)
+}
+
 #[instrument(level = "debug", skip_all)]
 fn add_random_cols<Val, R>(
     mat: RowMajorMatrix<Val>,
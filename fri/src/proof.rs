@@ -11,30 +11,22 @@ use serde::{Deserialize, Serialize};
 ))]
 pub struct FriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
-    pub query_proofs: Vec<QueryProof<F, M, InputProof>>,
+    /// Openings of the inputs at every query index.
+    pub input_proof: InputProof,
+    /// For each commit phase commitment, openings of its codeword at every query index.
+    pub commit_phase_openings: Vec<CommitPhaseOpenings<F, M>>,
     pub final_poly: Vec<F>,
     pub pow_witness: Witness,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(bound(
-    serialize = "InputProof: Serialize",
-    deserialize = "InputProof: Deserialize<'de>",
-))]
-pub struct QueryProof<F: Field, M: Mmcs<F>, InputProof> {
-    pub input_proof: InputProof,
-    /// For each commit phase commitment, this contains openings of a commit phase codeword at the
-    /// queried location, along with an opening proof.
-    pub commit_phase_openings: Vec<CommitPhaseProofStep<F, M>>,
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct CommitPhaseProofStep<F: Field, M: Mmcs<F>> {
-    /// The opening of the commit phase codeword at the sibling location.
-    // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
-    // folding arities besides 2, meaning that there can be multiple siblings.
-    pub sibling_value: F,
+pub struct CommitPhaseOpenings<F: Field, M: Mmcs<F>> {
+    /// For each query, the openings of the commit phase codeword at the sibling locations, i.e.
+    /// the row containing the queried location with the queried location itself removed.
+    pub sibling_values: Vec<Vec<F>>,
 
-    pub opening_proof: M::Proof,
+    /// A single proof for the rows of every query, which shares the Merkle paths of queries that
+    /// are close together.
+    pub opening_proof: M::MultiProof,
 }
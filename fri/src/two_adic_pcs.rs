@@ -6,15 +6,18 @@ use core::marker::PhantomData;
 
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs, PolynomialSpace, TwoAdicMultiplicativeCoset};
+use p3_commit::{
+    Mmcs, OpenedValues, Pcs, PcsSoundness, PolynomialSpace, ProximityRegime,
+    TwoAdicMultiplicativeCoset,
+};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::{
     batch_multiplicative_inverse, cyclic_subgroup_coset_known_order, dot_product, ExtensionField,
-    Field, TwoAdicField,
+    Field, FieldAlgebra, PackedValue, TwoAdicField,
 };
 use p3_interpolation::interpolate_coset;
 use p3_matrix::bitrev::{BitReversableMatrix, BitReversalPerm};
-use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::dense::{DenseMatrix, InMemory, RowMajorMatrix, RowMajorMatrixView, StorageBackend};
 use p3_matrix::{Dimensions, Matrix};
 use p3_maybe_rayon::prelude::*;
 use p3_util::linear_map::LinearMap;
@@ -22,23 +25,56 @@ use p3_util::{log2_strict_usize, reverse_bits_len, reverse_slice_index_bits, Vec
 use serde::{Deserialize, Serialize};
 use tracing::{info_span, instrument};
 
+use crate::fold_even_odd::fold_halves;
 use crate::verifier::{self, FriError};
-use crate::{prover, FriConfig, FriGenericConfig, FriProof};
+use crate::{fold_even_odd_ext, prover, FriConfig, FriGenericConfig, FriProof};
 
+/// A PCS which commits to the low-degree extensions of the given evaluations, and proves openings
+/// with FRI.
+///
+/// The LDEs are kept in the prover data until they're opened, in storage from `LdeStorage`, which
+/// is in memory by default.
 #[derive(Debug)]
-pub struct TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
-    dft: Dft,
+pub struct TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage = InMemory> {
+    pub(crate) dft: Dft,
     mmcs: InputMmcs,
     fri: FriConfig<FriMmcs>,
+    lde_storage: LdeStorage,
     _phantom: PhantomData<Val>,
 }
 
 impl<Val, Dft, InputMmcs, FriMmcs> TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
+    /// Panics if `fri.max_log_arity` is 0, as FRI must fold by at least 2.
     pub const fn new(dft: Dft, mmcs: InputMmcs, fri: FriConfig<FriMmcs>) -> Self {
+        assert!(fri.max_log_arity >= 1, "FRI must fold by at least 2");
         Self {
             dft,
             mmcs,
             fri,
+            lde_storage: InMemory,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
+    TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
+{
+    /// Keep committed LDEs in `lde_storage` rather than in memory, e.g. on disk with an
+    /// `MmapBackend`.
+    ///
+    /// `commit` panics if the storage can't hold an LDE, e.g. if an `MmapBackend` fails to spill
+    /// it because the disk is full.
+    #[must_use]
+    pub fn with_lde_storage<S>(
+        self,
+        lde_storage: S,
+    ) -> TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, S> {
+        TwoAdicFriPcs {
+            dft: self.dft,
+            mmcs: self.mmcs,
+            fri: self.fri,
+            lde_storage,
             _phantom: PhantomData,
         }
     }
@@ -47,19 +83,23 @@ impl<Val, Dft, InputMmcs, FriMmcs> TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
 pub struct BatchOpening<Val: Field, InputMmcs: Mmcs<Val>> {
-    pub opened_values: Vec<Vec<Val>>,
-    pub opening_proof: <InputMmcs as Mmcs<Val>>::Proof,
+    /// For each query, the opened rows of each matrix in the batch.
+    pub opened_values: Vec<Vec<Vec<Val>>>,
+    pub opening_proof: <InputMmcs as Mmcs<Val>>::MultiProof,
 }
 
-pub struct TwoAdicFriGenericConfig<InputProof, InputError>(
-    pub PhantomData<(InputProof, InputError)>,
+pub struct TwoAdicFriGenericConfig<Val, InputProof, InputError>(
+    pub PhantomData<(Val, InputProof, InputError)>,
 );
 
 pub type TwoAdicFriGenericConfigForMmcs<F, M> =
-    TwoAdicFriGenericConfig<Vec<BatchOpening<F, M>>, <M as Mmcs<F>>::Error>;
+    TwoAdicFriGenericConfig<F, Vec<BatchOpening<F, M>>, BatchOpeningError<<M as Mmcs<F>>::Error>>;
 
-impl<F: TwoAdicField, InputProof, InputError: Debug> FriGenericConfig<F>
-    for TwoAdicFriGenericConfig<InputProof, InputError>
+impl<Val, F, InputProof, InputError: Debug> FriGenericConfig<F>
+    for TwoAdicFriGenericConfig<Val, InputProof, InputError>
+where
+    Val: TwoAdicField,
+    F: TwoAdicField + ExtensionField<Val>,
 {
     type InputProof = InputProof;
     type InputError = InputError;
@@ -72,17 +112,15 @@ impl<F: TwoAdicField, InputProof, InputError: Debug> FriGenericConfig<F>
         &self,
         index: usize,
         log_height: usize,
-        beta: F,
+        log_arity: usize,
+        mut beta: F,
         evals: impl Iterator<Item = F>,
     ) -> F {
-        let arity = 2;
-        let log_arity = 1;
-        let (e0, e1) = evals
-            .collect_tuple()
-            .expect("TwoAdicFriFolder only supports arity=2");
-        // If performance critical, make this API stateful to avoid this
-        // This is a bit more math than is necessary, but leaving it here
-        // in case we want higher arity in the future
+        let arity = 1 << log_arity;
+        let mut evals = evals.collect_vec();
+        assert_eq!(evals.len(), arity, "row width must match the folding arity");
+        // The row holds evaluations on the coset `s <w>` of the `arity`-th roots of unity `<w>`,
+        // in bit-reversed order.
         let subgroup_start = F::two_adic_generator(log_height + log_arity)
             .exp_u64(reverse_bits_len(index, log_height) as u64);
         let mut xs = F::two_adic_generator(log_arity)
@@ -90,51 +128,48 @@ impl<F: TwoAdicField, InputProof, InputError: Debug> FriGenericConfig<F>
             .take(arity)
             .collect_vec();
         reverse_slice_index_bits(&mut xs);
-        assert_eq!(log_arity, 1, "can only interpolate two points for now");
-        // interpolate and evaluate at beta
-        e0 + (beta - xs[0]) * (e1 - e0) / (xs[1] - xs[0])
+        // Fold in halves, as arity 2 folding would over `log_arity` rounds with challenges
+        // `beta, beta^2, beta^4, ...`. In bit-reversed order, adjacent points are `x` and `-x`, and
+        // folding them interpolates a line through the two evaluations and evaluates it at beta.
+        while evals.len() > 1 {
+            (evals, xs) = evals
+                .chunks_exact(2)
+                .zip(xs.chunks_exact(2))
+                .map(|(e, x)| {
+                    (
+                        e[0] + (beta - x[0]) * (e[1] - e[0]) / (x[1] - x[0]),
+                        x[0].square(),
+                    )
+                })
+                .unzip();
+            beta = beta.square();
+        }
+        evals[0]
     }
 
     fn fold_matrix<M: Matrix<F>>(&self, beta: F, m: M) -> Vec<F> {
-        // We use the fact that
-        //     p_e(x^2) = (p(x) + p(-x)) / 2
-        //     p_o(x^2) = (p(x) - p(-x)) / (2 x)
-        // that is,
-        //     p_e(g^(2i)) = (p(g^i) + p(g^(n/2 + i))) / 2
-        //     p_o(g^(2i)) = (p(g^i) - p(g^(n/2 + i))) / (2 g^i)
-        // so
-        //     result(g^(2i)) = p_e(g^(2i)) + beta p_o(g^(2i))
-        //                    = (1/2 + beta/2 g_inv^i) p(g^i)
-        //                    + (1/2 - beta/2 g_inv^i) p(g^(n/2 + i))
-        let g_inv = F::two_adic_generator(log2_strict_usize(m.height()) + 1).inverse();
-        let one_half = F::ONE.halve();
-        let half_beta = beta * one_half;
-
-        // TODO: vectorize this (after we have packed extension fields)
-
-        // beta/2 times successive powers of g_inv
-        let mut powers = g_inv
-            .shifted_powers(half_beta)
-            .take(m.height())
-            .collect_vec();
-        reverse_slice_index_bits(&mut powers);
-
-        m.par_rows()
-            .zip(powers)
-            .map(|(mut row, power)| {
-                let (lo, hi) = row.next_tuple().unwrap();
-                (one_half + power) * lo + (one_half - power) * hi
-            })
-            .collect()
+        let log_arity = log2_strict_usize(m.width());
+        // In bit-reversed order, the rows of `m` laid end to end are the codeword, so folding by
+        // a higher arity is the same as folding in halves repeatedly with `beta, beta^2, ...`.
+        let mut folded = if log_arity == 1 {
+            fold_halves::<Val, F, _>(beta, m)
+        } else {
+            fold_even_odd_ext::<Val, F>(m.to_row_major_matrix().values, beta)
+        };
+        for i in 1..log_arity {
+            folded = fold_even_odd_ext::<Val, F>(folded, beta.exp_power_of_2(i));
+        }
+        folded
     }
 }
 
-impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
-    for TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs>
+impl<Val, Dft, InputMmcs, FriMmcs, LdeStorage, Challenge, Challenger> Pcs<Challenge, Challenger>
+    for TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
 where
     Val: TwoAdicField,
     Dft: TwoAdicSubgroupDft<Val>,
     InputMmcs: Mmcs<Val>,
+    LdeStorage: StorageBackend<Val>,
     FriMmcs: Mmcs<Challenge>,
     Challenge: TwoAdicField + ExtensionField<Val>,
     Challenger:
@@ -142,9 +177,28 @@ where
 {
     type Domain = TwoAdicMultiplicativeCoset<Val>;
     type Commitment = InputMmcs::Commitment;
-    type ProverData = InputMmcs::ProverData<RowMajorMatrix<Val>>;
+    type ProverData = InputMmcs::ProverData<DenseMatrix<Val, LdeStorage::Storage>>;
     type Proof = FriProof<Challenge, FriMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>;
-    type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
+    type Error = FriError<FriMmcs::Error, BatchOpeningError<InputMmcs::Error>>;
+
+    fn parameters(&self) -> Vec<usize> {
+        self.fri.parameters()
+    }
+
+    fn proven_soundness(
+        &self,
+        regime: ProximityRegime,
+        challenge_bits: f64,
+        log_max_degree: usize,
+        num_polys: usize,
+    ) -> Option<PcsSoundness> {
+        Some(self.fri.proven_soundness(
+            regime,
+            challenge_bits,
+            log_max_degree + self.fri.log_blowup,
+            num_polys,
+        ))
+    }
 
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         let log_n = log2_strict_usize(degree);
@@ -164,10 +218,12 @@ where
                 assert_eq!(domain.size(), evals.height());
                 let shift = Val::GENERATOR / domain.shift;
                 // Commit to the bit-reversed LDE.
-                self.dft
+                let lde = self
+                    .dft
                     .coset_lde_batch(evals, self.fri.log_blowup, shift)
                     .bit_reverse_rows()
-                    .to_row_major_matrix()
+                    .to_row_major_matrix();
+                DenseMatrix::new(self.lde_storage.store(lde.values), lde.width)
             })
             .collect();
 
@@ -283,32 +339,56 @@ where
                     let _guard =
                         info_span!("reduce matrix quotient", dims = %mat.dimensions()).entered();
 
-                    // Use Barycentric interpolation to evaluate the matrix at the given point.
-                    let ys = info_span!("compute opened values with Lagrange interpolation")
-                        .in_scope(|| {
-                            let h = mat.height() >> self.fri.log_blowup;
-                            let (low_coset, _) = mat.split_rows(h);
-                            let mut inv_denoms = inv_denoms.get(&point).unwrap()[..h].to_vec();
-                            reverse_slice_index_bits(&mut inv_denoms);
-                            interpolate_coset(
-                                &BitReversalPerm::new_view(low_coset),
-                                Val::GENERATOR,
-                                point,
-                                Some(&inv_denoms),
-                            )
-                        });
+                    let ys = interpolate_lde_at_point(
+                        &mat,
+                        self.fri.log_blowup,
+                        point,
+                        inv_denoms.get(&point).unwrap(),
+                    );
 
                     let alpha_pow_offset = alpha.exp_u64(num_reduced[log_height] as u64);
                     let reduced_ys: Challenge = dot_product(alpha.powers(), ys.iter().copied());
+                    // This might be longer, but we only need the smaller subgroup's prefix
+                    // (which is ok because it's bitrev)
+                    let inv_denoms = &inv_denoms.get(&point).unwrap()[..mat.height()];
 
                     info_span!("reduce rows").in_scope(|| {
-                        mat.dot_ext_powers(alpha)
-                            .zip(reduced_opening_for_log_height.par_iter_mut())
-                            // This might be longer, but zip will truncate to smaller subgroup
-                            // (which is ok because it's bitrev)
-                            .zip(inv_denoms.get(&point).unwrap().par_iter())
-                            .for_each(|((reduced_row, ro), &inv_denom)| {
-                                *ro += alpha_pow_offset * (reduced_ys - reduced_row) * inv_denom
+                        let packing_width = <Val as Field>::Packing::WIDTH;
+                        if mat.height() % packing_width != 0 {
+                            mat.dot_ext_powers(alpha)
+                                .zip(reduced_opening_for_log_height.par_iter_mut())
+                                .zip(inv_denoms.par_iter())
+                                .for_each(|((reduced_row, ro), &inv_denom)| {
+                                    *ro += alpha_pow_offset * (reduced_ys - reduced_row) * inv_denom
+                                });
+                            return;
+                        }
+
+                        // Reduce `packing_width` rows at a time with packed extension arithmetic,
+                        // packing each column vertically.
+                        let packed_alpha_powers = alpha
+                            .powers()
+                            .take(mat.width())
+                            .map(Challenge::ExtensionPacking::from_f)
+                            .collect_vec();
+                        let packed_alpha_pow_offset =
+                            Challenge::ExtensionPacking::from_f(alpha_pow_offset);
+                        let packed_reduced_ys = Challenge::ExtensionPacking::from_f(reduced_ys);
+                        reduced_opening_for_log_height
+                            .par_chunks_exact_mut(packing_width)
+                            .zip(inv_denoms.par_chunks_exact(packing_width))
+                            .enumerate()
+                            .for_each(|(i, (ro, inv_denom))| {
+                                let reduced_rows: Challenge::ExtensionPacking = dot_product(
+                                    packed_alpha_powers.iter().copied(),
+                                    mat.vertically_packed_row::<Val::Packing>(i * packing_width),
+                                );
+                                let packed_ro = Challenge::pack_ext_slice(ro)
+                                    + packed_alpha_pow_offset
+                                        * (packed_reduced_ys - reduced_rows)
+                                        * Challenge::pack_ext_slice(inv_denom);
+                                izip!(ro, Challenge::unpack_ext(packed_ro))
+                                    .for_each(|(ro, packed_ro)| *ro = packed_ro);
                             });
                     });
 
@@ -323,14 +403,18 @@ where
         let g: TwoAdicFriGenericConfigForMmcs<Val, InputMmcs> =
             TwoAdicFriGenericConfig(PhantomData);
 
-        let fri_proof = prover::prove(&g, &self.fri, fri_input, challenger, |index| {
+        let fri_proof = prover::prove(&g, &self.fri, fri_input, challenger, |indices| {
             rounds
                 .iter()
                 .map(|(data, _)| {
                     let log_max_height = log2_strict_usize(self.mmcs.get_max_height(data));
                     let bits_reduced = log_global_max_height - log_max_height;
-                    let reduced_index = index >> bits_reduced;
-                    let (opened_values, opening_proof) = self.mmcs.open_batch(reduced_index, data);
+                    let reduced_indices = indices
+                        .iter()
+                        .map(|index| index >> bits_reduced)
+                        .collect_vec();
+                    let (opened_values, opening_proof) =
+                        self.mmcs.open_multi_batch(&reduced_indices, data);
                     BatchOpening {
                         opened_values,
                         opening_proof,
@@ -363,93 +447,246 @@ where
         proof: &Self::Proof,
         challenger: &mut Challenger,
     ) -> Result<(), Self::Error> {
+        let widths = rounds
+            .iter()
+            .map(|(_, mats)| claimed_widths(mats))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(FriError::InvalidProofShape)?;
+        // Every round must be opened, or its claims would go unchecked.
+        if proof.input_proof.len() != rounds.len() {
+            return Err(FriError::InvalidProofShape);
+        }
+
         // Batch combination challenge
         let alpha: Challenge = challenger.sample_ext_element();
 
-        let log_global_max_height =
-            proof.commit_phase_commits.len() + self.fri.log_blowup + self.fri.log_final_poly_len;
+        // FRI has one input per distinct matrix height, as in `open`.
+        let log_input_heights = rounds
+            .iter()
+            .flat_map(|(_, mats)| mats)
+            .map(|(domain, _)| log2_strict_usize(domain.size()) + self.fri.log_blowup)
+            .sorted()
+            .rev()
+            .dedup()
+            .collect_vec();
+        let log_global_max_height = *log_input_heights
+            .first()
+            .ok_or(FriError::InvalidProofShape)?;
 
         let g: TwoAdicFriGenericConfigForMmcs<Val, InputMmcs> =
             TwoAdicFriGenericConfig(PhantomData);
 
-        verifier::verify(&g, &self.fri, proof, challenger, |index, input_proof| {
-            // TODO: separate this out into functions
-
-            // log_height -> (alpha_pow, reduced_opening)
-            let mut reduced_openings = BTreeMap::<usize, (Challenge, Challenge)>::new();
-
-            for (batch_opening, (batch_commit, mats)) in izip!(input_proof, &rounds) {
-                let batch_heights = mats
-                    .iter()
-                    .map(|(domain, _)| domain.size() << self.fri.log_blowup)
-                    .collect_vec();
-                let batch_dims = batch_heights
-                    .iter()
-                    // TODO: MMCS doesn't really need width; we put 0 for now.
-                    .map(|&height| Dimensions { width: 0, height })
-                    .collect_vec();
-
-                let batch_max_height = batch_heights.iter().max().expect("Empty batch?");
-                let log_batch_max_height = log2_strict_usize(*batch_max_height);
-                let bits_reduced = log_global_max_height - log_batch_max_height;
-                let reduced_index = index >> bits_reduced;
-
-                self.mmcs.verify_batch(
-                    batch_commit,
-                    &batch_dims,
-                    reduced_index,
-                    &batch_opening.opened_values,
-                    &batch_opening.opening_proof,
-                )?;
-                for (mat_opening, (mat_domain, mat_points_and_values)) in
-                    izip!(&batch_opening.opened_values, mats)
+        verifier::verify(
+            &g,
+            &self.fri,
+            &log_input_heights,
+            proof,
+            challenger,
+            |indices, input_proof| {
+                // TODO: separate this out into functions
+
+                // For each query, log_height -> (alpha_pow, reduced_opening)
+                let mut reduced_openings =
+                    vec![BTreeMap::<usize, (Challenge, Challenge)>::new(); indices.len()];
+
+                for (batch_opening, (batch_commit, mats), widths) in
+                    izip!(input_proof, &rounds, &widths)
                 {
-                    let log_height = log2_strict_usize(mat_domain.size()) + self.fri.log_blowup;
-
-                    let bits_reduced = log_global_max_height - log_height;
-                    let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
-
-                    // todo: this can be nicer with domain methods?
-
-                    let x = Val::GENERATOR
-                        * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
-
-                    let (alpha_pow, ro) = reduced_openings
-                        .entry(log_height)
-                        .or_insert((Challenge::ONE, Challenge::ZERO));
-
-                    for (z, ps_at_z) in mat_points_and_values {
-                        for (&p_at_x, &p_at_z) in izip!(mat_opening, ps_at_z) {
-                            let quotient = (-p_at_z + p_at_x) / (-*z + x);
-                            *ro += *alpha_pow * quotient;
-                            *alpha_pow *= alpha;
+                    let xs = verify_batch_opening(
+                        &self.mmcs,
+                        self.fri.log_blowup,
+                        log_global_max_height,
+                        batch_commit,
+                        mats,
+                        widths,
+                        indices,
+                        batch_opening,
+                    )?;
+                    for (query_opening, xs, reduced_openings) in
+                        izip!(&batch_opening.opened_values, xs, &mut reduced_openings)
+                    {
+                        for (mat_opening, x, (mat_domain, mat_points_and_values)) in
+                            izip!(query_opening, xs, mats)
+                        {
+                            let log_height =
+                                log2_strict_usize(mat_domain.size()) + self.fri.log_blowup;
+                            let (alpha_pow, ro) = reduced_openings
+                                .entry(log_height)
+                                .or_insert((Challenge::ONE, Challenge::ZERO));
+
+                            for (z, ps_at_z) in mat_points_and_values {
+                                for (&p_at_x, &p_at_z) in izip!(mat_opening, ps_at_z) {
+                                    let quotient = (-p_at_z + p_at_x) / (-*z + x);
+                                    *ro += *alpha_pow * quotient;
+                                    *alpha_pow *= alpha;
+                                }
+                            }
                         }
                     }
                 }
-            }
 
-            // `reduced_openings` would have a log_height = log_blowup entry only if there was a
-            // trace matrix of height 1. In this case the reduced opening can be skipped as it will
-            // not be checked against any commit phase commit.
-            if let Some((_alpha_pow, ro)) = reduced_openings.remove(&self.fri.log_blowup) {
-                debug_assert!(ro.is_zero());
-            }
+                Ok(reduced_openings
+                    .into_iter()
+                    .map(|mut reduced_openings| {
+                        // `reduced_openings` would have a log_height = log_blowup entry only if there
+                        // was a trace matrix of height 1. In this case the reduced opening can be
+                        // skipped as it will not be checked against any commit phase commit.
+                        if let Some((_alpha_pow, ro)) =
+                            reduced_openings.remove(&self.fri.log_blowup)
+                        {
+                            debug_assert!(ro.is_zero());
+                        }
 
-            // Return reduced openings descending by log_height.
-            Ok(reduced_openings
-                .into_iter()
-                .rev()
-                .map(|(log_height, (_alpha_pow, ro))| (log_height, ro))
-                .collect())
+                        // Return reduced openings descending by log_height.
+                        reduced_openings
+                            .into_iter()
+                            .rev()
+                            .map(|(log_height, (_alpha_pow, ro))| (log_height, ro))
+                            .collect()
+                    })
+                    .collect())
+            },
+        )
+    }
+}
+
+/// The claimed evaluations of each matrix of a round: its domain, and the values of its columns at
+/// each opening point.
+type MatClaims<Val, Challenge> = (
+    TwoAdicMultiplicativeCoset<Val>,
+    Vec<(Challenge, Vec<Challenge>)>,
+);
+
+/// Evaluate the polynomials whose LDEs over `Val::GENERATOR <w>` with blowup `2^log_blowup` are
+/// the columns of `mat`, in bit-reversed order, at `point`, by barycentric interpolation.
+///
+/// `inv_denoms` holds `1 / (point - x)` for the points `x` of the LDE's domain, or of a larger one,
+/// in bit-reversed order, as computed by `compute_inverse_denominators`.
+pub fn interpolate_lde_at_point<Val, Challenge>(
+    mat: &RowMajorMatrixView<'_, Val>,
+    log_blowup: usize,
+    point: Challenge,
+    inv_denoms: &[Challenge],
+) -> Vec<Challenge>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val>,
+{
+    info_span!("compute opened values with Lagrange interpolation").in_scope(|| {
+        let h = mat.height() >> log_blowup;
+        let (low_coset, _) = mat.split_rows(h);
+        let mut inv_denoms = inv_denoms[..h].to_vec();
+        reverse_slice_index_bits(&mut inv_denoms);
+        interpolate_coset(
+            &BitReversalPerm::new_view(low_coset),
+            Val::GENERATOR,
+            point,
+            Some(&inv_denoms),
+        )
+    })
+}
+
+/// The width of each of a round's matrices, given its domain and its claimed evaluations at each
+/// opening point, as one value per column at every point.
+///
+/// Returns `None` if a matrix isn't opened at any point, or if the number of values claimed at its
+/// points differ, so that the widths never have to be taken from the proof.
+pub fn claimed_widths<Domain, Challenge>(
+    mats: &[(Domain, Vec<(Challenge, Vec<Challenge>)>)],
+) -> Option<Vec<usize>> {
+    mats.iter()
+        .map(|(_, points_and_values)| {
+            let (_, first_values) = points_and_values.first()?;
+            let width = first_values.len();
+            points_and_values
+                .iter()
+                .all(|(_, values)| values.len() == width)
+                .then_some(width)
         })
-        .expect("fri err");
+        .collect()
+}
+
+/// An error from verifying a `BatchOpening`.
+#[derive(Debug)]
+pub enum BatchOpeningError<MmcsError> {
+    /// The opening doesn't have exactly one row of each matrix for each query.
+    InvalidProofShape,
+    MmcsError(MmcsError),
+}
 
-        Ok(())
+/// Verify a batch opening of the LDEs of a round's matrices, with blowup `2^log_blowup`, at query
+/// indices into the tallest LDE of all rounds, of height `2^log_global_max_height`. `widths` are
+/// the matrices' widths, as returned by `claimed_widths`.
+///
+/// Returns, for each query, the point of each matrix's LDE domain which it opens.
+pub fn verify_batch_opening<Val, Challenge, InputMmcs>(
+    mmcs: &InputMmcs,
+    log_blowup: usize,
+    log_global_max_height: usize,
+    commit: &InputMmcs::Commitment,
+    mats: &[MatClaims<Val, Challenge>],
+    widths: &[usize],
+    indices: &[usize],
+    batch_opening: &BatchOpening<Val, InputMmcs>,
+) -> Result<Vec<Vec<Val>>, BatchOpeningError<InputMmcs::Error>>
+where
+    Val: TwoAdicField,
+    InputMmcs: Mmcs<Val>,
+{
+    if batch_opening.opened_values.len() != indices.len()
+        || batch_opening
+            .opened_values
+            .iter()
+            .any(|query_opening| query_opening.len() != mats.len())
+    {
+        return Err(BatchOpeningError::InvalidProofShape);
     }
+
+    let batch_heights = mats
+        .iter()
+        .map(|(domain, _)| domain.size() << log_blowup)
+        .collect_vec();
+    let batch_dims = izip!(widths, &batch_heights)
+        .map(|(&width, &height)| Dimensions { width, height })
+        .collect_vec();
+
+    let batch_max_height = batch_heights.iter().max().expect("Empty batch?");
+    let log_batch_max_height = log2_strict_usize(*batch_max_height);
+    let bits_reduced = log_global_max_height - log_batch_max_height;
+    let reduced_indices = indices
+        .iter()
+        .map(|index| index >> bits_reduced)
+        .collect_vec();
+
+    mmcs.verify_multi_batch(
+        commit,
+        &batch_dims,
+        &reduced_indices,
+        &batch_opening.opened_values,
+        &batch_opening.opening_proof,
+    )
+    .map_err(BatchOpeningError::MmcsError)?;
+
+    Ok(indices
+        .iter()
+        .map(|&index| {
+            mats.iter()
+                .map(|(mat_domain, _)| {
+                    let log_height = log2_strict_usize(mat_domain.size()) + log_blowup;
+                    let bits_reduced = log_global_max_height - log_height;
+                    let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
+                    Val::GENERATOR
+                        * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64)
+                })
+                .collect()
+        })
+        .collect())
 }
 
+/// For each opening point `z`, compute `1 / (z - x)` for the points `x` of the largest LDE domain
+/// opened at `z`, in bit-reversed order. Smaller domains' values are a prefix.
 #[instrument(skip_all)]
-fn compute_inverse_denominators<F: TwoAdicField, EF: ExtensionField<F>, M: Matrix<F>>(
+pub fn compute_inverse_denominators<F: TwoAdicField, EF: ExtensionField<F>, M: Matrix<F>>(
     mats_and_points: &[(Vec<M>, &Vec<Vec<EF>>)],
     coset_shift: F,
 ) -> LinearMap<EF, Vec<EF>> {
@@ -8,7 +8,7 @@ use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::Dimensions;
 use p3_util::reverse_bits_len;
 
-use crate::{CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof};
+use crate::{CommitPhaseOpenings, FriConfig, FriGenericConfig, FriProof};
 
 #[derive(Debug)]
 pub enum FriError<CommitMmcsErr, InputError> {
@@ -19,12 +19,14 @@ pub enum FriError<CommitMmcsErr, InputError> {
     InvalidPowWitness,
 }
 
+/// Verify a FRI proof for inputs whose log heights are `log_input_heights`, in descending order.
 pub fn verify<G, Val, Challenge, M, Challenger>(
     g: &G,
     config: &FriConfig<M>,
+    log_input_heights: &[usize],
     proof: &FriProof<Challenge, M, Challenger::Witness, G::InputProof>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+    open_input: impl Fn(&[usize], &G::InputProof) -> Result<Vec<Vec<(usize, Challenge)>>, G::InputError>,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
     Val: Field,
@@ -33,10 +35,24 @@ where
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
-    let betas: Vec<Challenge> = proof
-        .commit_phase_commits
-        .iter()
-        .map(|comm| {
+    let log_max_height = *log_input_heights
+        .first()
+        .ok_or(FriError::InvalidProofShape)?;
+    if log_max_height < config.log_blowup + config.log_final_poly_len {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    // The folding schedule is fixed by the configuration and the input heights.
+    let log_arities = config.log_arities(log_input_heights);
+    if proof.commit_phase_commits.len() != log_arities.len()
+        || proof.commit_phase_openings.len() != log_arities.len()
+    {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    let betas: Vec<Challenge> = izip!(&proof.commit_phase_commits, &log_arities)
+        .map(|(comm, &log_arity)| {
+            challenger.observe(Val::from_canonical_usize(log_arity));
             challenger.observe(comm.clone());
             challenger.sample_ext_element()
         })
@@ -48,41 +64,47 @@ where
         .iter()
         .for_each(|x| challenger.observe_ext_element(*x));
 
-    if proof.query_proofs.len() != config.num_queries {
-        return Err(FriError::InvalidProofShape);
-    }
-
     // Check PoW.
     if !challenger.check_witness(config.proof_of_work_bits, proof.pow_witness) {
         return Err(FriError::InvalidPowWitness);
     }
 
-    let log_max_height =
-        proof.commit_phase_commits.len() + config.log_blowup + config.log_final_poly_len;
-
-    for qp in &proof.query_proofs {
-        let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
-        let ro = open_input(index, &qp.input_proof).map_err(FriError::InputError)?;
-
-        debug_assert!(
-            ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
-            "reduced openings sorted by height descending"
-        );
-
-        let folded_eval = verify_query(
-            g,
-            config,
-            index >> g.extra_query_index_bits(),
-            izip!(
-                &betas,
-                &proof.commit_phase_commits,
-                &qp.commit_phase_openings
-            ),
-            ro,
-            log_max_height,
-        )?;
-
-        let final_poly_index = index >> (proof.commit_phase_commits.len());
+    let indices = (0..config.num_queries)
+        .map(|_| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
+        .collect_vec();
+    let reduced_openings =
+        open_input(&indices, &proof.input_proof).map_err(FriError::InputError)?;
+    if reduced_openings.len() != indices.len() {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    debug_assert!(
+        reduced_openings
+            .iter()
+            .all(|ro| ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r)),
+        "reduced openings sorted by height descending"
+    );
+
+    let folded_evals = verify_queries(
+        g,
+        config,
+        indices
+            .iter()
+            .map(|index| index >> g.extra_query_index_bits())
+            .collect(),
+        izip!(
+            &betas,
+            &proof.commit_phase_commits,
+            &log_arities,
+            &proof.commit_phase_openings
+        ),
+        reduced_openings,
+        log_max_height,
+    )?;
+
+    for (index, folded_eval) in izip!(indices, folded_evals) {
+        let final_poly_index =
+            index >> (log_max_height - config.log_blowup - config.log_final_poly_len);
 
         let mut eval = Challenge::ZERO;
 
@@ -110,65 +132,97 @@ where
 type CommitStep<'a, F, M> = (
     &'a F,
     &'a <M as Mmcs<F>>::Commitment,
-    &'a CommitPhaseProofStep<F, M>,
+    &'a usize,
+    &'a CommitPhaseOpenings<F, M>,
 );
 
-fn verify_query<'a, G, F, M>(
+/// Fold every query through the commit phase, checking each round's openings against its
+/// commitment, and return each query's final folded evaluation.
+fn verify_queries<'a, G, F, M>(
     g: &G,
     config: &FriConfig<M>,
-    mut index: usize,
+    mut indices: Vec<usize>,
     steps: impl Iterator<Item = CommitStep<'a, F, M>>,
-    reduced_openings: Vec<(usize, F)>,
+    reduced_openings: Vec<Vec<(usize, F)>>,
     log_max_height: usize,
-) -> Result<F, FriError<M::Error, G::InputError>>
+) -> Result<Vec<F>, FriError<M::Error, G::InputError>>
 where
     F: Field,
     M: Mmcs<F> + 'a,
     G: FriGenericConfig<F>,
 {
-    let mut folded_eval = F::ZERO;
-    let mut ro_iter = reduced_openings.into_iter().peekable();
-
-    for (log_folded_height, (&beta, comm, opening)) in izip!((0..log_max_height).rev(), steps) {
-        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
-            folded_eval += ro;
+    let mut folded_evals = vec![F::ZERO; indices.len()];
+    let mut ro_iters = reduced_openings
+        .into_iter()
+        .map(|ro| ro.into_iter().peekable())
+        .collect_vec();
+    let mut log_height = log_max_height;
+
+    for (&beta, comm, &log_arity, openings) in steps {
+        if openings.sibling_values.len() != indices.len() {
+            return Err(FriError::InvalidProofShape);
         }
 
-        let index_sibling = index ^ 1;
-        let index_pair = index >> 1;
+        let rows = izip!(
+            &mut folded_evals,
+            &mut ro_iters,
+            &indices,
+            &openings.sibling_values
+        )
+        .map(|(folded_eval, ro_iter, &index, sibling_values)| {
+            if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_height) {
+                *folded_eval += ro;
+            }
+
+            if sibling_values.len() != (1 << log_arity) - 1 {
+                return Err(FriError::InvalidProofShape);
+            }
+
+            let mut evals = sibling_values.clone();
+            evals.insert(index % (1 << log_arity), *folded_eval);
+            Ok(vec![evals])
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-        let mut evals = vec![folded_eval; 2];
-        evals[index_sibling % 2] = opening.sibling_value;
+        let log_folded_height = log_height - log_arity;
+        let index_rows = indices.iter().map(|index| index >> log_arity).collect_vec();
 
         let dims = &[Dimensions {
-            width: 2,
+            width: 1 << log_arity,
             height: 1 << log_folded_height,
         }];
         config
             .mmcs
-            .verify_batch(
-                comm,
-                dims,
-                index_pair,
-                &[evals.clone()],
-                &opening.opening_proof,
-            )
+            .verify_multi_batch(comm, dims, &index_rows, &rows, &openings.opening_proof)
             .map_err(FriError::CommitPhaseMmcsError)?;
 
-        index = index_pair;
-
-        folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
+        indices = index_rows;
+        log_height = log_folded_height;
+
+        for (folded_eval, &index, mut row) in izip!(&mut folded_evals, &indices, rows) {
+            *folded_eval = g.fold_row(
+                index,
+                log_height,
+                log_arity,
+                beta,
+                row.pop().unwrap().into_iter(),
+            );
+        }
     }
 
     debug_assert!(
-        index < config.blowup() * config.final_poly_len(),
-        "index was {}",
-        index,
-    );
-    debug_assert!(
-        ro_iter.next().is_none(),
-        "verifier reduced_openings were not in descending order?"
+        indices
+            .iter()
+            .all(|&index| index < config.blowup() * config.final_poly_len()),
+        "indices were {:?}",
+        indices,
     );
 
-    Ok(folded_eval)
+    // Every reduced opening must have been folded in, which fails if the folding arities skipped
+    // past the height of an input.
+    if ro_iters.iter_mut().any(|ro_iter| ro_iter.next().is_some()) {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    Ok(folded_evals)
 }
@@ -7,7 +7,8 @@ use p3_commit::ExtensionMmcs;
 use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
 use p3_field::extension::BinomialExtensionField;
 use p3_field::{Field, FieldAlgebra};
-use p3_fri::{prover, verifier, FriConfig, TwoAdicFriGenericConfig};
+use p3_fri::verifier::FriError;
+use p3_fri::{prover, verifier, FriConfig, FriProof, TwoAdicFriGenericConfig};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::util::reverse_matrix_index_bits;
 use p3_matrix::Matrix;
@@ -28,8 +29,14 @@ type ValMmcs =
 type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
 type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
 type MyFriConfig = FriConfig<ChallengeMmcs>;
-
-fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm, MyFriConfig) {
+/// The input opening proof is just each query's reduced openings, passed through.
+type InputProof = Vec<Vec<(usize, Challenge)>>;
+
+fn get_ldt_for_testing<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+) -> (Perm, MyFriConfig) {
     let perm = Perm::new_from_rng_128(rng);
     let hash = MyHash::new(perm.clone());
     let compress = MyCompress::new(perm.clone());
@@ -37,6 +44,7 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
     let fri_config = FriConfig {
         log_blowup: 1,
         log_final_poly_len,
+        max_log_arity,
         num_queries: 10,
         proof_of_work_bits: 8,
         mmcs,
@@ -44,14 +52,20 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
     (perm, fri_config)
 }
 
-fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
-    let (perm, fc) = get_ldt_for_testing(rng, log_final_poly_len);
+fn do_test_fri_ldt<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+    log_degrees: &[usize],
+) -> FriProof<Challenge, ChallengeMmcs, Val, InputProof> {
+    let (perm, fc) = get_ldt_for_testing(rng, log_final_poly_len, max_log_arity);
     let dft = Radix2Dit::default();
 
     let shift = Val::GENERATOR;
 
-    let ldes: Vec<RowMajorMatrix<Val>> = (5..10)
-        .map(|deg_bits| {
+    let ldes: Vec<RowMajorMatrix<Val>> = log_degrees
+        .iter()
+        .map(|&deg_bits| {
             let evals = RowMajorMatrix::<Val>::rand_nonzero(rng, 1 << deg_bits, 16);
             let mut lde = dft.coset_lde_batch(evals, 1, shift);
             reverse_matrix_index_bits(&mut lde);
@@ -59,7 +73,7 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         })
         .collect();
 
-    let (proof, p_sample) = {
+    let (proof, p_sample, log_input_heights) = {
         // Prover world
         let mut chal = Challenger::new(perm.clone());
         let alpha: Challenge = chal.sample_ext_element();
@@ -90,33 +104,41 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         let log_max_height = log2_strict_usize(input[0].len());
 
         let proof = prover::prove(
-            &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+            &TwoAdicFriGenericConfig::<Val, InputProof, ()>(PhantomData),
             &fc,
             input.clone(),
             &mut chal,
-            |idx| {
+            |indices| {
                 // As our "input opening proof", just pass through the literal reduced openings.
-                let mut ro = vec![];
-                for v in &input {
-                    let log_height = log2_strict_usize(v.len());
-                    ro.push((log_height, v[idx >> (log_max_height - log_height)]));
-                }
-                ro.sort_by_key(|(lh, _)| Reverse(*lh));
-                ro
+                indices
+                    .iter()
+                    .map(|&idx| {
+                        let mut ro = vec![];
+                        for v in &input {
+                            let log_height = log2_strict_usize(v.len());
+                            ro.push((log_height, v[idx >> (log_max_height - log_height)]));
+                        }
+                        ro.sort_by_key(|(lh, _)| Reverse(*lh));
+                        ro
+                    })
+                    .collect()
             },
         );
 
-        (proof, chal.sample_bits(8))
+        let log_input_heights: Vec<usize> =
+            input.iter().map(|v| log2_strict_usize(v.len())).collect();
+        (proof, chal.sample_bits(8), log_input_heights)
     };
 
     let mut v_challenger = Challenger::new(perm);
     let _alpha: Challenge = v_challenger.sample_ext_element();
     verifier::verify(
-        &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+        &TwoAdicFriGenericConfig::<Val, InputProof, ()>(PhantomData),
         &fc,
+        &log_input_heights,
         &proof,
         &mut v_challenger,
-        |_index, proof| Ok(proof.clone()),
+        |_indices, proof| Ok(proof.clone()),
     )
     .unwrap();
 
@@ -125,6 +147,8 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         v_challenger.sample_bits(8),
         "prover and verifier transcript have same state after FRI"
     );
+
+    proof
 }
 
 #[test]
@@ -132,7 +156,67 @@ fn test_fri_ldt() {
     // FRI is kind of flaky depending on indexing luck
     for i in 0..4 {
         let mut rng = ChaCha20Rng::seed_from_u64(i as u64);
-        do_test_fri_ldt(&mut rng, i + 1);
+        do_test_fri_ldt(&mut rng, i + 1, 1, &[5, 6, 7, 8, 9]);
+    }
+}
+
+#[test]
+fn test_fri_ldt_higher_arity() {
+    for max_log_arity in [2, 3, 4] {
+        for i in 0..4 {
+            let mut rng = ChaCha20Rng::seed_from_u64(i as u64);
+            let proof = do_test_fri_ldt(&mut rng, i, max_log_arity, &[5, 9, 14]);
+            let (_, fc) = get_ldt_for_testing(&mut rng, i, max_log_arity);
+            let log_arities = fc.log_arities(&[15, 10, 6]);
+            assert_eq!(proof.commit_phase_commits.len(), log_arities.len());
+            // Rounds fold by the full arity, except where that would skip past an input's height
+            // or the final polynomial's.
+            let mut log_height = 15;
+            for log_arity in log_arities {
+                let next_input = [10, 6].into_iter().find(|&h| h < log_height).unwrap_or(0);
+                let expected = max_log_arity
+                    .min(log_height - next_input)
+                    .min(log_height - 1 - i);
+                assert_eq!(log_arity, expected);
+                log_height -= log_arity;
+            }
+            assert_eq!(log_height, 1 + i);
+        }
+    }
+}
+
+#[test]
+fn test_fri_ldt_rejects_other_schedules() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (perm, fc) = get_ldt_for_testing(&mut rng, 0, 2);
+    // A constant codeword is trivially low degree.
+    let input = vec![rng.gen::<Challenge>(); 1 << 6];
+
+    let g = TwoAdicFriGenericConfig::<Val, InputProof, ()>(PhantomData);
+    let proof = prover::prove(
+        &g,
+        &fc,
+        vec![input.clone()],
+        &mut Challenger::new(perm.clone()),
+        |indices| indices.iter().map(|&idx| vec![(6, input[idx])]).collect(),
+    );
+    assert_eq!(fc.log_arities(&[6]), vec![2, 2, 1]);
+
+    // The verifier derives the schedule from the input heights, so a proof with a round missing,
+    // or one checked against inputs of another height, is rejected.
+    let mut missing_round = proof.clone();
+    missing_round.commit_phase_commits.pop();
+    missing_round.commit_phase_openings.pop();
+    for (log_input_heights, proof) in [(&[6], &missing_round), (&[8], &proof)] {
+        let result = verifier::verify(
+            &g,
+            &fc,
+            log_input_heights,
+            proof,
+            &mut Challenger::new(perm.clone()),
+            |_indices, proof| Ok(proof.clone()),
+        );
+        assert!(matches!(result, Err(FriError::InvalidProofShape)));
     }
 }
 
@@ -143,6 +227,14 @@ fn test_fri_ldt_should_panic() {
     // FRI is kind of flaky depending on indexing luck
     for i in 0..4 {
         let mut rng = ChaCha20Rng::seed_from_u64(i);
-        do_test_fri_ldt(&mut rng, 5);
+        do_test_fri_ldt(&mut rng, 5, 1, &[5, 6, 7, 8, 9]);
     }
 }
+
+#[test]
+#[should_panic(expected = "FRI must fold by at least 2")]
+fn test_fri_rejects_zero_max_arity() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (_, fc) = get_ldt_for_testing(&mut rng, 0, 0);
+    fc.log_arities(&[6]);
+}
@@ -5,7 +5,8 @@ use p3_commit::{ExtensionMmcs, Pcs, PolynomialSpace};
 use p3_dft::Radix2DitParallel;
 use p3_field::extension::BinomialExtensionField;
 use p3_field::{ExtensionField, Field};
-use p3_fri::{FriConfig, TwoAdicFriPcs};
+use p3_fri::verifier::FriError;
+use p3_fri::{BatchOpeningError, FriConfig, TwoAdicFriPcs};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
@@ -168,7 +169,7 @@ mod babybear_fri_pcs {
     type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
     type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
 
-    fn get_pcs(log_blowup: usize) -> (MyPcs, Challenger) {
+    fn get_pcs(log_blowup: usize, max_log_arity: usize) -> (MyPcs, Challenger) {
         let perm = Perm::new_from_rng_128(&mut seeded_rng());
         let hash = MyHash::new(perm.clone());
         let compress = MyCompress::new(perm.clone());
@@ -179,6 +180,7 @@ mod babybear_fri_pcs {
         let fri_config = FriConfig {
             log_blowup,
             log_final_poly_len: 0,
+            max_log_arity,
             num_queries: 10,
             proof_of_work_bits: 8,
             mmcs: challenge_mmcs,
@@ -188,6 +190,130 @@ mod babybear_fri_pcs {
         (pcs, Challenger::new(perm.clone()))
     }
 
+    mod blowup_1 {
+        make_tests_for_pcs!(super::get_pcs(1, 1));
+    }
+    mod blowup_2 {
+        make_tests_for_pcs!(super::get_pcs(2, 1));
+    }
+    mod blowup_1_arity_4 {
+        make_tests_for_pcs!(super::get_pcs(1, 2));
+    }
+    mod blowup_2_arity_16 {
+        make_tests_for_pcs!(super::get_pcs(2, 4));
+    }
+
+    #[test]
+    fn rejects_missing_input_openings() {
+        let (pcs, challenger) = get_pcs(1, 1);
+        let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 8);
+        let mat = RowMajorMatrix::<Val>::rand(&mut seeded_rng(), 8, 3);
+        let (commit, data) =
+            <MyPcs as Pcs<Challenge, Challenger>>::commit(&pcs, vec![(domain, mat)]);
+
+        let mut p_challenger = challenger.clone();
+        p_challenger.observe(commit);
+        let zeta: Challenge = p_challenger.sample_ext_element();
+        let (opened_values, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut p_challenger);
+        let claims = vec![(
+            commit,
+            vec![(domain, vec![(zeta, opened_values[0][0][0].clone())])],
+        )];
+        let verify = |proof: &<MyPcs as Pcs<Challenge, Challenger>>::Proof| {
+            let mut v_challenger = challenger.clone();
+            v_challenger.observe(commit);
+            let _: Challenge = v_challenger.sample_ext_element();
+            pcs.verify(claims.clone(), proof, &mut v_challenger)
+        };
+        assert!(verify(&proof).is_ok());
+
+        let mut no_rounds = proof.clone();
+        no_rounds.input_proof.clear();
+        assert!(matches!(
+            verify(&no_rounds),
+            Err(FriError::InvalidProofShape)
+        ));
+
+        let mut no_matrices = proof;
+        no_matrices.input_proof[0].opened_values[0].clear();
+        assert!(matches!(
+            verify(&no_matrices),
+            Err(FriError::InputError(BatchOpeningError::InvalidProofShape))
+        ));
+    }
+
+    #[test]
+    fn rejects_unopened_matrix() {
+        let (pcs, challenger) = get_pcs(1, 1);
+        let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 8);
+        let mat = RowMajorMatrix::<Val>::rand(&mut seeded_rng(), 8, 3);
+        let (commit, data) =
+            <MyPcs as Pcs<Challenge, Challenger>>::commit(&pcs, vec![(domain, mat)]);
+
+        let mut p_challenger = challenger.clone();
+        let zeta: Challenge = p_challenger.sample_ext_element();
+        let (_, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut p_challenger);
+
+        // The matrix's width can only come from its claimed evaluations, of which there are none.
+        let result = pcs.verify(
+            vec![(commit, vec![(domain, vec![])])],
+            &proof,
+            &mut challenger.clone(),
+        );
+        assert!(matches!(result, Err(FriError::InvalidProofShape)));
+    }
+}
+
+mod babybear_fri_pcs_on_disk {
+    use p3_matrix::mmap::MmapBackend;
+
+    use super::*;
+
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs = MerkleTreeMmcs<
+        <Val as Field>::Packing,
+        <Val as Field>::Packing,
+        MyHash,
+        MyCompress,
+        8,
+        2,
+        MmapBackend,
+    >;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs, MmapBackend>;
+
+    /// Keeps both the LDEs and the Merkle digest layers in memory-mapped files.
+    fn get_pcs(log_blowup: usize) -> (MyPcs, Challenger) {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = MerkleTreeMmcs::new(hash, compress).with_storage(MmapBackend::default());
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let fri_config = FriConfig {
+            log_blowup,
+            log_final_poly_len: 0,
+            max_log_arity: 1,
+            num_queries: 10,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = TwoAdicFriPcs::new(Dft::default(), val_mmcs, fri_config)
+            .with_lde_storage(MmapBackend::default());
+        (pcs, Challenger::new(perm.clone()))
+    }
+
     mod blowup_1 {
         make_tests_for_pcs!(super::get_pcs(1));
     }
@@ -197,8 +323,6 @@ mod babybear_fri_pcs {
 }
 
 mod m31_fri_pcs {
-    use std::marker::PhantomData;
-
     use p3_challenger::{HashChallenger, SerializingChallenger32};
     use p3_circle::CirclePcs;
     use p3_keccak::Keccak256Hash;
@@ -232,15 +356,12 @@ mod m31_fri_pcs {
         let fri_config = FriConfig {
             log_blowup,
             log_final_poly_len: 0,
+            max_log_arity: 1,
             num_queries: 10,
             proof_of_work_bits: 8,
             mmcs: challenge_mmcs,
         };
-        let pcs = Pcs {
-            mmcs: val_mmcs,
-            fri_config,
-            _phantom: PhantomData,
-        };
+        let pcs = Pcs::new(val_mmcs, fri_config);
         (pcs, Challenger::from_hasher(vec![], byte_hash))
     }
 
@@ -22,7 +22,7 @@ type Dft = p3_dft::Radix2DitParallel<BabyBear>;
 #[cfg(not(feature = "parallel"))]
 type Dft = p3_dft::Radix2Bowers;
 
-const NUM_HASHES: usize = 1_365;
+const NUM_HASHES: usize = 1_024;
 
 fn main() -> Result<(), impl Debug> {
     let env_filter = EnvFilter::builder()
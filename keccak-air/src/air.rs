@@ -2,18 +2,31 @@ use alloc::vec::Vec;
 use core::borrow::Borrow;
 
 use p3_air::utils::{andn, xor, xor3};
-use p3_air::{Air, AirBuilder, BaseAir};
-use p3_field::{FieldAlgebra, PrimeField64};
+use p3_air::{Air, BaseAir, PeriodicAirBuilder};
+use p3_field::{Field, FieldAlgebra, PrimeField64};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use rand::random;
 
 use crate::columns::{KeccakCols, NUM_KECCAK_COLS};
 use crate::constants::rc_value_bit;
-use crate::round_flags::eval_round_flags;
-use crate::{generate_trace_rows, BITS_PER_LIMB, NUM_ROUNDS, U64_LIMBS};
+use crate::{generate_trace_rows, BITS_PER_LIMB, NUM_PADDED_ROUNDS, NUM_ROUNDS, U64_LIMBS};
+
+/// The periodic column which is 1 on the row of each permutation's first round.
+const FIRST_STEP: usize = 0;
+/// The periodic column which is 1 on the row of each permutation's final round.
+const FINAL_STEP: usize = 1;
+/// The periodic column which is 1 on each permutation's last row, i.e. its last padding round.
+const LAST_STEP: usize = 2;
+/// The periodic columns holding the bits of each round's constant, from this index on. Keccak's
+/// round constants only have bits set at positions `2^j - 1`, so those are the only bits stored.
+const RC_BITS: usize = 3;
+const RC_BIT_POSITIONS: [usize; 7] = [0, 1, 3, 7, 15, 31, 63];
 
 /// Assumes the field size is at least 16 bits.
+///
+/// Each permutation takes up `NUM_PADDED_ROUNDS` rows: one per round, followed by padding rounds
+/// without a round constant. The round flags and constants are periodic columns.
 #[derive(Debug)]
 pub struct KeccakAir {}
 
@@ -24,25 +37,54 @@ impl KeccakAir {
     }
 }
 
-impl<F> BaseAir<F> for KeccakAir {
+impl<F: Field> BaseAir<F> for KeccakAir {
     fn width(&self) -> usize {
         NUM_KECCAK_COLS
     }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        let flag = |step| -> Vec<F> {
+            (0..NUM_PADDED_ROUNDS)
+                .map(|round| F::from_bool(round == step))
+                .collect()
+        };
+        let rc_bit = |position| -> Vec<F> {
+            (0..NUM_PADDED_ROUNDS)
+                .map(|round| {
+                    if round < NUM_ROUNDS {
+                        F::from_canonical_u8(rc_value_bit(round, position))
+                    } else {
+                        F::ZERO
+                    }
+                })
+                .collect()
+        };
+        [flag(0), flag(NUM_ROUNDS - 1), flag(NUM_PADDED_ROUNDS - 1)]
+            .into_iter()
+            .chain(RC_BIT_POSITIONS.map(rc_bit))
+            .collect()
+    }
 }
 
-impl<AB: AirBuilder> Air<AB> for KeccakAir {
+impl<AB: PeriodicAirBuilder> Air<AB> for KeccakAir {
     #[inline]
     fn eval(&self, builder: &mut AB) {
-        eval_round_flags(builder);
+        let periodic = builder.periodic_values();
+        let first_step = periodic[FIRST_STEP];
+        let final_step: AB::Expr = periodic[FINAL_STEP].into();
+        let last_step: AB::Expr = periodic[LAST_STEP].into();
+        let rc_bits: [AB::PeriodicVar; RC_BIT_POSITIONS.len()] =
+            core::array::from_fn(|j| periodic[RC_BITS + j]);
 
         let main = builder.main();
         let (local, next) = (main.row_slice(0), main.row_slice(1));
         let local: &KeccakCols<AB::Var> = (*local).borrow();
         let next: &KeccakCols<AB::Var> = (*next).borrow();
 
-        let first_step = local.step_flags[0];
-        let final_step = local.step_flags[NUM_ROUNDS - 1];
         let not_final_step = AB::Expr::ONE - final_step;
+        // The trace's last row is always a permutation's last row, so this also excludes the
+        // wrap-around from the last row to the first.
+        let not_last_step = AB::Expr::ONE - last_step;
 
         // If this is the first step, the input A must match the preimage.
         for y in 0..5 {
@@ -63,13 +105,12 @@ impl<AB: AirBuilder> Air<AB> for KeccakAir {
             .when(not_final_step.clone())
             .assert_zero(local.export);
 
-        // If this is not the final step, the local and next preimages must match.
+        // Within a permutation, the local and next preimages must match.
         for y in 0..5 {
             for x in 0..5 {
                 for limb in 0..U64_LIMBS {
                     builder
-                        .when(not_final_step.clone())
-                        .when_transition()
+                        .when(not_last_step.clone())
                         .assert_eq(local.preimage[y][x][limb], next.preimage[y][x][limb]);
                 }
             }
@@ -163,14 +204,11 @@ impl<AB: AirBuilder> Air<AB> for KeccakAir {
         }
 
         let get_xored_bit = |i| {
-            let mut rc_bit_i = AB::Expr::ZERO;
-            for r in 0..NUM_ROUNDS {
-                let this_round = local.step_flags[r];
-                let this_round_constant = AB::Expr::from_canonical_u8(rc_value_bit(r, i));
-                rc_bit_i += this_round * this_round_constant;
+            let bit: AB::Expr = local.a_prime_prime_0_0_bits[i].into();
+            match RC_BIT_POSITIONS.iter().position(|&position| position == i) {
+                Some(j) => xor::<AB::Expr>(bit, rc_bits[j].into()),
+                None => bit,
             }
-
-            xor::<AB::Expr>(local.a_prime_prime_0_0_bits[i].into(), rc_bit_i)
         };
 
         for limb in 0..U64_LIMBS {
@@ -185,16 +223,14 @@ impl<AB: AirBuilder> Air<AB> for KeccakAir {
             );
         }
 
-        // Enforce that this round's output equals the next round's input.
+        // Within a permutation, enforce that this round's output equals the next round's input,
+        // which carries on into the padding rounds.
         for x in 0..5 {
             for y in 0..5 {
                 for limb in 0..U64_LIMBS {
                     let output = local.a_prime_prime_prime(y, x, limb);
                     let input = next.a[y][x][limb];
-                    builder
-                        .when_transition()
-                        .when(not_final_step.clone())
-                        .assert_eq(output, input);
+                    builder.when(not_last_step.clone()).assert_eq(output, input);
                 }
             }
         }
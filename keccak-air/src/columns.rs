@@ -4,7 +4,7 @@ use core::mem::{size_of, transmute};
 use p3_util::indices_arr;
 
 use crate::constants::R;
-use crate::{NUM_ROUNDS, RATE_LIMBS, U64_LIMBS};
+use crate::{RATE_LIMBS, U64_LIMBS};
 
 /// Note: The ordering of each array is based on the input mapping. As the spec says,
 ///
@@ -16,12 +16,9 @@ use crate::{NUM_ROUNDS, RATE_LIMBS, U64_LIMBS};
 #[derive(Debug)]
 #[repr(C)]
 pub struct KeccakCols<T> {
-    /// The `i`th value is set to 1 if we are in the `i`th round, otherwise 0.
-    pub step_flags: [T; NUM_ROUNDS],
-
     /// A register which indicates if a row should be exported, i.e. included in a multiset equality
-    /// argument. Should be 1 only for certain rows which are final steps, i.e. with
-    /// `step_flags[23] = 1`.
+    /// argument. Should be 1 only for certain rows which are final steps, i.e. the rows of the 24th
+    /// rounds.
     pub export: T,
 
     /// Permutation inputs, stored in y-major order.
@@ -9,24 +9,26 @@ use tracing::instrument;
 
 use crate::columns::{KeccakCols, NUM_KECCAK_COLS};
 use crate::constants::rc_value_limb;
-use crate::{BITS_PER_LIMB, NUM_ROUNDS, U64_LIMBS};
+use crate::{BITS_PER_LIMB, NUM_PADDED_ROUNDS, NUM_ROUNDS, U64_LIMBS};
 
 // TODO: Take generic iterable
 #[instrument(name = "generate Keccak trace", skip_all)]
 pub fn generate_trace_rows<F: PrimeField64>(inputs: Vec<[u64; 25]>) -> RowMajorMatrix<F> {
-    let num_rows = (inputs.len() * NUM_ROUNDS).next_power_of_two();
+    let num_rows = (inputs.len() * NUM_PADDED_ROUNDS)
+        .next_power_of_two()
+        .max(NUM_PADDED_ROUNDS);
     let mut trace = RowMajorMatrix::new(F::zero_vec(num_rows * NUM_KECCAK_COLS), NUM_KECCAK_COLS);
     let (prefix, rows, suffix) = unsafe { trace.values.align_to_mut::<KeccakCols<F>>() };
     assert!(prefix.is_empty(), "Alignment should match");
     assert!(suffix.is_empty(), "Alignment should match");
     assert_eq!(rows.len(), num_rows);
 
-    let num_padding_inputs = num_rows.div_ceil(NUM_ROUNDS) - inputs.len();
+    let num_padding_inputs = num_rows / NUM_PADDED_ROUNDS - inputs.len();
     let padded_inputs = inputs
         .into_par_iter()
         .chain(repeat([0; 25]).take(num_padding_inputs));
 
-    rows.par_chunks_mut(NUM_ROUNDS)
+    rows.par_chunks_mut(NUM_PADDED_ROUNDS)
         .zip(padded_inputs)
         .for_each(|(row, input)| {
             generate_trace_rows_for_perm(row, input);
@@ -35,7 +37,8 @@ pub fn generate_trace_rows<F: PrimeField64>(inputs: Vec<[u64; 25]>) -> RowMajorM
     trace
 }
 
-/// `rows` will normally consist of 24 rows, with an exception for the final row.
+/// `rows` consists of a row for each of the 24 rounds, followed by padding rounds, which continue
+/// from the permutation's output without a round constant.
 fn generate_trace_rows_for_perm<F: PrimeField64>(rows: &mut [KeccakCols<F>], input: [u64; 25]) {
     // Populate the preimage for each row.
     for row in rows.iter_mut() {
@@ -77,8 +80,6 @@ fn generate_trace_rows_for_perm<F: PrimeField64>(rows: &mut [KeccakCols<F>], inp
 }
 
 fn generate_trace_row_for_round<F: PrimeField64>(row: &mut KeccakCols<F>, round: usize) {
-    row.step_flags[round] = F::ONE;
-
     // Populate C[x] = xor(A[x, 0], A[x, 1], A[x, 2], A[x, 3], A[x, 4]).
     for x in 0..5 {
         for z in 0..64 {
@@ -154,9 +155,13 @@ fn generate_trace_row_for_round<F: PrimeField64>(row: &mut KeccakCols<F>, round:
         *bit = F::from_bool(val_bits[i]);
     }
 
-    // A''[0, 0] is additionally xor'd with RC.
+    // A''[0, 0] is additionally xor'd with RC, except in padding rounds.
     for limb in 0..U64_LIMBS {
-        let rc_lo = rc_value_limb(round, limb);
+        let rc_lo = if round < NUM_ROUNDS {
+            rc_value_limb(round, limb)
+        } else {
+            0
+        };
         row.a_prime_prime_prime_0_0_limbs[limb] =
             F::from_canonical_u16(row.a_prime_prime[0][0][limb].as_canonical_u64() as u16 ^ rc_lo);
     }
@@ -8,7 +8,6 @@ mod air;
 mod columns;
 mod constants;
 mod generation;
-mod round_flags;
 
 pub use air::*;
 pub use columns::*;
@@ -16,6 +15,10 @@ pub use constants::*;
 pub use generation::*;
 
 pub const NUM_ROUNDS: usize = 24;
+/// The number of rows each permutation takes up: one per round, followed by rounds without a round
+/// constant up to the next power of two, so that the round flags and constants can be periodic
+/// columns.
+pub const NUM_PADDED_ROUNDS: usize = NUM_ROUNDS.next_power_of_two();
 const BITS_PER_LIMB: usize = 16;
 pub const U64_LIMBS: usize = 64 / BITS_PER_LIMB;
 const RATE_BITS: usize = 1088;
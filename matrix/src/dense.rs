@@ -53,6 +53,27 @@ impl<T: Clone + Send + Sync> DenseStorage<T> for Cow<'_, [T]> {
     }
 }
 
+/// Decides where large buffers, such as the values of committed matrices, live once they've been
+/// computed.
+pub trait StorageBackend<T>: Send + Sync {
+    type Storage: DenseStorage<T>;
+
+    /// Move `values` into storage of this backend.
+    fn store(&self, values: Vec<T>) -> Self::Storage;
+}
+
+/// A `StorageBackend` which keeps values in memory, as a `Vec`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct InMemory;
+
+impl<T: Clone + Send + Sync> StorageBackend<T> for InMemory {
+    type Storage = Vec<T>;
+
+    fn store(&self, values: Vec<T>) -> Vec<T> {
+        values
+    }
+}
+
 impl<T: Clone + Send + Sync + Default> DenseMatrix<T> {
     /// Create a new dense matrix of the given dimensions, backed by a `Vec`, and filled with
     /// default values.
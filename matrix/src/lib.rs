@@ -3,6 +3,8 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "mmap")]
+extern crate std;
 
 use alloc::vec::Vec;
 use core::fmt::{Debug, Display, Formatter};
@@ -22,6 +24,8 @@ pub mod bitrev;
 pub mod dense;
 pub mod extension;
 pub mod horizontally_truncated;
+#[cfg(feature = "mmap")]
+pub mod mmap;
 pub mod mul;
 pub mod row_index_mapped;
 pub mod sparse;
@@ -209,6 +213,28 @@ pub trait Matrix<T: Send + Sync>: Send + Sync {
             .collect_vec()
     }
 
+    /// Pack together a collection of `window_size` consecutive rows from the matrix, each `step`
+    /// rows apart.
+    ///
+    /// Returns a vector corresponding to `window_size` packed rows, as in
+    /// `vertically_packed_row_pair`, which this generalizes. If at some point we exceed the
+    /// height of the matrix, wrap around and include initial rows.
+    #[inline]
+    fn vertically_packed_row_window<P>(&self, r: usize, step: usize, window_size: usize) -> Vec<P>
+    where
+        T: Copy,
+        P: PackedValue<Value = T>,
+    {
+        (0..window_size)
+            .flat_map(|j| {
+                let rows = (0..P::WIDTH)
+                    .map(|c| self.row_slice((r + c + j * step) % self.height()))
+                    .collect_vec();
+                (0..self.width()).map(move |c| P::from_fn(|i| rows[i][c]))
+            })
+            .collect_vec()
+    }
+
     fn vertically_strided(self, stride: usize, offset: usize) -> VerticallyStridedMatrixView<Self>
     where
         Self: Sized,
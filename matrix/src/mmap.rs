@@ -0,0 +1,204 @@
+//! Dense matrix storage in memory-mapped temporary files, which lets the OS page large matrices out
+//! to disk rather than keeping them resident.
+
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::fmt::{Debug, Formatter};
+use core::marker::PhantomData;
+use core::{mem, ptr, slice};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use memmap2::MmapMut;
+
+use crate::dense::{DenseMatrix, DenseStorage, StorageBackend};
+
+/// Values of type `T` stored in a memory-mapped temporary file.
+///
+/// The file is unlinked as soon as it's created, so it's removed once the storage is dropped, or
+/// the process exits. `T` is copied into and out of the file as is, so it should be plain data,
+/// such as a field element, rather than something which owns memory elsewhere.
+pub struct MmapStorage<T> {
+    mmap: MmapMut,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+pub type MmapMatrix<T> = DenseMatrix<T, MmapStorage<T>>;
+
+impl<T: Copy> MmapStorage<T> {
+    /// Copy `values` to a new temporary file in the system's temporary directory.
+    pub fn new(values: &[T]) -> io::Result<Self> {
+        Self::new_in_file(tempfile::tempfile()?, values)
+    }
+
+    /// Copy `values` to a new temporary file in `dir`.
+    pub fn new_in<P: AsRef<Path>>(dir: P, values: &[T]) -> io::Result<Self> {
+        Self::new_in_file(tempfile::tempfile_in(dir)?, values)
+    }
+
+    fn new_in_file(file: File, values: &[T]) -> io::Result<Self> {
+        assert_ne!(
+            mem::size_of::<T>(),
+            0,
+            "Zero-sized values can't be stored in a file"
+        );
+
+        file.set_len(mem::size_of_val(values) as u64)?;
+        // SAFETY: The file is a fresh temporary file which has already been unlinked, so nothing
+        // else can modify it while it's mapped.
+        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+        if !values.is_empty() {
+            let dst = mmap.as_mut_ptr().cast::<T>();
+            // Mappings are page-aligned, which is plenty for any `T`.
+            debug_assert!(dst.is_aligned());
+            // SAFETY: The mapping is exactly large enough for `values`, and is a separate
+            // allocation.
+            unsafe { ptr::copy_nonoverlapping(values.as_ptr(), dst, values.len()) };
+        }
+
+        Ok(Self {
+            mmap,
+            len: values.len(),
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T> Borrow<[T]> for MmapStorage<T> {
+    fn borrow(&self) -> &[T] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: The mapping is aligned, and holds the `len` values copied in by `new_in_file`.
+        unsafe { slice::from_raw_parts(self.mmap.as_ptr().cast(), self.len) }
+    }
+}
+
+impl<T> BorrowMut<[T]> for MmapStorage<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        if self.len == 0 {
+            return &mut [];
+        }
+        // SAFETY: As in `borrow`, and the mapping is writable.
+        unsafe { slice::from_raw_parts_mut(self.mmap.as_mut_ptr().cast(), self.len) }
+    }
+}
+
+impl<T: Copy + Send + Sync> DenseStorage<T> for MmapStorage<T> {
+    fn to_vec(self) -> Vec<T> {
+        <[T]>::to_vec(self.borrow())
+    }
+}
+
+impl<T> Debug for MmapStorage<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MmapStorage")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<T: Copy + Send + Sync, S: DenseStorage<T>> DenseMatrix<T, S> {
+    /// Copy this matrix to a new temporary file in the system's temporary directory.
+    pub fn to_mmap(&self) -> io::Result<MmapMatrix<T>> {
+        Ok(DenseMatrix::new(
+            MmapStorage::new(self.values.borrow())?,
+            self.width,
+        ))
+    }
+}
+
+/// A `StorageBackend` which spills values to memory-mapped temporary files, in the given directory
+/// or otherwise the system's temporary directory.
+///
+/// `store` panics if the file can't be written, e.g. if the disk is full.
+#[derive(Clone, Debug, Default)]
+pub struct MmapBackend {
+    dir: Option<PathBuf>,
+}
+
+impl MmapBackend {
+    /// Spill values to temporary files in `dir`, after checking that a temporary file can be
+    /// created there, so that a missing or read-only directory is reported here rather than by a
+    /// panic in `store`.
+    pub fn in_dir<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+        let dir = dir.into();
+        tempfile::tempfile_in(&dir)?;
+        Ok(Self { dir: Some(dir) })
+    }
+}
+
+impl<T: Copy + Send + Sync> StorageBackend<T> for MmapBackend {
+    type Storage = MmapStorage<T>;
+
+    fn store(&self, values: Vec<T>) -> MmapStorage<T> {
+        let storage = match &self.dir {
+            Some(dir) => MmapStorage::new_in(dir, &values),
+            None => MmapStorage::new(&values),
+        };
+        storage
+            .unwrap_or_else(|err| panic!("Failed to spill {} values to disk: {err}", values.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::bitrev::BitReversableMatrix;
+    use crate::dense::RowMajorMatrix;
+    use crate::Matrix;
+
+    #[test]
+    fn round_trip() {
+        let values = (0..1000u32).collect::<Vec<_>>();
+        let storage = MmapStorage::new(&values).unwrap();
+        assert_eq!(Borrow::<[u32]>::borrow(&storage), values.as_slice());
+        assert_eq!(storage.to_vec(), values);
+    }
+
+    #[test]
+    fn empty() {
+        let storage = MmapBackend::default().store(Vec::<u64>::new());
+        assert!(Borrow::<[u64]>::borrow(&storage).is_empty());
+    }
+
+    #[test]
+    fn in_dir() {
+        let backend = MmapBackend::in_dir(std::env::temp_dir()).unwrap();
+        let storage = backend.store(vec![1u32, 2, 3]);
+        assert_eq!(Borrow::<[u32]>::borrow(&storage), [1, 2, 3]);
+
+        let missing = std::env::temp_dir().join("p3-matrix-mmap-missing-dir");
+        assert!(MmapBackend::in_dir(missing).is_err());
+    }
+
+    #[test]
+    fn matrix_on_disk_matches_in_memory() {
+        let matrix = RowMajorMatrix::new((0..96u64).collect(), 3);
+        let mut on_disk = DenseMatrix::new(MmapBackend::default().store(matrix.values.clone()), 3);
+        assert_eq!(on_disk.dimensions(), matrix.dimensions());
+        assert_eq!(
+            on_disk.row_slice(17).to_vec(),
+            matrix.row_slice(17).to_vec()
+        );
+
+        BorrowMut::<[u64]>::borrow_mut(&mut on_disk.values)[5] = 1000;
+        let mut expected = matrix.clone();
+        expected.values[5] = 1000;
+        assert_eq!(on_disk.as_view().to_row_major_matrix(), expected);
+        assert_eq!(
+            on_disk.bit_reverse_rows().to_row_major_matrix(),
+            expected.bit_reverse_rows().to_row_major_matrix()
+        );
+    }
+
+    #[test]
+    fn to_mmap() {
+        let matrix = RowMajorMatrix::new(vec![1u32, 2, 3, 4, 5, 6], 2);
+        assert_eq!(matrix.to_mmap().unwrap().to_row_major_matrix(), matrix);
+    }
+}
@@ -22,6 +22,7 @@ use serde::Serialize;
 
 fn bench_merkle_trees(criterion: &mut Criterion) {
     bench_bb_poseidon2(criterion);
+    bench_bb_poseidon2_ternary(criterion);
     bench_bb_rescue(criterion);
     bench_bb_blake3(criterion);
     bench_bb_keccak(criterion);
@@ -44,7 +45,24 @@ fn bench_bb_poseidon2(criterion: &mut Criterion) {
         h.clone(),
         c.clone(),
     );
-    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8>(criterion, h, c);
+    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8, 2>(criterion, h, c);
+}
+
+/// A width-24 permutation compresses three digests at once, so the tree has `log2(3)` times fewer
+/// layers than the binary one above.
+fn bench_bb_poseidon2_ternary(criterion: &mut Criterion) {
+    type F = BabyBear;
+
+    type Perm = Poseidon2BabyBear<24>;
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+
+    type H = PaddingFreeSponge<Perm, 24, 16, 8>;
+    let h = H::new(perm.clone());
+
+    type C = TruncatedPermutation<Perm, 3, 8, 24>;
+    let c = C::new(perm);
+
+    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8, 3>(criterion, h, c);
 }
 
 fn bench_bb_rescue(criterion: &mut Criterion) {
@@ -68,7 +86,7 @@ fn bench_bb_rescue(criterion: &mut Criterion) {
         h.clone(),
         c.clone(),
     );
-    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8>(criterion, h, c);
+    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8, 2>(criterion, h, c);
 }
 
 fn bench_bb_blake3(criterion: &mut Criterion) {
@@ -82,7 +100,7 @@ fn bench_bb_blake3(criterion: &mut Criterion) {
     let c = C::new(b);
 
     bench_mmcs::<F, u8, H, C, 32>(criterion, h, c.clone());
-    bench_merkle_tree::<F, u8, H, C, 32>(criterion, h, c);
+    bench_merkle_tree::<F, u8, H, C, 32, 2>(criterion, h, c);
 }
 
 fn bench_bb_keccak(criterion: &mut Criterion) {
@@ -96,18 +114,21 @@ fn bench_bb_keccak(criterion: &mut Criterion) {
     let c = C::new(k);
 
     bench_mmcs::<F, u8, H, C, 32>(criterion, h, c.clone());
-    bench_merkle_tree::<F, u8, H, C, 32>(criterion, h, c);
+    bench_merkle_tree::<F, u8, H, C, 32, 2>(criterion, h, c);
 }
 
-fn bench_merkle_tree<P, PW, H, C, const DIGEST_ELEMS: usize>(criterion: &mut Criterion, h: H, c: C)
-where
+fn bench_merkle_tree<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
+    criterion: &mut Criterion,
+    h: H,
+    c: C,
+) where
     P: PackedField,
     PW: PackedValue,
     H: CryptographicHasher<P::Scalar, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
     [PW::Value; DIGEST_ELEMS]: Serialize + DeserializeOwned,
     Standard: Distribution<P::Scalar>,
@@ -129,7 +150,7 @@ where
     let mut group = criterion.benchmark_group(name);
     group.sample_size(10);
 
-    let mmcs = MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS>::new(h, c);
+    let mmcs = MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS, ARITY>::new(h, c);
     group.bench_with_input(params, &leaves, |b, input| {
         b.iter(|| mmcs.commit(input.clone()))
     });
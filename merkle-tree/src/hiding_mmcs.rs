@@ -1,5 +1,6 @@
 use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::fmt::Debug;
 
 use itertools::Itertools;
 use p3_commit::Mmcs;
@@ -7,7 +8,7 @@ use p3_field::PackedValue;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::stack::HorizontalPair;
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 use serde::de::DeserializeOwned;
@@ -35,14 +36,22 @@ use crate::{MerkleTree, MerkleTreeError, MerkleTreeMmcs};
 /// - `C`: the digest compression function
 /// - `R`: a random number generator for blinding leaves
 #[derive(Clone, Debug)]
-pub struct MerkleTreeHidingMmcs<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-{
-    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>,
+pub struct MerkleTreeHidingMmcs<
+    P,
+    PW,
+    H,
+    C,
+    R,
+    const DIGEST_ELEMS: usize,
+    const SALT_ELEMS: usize,
+    const ARITY: usize = 2,
+> {
+    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>,
     rng: RefCell<R>,
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
 {
     pub fn new(hash: H, compress: C, rng: R) -> Self {
         let inner = MerkleTreeMmcs::new(hash, compress);
@@ -51,10 +60,17 @@ impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
             rng: rng.into(),
         }
     }
+
+    /// Commit to a cap of `ARITY^cap_height` digests; see `MerkleTreeMmcs::with_cap_height`.
+    #[must_use]
+    pub fn with_cap_height(mut self, cap_height: usize) -> Self {
+        self.inner = self.inner.with_cap_height(cap_height);
+        self
+    }
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    Mmcs<P::Value> for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
 where
     P: PackedValue,
     P::Value: Serialize + DeserializeOwned,
@@ -62,20 +78,27 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
     R: Rng + Clone,
-    PW::Value: Eq,
+    PW::Value: Eq + Debug,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
     Standard: Distribution<P::Value>,
 {
-    type ProverData<M> =
-        MerkleTree<P::Value, PW::Value, HorizontalPair<M, RowMajorMatrix<P::Value>>, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<
+        P::Value,
+        PW::Value,
+        HorizontalPair<M, RowMajorMatrix<P::Value>>,
+        DIGEST_ELEMS,
+        ARITY,
+    >;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
     /// The first item is salts; the second is the usual Merkle proof (sibling digests).
     type Proof = (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>);
-    type Error = MerkleTreeError;
+    /// The first item is salts, for each query; the second is the deduplicated sibling digests.
+    type MultiProof = (Vec<Vec<Vec<P::Value>>>, Vec<[PW::Value; DIGEST_ELEMS]>);
+    type Error = MerkleTreeError<[PW::Value; DIGEST_ELEMS]>;
 
     fn commit<M: Matrix<P::Value>>(
         &self,
@@ -101,12 +124,22 @@ where
         (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>),
     ) {
         let (salted_openings, siblings) = self.inner.open_batch(index, prover_data);
-        let (openings, salts): (Vec<_>, Vec<_>) = salted_openings
+        let (openings, salts) = unsalt::<P::Value, SALT_ELEMS>(salted_openings);
+        (openings, (salts, siblings))
+    }
+
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (
+        Vec<Vec<Vec<P::Value>>>,
+        (Vec<Vec<Vec<P::Value>>>, Vec<[PW::Value; DIGEST_ELEMS]>),
+    ) {
+        let (salted_openings, siblings) = self.inner.open_multi_batch(indices, prover_data);
+        let (openings, salts) = salted_openings
             .into_iter()
-            .map(|row| {
-                let (a, b) = row.split_at(row.len() - SALT_ELEMS);
-                (a.to_vec(), b.to_vec())
-            })
+            .map(unsalt::<P::Value, SALT_ELEMS>)
             .unzip();
         (openings, (salts, siblings))
     }
@@ -128,17 +161,79 @@ where
     ) -> Result<(), Self::Error> {
         let (salts, siblings) = proof;
 
+        let opened_salted_values = salt(opened_values, salts);
+        let salted_dimensions = salted_dimensions::<SALT_ELEMS>(dimensions);
+        self.inner.verify_batch(
+            commit,
+            &salted_dimensions,
+            index,
+            &opened_salted_values,
+            siblings,
+        )
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        let (salts, siblings) = proof;
+        if salts.len() != opened_values.len() {
+            return Err(MerkleTreeError::WrongBatchSize);
+        }
+
         let opened_salted_values = opened_values
             .iter()
-            .zip(salts.iter())
-            .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
+            .zip(salts)
+            .map(|(opened, salts)| salt(opened, salts))
             .collect_vec();
-
-        self.inner
-            .verify_batch(commit, dimensions, index, &opened_salted_values, siblings)
+        let salted_dimensions = salted_dimensions::<SALT_ELEMS>(dimensions);
+        self.inner.verify_multi_batch(
+            commit,
+            &salted_dimensions,
+            indices,
+            &opened_salted_values,
+            siblings,
+        )
     }
 }
 
+/// Append each row's salt to its opened values.
+fn salt<T: Copy>(opened_values: &[Vec<T>], salts: &[Vec<T>]) -> Vec<Vec<T>> {
+    opened_values
+        .iter()
+        .zip(salts.iter())
+        .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
+        .collect_vec()
+}
+
+/// The dimensions of the salted matrices, which each have `SALT_ELEMS` more columns.
+fn salted_dimensions<const SALT_ELEMS: usize>(dimensions: &[Dimensions]) -> Vec<Dimensions> {
+    dimensions
+        .iter()
+        .map(|dims| Dimensions {
+            width: dims.width + SALT_ELEMS,
+            height: dims.height,
+        })
+        .collect()
+}
+
+/// Split the salt off the end of each opened row, returning the opened values and the salts.
+fn unsalt<T: Clone, const SALT_ELEMS: usize>(
+    salted_openings: Vec<Vec<T>>,
+) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+    salted_openings
+        .into_iter()
+        .map(|row| {
+            let (a, b) = row.split_at(row.len() - SALT_ELEMS);
+            (a.to_vec(), b.to_vec())
+        })
+        .unzip()
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
@@ -191,7 +286,7 @@ mod tests {
     }
 
     #[test]
-    fn different_widths() -> Result<(), MerkleTreeError> {
+    fn different_widths() -> Result<(), MerkleTreeError<[F; 8]>> {
         let mut rng = thread_rng();
         let perm = Perm::new_from_rng_128(&mut rng);
         let hash = MyHash::new(perm.clone());
@@ -208,4 +303,77 @@ mod tests {
         let (opened_values, proof) = mmcs.open_batch(17, &prover_data);
         mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
     }
+
+    #[test]
+    fn multi_open() -> Result<(), MerkleTreeError<[F; 8]>> {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(
+            MyHash::new(perm.clone()),
+            MyCompress::new(perm),
+            thread_rng(),
+        );
+
+        let mats = [32, 8]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        let (commit, prover_data) = mmcs.commit(mats);
+        let indices = [17, 4, 17, 16];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+    }
+
+    #[test]
+    fn short_salt_fails() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(
+            MyHash::new(perm.clone()),
+            MyCompress::new(perm),
+            thread_rng(),
+        );
+
+        let mat = RowMajorMatrix::<F>::rand(&mut thread_rng(), 16, 3);
+        let dims = [mat.dimensions()];
+        let (commit, prover_data) = mmcs.commit_matrix(mat);
+        let (opened_values, (mut salts, siblings)) = mmcs.open_batch(5, &prover_data);
+        salts[0].pop();
+
+        let err = mmcs
+            .verify_batch(&commit, &dims, 5, &opened_values, &(salts, siblings))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            MerkleTreeError::WrongWidth {
+                matrix: 0,
+                index: 5,
+                expected: 3 + SALT_ELEMS,
+                actual: 2 + SALT_ELEMS,
+            }
+        );
+    }
+
+    #[test]
+    fn multi_open_with_cap() -> Result<(), MerkleTreeError<[F; 8]>> {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(
+            MyHash::new(perm.clone()),
+            MyCompress::new(perm),
+            thread_rng(),
+        )
+        .with_cap_height(2);
+
+        let mats = [32, 8]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        let (commit, prover_data) = mmcs.commit(mats);
+        assert_eq!(commit.len(), 4);
+        let indices = [17, 4, 17, 16];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+    }
 }
@@ -0,0 +1,349 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use itertools::Itertools;
+use p3_commit::Mmcs;
+use p3_field::PackedValue;
+use p3_matrix::dense::{InMemory, StorageBackend};
+use p3_matrix::{Dimensions, Matrix};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
+use serde::{Deserialize, Serialize};
+
+use crate::{MerkleTree, MerkleTreeError, MerkleTreeMmcs};
+
+/// A vector commitment scheme backed by a `MerkleTree`, which compresses the layers near the leaves
+/// with a different function than the layers near the root.
+///
+/// This is similar to `MerkleTreeMmcs`, but the bottom `bottom_layers` layers above the leaves,
+/// including any matrices injected into them, are computed with `C1`, and the layers above them
+/// with `C2`. This lets a prover use whichever compression is fastest natively for the bulk of the
+/// tree, while a recursive verifier, which mostly sees the layers near the root, pays for a
+/// compression which is cheap in-circuit. Both functions must produce the same kind of digest.
+///
+/// The number of bottom layers is part of the configuration, so the prover and verifier must
+/// agree on it.
+///
+/// Generics:
+/// - `P`: a leaf value
+/// - `PW`: an element of a digest
+/// - `H`: the leaf hasher
+/// - `C1`: the digest compression function for the bottom layers
+/// - `C2`: the digest compression function for the layers above them
+/// - `B`: where committed trees keep their digest layers, in memory by default
+#[derive(Copy, Clone, Debug)]
+pub struct HybridMerkleTreeMmcs<
+    P,
+    PW,
+    H,
+    C1,
+    C2,
+    const DIGEST_ELEMS: usize,
+    const ARITY: usize = 2,
+    B = InMemory,
+> {
+    inner: MerkleTreeMmcs<P, PW, H, C1, DIGEST_ELEMS, ARITY, B>,
+    top_compress: C2,
+    bottom_layers: usize,
+}
+
+impl<P, PW, H, C1, C2, const DIGEST_ELEMS: usize, const ARITY: usize>
+    HybridMerkleTreeMmcs<P, PW, H, C1, C2, DIGEST_ELEMS, ARITY>
+{
+    pub const fn new(hash: H, bottom_compress: C1, top_compress: C2, bottom_layers: usize) -> Self {
+        Self {
+            inner: MerkleTreeMmcs::new(hash, bottom_compress),
+            top_compress,
+            bottom_layers,
+        }
+    }
+}
+
+impl<P, PW, H, C1, C2, const DIGEST_ELEMS: usize, const ARITY: usize, B>
+    HybridMerkleTreeMmcs<P, PW, H, C1, C2, DIGEST_ELEMS, ARITY, B>
+{
+    /// Commit to a cap of `ARITY^cap_height` digests; see `MerkleTreeMmcs::with_cap_height`.
+    #[must_use]
+    pub fn with_cap_height(mut self, cap_height: usize) -> Self {
+        self.inner = self.inner.with_cap_height(cap_height);
+        self
+    }
+
+    /// Keep the digest layers of committed trees in `storage`; see `MerkleTreeMmcs::with_storage`.
+    #[must_use]
+    pub fn with_storage<B2>(
+        self,
+        storage: B2,
+    ) -> HybridMerkleTreeMmcs<P, PW, H, C1, C2, DIGEST_ELEMS, ARITY, B2> {
+        HybridMerkleTreeMmcs {
+            inner: self.inner.with_storage(storage),
+            top_compress: self.top_compress,
+            bottom_layers: self.bottom_layers,
+        }
+    }
+
+    /// Compress the children of a node of the given layer above the leaves.
+    fn compress_layer<T>(&self, layer: usize, children: [T; ARITY]) -> T
+    where
+        C1: PseudoCompressionFunction<T, ARITY>,
+        C2: PseudoCompressionFunction<T, ARITY>,
+    {
+        if layer <= self.bottom_layers {
+            self.inner.compress.compress(children)
+        } else {
+            self.top_compress.compress(children)
+        }
+    }
+}
+
+impl<P, PW, H, C1, C2, const DIGEST_ELEMS: usize, const ARITY: usize, B> Mmcs<P::Value>
+    for HybridMerkleTreeMmcs<P, PW, H, C1, C2, DIGEST_ELEMS, ARITY, B>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
+    H: Sync,
+    C1: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C1: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+    C1: Sync,
+    C2: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C2: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+    C2: Sync,
+    B: StorageBackend<[PW::Value; DIGEST_ELEMS]> + Clone,
+    PW::Value: Eq + Debug,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+{
+    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY, B::Storage>;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
+    type Proof = Vec<[PW::Value; DIGEST_ELEMS]>;
+    type MultiProof = Vec<[PW::Value; DIGEST_ELEMS]>;
+    type Error = MerkleTreeError<[PW::Value; DIGEST_ELEMS]>;
+
+    fn commit<M: Matrix<P::Value>>(
+        &self,
+        inputs: Vec<M>,
+    ) -> (Self::Commitment, Self::ProverData<M>) {
+        let tree = MerkleTree::new_hybrid::<P, PW, H, C1, C2, B>(
+            &self.inner.hash,
+            &self.inner.compress,
+            &self.top_compress,
+            self.bottom_layers,
+            inputs,
+            self.inner.cap_height,
+            &self.inner.storage,
+        );
+        let cap = tree.cap();
+        (cap, tree)
+    }
+
+    fn open_batch<M: Matrix<P::Value>>(
+        &self,
+        index: usize,
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<P::Value>>, Self::Proof) {
+        self.inner.open_batch(index, prover_data)
+    }
+
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<P::Value>>>, Self::MultiProof) {
+        self.inner.open_multi_batch(indices, prover_data)
+    }
+
+    fn get_matrices<'a, M: Matrix<P::Value>>(
+        &self,
+        prover_data: &'a Self::ProverData<M>,
+    ) -> Vec<&'a M> {
+        self.inner.get_matrices(prover_data)
+    }
+
+    fn verify_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        index: usize,
+        opened_values: &[Vec<P::Value>],
+        proof: &Self::Proof,
+    ) -> Result<(), Self::Error> {
+        self.inner.verify_openings(
+            |layer, children| self.compress_layer(layer, children),
+            commit,
+            dimensions,
+            &[index],
+            &[opened_values],
+            proof,
+        )
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        let opened_values = opened_values.iter().map(Vec::as_slice).collect_vec();
+        self.inner.verify_openings(
+            |layer, children| self.compress_layer(layer, children),
+            commit,
+            dimensions,
+            indices,
+            &opened_values,
+            proof,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_commit::Mmcs;
+    use p3_field::{Field, FieldAlgebra};
+    use p3_matrix::dense::RowMajorMatrix;
+    use p3_matrix::Matrix;
+    use p3_symmetric::{
+        CryptographicHasher, PaddingFreeSponge, PseudoCompressionFunction, TruncatedPermutation,
+    };
+    use rand::thread_rng;
+
+    use super::HybridMerkleTreeMmcs;
+    use crate::{MerkleTreeError, MerkleTreeMmcs};
+
+    type F = BabyBear;
+
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type BottomCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type TopCompress = TruncatedPermutation<Poseidon2BabyBear<24>, 2, 8, 24>;
+    type MyMmcs = HybridMerkleTreeMmcs<
+        <F as Field>::Packing,
+        <F as Field>::Packing,
+        MyHash,
+        BottomCompress,
+        TopCompress,
+        8,
+    >;
+
+    fn hybrid_mmcs(bottom_layers: usize) -> (MyHash, BottomCompress, TopCompress, MyMmcs) {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let hash = MyHash::new(perm.clone());
+        let bottom_compress = BottomCompress::new(perm);
+        let top_compress = TopCompress::new(Poseidon2BabyBear::new_from_rng_128(&mut thread_rng()));
+        let mmcs = MyMmcs::new(
+            hash.clone(),
+            bottom_compress.clone(),
+            top_compress.clone(),
+            bottom_layers,
+        );
+        (hash, bottom_compress, top_compress, mmcs)
+    }
+
+    #[test]
+    fn commit_single_8x1() {
+        let (hash, bottom_compress, top_compress, mmcs) = hybrid_mmcs(1);
+
+        let v = (0..8).map(F::from_canonical_u32).collect_vec();
+        let (commit, _) = mmcs.commit_vec(v.clone());
+
+        let leaves = v.into_iter().map(|x| hash.hash_item(x)).collect_vec();
+        let layer_1 = leaves
+            .chunks_exact(2)
+            .map(|pair| bottom_compress.compress([pair[0], pair[1]]))
+            .collect_vec();
+        let layer_2 = layer_1
+            .chunks_exact(2)
+            .map(|pair| top_compress.compress([pair[0], pair[1]]))
+            .collect_vec();
+        let expected_result = top_compress.compress([layer_2[0], layer_2[1]]);
+        assert_eq!(commit.digests(), [expected_result]);
+    }
+
+    #[test]
+    fn matches_plain_tree_outside_switch() {
+        let (hash, bottom_compress, top_compress, _) = hybrid_mmcs(0);
+        let mat = RowMajorMatrix::<F>::rand(&mut thread_rng(), 64, 3);
+
+        // With no bottom layers, only the top compression is used.
+        let (commit, _) = MyMmcs::new(
+            hash.clone(),
+            bottom_compress.clone(),
+            top_compress.clone(),
+            0,
+        )
+        .commit_matrix(mat.clone());
+        let (top_commit, _) =
+            MerkleTreeMmcs::<<F as Field>::Packing, <F as Field>::Packing, _, _, 8>::new(
+                hash.clone(),
+                top_compress.clone(),
+            )
+            .commit_matrix(mat.clone());
+        assert_eq!(commit, top_commit);
+
+        // With as many bottom layers as the tree has, only the bottom compression is used.
+        let (commit, _) = MyMmcs::new(hash.clone(), bottom_compress.clone(), top_compress, 6)
+            .commit_matrix(mat.clone());
+        let (bottom_commit, _) =
+            MerkleTreeMmcs::<<F as Field>::Packing, <F as Field>::Packing, _, _, 8>::new(
+                hash,
+                bottom_compress,
+            )
+            .commit_matrix(mat);
+        assert_eq!(commit, bottom_commit);
+    }
+
+    #[test]
+    fn mixed_heights_across_switch() -> Result<(), MerkleTreeError<[F; 8]>> {
+        let (hash, bottom_compress, top_compress, mmcs) = hybrid_mmcs(3);
+
+        // The 16- and 8-row matrices are injected below the switch, the 2-row one above it.
+        let mats = [64, 16, 8, 2]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        let (opened_values, proof) = mmcs.open_batch(37, &prover_data);
+        mmcs.verify_batch(&commit, &dims, 37, &opened_values, &proof)?;
+
+        let indices = [37, 36, 0, 63, 37];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)?;
+
+        // A verifier which switches compressions at another layer rejects the openings.
+        for bottom_layers in [2, 4] {
+            let other_mmcs = MyMmcs::new(
+                hash.clone(),
+                bottom_compress.clone(),
+                top_compress.clone(),
+                bottom_layers,
+            );
+            other_mmcs
+                .verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+                .expect_err("expected a different switch layer to fail");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn multi_open_with_cap() -> Result<(), MerkleTreeError<[F; 8]>> {
+        let (_, _, _, mmcs) = hybrid_mmcs(2);
+        let mmcs = mmcs.with_cap_height(2);
+
+        let mats = [32, 8]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        let (commit, prover_data) = mmcs.commit(mats);
+        assert_eq!(commit.len(), 4);
+        let indices = [17, 4, 17, 16];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+    }
+}
@@ -3,9 +3,11 @@
 extern crate alloc;
 
 mod hiding_mmcs;
+mod hybrid_mmcs;
 mod merkle_tree;
 mod mmcs;
 
 pub use hiding_mmcs::*;
+pub use hybrid_mmcs::*;
 pub use merkle_tree::*;
 pub use mmcs::*;
@@ -1,64 +1,136 @@
 use alloc::vec;
 use alloc::vec::Vec;
 use core::array;
+use core::borrow::Borrow;
 use core::cmp::Reverse;
 use core::marker::PhantomData;
 
 use itertools::Itertools;
 use p3_field::PackedValue;
+use p3_matrix::dense::{InMemory, StorageBackend};
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
+use p3_util::log2_ceil_usize;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-/// A binary Merkle tree for packed data. It has leaves of type `F` and digests of type
-/// `[W; DIGEST_ELEMS]`.
+/// A Merkle tree for packed data, in which each node has `ARITY` children. It has leaves of type
+/// `F` and digests of type `[W; DIGEST_ELEMS]`.
+///
+/// The tree stops at its cap, the layer of `ARITY^cap_height` digests, rather than always
+/// compressing down to a single root.
+///
+/// Each layer of digests is kept in storage of type `L`, which is in memory by default but may
+/// e.g. be on disk; see `StorageBackend`.
 ///
 /// This generally shouldn't be used directly. If you're using a Merkle tree as an MMCS,
 /// see `MerkleTreeMmcs`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
+pub struct MerkleTree<
+    F,
+    W,
+    M,
+    const DIGEST_ELEMS: usize,
+    const ARITY: usize = 2,
+    L = Vec<[W; DIGEST_ELEMS]>,
+> {
     pub(crate) leaves: Vec<M>,
-    // Enable serialization for this type whenever the underlying array type supports it (len 1-32).
-    #[serde(bound(serialize = "[W; DIGEST_ELEMS]: Serialize"))]
-    // Enable deserialization for this type whenever the underlying array type supports it (len 1-32).
-    #[serde(bound(deserialize = "[W; DIGEST_ELEMS]: Deserialize<'de>"))]
-    pub(crate) digest_layers: Vec<Vec<[W; DIGEST_ELEMS]>>,
+    // Enable serialization for this type whenever the layer storage supports it, e.g. a `Vec` of
+    // an array type of length 1-32.
+    #[serde(bound(serialize = "L: Serialize"))]
+    #[serde(bound(deserialize = "L: Deserialize<'de>"))]
+    pub(crate) digest_layers: Vec<L>,
+    pub(crate) cap_height: usize,
     _phantom: PhantomData<F>,
 }
 
-impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
-    MerkleTree<F, W, M, DIGEST_ELEMS>
+impl<
+        F: Clone + Send + Sync,
+        W: Clone,
+        M: Matrix<F>,
+        const DIGEST_ELEMS: usize,
+        const ARITY: usize,
+    > MerkleTree<F, W, M, DIGEST_ELEMS, ARITY>
 {
     /// Matrix heights need not be powers of two. However, if the heights of two given matrices
     /// round up to the same power of two, they must be equal.
+    ///
+    /// Shorter matrices are injected at the layer whose node indices are their row indices, so
+    /// the (padded) heights of the tallest matrix and of each other matrix must differ by a power
+    /// of `ARITY`; see `injection_layer`.
+    ///
+    /// Every matrix is injected below the cap, so the cap height is lowered to fit if it's too
+    /// high; see `effective_cap_height`.
+    pub fn new<P, PW, H, C>(h: &H, c: &C, leaves: Vec<M>, cap_height: usize) -> Self
+    where
+        P: PackedValue<Value = F>,
+        PW: PackedValue<Value = W>,
+        H: CryptographicHasher<F, [W; DIGEST_ELEMS]>,
+        H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
+        H: Sync,
+        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], ARITY>,
+        C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+        C: Sync,
+        W: Copy + Send + Sync,
+    {
+        Self::new_hybrid::<P, PW, H, C, C, InMemory>(h, c, c, 0, leaves, cap_height, &InMemory)
+    }
+}
+
+impl<
+        F: Clone + Send + Sync,
+        W: Clone,
+        M: Matrix<F>,
+        const DIGEST_ELEMS: usize,
+        const ARITY: usize,
+        L: Borrow<[[W; DIGEST_ELEMS]]>,
+    > MerkleTree<F, W, M, DIGEST_ELEMS, ARITY, L>
+{
+    /// Like `new`, but computes the bottom `bottom_layers` layers above the leaves, including any
+    /// matrices injected into them, with `bottom_c`, and the layers above them with `top_c`.
+    ///
+    /// Each layer is moved to `storage` once it's computed, so only the layer being computed is
+    /// ever in memory outside of it.
     #[instrument(name = "build merkle tree", level = "debug", skip_all,
                  fields(dimensions = alloc::format!("{:?}", leaves.iter().map(|l| l.dimensions()).collect::<Vec<_>>())))]
-    pub fn new<P, PW, H, C>(h: &H, c: &C, leaves: Vec<M>) -> Self
+    pub fn new_hybrid<P, PW, H, C1, C2, B>(
+        h: &H,
+        bottom_c: &C1,
+        top_c: &C2,
+        bottom_layers: usize,
+        leaves: Vec<M>,
+        cap_height: usize,
+        storage: &B,
+    ) -> Self
     where
         P: PackedValue<Value = F>,
         PW: PackedValue<Value = W>,
         H: CryptographicHasher<F, [W; DIGEST_ELEMS]>,
         H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
         H: Sync,
-        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], 2>,
-        C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
-        C: Sync,
+        C1: PseudoCompressionFunction<[W; DIGEST_ELEMS], ARITY>,
+        C1: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+        C1: Sync,
+        C2: PseudoCompressionFunction<[W; DIGEST_ELEMS], ARITY>,
+        C2: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+        C2: Sync,
+        B: StorageBackend<[W; DIGEST_ELEMS], Storage = L>,
     {
+        assert!(ARITY >= 2, "Merkle trees must have an arity of at least 2");
         assert!(!leaves.is_empty(), "No matrices given?");
 
         assert_eq!(P::WIDTH, PW::WIDTH, "Packing widths must match");
 
-        let mut leaves_largest_first = leaves
+        let leaves_largest_first = leaves
             .iter()
             .sorted_by_key(|l| Reverse(l.height()))
-            .peekable();
+            .collect_vec();
 
         // check height property
         assert!(
             leaves_largest_first
-                .clone()
+                .iter()
                 .map(|m| m.height())
                 .tuple_windows()
                 .all(|(curr, next)| curr == next
@@ -66,54 +138,139 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
             "matrix heights that round up to the same power of two must be equal"
         );
 
-        let max_height = leaves_largest_first.peek().unwrap().height();
-        let tallest_matrices = leaves_largest_first
-            .peeking_take_while(|m| m.height() == max_height)
+        let max_height = leaves_largest_first[0].height();
+        let leaves_by_layer = leaves_largest_first
+            .into_iter()
+            .map(|m| {
+                let layer = injection_layer::<ARITY>(max_height, m.height()).unwrap_or_else(|| {
+                    panic!(
+                        "matrix of height {} can't be injected into a tree of arity {ARITY} \
+                         with leaves of height {max_height}",
+                        m.height()
+                    )
+                });
+                (layer, m)
+            })
             .collect_vec();
 
-        let mut digest_layers = vec![first_digest_layer::<P, PW, H, M, DIGEST_ELEMS>(
-            h,
-            tallest_matrices,
-        )];
-        loop {
-            let prev_layer = digest_layers.last().unwrap().as_slice();
-            if prev_layer.len() == 1 {
-                break;
-            }
-            let next_layer_len = (prev_layer.len() / 2).next_power_of_two();
+        let top_injection_layer = leaves_by_layer.last().unwrap().0;
+        let cap_height = effective_cap_height::<ARITY>(cap_height, max_height, top_injection_layer);
+        let num_layers = log_arity_ceil::<ARITY>(max_height) - cap_height + 1;
+        let mut leaves_by_layer = leaves_by_layer.into_iter().peekable();
+        let tallest_matrices = leaves_by_layer
+            .peeking_take_while(|&(layer, _)| layer == 0)
+            .map(|(_, m)| m)
+            .collect_vec();
+
+        let first_layer =
+            first_digest_layer::<P, PW, H, M, DIGEST_ELEMS, ARITY>(h, tallest_matrices);
+        let mut digest_layers = vec![storage.store(first_layer)];
+        while digest_layers.len() < num_layers {
+            let prev_layer: &[[W; DIGEST_ELEMS]] = digest_layers.last().unwrap().borrow();
 
             // The matrices that get injected at this layer.
-            let matrices_to_inject = leaves_largest_first
-                .peeking_take_while(|m| m.height().next_power_of_two() == next_layer_len)
+            let matrices_to_inject = leaves_by_layer
+                .peeking_take_while(|&(layer, _)| layer == digest_layers.len())
+                .map(|(_, m)| m)
                 .collect_vec();
 
-            let next_digests = compress_and_inject::<P, PW, H, C, M, DIGEST_ELEMS>(
-                prev_layer,
-                matrices_to_inject,
-                h,
-                c,
-            );
-            digest_layers.push(next_digests);
+            let next_digests = if digest_layers.len() <= bottom_layers {
+                compress_and_inject::<P, PW, H, C1, M, DIGEST_ELEMS, ARITY>(
+                    prev_layer,
+                    matrices_to_inject,
+                    h,
+                    bottom_c,
+                )
+            } else {
+                compress_and_inject::<P, PW, H, C2, M, DIGEST_ELEMS, ARITY>(
+                    prev_layer,
+                    matrices_to_inject,
+                    h,
+                    top_c,
+                )
+            };
+            digest_layers.push(storage.store(next_digests));
         }
 
         Self {
             leaves,
             digest_layers,
+            cap_height,
             _phantom: PhantomData,
         }
     }
 
+    /// The top layer of the tree, padded with default digests to `ARITY^cap_height` digests.
     #[must_use]
-    pub fn root(&self) -> Hash<F, W, DIGEST_ELEMS>
+    pub fn cap(&self) -> MerkleCap<F, W, DIGEST_ELEMS>
     where
-        W: Copy,
+        W: Copy + Default,
     {
-        self.digest_layers.last().unwrap()[0].into()
+        let top_layer: &[[W; DIGEST_ELEMS]] = self.digest_layers.last().unwrap().borrow();
+        let mut cap = top_layer.to_vec();
+        cap.resize(
+            ARITY.pow(self.cap_height as u32),
+            [W::default(); DIGEST_ELEMS],
+        );
+        cap.into()
+    }
+}
+
+/// The number of layers above the leaves in a tree of arity `ARITY` with `height` leaves, i.e. the
+/// least `n` such that `ARITY^n >= height`.
+#[must_use]
+pub(crate) fn log_arity_ceil<const ARITY: usize>(height: usize) -> usize {
+    let mut num_layers = 0;
+    let mut num_leaves = 1;
+    while num_leaves < height {
+        num_leaves *= ARITY;
+        num_layers += 1;
+    }
+    num_layers
+}
+
+/// The layer at which a matrix of height `height` is injected into a tree of arity `ARITY` whose
+/// tallest matrix has height `max_height`.
+///
+/// Row `index >> bits_reduced` is opened at `index`, where `bits_reduced` is
+/// `log2_ceil(max_height) - log2_ceil(height)`, so the rows are injected at the layer whose nodes
+/// each cover `2^bits_reduced` leaves. Returns `None` if there is no such layer, as the number of
+/// leaves under a node is always a power of `ARITY`.
+#[must_use]
+pub(crate) fn injection_layer<const ARITY: usize>(
+    max_height: usize,
+    height: usize,
+) -> Option<usize> {
+    let bits_reduced = log2_ceil_usize(max_height) - log2_ceil_usize(height);
+    let leaves_per_node = 1 << bits_reduced;
+    let layer = log_arity_ceil::<ARITY>(leaves_per_node);
+    (ARITY.pow(layer as u32) == leaves_per_node).then_some(layer)
+}
+
+/// The height of the cap of a tree of arity `ARITY` whose tallest matrix has height `max_height`
+/// and whose shortest matrices are injected at `top_injection_layer`, given the requested
+/// `cap_height`.
+#[must_use]
+pub(crate) fn effective_cap_height<const ARITY: usize>(
+    cap_height: usize,
+    max_height: usize,
+    top_injection_layer: usize,
+) -> usize {
+    cap_height.min(log_arity_ceil::<ARITY>(max_height) - top_injection_layer)
+}
+
+/// The length of the layer above one of length `prev_len`. We always want a multiple of `ARITY`
+/// digests, except when it's the root.
+fn next_layer_len_padded<const ARITY: usize>(prev_len: usize) -> usize {
+    if prev_len == ARITY {
+        1
+    } else {
+        (prev_len / ARITY).next_multiple_of(ARITY)
     }
 }
 
 #[instrument(name = "first digest layer", level = "debug", skip_all)]
-fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize>(
+fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize, const ARITY: usize>(
     h: &H,
     tallest_matrices: Vec<&M>,
 ) -> Vec<[PW::Value; DIGEST_ELEMS]>
@@ -127,11 +284,11 @@ where
 {
     let width = PW::WIDTH;
     let max_height = tallest_matrices[0].height();
-    // we always want to return an even number of digests, except when it's the root.
+    // we always want to return a multiple of ARITY digests, except when it's the root.
     let max_height_padded = if max_height == 1 {
         1
     } else {
-        max_height + max_height % 2
+        max_height.next_multiple_of(ARITY)
     };
 
     let default_digest: [PW::Value; DIGEST_ELEMS] = [PW::Value::default(); DIGEST_ELEMS];
@@ -163,9 +320,9 @@ where
     digests
 }
 
-/// Compress `n` digests from the previous layer into `n/2` digests, while potentially mixing in
-/// some leaf data, if there are input matrices with (padded) height `n/2`.
-fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize>(
+/// Compress `n` digests from the previous layer into `n/ARITY` digests, while potentially mixing
+/// in some leaf data, if there are input matrices injected at this layer.
+fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize, const ARITY: usize>(
     prev_layer: &[[PW::Value; DIGEST_ELEMS]],
     matrices_to_inject: Vec<&M>,
     h: &H,
@@ -177,40 +334,42 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
     M: Matrix<P::Value>,
 {
     if matrices_to_inject.is_empty() {
-        return compress::<PW, C, DIGEST_ELEMS>(prev_layer, c);
+        return compress::<PW, C, DIGEST_ELEMS, ARITY>(prev_layer, c);
     }
 
     let width = PW::WIDTH;
     let next_len = matrices_to_inject[0].height();
-    // We always want to return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        (prev_layer.len() / 2 + 1) & !1
-    };
+    let next_len_padded = next_layer_len_padded::<ARITY>(prev_layer.len());
 
     let default_digest: [PW::Value; DIGEST_ELEMS] = [PW::Value::default(); DIGEST_ELEMS];
+    let packed_default_digest: [PW; DIGEST_ELEMS] =
+        [PW::from_fn(|_| PW::Value::default()); DIGEST_ELEMS];
     let mut next_digests = vec![default_digest; next_len_padded];
     next_digests[0..next_len]
         .par_chunks_exact_mut(width)
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let mut packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| PW::from_fn(|k| prev_layer[ARITY * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             let tallest_digest = h.hash_iter(
                 matrices_to_inject
                     .iter()
                     .flat_map(|m| m.vertically_packed_row(first_row)),
             );
-            packed_digest = c.compress([packed_digest, tallest_digest]);
+            let packed_digest = c.compress(injection_input(
+                packed_digest,
+                tallest_digest,
+                packed_default_digest,
+            ));
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -219,45 +378,36 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
+        let digest = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
         let rows_digest = h.hash_iter(matrices_to_inject.iter().flat_map(|m| m.row(i)));
-        next_digests[i] = c.compress([digest, rows_digest]);
+        next_digests[i] = c.compress(injection_input(digest, rows_digest, default_digest));
     }
 
     // At this point, we've exceeded the height of the matrices to inject, so we continue the
     // process above except with default_digest in place of an input digest.
-    // We only need go as far as half the length of the previous layer.
-    for i in next_len..(prev_layer.len() / 2) {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
-        next_digests[i] = c.compress([digest, default_digest]);
+    // We only need go as far as the length of the previous layer over ARITY.
+    for i in next_len..(prev_layer.len() / ARITY) {
+        let digest = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
+        next_digests[i] = c.compress(injection_input(digest, default_digest, default_digest));
     }
 
     next_digests
 }
 
-/// Compress `n` digests from the previous layer into `n/2` digests.
-fn compress<P, C, const DIGEST_ELEMS: usize>(
+/// Compress `n` digests from the previous layer into `n/ARITY` digests.
+fn compress<P, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
     prev_layer: &[[P::Value; DIGEST_ELEMS]],
     c: &C,
 ) -> Vec<[P::Value; DIGEST_ELEMS]>
 where
     P: PackedValue,
-    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[P; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[P; DIGEST_ELEMS], ARITY>,
     C: Sync,
 {
     let width = P::WIDTH;
-    // Always return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        (prev_layer.len() / 2 + 1) & !1
-    };
-    let next_len = prev_layer.len() / 2;
+    let next_len_padded = next_layer_len_padded::<ARITY>(prev_layer.len());
+    let next_len = prev_layer.len() / ARITY;
 
     let default_digest: [P::Value; DIGEST_ELEMS] = [P::Value::default(); DIGEST_ELEMS];
     let mut next_digests = vec![default_digest; next_len_padded];
@@ -267,9 +417,10 @@ where
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| P::from_fn(|k| prev_layer[ARITY * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -278,15 +429,28 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        next_digests[i] = c.compress([left, right]);
+        next_digests[i] = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
     }
 
     // Everything has been initialized so we can safely cast.
     next_digests
 }
 
+/// The input to the compression which mixes the digest of some injected rows into the digest of a
+/// node, with the compression function's remaining inputs filled with `default_digest`.
+#[inline]
+pub(crate) fn injection_input<T: Copy, const ARITY: usize>(
+    digest: T,
+    rows_digest: T,
+    default_digest: T,
+) -> [T; ARITY] {
+    array::from_fn(|i| match i {
+        0 => digest,
+        1 => rows_digest,
+        _ => default_digest,
+    })
+}
+
 /// Converts a packed array `[P; N]` into its underlying `P::WIDTH` scalar arrays.
 #[inline]
 fn unpack_array<P: PackedValue, const N: usize>(
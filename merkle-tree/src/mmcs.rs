@@ -1,103 +1,246 @@
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use core::borrow::Borrow;
 use core::cmp::Reverse;
+use core::fmt::Debug;
 use core::marker::PhantomData;
 
 use itertools::Itertools;
 use p3_commit::Mmcs;
 use p3_field::PackedValue;
+use p3_matrix::dense::{InMemory, StorageBackend};
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
 use p3_util::log2_ceil_usize;
 use serde::{Deserialize, Serialize};
 
+use crate::merkle_tree::{effective_cap_height, injection_input, injection_layer, log_arity_ceil};
 use crate::MerkleTree;
-use crate::MerkleTreeError::{RootMismatch, WrongBatchSize, WrongHeight};
+use crate::MerkleTreeError::{
+    InconsistentOpenings, IndexOutOfBounds, RootMismatch, WrongBatchSize, WrongCapHeight,
+    WrongHeight, WrongWidth,
+};
 
 /// A vector commitment scheme backed by a `MerkleTree`.
 ///
+/// The commitment is the tree's cap of `ARITY^cap_height` digests, so opening proofs stop
+/// `cap_height` layers short of the root. A larger cap makes the commitment bigger but every proof
+/// shorter.
+///
+/// Each node of the tree has `ARITY` children, so a proof carries `ARITY - 1` sibling digests per
+/// layer. A higher arity makes the tree shallower, which pays off when an `ARITY`-to-1 compression
+/// costs about the same as a 2-to-1 one, e.g. a wider permutation.
+///
 /// Generics:
 /// - `P`: a leaf value
 /// - `PW`: an element of a digest
 /// - `H`: the leaf hasher
-/// - `C`: the digest compression function
+/// - `C`: the digest compression function, which compresses `ARITY` digests into one
+/// - `B`: where committed trees keep their digest layers, in memory by default
 #[derive(Copy, Clone, Debug)]
-pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize> {
-    hash: H,
-    compress: C,
+pub struct MerkleTreeMmcs<
+    P,
+    PW,
+    H,
+    C,
+    const DIGEST_ELEMS: usize,
+    const ARITY: usize = 2,
+    B = InMemory,
+> {
+    pub(crate) hash: H,
+    pub(crate) compress: C,
+    pub(crate) cap_height: usize,
+    pub(crate) storage: B,
     _phantom: PhantomData<(P, PW)>,
 }
 
-#[derive(Debug)]
-pub enum MerkleTreeError {
+/// Why an opening failed to verify, generic over the tree's digest type `Digest`.
+///
+/// Layers are counted from the digests of the tallest matrices' rows, which are layer 0, up to the
+/// layer of the cap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MerkleTreeError<Digest> {
+    /// The number of queries, or of rows opened for some query, doesn't match the dimensions.
     WrongBatchSize,
-    WrongWidth,
+    /// The row of `matrix` opened at `index` doesn't have the width given in its dimensions.
+    WrongWidth {
+        matrix: usize,
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A query opens row `index` of `matrix`, which only has `height` rows.
+    IndexOutOfBounds {
+        matrix: usize,
+        index: usize,
+        height: usize,
+    },
+    /// The proof doesn't have the number of siblings a tree of this height needs.
     WrongHeight {
         max_height: usize,
         num_siblings: usize,
     },
-    RootMismatch,
+    /// The commitment doesn't have one digest for each node of the expected cap.
+    WrongCapHeight {
+        cap_height: usize,
+        num_digests: usize,
+    },
+    /// Queries which share node `index` of `layer` open rows that hash to different digests there.
+    InconsistentOpenings {
+        layer: usize,
+        index: usize,
+        first: Digest,
+        second: Digest,
+    },
+    /// The digest computed for node `index` of the cap, at `layer`, doesn't match the commitment.
+    RootMismatch {
+        layer: usize,
+        index: usize,
+        expected: Digest,
+        computed: Digest,
+    },
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS> {
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
+{
     pub const fn new(hash: H, compress: C) -> Self {
         Self {
             hash,
             compress,
+            cap_height: 0,
+            storage: InMemory,
             _phantom: PhantomData,
         }
     }
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize, B>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY, B>
+{
+    /// Commit to the `ARITY^cap_height` digests of the layer `cap_height` below the root, rather
+    /// than to the root alone.
+    ///
+    /// The cap is lowered for any commitment with a matrix injected above it, e.g. one shorter
+    /// than `ARITY^cap_height`, since every matrix must be injected below the cap.
+    #[must_use]
+    pub fn with_cap_height(mut self, cap_height: usize) -> Self {
+        self.cap_height = cap_height;
+        self
+    }
+
+    /// Keep the digest layers of committed trees in `storage` rather than in memory, e.g. on disk
+    /// with an `MmapBackend`. Openings read the siblings they need back from it.
+    ///
+    /// `commit` panics if the storage can't hold a layer, e.g. if an `MmapBackend` fails to spill
+    /// it because the disk is full.
+    #[must_use]
+    pub fn with_storage<B2>(
+        self,
+        storage: B2,
+    ) -> MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY, B2> {
+        MerkleTreeMmcs {
+            hash: self.hash,
+            compress: self.compress,
+            cap_height: self.cap_height,
+            storage,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize, B> Mmcs<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY, B>
 where
     P: PackedValue,
     PW: PackedValue,
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
-    PW::Value: Eq,
+    B: StorageBackend<[PW::Value; DIGEST_ELEMS]> + Clone,
+    PW::Value: Eq + Debug,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
 {
-    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY, B::Storage>;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
     type Proof = Vec<[PW::Value; DIGEST_ELEMS]>;
-    type Error = MerkleTreeError;
+    type MultiProof = Vec<[PW::Value; DIGEST_ELEMS]>;
+    type Error = MerkleTreeError<[PW::Value; DIGEST_ELEMS]>;
 
     fn commit<M: Matrix<P::Value>>(
         &self,
         inputs: Vec<M>,
     ) -> (Self::Commitment, Self::ProverData<M>) {
-        let tree = MerkleTree::new::<P, PW, H, C>(&self.hash, &self.compress, inputs);
-        let root = tree.root();
-        (root, tree)
+        let tree = MerkleTree::new_hybrid::<P, PW, H, C, C, B>(
+            &self.hash,
+            &self.compress,
+            &self.compress,
+            0,
+            inputs,
+            self.cap_height,
+            &self.storage,
+        );
+        let cap = tree.cap();
+        (cap, tree)
     }
 
     fn open_batch<M: Matrix<P::Value>>(
         &self,
         index: usize,
-        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>,
+        prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>) {
+        let (mut openings, proof) = self.open_multi_batch(&[index], prover_data);
+        (openings.pop().unwrap(), proof)
+    }
+
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<P::Value>>>, Vec<[PW::Value; DIGEST_ELEMS]>) {
         let max_height = self.get_max_height(prover_data);
         let log_max_height = log2_ceil_usize(max_height);
 
-        let openings = prover_data
-            .leaves
+        let openings = indices
             .iter()
-            .map(|matrix| {
-                let log2_height = log2_ceil_usize(matrix.height());
-                let bits_reduced = log_max_height - log2_height;
-                let reduced_index = index >> bits_reduced;
-                matrix.row(reduced_index).collect()
+            .map(|&index| {
+                prover_data
+                    .leaves
+                    .iter()
+                    .map(|matrix| {
+                        let log2_height = log2_ceil_usize(matrix.height());
+                        let bits_reduced = log_max_height - log2_height;
+                        let reduced_index = index >> bits_reduced;
+                        matrix.row(reduced_index).collect()
+                    })
+                    .collect_vec()
             })
             .collect_vec();
 
-        let proof: Vec<_> = (0..log_max_height)
-            .map(|i| prover_data.digest_layers[i][(index >> i) ^ 1])
-            .collect();
+        // Walk up the tree to the cap, including only the siblings which the verifier can't compute
+        // from the openings, in the order `verify_openings` consumes them.
+        let mut proof = Vec::new();
+        let mut known = indices.iter().copied().sorted().dedup().collect_vec();
+        let (_cap, layers_below_cap) = prover_data.digest_layers.split_last().unwrap();
+        for layer in layers_below_cap {
+            let layer: &[[PW::Value; DIGEST_ELEMS]] = layer.borrow();
+            for (parent, children) in &known.iter().chunk_by(|&&index| index / ARITY) {
+                let children = children.copied().collect_vec();
+                proof.extend(
+                    (parent * ARITY..(parent + 1) * ARITY)
+                        .filter(|sibling| !children.contains(sibling))
+                        .map(|sibling| layer[sibling]),
+                );
+            }
+            known = known
+                .into_iter()
+                .map(|index| index / ARITY)
+                .dedup()
+                .collect();
+        }
 
         (openings, proof)
     }
@@ -113,84 +256,259 @@ where
         &self,
         commit: &Self::Commitment,
         dimensions: &[Dimensions],
-        mut index: usize,
+        index: usize,
         opened_values: &[Vec<P::Value>],
         proof: &Self::Proof,
     ) -> Result<(), Self::Error> {
+        self.verify_openings(
+            |_, input| self.compress.compress(input),
+            commit,
+            dimensions,
+            &[index],
+            &[opened_values],
+            proof,
+        )
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        let opened_values = opened_values.iter().map(Vec::as_slice).collect_vec();
+        self.verify_openings(
+            |_, input| self.compress.compress(input),
+            commit,
+            dimensions,
+            indices,
+            &opened_values,
+            proof,
+        )
+    }
+}
+
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize, B>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY, B>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    PW::Value: Eq,
+{
+    /// Check that the rows `opened_values[q]` at `indices[q]` are consistent with `commit`, given
+    /// the siblings in `proof` which can't be computed from the openings themselves.
+    ///
+    /// `compress_layer(layer, children)` computes a node of the given layer above the leaves.
+    pub(crate) fn verify_openings<CompressLayer>(
+        &self,
+        compress_layer: CompressLayer,
+        commit: &MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[&[Vec<P::Value>]],
+        proof: &[[PW::Value; DIGEST_ELEMS]],
+    ) -> Result<(), MerkleTreeError<[PW::Value; DIGEST_ELEMS]>>
+    where
+        CompressLayer: Fn(usize, [[PW::Value; DIGEST_ELEMS]; ARITY]) -> [PW::Value; DIGEST_ELEMS],
+    {
         // Check that the openings have the correct shape.
-        if dimensions.len() != opened_values.len() {
+        if indices.is_empty()
+            || indices.len() != opened_values.len()
+            || opened_values
+                .iter()
+                .any(|openings| openings.len() != dimensions.len())
+        {
             return Err(WrongBatchSize);
         }
 
-        // TODO: Disabled for now since TwoAdicFriPcs and CirclePcs currently pass 0 for width.
-        // for (dims, opened_vals) in dimensions.iter().zip(opened_values) {
-        //     if opened_vals.len() != dims.width {
-        //         return Err(WrongWidth);
-        //     }
-        // }
-
-        // TODO: Disabled for now, CirclePcs sometimes passes a height that's off by 1 bit.
         let max_height = dimensions.iter().map(|dim| dim.height).max().unwrap();
         let log_max_height = log2_ceil_usize(max_height);
-        if proof.len() != log_max_height {
-            return Err(WrongHeight {
-                max_height,
-                num_siblings: proof.len(),
-            });
+        for (&index, openings) in indices.iter().zip(opened_values) {
+            for (matrix, (dims, row)) in dimensions.iter().zip(*openings).enumerate() {
+                let row_index = index >> (log_max_height - log2_ceil_usize(dims.height));
+                if row_index >= dims.height {
+                    return Err(IndexOutOfBounds {
+                        matrix,
+                        index: row_index,
+                        height: dims.height,
+                    });
+                }
+                if row.len() != dims.width {
+                    return Err(WrongWidth {
+                        matrix,
+                        index: row_index,
+                        expected: dims.width,
+                        actual: row.len(),
+                    });
+                }
+            }
         }
 
-        let mut heights_tallest_first = dimensions
+        let wrong_height = || WrongHeight {
+            max_height,
+            num_siblings: proof.len(),
+        };
+
+        // Each matrix is hashed into the layer at which the tree injects it, tallest first.
+        let matrices_by_layer = dimensions
             .iter()
             .enumerate()
             .sorted_by_key(|(_, dims)| Reverse(dims.height))
-            .peekable();
-
-        let mut curr_height_padded = heights_tallest_first
-            .peek()
-            .unwrap()
-            .1
-            .height
-            .next_power_of_two();
-
-        let mut root = self.hash.hash_iter_slices(
-            heights_tallest_first
-                .peeking_take_while(|(_, dims)| {
-                    dims.height.next_power_of_two() == curr_height_padded
-                })
-                .map(|(i, _)| opened_values[i].as_slice()),
-        );
+            .map(|(i, dims)| {
+                let layer = injection_layer::<ARITY>(max_height, dims.height);
+                layer.map(|layer| (layer, i)).ok_or_else(wrong_height)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let top_injection_layer = matrices_by_layer.last().unwrap().0;
+        let cap_height =
+            effective_cap_height::<ARITY>(self.cap_height, max_height, top_injection_layer);
+        if commit.len() != ARITY.pow(cap_height as u32) {
+            return Err(WrongCapHeight {
+                cap_height,
+                num_digests: commit.len(),
+            });
+        }
+        let mut matrices_by_layer = matrices_by_layer.into_iter().peekable();
 
-        for &sibling in proof.iter() {
-            let (left, right) = if index & 1 == 0 {
-                (root, sibling)
-            } else {
-                (sibling, root)
-            };
-
-            root = self.compress.compress([left, right]);
-            index >>= 1;
-            curr_height_padded >>= 1;
-
-            let next_height = heights_tallest_first
-                .peek()
-                .map(|(_, dims)| dims.height)
-                .filter(|h| h.next_power_of_two() == curr_height_padded);
-            if let Some(next_height) = next_height {
-                let next_height_openings_digest = self.hash.hash_iter_slices(
-                    heights_tallest_first
-                        .peeking_take_while(|(_, dims)| dims.height == next_height)
-                        .map(|(i, _)| opened_values[i].as_slice()),
-                );
+        // The digests of the current layer which the openings determine, by index.
+        let tallest_matrices = matrices_by_layer
+            .peeking_take_while(|&(injected_at, _)| injected_at == 0)
+            .map(|(_, i)| i)
+            .collect_vec();
+        let mut layer = self.hash_openings(indices, 0, opened_values, &tallest_matrices)?;
+
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+        let mut siblings = proof.iter();
+        let cap_layer = log_arity_ceil::<ARITY>(max_height) - cap_height;
+        for layer_index in 1..=cap_layer {
+            let mut next_layer = BTreeMap::new();
+            let mut nodes = layer.into_iter().peekable();
+            while let Some((index, digest)) = nodes.next() {
+                // Fill in the children of this node's parent, taking any which the openings don't
+                // determine from the proof.
+                let parent = index / ARITY;
+                let mut children = [None; ARITY];
+                children[index % ARITY] = Some(digest);
+                while let Some((next, digest)) = nodes.next_if(|&(next, _)| next / ARITY == parent)
+                {
+                    children[next % ARITY] = Some(digest);
+                }
+                let mut compress_input = [default_digest; ARITY];
+                for (input, child) in compress_input.iter_mut().zip(children) {
+                    *input = match child {
+                        Some(digest) => digest,
+                        None => *siblings.next().ok_or_else(wrong_height)?,
+                    };
+                }
+                next_layer.insert(parent, compress_layer(layer_index, compress_input));
+            }
+            layer = next_layer;
+
+            let matrices_to_inject = matrices_by_layer
+                .peeking_take_while(|&(injected_at, _)| injected_at == layer_index)
+                .map(|(_, i)| i)
+                .collect_vec();
+            if !matrices_to_inject.is_empty() {
+                let openings_digests =
+                    self.hash_openings(indices, layer_index, opened_values, &matrices_to_inject)?;
+                for (index, openings_digest) in openings_digests {
+                    let digest = layer.get_mut(&index).unwrap();
+                    *digest = compress_layer(
+                        layer_index,
+                        injection_input(*digest, openings_digest, default_digest),
+                    );
+                }
+            }
+        }
+
+        if siblings.next().is_some() {
+            return Err(wrong_height());
+        }
 
-                root = self.compress.compress([root, next_height_openings_digest]);
+        // Whatever is left of the layer must match the corresponding digests of the cap. Every
+        // index is in bounds, since every query is below `max_height`.
+        for (index, computed) in layer {
+            let expected = commit.digests()[index];
+            if computed != expected {
+                return Err(RootMismatch {
+                    layer: cap_layer,
+                    index,
+                    expected,
+                    computed,
+                });
             }
         }
+        Ok(())
+    }
 
-        if commit == &root {
-            Ok(())
-        } else {
-            Err(RootMismatch)
+    /// Hash the opened rows of the given matrices for each query, keyed by the index of the query
+    /// in `layer`. Queries which share an index must agree.
+    fn hash_openings(
+        &self,
+        indices: &[usize],
+        layer: usize,
+        opened_values: &[&[Vec<P::Value>]],
+        matrices: &[usize],
+    ) -> Result<
+        BTreeMap<usize, [PW::Value; DIGEST_ELEMS]>,
+        MerkleTreeError<[PW::Value; DIGEST_ELEMS]>,
+    > {
+        let leaves_per_node = ARITY.pow(layer as u32);
+        let mut digests = BTreeMap::new();
+        for (&index, openings) in indices.iter().zip(opened_values) {
+            let node = index / leaves_per_node;
+            let digest = self
+                .hash
+                .hash_iter_slices(matrices.iter().map(|&i| openings[i].as_slice()));
+            let first = *digests.entry(node).or_insert(digest);
+            if first != digest {
+                return Err(InconsistentOpenings {
+                    layer,
+                    index: node,
+                    first,
+                    second: digest,
+                });
+            }
         }
+        Ok(digests)
+    }
+}
+
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize, B>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY, B>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    PW::Value: Eq,
+{
+    /// Check that `row` is row `index` of a matrix with dimensions `dims`, given a commitment to
+    /// that matrix alone, e.g. from `commit_matrix`, and the proof from `open_batch` at `index`.
+    ///
+    /// With the default cap height of 0, `commit` is just the root. This is `verify_batch` for a
+    /// single matrix, without wrapping the row in a batch of one.
+    pub fn verify_row(
+        &self,
+        commit: &MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>,
+        dims: Dimensions,
+        index: usize,
+        row: &[P::Value],
+        proof: &[[PW::Value; DIGEST_ELEMS]],
+    ) -> Result<(), MerkleTreeError<[PW::Value; DIGEST_ELEMS]>> {
+        let opened_values = [row.to_vec()];
+        self.verify_openings(
+            |_, input| self.compress.compress(input),
+            commit,
+            &[dims],
+            &[index],
+            &[opened_values.as_slice()],
+            proof,
+        )
     }
 }
 
@@ -203,13 +521,16 @@ mod tests {
     use p3_commit::Mmcs;
     use p3_field::{Field, FieldAlgebra};
     use p3_matrix::dense::RowMajorMatrix;
+    use p3_matrix::mmap::MmapBackend;
     use p3_matrix::{Dimensions, Matrix};
     use p3_symmetric::{
-        CryptographicHasher, PaddingFreeSponge, PseudoCompressionFunction, TruncatedPermutation,
+        CryptographicHasher, MerkleCap, PaddingFreeSponge, PseudoCompressionFunction,
+        TruncatedPermutation,
     };
     use rand::thread_rng;
 
     use super::MerkleTreeMmcs;
+    use crate::MerkleTreeError;
 
     type F = BabyBear;
 
@@ -219,6 +540,30 @@ mod tests {
     type MyMmcs =
         MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress, 8>;
 
+    // A 4-to-1 compression of half-size digests fits in the same permutation.
+    type QuaternaryHash = PaddingFreeSponge<Perm, 16, 8, 4>;
+    type QuaternaryCompress = TruncatedPermutation<Perm, 4, 4, 16>;
+    type QuaternaryMmcs = MerkleTreeMmcs<
+        <F as Field>::Packing,
+        <F as Field>::Packing,
+        QuaternaryHash,
+        QuaternaryCompress,
+        4,
+        4,
+    >;
+
+    type Perm24 = Poseidon2BabyBear<24>;
+    type TernaryHash = PaddingFreeSponge<Perm24, 24, 16, 8>;
+    type TernaryCompress = TruncatedPermutation<Perm24, 3, 8, 24>;
+    type TernaryMmcs = MerkleTreeMmcs<
+        <F as Field>::Packing,
+        <F as Field>::Packing,
+        TernaryHash,
+        TernaryCompress,
+        8,
+        3,
+    >;
+
     #[test]
     fn commit_single_1x8() {
         let perm = Perm::new_from_rng_128(&mut thread_rng());
@@ -249,7 +594,7 @@ mod tests {
                 compress.compress([hash.hash_item(v[6]), hash.hash_item(v[7])]),
             ]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.digests(), [expected_result]);
     }
 
     #[test]
@@ -262,8 +607,8 @@ mod tests {
         let mat = RowMajorMatrix::<F>::rand(&mut thread_rng(), 1, 8);
         let (commit, _) = mmcs.commit(vec![mat.clone()]);
 
-        let expected_result = hash.hash_iter(mat.clone().vertically_packed_row(0));
-        assert_eq!(commit, expected_result);
+        let expected_result: [F; 8] = hash.hash_iter(mat.clone().vertically_packed_row(0));
+        assert_eq!(commit.digests(), [expected_result]);
     }
 
     #[test]
@@ -285,7 +630,7 @@ mod tests {
             hash.hash_slice(&[F::ZERO, F::ONE]),
             hash.hash_slice(&[F::TWO, F::ONE]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.digests(), [expected_result]);
     }
 
     #[test]
@@ -312,7 +657,7 @@ mod tests {
             ]),
             compress.compress([hash.hash_slice(&[F::TWO, F::TWO]), default_digest]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.digests(), [expected_result]);
     }
 
     #[test]
@@ -400,7 +745,7 @@ mod tests {
             ]),
         ]);
 
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.digests(), [expected_result]);
 
         let (opened_values, _proof) = mmcs.open_batch(2, &prover_data);
         assert_eq!(
@@ -558,4 +903,343 @@ mod tests {
         mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
             .expect("expected verification to succeed");
     }
+
+    #[test]
+    fn multi_open_size_gaps() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+
+        let mats = [1000, 1000, 70, 8, 1]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 8))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // Repeated and adjacent indices, in no particular order.
+        let indices = [6, 559, 6, 7, 512, 0, 513];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // Each query opens the same rows as it would alone, but shares the siblings on its path.
+        let mut num_single_siblings = 0;
+        for (&index, opened) in indices.iter().zip(&opened_values) {
+            let (single_opened, single_proof) = mmcs.open_batch(index, &prover_data);
+            assert_eq!(opened, &single_opened);
+            num_single_siblings += single_proof.len();
+        }
+        assert!(proof.len() < num_single_siblings);
+    }
+
+    #[test]
+    fn multi_open_single_index_matches_single_open() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+
+        let mat = RowMajorMatrix::<F>::rand(&mut thread_rng(), 64, 3);
+        let (_, prover_data) = mmcs.commit_matrix(mat);
+
+        let (opened_values, proof) = mmcs.open_multi_batch(&[37], &prover_data);
+        assert_eq!(
+            (opened_values[0].clone(), proof),
+            mmcs.open_batch(37, &prover_data)
+        );
+    }
+
+    #[test]
+    fn multi_open_tampering_fails() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+
+        let mats = [64, 16]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 2))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        let indices = [3, 40, 3, 41];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        let mut bad_proof = proof.clone();
+        bad_proof[0][0] += F::ONE;
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &bad_proof)
+            .expect_err("expected a tampered sibling to fail");
+
+        let mut short_proof = proof.clone();
+        short_proof.pop();
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &short_proof)
+            .expect_err("expected a missing sibling to fail");
+
+        // Duplicate queries must open the same rows.
+        let mut bad_opened = opened_values.clone();
+        bad_opened[2][1][0] += F::ONE;
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &bad_opened, &proof)
+            .expect_err("expected inconsistent duplicate openings to fail");
+
+        mmcs.verify_multi_batch(&commit, &dims, &[3, 40, 3, 43], &opened_values, &proof)
+            .expect_err("expected openings at the wrong index to fail");
+    }
+
+    #[test]
+    fn cap_compresses_to_root() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let compress = MyCompress::new(perm.clone());
+        let mmcs = MyMmcs::new(MyHash::new(perm), compress.clone());
+        let capped_mmcs = mmcs.clone().with_cap_height(2);
+
+        let mats = [64, 16]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (root, prover_data) = mmcs.commit(mats.clone());
+        let (cap, capped_prover_data) = capped_mmcs.commit(mats);
+
+        let cap = cap.digests();
+        assert_eq!(cap.len(), 4);
+        let expected_root = compress.compress([
+            compress.compress([cap[0], cap[1]]),
+            compress.compress([cap[2], cap[3]]),
+        ]);
+        assert_eq!(root.digests(), [expected_root]);
+
+        // The proof stops at the cap, two layers short of the root.
+        let (opened_values, proof) = mmcs.open_batch(37, &prover_data);
+        let (capped_opened_values, capped_proof) = capped_mmcs.open_batch(37, &capped_prover_data);
+        assert_eq!(opened_values, capped_opened_values);
+        assert_eq!(capped_proof.len() + 2, proof.len());
+        capped_mmcs
+            .verify_batch(
+                &MerkleCap::from(cap.to_vec()),
+                &dims,
+                37,
+                &capped_opened_values,
+                &capped_proof,
+            )
+            .expect("expected verification to succeed");
+
+        // A verifier expecting a root alone rejects the cap.
+        mmcs.verify_batch(
+            &MerkleCap::from(cap.to_vec()),
+            &dims,
+            37,
+            &capped_opened_values,
+            &capped_proof,
+        )
+        .expect_err("expected a cap of the wrong height to fail");
+    }
+
+    #[test]
+    fn cap_lowered_for_short_matrices() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm)).with_cap_height(5);
+
+        let mats = [1000, 70, 8]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 4))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // The 8-row matrix is injected 3 layers below the root, so the cap can be no higher.
+        assert_eq!(commit.len(), 8);
+
+        let indices = [6, 559, 7, 999];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        let mut bad_commit = commit.digests().to_vec();
+        bad_commit[indices[1] >> 7][0] += F::ONE;
+        mmcs.verify_multi_batch(&bad_commit.into(), &dims, &indices, &opened_values, &proof)
+            .expect_err("expected a tampered cap digest to fail");
+    }
+
+    #[test]
+    fn arity_4_commit_single_16x1() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let hash = QuaternaryHash::new(perm.clone());
+        let compress = QuaternaryCompress::new(perm);
+        let mmcs = QuaternaryMmcs::new(hash.clone(), compress.clone());
+
+        let v = (0..16).map(F::from_canonical_u32).collect_vec();
+        let (commit, _) = mmcs.commit_vec(v.clone());
+
+        let leaves = v.into_iter().map(|x| hash.hash_item(x)).collect_vec();
+        let layer_1 = leaves
+            .chunks_exact(4)
+            .map(|children| compress.compress(children.try_into().unwrap()))
+            .collect_vec();
+        let expected_result = compress.compress(layer_1.try_into().unwrap());
+        assert_eq!(commit.digests(), [expected_result]);
+    }
+
+    #[test]
+    fn arity_4_mixed_heights() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = QuaternaryMmcs::new(
+            QuaternaryHash::new(perm.clone()),
+            QuaternaryCompress::new(perm),
+        );
+
+        // The shorter matrices are injected 1, 2 and 3 layers up, the last at the root.
+        let mats = [64, 64, 16, 4, 1]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // A single opening carries 3 siblings for each of the 3 layers below the root.
+        let (opened_values, proof) = mmcs.open_batch(37, &prover_data);
+        assert_eq!(proof.len(), 9);
+        mmcs.verify_batch(&commit, &dims, 37, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        let mut bad_proof = proof.clone();
+        bad_proof[4][0] += F::ONE;
+        mmcs.verify_batch(&commit, &dims, 37, &opened_values, &bad_proof)
+            .expect_err("expected a tampered sibling to fail");
+
+        let mut bad_opened = opened_values.clone();
+        bad_opened[3][2] += F::ONE;
+        mmcs.verify_batch(&commit, &dims, 37, &bad_opened, &proof)
+            .expect_err("expected a tampered opening to fail");
+
+        let indices = [37, 36, 0, 63, 37];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+        for (&index, opened) in indices.iter().zip(&opened_values) {
+            assert_eq!(opened, &mmcs.open_batch(index, &prover_data).0);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn arity_4_misaligned_heights() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = QuaternaryMmcs::new(
+            QuaternaryHash::new(perm.clone()),
+            QuaternaryCompress::new(perm),
+        );
+
+        // Rows of a 32-row matrix would each cover 2 leaves, which no layer of the tree does.
+        let mats = [64, 32]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let _ = mmcs.commit(mats);
+    }
+
+    #[test]
+    fn arity_3_with_cap() {
+        let perm = Perm24::new_from_rng_128(&mut thread_rng());
+        let mmcs = TernaryMmcs::new(TernaryHash::new(perm.clone()), TernaryCompress::new(perm))
+            .with_cap_height(1);
+
+        let mat = RowMajorMatrix::<F>::rand(&mut thread_rng(), 20, 5);
+        let dims = [mat.dimensions()];
+        let (commit, prover_data) = mmcs.commit_matrix(mat);
+        assert_eq!(commit.len(), 3);
+
+        // 2 siblings for each of the 2 layers below the cap.
+        let (opened_values, proof) = mmcs.open_batch(19, &prover_data);
+        assert_eq!(proof.len(), 4);
+        mmcs.verify_batch(&commit, &dims, 19, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        let indices = [19, 0, 1, 2, 10];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        let mut bad_commit = commit.digests().to_vec();
+        bad_commit[1][0] += F::ONE;
+        mmcs.verify_multi_batch(&bad_commit.into(), &dims, &indices, &opened_values, &proof)
+            .expect_err("expected a tampered cap digest to fail");
+    }
+
+    #[test]
+    fn verify_single_row() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+
+        let mat = RowMajorMatrix::<F>::rand(&mut thread_rng(), 32, 5);
+        let dims = mat.dimensions();
+        let row = mat.row_slice(9).to_vec();
+        let (commit, prover_data) = mmcs.commit_matrix(mat);
+        let (_, proof) = mmcs.open_batch(9, &prover_data);
+        mmcs.verify_row(&commit, dims, 9, &row, &proof)
+            .expect("expected verification to succeed");
+
+        let mut bad_row = row.clone();
+        bad_row[2] += F::ONE;
+        match mmcs.verify_row(&commit, dims, 9, &bad_row, &proof) {
+            Err(MerkleTreeError::RootMismatch {
+                layer,
+                index,
+                expected,
+                computed,
+            }) => {
+                assert_eq!((layer, index), (5, 0));
+                assert_eq!(expected, commit.digests()[0]);
+                assert_ne!(computed, expected);
+            }
+            res => panic!("expected a root mismatch, got {res:?}"),
+        }
+
+        assert_eq!(
+            mmcs.verify_row(&commit, dims, 9, &row[..4], &proof),
+            Err(MerkleTreeError::WrongWidth {
+                matrix: 0,
+                index: 9,
+                expected: 5,
+                actual: 4,
+            })
+        );
+        assert_eq!(
+            mmcs.verify_row(&commit, dims, 32, &row, &proof),
+            Err(MerkleTreeError::IndexOutOfBounds {
+                matrix: 0,
+                index: 32,
+                height: 32,
+            })
+        );
+    }
+
+    #[test]
+    fn on_disk_matches_in_memory() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let mmcs = MyMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+        let disk_mmcs = mmcs.clone().with_storage(MmapBackend::default());
+
+        let mats = [64, 64, 16]
+            .into_iter()
+            .map(|height| RowMajorMatrix::<F>::rand(&mut thread_rng(), height, 3))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats.clone());
+        let (disk_commit, disk_prover_data) = disk_mmcs.commit(mats);
+        assert_eq!(commit, disk_commit);
+
+        let (opened_values, proof) = disk_mmcs.open_batch(45, &disk_prover_data);
+        assert_eq!(
+            (opened_values.clone(), proof.clone()),
+            mmcs.open_batch(45, &prover_data)
+        );
+        disk_mmcs
+            .verify_batch(&commit, &dims, 45, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        let indices = [3, 45, 2];
+        let (opened_values, proof) = disk_mmcs.open_multi_batch(&indices, &disk_prover_data);
+        disk_mmcs
+            .verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+    }
 }
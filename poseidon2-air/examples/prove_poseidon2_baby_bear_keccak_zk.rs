@@ -4,7 +4,7 @@ use p3_baby_bear::{BabyBear, GenericPoseidon2LinearLayersBabyBear};
 use p3_challenger::{HashChallenger, SerializingChallenger32};
 use p3_commit::ExtensionMmcs;
 use p3_field::extension::BinomialExtensionField;
-use p3_fri::{create_benchmark_fri_config, HidingFriPcs};
+use p3_fri::{create_benchmark_fri_config, FriConfig, HidingFriPcs};
 use p3_keccak::{Keccak256Hash, KeccakF};
 use p3_merkle_tree::MerkleTreeHidingMmcs;
 use p3_poseidon2_air::{RoundConstants, VectorizedPoseidon2Air};
@@ -93,7 +93,11 @@ fn main() -> Result<(), impl Debug> {
 
     let dft = Dft::default();
 
-    let fri_config = create_benchmark_fri_config(challenge_mmcs);
+    // The hiding PCS doubles the degree of the committed traces, so we need a larger blowup.
+    let fri_config = FriConfig {
+        log_blowup: 2,
+        ..create_benchmark_fri_config(challenge_mmcs)
+    };
     type Pcs = HidingFriPcs<Val, Dft, ValMmcs, ChallengeMmcs, StdRng>;
     let pcs = Pcs::new(dft, val_mmcs, fri_config, 4, StdRng::from_entropy());
 
@@ -0,0 +1,98 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug)]
+pub struct StirConfig<M> {
+    pub log_blowup: usize,
+    /// The log of the folding factor `k`. Each round folds the polynomial's degree by `k`, while
+    /// its evaluation domain only halves, so the rate of the code improves from round to round.
+    pub log_folding_factor: usize,
+    pub log_final_poly_len: usize,
+    /// The conjectured security level which each round's queries must reach, together with its
+    /// proof of work. Rounds with a better rate need fewer queries.
+    pub security_bits: usize,
+    /// The proof of work bits before each round's queries.
+    pub proof_of_work_bits: usize,
+    pub mmcs: M,
+}
+
+impl<M> StirConfig<M> {
+    pub const fn blowup(&self) -> usize {
+        1 << self.log_blowup
+    }
+
+    pub const fn folding_factor(&self) -> usize {
+        1 << self.log_folding_factor
+    }
+
+    /// The number of rounds which commit to a new codeword when testing a codeword of height
+    /// `2^log_height`, not counting the final round, which sends its folded polynomial in the
+    /// clear instead.
+    pub const fn num_rounds(&self, log_height: usize) -> usize {
+        let log_degree = log_height - self.log_blowup;
+        log_degree
+            .saturating_sub(self.log_final_poly_len + self.log_folding_factor)
+            .div_ceil(self.log_folding_factor)
+    }
+
+    /// The log of the length of the final polynomial when testing a codeword of height
+    /// `2^log_height`.
+    pub const fn log_final_poly_len(&self, log_height: usize) -> usize {
+        let log_degree = log_height - self.log_blowup;
+        log_degree.saturating_sub((self.num_rounds(log_height) + 1) * self.log_folding_factor)
+    }
+
+    /// The log of the inverse rate of the codeword queried in the given round. Each round divides
+    /// the degree by the folding factor and the domain size by 2.
+    pub const fn log_inv_rate(&self, round: usize) -> usize {
+        self.log_blowup + round * (self.log_folding_factor - 1)
+    }
+
+    /// The number of queries in the given round, following the
+    /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture that each query contributes the
+    /// log of the inverse rate in bits of security. If the proof of work alone reaches the
+    /// security level, no queries are needed.
+    pub const fn num_queries(&self, round: usize) -> usize {
+        self.security_bits
+            .saturating_sub(self.proof_of_work_bits)
+            .div_ceil(self.log_inv_rate(round))
+    }
+
+    /// The parameters which determine the shape and soundness of STIR proofs, for binding into a
+    /// transcript.
+    pub fn parameters(&self) -> Vec<usize> {
+        vec![
+            self.log_blowup,
+            self.log_folding_factor,
+            self.log_final_poly_len,
+            self.security_bits,
+            self.proof_of_work_bits,
+        ]
+    }
+}
+
+/// Creates a minimal `StirConfig` for testing purposes.
+/// This configuration is designed to reduce computational cost during tests.
+pub const fn create_test_stir_config<Mmcs>(mmcs: Mmcs) -> StirConfig<Mmcs> {
+    StirConfig {
+        log_blowup: 1,
+        log_folding_factor: 2,
+        log_final_poly_len: 0,
+        security_bits: 4,
+        proof_of_work_bits: 1,
+        mmcs,
+    }
+}
+
+/// Creates a `StirConfig` suitable for benchmarking.
+/// Its conjectured security matches that of `create_benchmark_fri_config` in `p3-fri`.
+pub const fn create_benchmark_stir_config<Mmcs>(mmcs: Mmcs) -> StirConfig<Mmcs> {
+    StirConfig {
+        log_blowup: 1,
+        log_folding_factor: 4,
+        log_final_poly_len: 0,
+        security_bits: 116,
+        proof_of_work_bits: 16,
+        mmcs,
+    }
+}
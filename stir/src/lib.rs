@@ -0,0 +1,17 @@
+//! An implementation of the [STIR](https://eprint.iacr.org/2024/390) low-degree test, and a PCS
+//! for two-adic fields built on it.
+
+#![no_std]
+
+extern crate alloc;
+
+mod config;
+mod polynomial;
+mod proof;
+pub mod prover;
+mod two_adic_pcs;
+pub mod verifier;
+
+pub use config::*;
+pub use proof::*;
+pub use two_adic_pcs::*;
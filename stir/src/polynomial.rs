@@ -0,0 +1,113 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_field::{Field, TwoAdicField};
+use p3_util::{reverse_bits_len, reverse_slice_index_bits};
+
+/// Fold a polynomial `f(x) = sum_j x^j f_j(x^k)`, given by its coefficients, into
+/// `sum_j r^j f_j(x)`.
+pub(crate) fn fold_coeffs<F: Field>(coeffs: &[F], log_folding_factor: usize, r: F) -> Vec<F> {
+    coeffs
+        .chunks(1 << log_folding_factor)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .zip(r.powers())
+                .map(|(&c, r_pow)| c * r_pow)
+                .sum()
+        })
+        .collect()
+}
+
+/// Evaluate a polynomial, given by its coefficients, at `x`.
+pub(crate) fn eval_poly<F: Field>(coeffs: &[F], x: F) -> F {
+    coeffs
+        .iter()
+        .rev()
+        .fold(F::ZERO, |acc, &coeff| acc * x + coeff)
+}
+
+/// The quotient of a polynomial, given by its coefficients, by `X - z`, discarding the remainder.
+pub(crate) fn divide_by_linear<F: Field>(coeffs: &[F], z: F) -> Vec<F> {
+    let mut quotient = vec![F::ZERO; coeffs.len().saturating_sub(1)];
+    let mut acc = F::ZERO;
+    for (q, &coeff) in quotient.iter_mut().rev().zip(coeffs.iter().rev()) {
+        acc = acc * z + coeff;
+        *q = acc;
+    }
+    quotient
+}
+
+/// Multiply a polynomial, given by its coefficients, by `sum_{i <= e} (r x)^i`. This is STIR's
+/// degree correction, which raises the degree of a quotient by `e` points back to the degree bound
+/// of the polynomial it was taken of.
+pub(crate) fn degree_correct<F: Field>(coeffs: &[F], r: F, e: usize) -> Vec<F> {
+    if coeffs.is_empty() {
+        return vec![];
+    }
+    // Since the factor is `(1 - (r x)^(e + 1)) / (1 - r x)`, each coefficient of the product is
+    // `r` times the previous one, plus `coeffs[j]`, minus `r^(e + 1) coeffs[j - e - 1]`.
+    let r_pow = r.exp_u64(e as u64 + 1);
+    let mut acc = F::ZERO;
+    (0..coeffs.len() + e)
+        .map(|j| {
+            acc = acc * r + coeffs.get(j).copied().unwrap_or(F::ZERO);
+            if j > e {
+                acc -= r_pow * coeffs[j - e - 1];
+            }
+            acc
+        })
+        .collect()
+}
+
+/// Evaluate the degree correction factor `sum_{i <= e} (r x)^i` at `x`.
+pub(crate) fn degree_correction_at<F: Field>(r: F, e: usize, x: F) -> F {
+    let rx = r * x;
+    if rx == F::ONE {
+        F::from_canonical_usize(e + 1)
+    } else {
+        (F::ONE - rx.exp_u64(e as u64 + 1)) / (F::ONE - rx)
+    }
+}
+
+/// The points of the given row of a codeword of height `2^log_height`, committed in bit-reversed
+/// order over the coset `shift <w>` in rows of `2^log_folding_factor` evaluations. Returns the
+/// points of the row, which are the `k`-th roots of a single point, and that point.
+pub(crate) fn row_points<F: TwoAdicField>(
+    shift: F,
+    log_height: usize,
+    log_folding_factor: usize,
+    row: usize,
+) -> (Vec<F>, F) {
+    let log_num_rows = log_height - log_folding_factor;
+    let row_start = shift
+        * F::two_adic_generator(log_height).exp_u64(reverse_bits_len(row, log_num_rows) as u64);
+    let mut xs = F::two_adic_generator(log_folding_factor)
+        .shifted_powers(row_start)
+        .take(1 << log_folding_factor)
+        .collect_vec();
+    reverse_slice_index_bits(&mut xs);
+    (xs, row_start.exp_power_of_2(log_folding_factor))
+}
+
+/// Fold the evaluations of a polynomial `f(x) = sum_j x^j f_j(x^k)` at the `k`-th roots `xs` of
+/// a point `y`, in bit-reversed order, into `sum_j r^j f_j(y)`.
+pub(crate) fn fold_row<F: Field>(mut xs: Vec<F>, mut evals: Vec<F>, mut r: F) -> F {
+    // Fold in halves. In bit-reversed order, adjacent points are `x` and `-x`, and folding them
+    // interpolates a line through the two evaluations and evaluates it at r.
+    while evals.len() > 1 {
+        (evals, xs) = evals
+            .chunks_exact(2)
+            .zip(xs.chunks_exact(2))
+            .map(|(e, x)| {
+                (
+                    e[0] + (r - x[0]) * (e[1] - e[0]) / (x[1] - x[0]),
+                    x[0].square(),
+                )
+            })
+            .unzip();
+        r = r.square();
+    }
+    evals[0]
+}
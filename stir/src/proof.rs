@@ -0,0 +1,37 @@
+use alloc::vec::Vec;
+
+use p3_commit::Mmcs;
+use p3_field::Field;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize, InputProof: Serialize",
+    deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
+))]
+pub struct StirProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
+    /// For each round but the final one, the commitment to its folded codeword.
+    pub commitments: Vec<M::Commitment>,
+    /// For each round but the final one, the evaluation of its folded polynomial at an
+    /// out-of-domain point.
+    pub ood_answers: Vec<F>,
+    /// For each round after the first, the openings of the codeword it folds at its query
+    /// locations. The first round folds the input, which is opened by `input_proof`.
+    pub query_openings: Vec<QueryOpenings<F, M>>,
+    /// For each round, the proof of work before its queries.
+    pub pow_witnesses: Vec<Witness>,
+    /// Openings of the inputs at every location of the first round's queried rows.
+    pub input_proof: InputProof,
+    /// The folded polynomial of the final round, in coefficient form.
+    pub final_poly: Vec<F>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(bound = "")]
+pub struct QueryOpenings<F: Field, M: Mmcs<F>> {
+    /// For each query, the row of the codeword holding the evaluations which fold into the
+    /// queried location.
+    pub rows: Vec<Vec<F>>,
+
+    pub opening_proof: M::MultiProof,
+}
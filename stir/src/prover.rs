@@ -0,0 +1,171 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::Itertools;
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::Mmcs;
+use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
+use p3_field::{ExtensionField, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::{log2_strict_usize, reverse_slice_index_bits};
+use tracing::{info_span, instrument};
+
+use crate::polynomial::{degree_correct, divide_by_linear, eval_poly, fold_coeffs, row_points};
+use crate::{QueryOpenings, StirConfig, StirProof};
+
+/// Prove that `input`, the evaluations of a polynomial over the coset `Val::GENERATOR <w>` in
+/// bit-reversed order, is close to a polynomial of degree less than `input.len() / blowup`. If
+/// it isn't, the proof is still produced, and the verifier rejects it.
+///
+/// Each round folds the current polynomial by the folding factor and commits to its evaluations
+/// over a domain half the size, so that the rate improves. The verifier then checks the fold at a
+/// few queried locations, and the next round continues with the quotient of the folded
+/// polynomial by the points checked, including an out-of-domain sample, which forces the prover
+/// to stay consistent with them. The quotient is degree corrected, so that it's tested against a
+/// degree bound lowered by the number of points.
+///
+/// The first round folds the input itself, so its queries open every evaluation in each queried
+/// row of the input with `open_input`, rather than a commitment of the prover's.
+#[instrument(name = "STIR prover", skip_all)]
+pub fn prove<Val, Challenge, M, Challenger, InputProof>(
+    config: &StirConfig<M>,
+    input: Vec<Challenge>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(&[usize]) -> InputProof,
+) -> StirProof<Challenge, M, Challenger::Witness, InputProof>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val> + TwoAdicField,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let log_k = config.log_folding_factor;
+    let log_max_height = log2_strict_usize(input.len());
+    let num_rounds = config.num_rounds(log_max_height);
+    assert!(
+        log_max_height - num_rounds >= log_k,
+        "The codeword is too small to fold by the folding factor in every round."
+    );
+
+    let dft = Radix2Dit::default();
+    let shift = Challenge::from_base(Val::GENERATOR);
+
+    // Interpolate the input, which should have degree less than its height over the blowup. We
+    // drop the zero coefficients above that, but keep any others, so that a high degree input
+    // gives a proof which is rejected rather than a proof about a different polynomial.
+    let degree = input.len() >> config.log_blowup;
+    let mut coeffs = {
+        let mut evals = input;
+        reverse_slice_index_bits(&mut evals);
+        dft.coset_idft(evals, shift)
+    };
+    let len = coeffs
+        .iter()
+        .rposition(|x| !x.is_zero())
+        .map_or(0, |i| i + 1);
+    coeffs.truncate(len.max(degree));
+
+    let mut commitments = vec![];
+    let mut ood_answers = vec![];
+    let mut query_openings = vec![];
+    let mut pow_witnesses = vec![];
+    let mut input_proof = None;
+    // The committed codeword which the current round folds, except in the first round, which
+    // folds the input.
+    let mut prover_data = None;
+
+    for round in 0..=num_rounds {
+        let log_height = log_max_height - round;
+        let r_fold: Challenge = challenger.sample_ext_element();
+        let mut folded = fold_coeffs(&coeffs, log_k, r_fold);
+
+        // The folded polynomial and its out-of-domain sample, except in the final round.
+        let mut next = None;
+        if round < num_rounds {
+            let mut evals = folded.clone();
+            evals.resize(1 << (log_height - 1), Challenge::ZERO);
+            let mut evals = dft.coset_dft(evals, shift);
+            reverse_slice_index_bits(&mut evals);
+            let (commit, data) = config
+                .mmcs
+                .commit_matrix(RowMajorMatrix::new(evals, 1 << log_k));
+            challenger.observe(commit.clone());
+            commitments.push(commit);
+
+            let r_ood: Challenge = challenger.sample_ext_element();
+            let ood_answer = eval_poly(&folded, r_ood);
+            challenger.observe_ext_element(ood_answer);
+            ood_answers.push(ood_answer);
+
+            next = Some((data, r_ood));
+        } else {
+            // The final polynomial has a fixed length. If the folded polynomial is longer, its
+            // truncation doesn't match the verifier's folds.
+            folded.resize(
+                1 << config.log_final_poly_len(log_max_height),
+                Challenge::ZERO,
+            );
+            for &x in &folded {
+                challenger.observe_ext_element(x);
+            }
+        }
+
+        pow_witnesses.push(challenger.grind(config.proof_of_work_bits));
+
+        let log_num_rows = log_height - log_k;
+        let rows = iter::repeat_with(|| challenger.sample_bits(log_num_rows))
+            .take(config.num_queries(round))
+            .collect_vec();
+        info_span!("answer queries", round).in_scope(|| match &prover_data {
+            None => {
+                let indices = rows
+                    .iter()
+                    .flat_map(|&row| (row << log_k)..((row + 1) << log_k))
+                    .collect_vec();
+                input_proof = Some(open_input(&indices));
+            }
+            Some(prover_data) => {
+                let (opened_rows, opening_proof) = config.mmcs.open_multi_batch(&rows, prover_data);
+                query_openings.push(QueryOpenings {
+                    rows: opened_rows
+                        .into_iter()
+                        .map(|mut opened_rows| opened_rows.pop().unwrap())
+                        .collect(),
+                    opening_proof,
+                });
+            }
+        });
+
+        let Some((data, r_ood)) = next else {
+            return StirProof {
+                commitments,
+                ood_answers,
+                query_openings,
+                pow_witnesses,
+                input_proof: input_proof.unwrap(),
+                final_poly: folded,
+            };
+        };
+
+        // Continue with the quotient of the folded polynomial by the points whose evaluations the
+        // verifier now knows. The remainder is their interpolant, which the verifier subtracts.
+        let shift_points = rows.into_iter().sorted().dedup().map(|row| {
+            let (_, y) = row_points(Val::GENERATOR, log_height, log_k, row);
+            Challenge::from_base(y)
+        });
+        let points = iter::once(r_ood).chain(shift_points).collect_vec();
+        let quotient = points
+            .iter()
+            .fold(folded, |quotient, &z| divide_by_linear(&quotient, z));
+
+        // The quotient's degree bound is lower than the folded polynomial's by the number of
+        // points, but the next round only tests the latter. Multiplying by a random polynomial
+        // of that degree makes it test the former.
+        let r_comb: Challenge = challenger.sample_ext_element();
+        coeffs = degree_correct(&quotient, r_comb, points.len());
+        prover_data = Some(data);
+    }
+
+    unreachable!("the final round returns the proof")
+}
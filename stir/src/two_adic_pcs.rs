@@ -0,0 +1,299 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::{Mmcs, OpenedValues, Pcs, PolynomialSpace, TwoAdicMultiplicativeCoset};
+use p3_dft::TwoAdicSubgroupDft;
+use p3_field::{dot_product, ExtensionField, TwoAdicField};
+use p3_fri::{
+    claimed_widths, compute_inverse_denominators, interpolate_lde_at_point, verify_batch_opening,
+    BatchOpening, BatchOpeningError,
+};
+use p3_matrix::bitrev::BitReversableMatrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::*;
+use p3_util::{log2_strict_usize, VecExt};
+use tracing::info_span;
+
+use crate::verifier::{self, StirError};
+use crate::{prover, StirConfig, StirProof};
+
+/// A PCS for two-adic fields which batches its openings into a single STIR low-degree test.
+#[derive(Debug)]
+pub struct StirPcs<Val, Dft, InputMmcs, StirMmcs> {
+    dft: Dft,
+    mmcs: InputMmcs,
+    stir: StirConfig<StirMmcs>,
+    _phantom: PhantomData<Val>,
+}
+
+impl<Val, Dft, InputMmcs, StirMmcs> StirPcs<Val, Dft, InputMmcs, StirMmcs> {
+    pub const fn new(dft: Dft, mmcs: InputMmcs, stir: StirConfig<StirMmcs>) -> Self {
+        Self {
+            dft,
+            mmcs,
+            stir,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<Val, Dft, InputMmcs, StirMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
+    for StirPcs<Val, Dft, InputMmcs, StirMmcs>
+where
+    Val: TwoAdicField,
+    Dft: TwoAdicSubgroupDft<Val>,
+    InputMmcs: Mmcs<Val>,
+    StirMmcs: Mmcs<Challenge>,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    Challenger:
+        FieldChallenger<Val> + CanObserve<StirMmcs::Commitment> + GrindingChallenger<Witness = Val>,
+{
+    type Domain = TwoAdicMultiplicativeCoset<Val>;
+    type Commitment = InputMmcs::Commitment;
+    type ProverData = InputMmcs::ProverData<RowMajorMatrix<Val>>;
+    type Proof = StirProof<Challenge, StirMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>;
+    type Error = StirError<StirMmcs::Error, BatchOpeningError<InputMmcs::Error>>;
+
+    fn parameters(&self) -> Vec<usize> {
+        self.stir.parameters()
+    }
+
+    fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
+        let log_n = log2_strict_usize(degree);
+        TwoAdicMultiplicativeCoset {
+            log_n,
+            shift: Val::ONE,
+        }
+    }
+
+    fn commit(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let ldes: Vec<_> = evaluations
+            .into_iter()
+            .map(|(domain, evals)| {
+                assert_eq!(domain.size(), evals.height());
+                let shift = Val::GENERATOR / domain.shift;
+                // Commit to the bit-reversed LDE.
+                self.dft
+                    .coset_lde_batch(evals, self.stir.log_blowup, shift)
+                    .bit_reverse_rows()
+                    .to_row_major_matrix()
+            })
+            .collect();
+
+        self.mmcs.commit(ldes)
+    }
+
+    fn get_evaluations_on_domain<'a>(
+        &self,
+        prover_data: &'a Self::ProverData,
+        idx: usize,
+        domain: Self::Domain,
+    ) -> impl Matrix<Val> + 'a {
+        assert_eq!(domain.shift, Val::GENERATOR);
+        let lde = self.mmcs.get_matrices(prover_data)[idx];
+        assert!(lde.height() >= domain.size());
+        lde.split_rows(domain.size()).0.bit_reverse_rows()
+    }
+
+    fn open(
+        &self,
+        // For each round,
+        rounds: Vec<(
+            &Self::ProverData,
+            // for each matrix,
+            Vec<
+                // points to open
+                Vec<Challenge>,
+            >,
+        )>,
+        challenger: &mut Challenger,
+    ) -> (OpenedValues<Challenge>, Self::Proof) {
+        // Batch combination challenge
+        let alpha: Challenge = challenger.sample_ext_element();
+
+        let mats_and_points = rounds
+            .iter()
+            .map(|(data, points)| {
+                (
+                    self.mmcs
+                        .get_matrices(data)
+                        .into_iter()
+                        .map(|m| m.as_view())
+                        .collect_vec(),
+                    points,
+                )
+            })
+            .collect_vec();
+
+        let log_global_max_height = mats_and_points
+            .iter()
+            .flat_map(|(mats, _)| mats)
+            .map(|m| log2_strict_usize(m.height()))
+            .max()
+            .unwrap();
+
+        let inv_denoms = compute_inverse_denominators(&mats_and_points, Val::GENERATOR);
+
+        // Unlike FRI, STIR tests a single codeword, so the quotients of every matrix and point are
+        // combined with distinct powers of alpha, and those of smaller matrices are lifted to the
+        // largest height by repeating each of their evaluations.
+        let mut all_opened_values: OpenedValues<Challenge> = vec![];
+        let mut stir_input = vec![Challenge::ZERO; 1 << log_global_max_height];
+        let mut alpha_pow_offset = Challenge::ONE;
+
+        for (mats, points) in mats_and_points {
+            let opened_values_for_round = all_opened_values.pushed_mut(vec![]);
+            for (mat, points_for_mat) in izip!(mats, points) {
+                let log_height = log2_strict_usize(mat.height());
+                let bits_reduced = log_global_max_height - log_height;
+
+                let opened_values_for_mat = opened_values_for_round.pushed_mut(vec![]);
+                for &point in points_for_mat {
+                    let _guard =
+                        info_span!("reduce matrix quotient", dims = %mat.dimensions()).entered();
+
+                    let ys = interpolate_lde_at_point(
+                        &mat,
+                        self.stir.log_blowup,
+                        point,
+                        inv_denoms.get(&point).unwrap(),
+                    );
+
+                    let reduced_ys: Challenge = dot_product(alpha.powers(), ys.iter().copied());
+                    let reduced_rows = info_span!("reduce rows").in_scope(|| {
+                        mat.dot_ext_powers(alpha)
+                            .zip(inv_denoms.get(&point).unwrap().par_iter())
+                            .map(|(reduced_row, &inv_denom)| {
+                                alpha_pow_offset * (reduced_ys - reduced_row) * inv_denom
+                            })
+                            .collect::<Vec<_>>()
+                    });
+                    stir_input
+                        .par_iter_mut()
+                        .enumerate()
+                        .for_each(|(i, ro)| *ro += reduced_rows[i >> bits_reduced]);
+
+                    alpha_pow_offset *= alpha.exp_u64(mat.width() as u64);
+                    opened_values_for_mat.push(ys);
+                }
+            }
+        }
+
+        let stir_proof = prover::prove(&self.stir, stir_input, challenger, |indices| {
+            rounds
+                .iter()
+                .map(|(data, _)| {
+                    let log_max_height = log2_strict_usize(self.mmcs.get_max_height(data));
+                    let bits_reduced = log_global_max_height - log_max_height;
+                    let reduced_indices = indices
+                        .iter()
+                        .map(|index| index >> bits_reduced)
+                        .collect_vec();
+                    let (opened_values, opening_proof) =
+                        self.mmcs.open_multi_batch(&reduced_indices, data);
+                    BatchOpening {
+                        opened_values,
+                        opening_proof,
+                    }
+                })
+                .collect()
+        });
+
+        (all_opened_values, stir_proof)
+    }
+
+    fn verify(
+        &self,
+        // For each round:
+        rounds: Vec<(
+            Self::Commitment,
+            // for each matrix:
+            Vec<(
+                // its domain,
+                Self::Domain,
+                // for each point:
+                Vec<(
+                    // the point,
+                    Challenge,
+                    // values at the point
+                    Vec<Challenge>,
+                )>,
+            )>,
+        )>,
+        proof: &Self::Proof,
+        challenger: &mut Challenger,
+    ) -> Result<(), Self::Error> {
+        let widths = rounds
+            .iter()
+            .map(|(_, mats)| claimed_widths(mats))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(StirError::InvalidProofShape)?;
+        // Every round must be opened, or its claims would go unchecked.
+        if proof.input_proof.len() != rounds.len() {
+            return Err(StirError::InvalidProofShape);
+        }
+
+        // Batch combination challenge
+        let alpha: Challenge = challenger.sample_ext_element();
+
+        let log_global_max_height = rounds
+            .iter()
+            .flat_map(|(_, mats)| mats)
+            .map(|(domain, _)| domain.log_n + self.stir.log_blowup)
+            .max()
+            .unwrap();
+
+        verifier::verify(
+            &self.stir,
+            log_global_max_height,
+            proof,
+            challenger,
+            |indices, input_proof| {
+                let mut stir_input = vec![Challenge::ZERO; indices.len()];
+                let mut alpha_pow_offset = Challenge::ONE;
+
+                for (batch_opening, (batch_commit, mats), widths) in
+                    izip!(input_proof, &rounds, &widths)
+                {
+                    let xs = verify_batch_opening(
+                        &self.mmcs,
+                        self.stir.log_blowup,
+                        log_global_max_height,
+                        batch_commit,
+                        mats,
+                        widths,
+                        indices,
+                        batch_opening,
+                    )?;
+
+                    for (mat_index, (_, mat_points_and_values)) in mats.iter().enumerate() {
+                        for (z, ps_at_z) in mat_points_and_values {
+                            for (ro, query_xs, query_opening) in
+                                izip!(&mut stir_input, &xs, &batch_opening.opened_values)
+                            {
+                                let x = query_xs[mat_index];
+                                let mut alpha_pow = alpha_pow_offset;
+                                for (&p_at_x, &p_at_z) in izip!(&query_opening[mat_index], ps_at_z)
+                                {
+                                    let quotient = (-p_at_z + p_at_x) / (-*z + x);
+                                    *ro += alpha_pow * quotient;
+                                    alpha_pow *= alpha;
+                                }
+                            }
+                            alpha_pow_offset *= alpha.exp_u64(ps_at_z.len() as u64);
+                        }
+                    }
+                }
+
+                Ok(stir_input)
+            },
+        )
+    }
+}
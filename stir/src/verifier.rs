@@ -0,0 +1,213 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::Mmcs;
+use p3_field::{batch_multiplicative_inverse, ExtensionField, Field, TwoAdicField};
+use p3_matrix::Dimensions;
+
+use crate::polynomial::{degree_correction_at, eval_poly, fold_row, row_points};
+use crate::{StirConfig, StirProof};
+
+#[derive(Debug)]
+pub enum StirError<MmcsErr, InputError> {
+    InvalidProofShape,
+    MmcsError(MmcsErr),
+    InputError(InputError),
+    FinalPolyMismatch,
+    InvalidPowWitness,
+}
+
+/// Verify a STIR proof that a codeword of height `2^log_max_height` is close to a polynomial of
+/// degree less than `2^log_max_height / blowup`. `open_input` should check the input proof and
+/// return the codeword's value at each of the given indices, which are every index in each of the
+/// first round's queried rows.
+pub fn verify<Val, Challenge, M, Challenger, InputProof, InputError>(
+    config: &StirConfig<M>,
+    log_max_height: usize,
+    proof: &StirProof<Challenge, M, Challenger::Witness, InputProof>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(&[usize], &InputProof) -> Result<Vec<Challenge>, InputError>,
+) -> Result<(), StirError<M::Error, InputError>>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val> + TwoAdicField,
+    M: Mmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let log_k = config.log_folding_factor;
+    let num_rounds = config.num_rounds(log_max_height);
+    if log_max_height - num_rounds < log_k
+        || proof.commitments.len() != num_rounds
+        || proof.ood_answers.len() != num_rounds
+        || proof.query_openings.len() != num_rounds
+        || proof.pow_witnesses.len() != num_rounds + 1
+        || proof.final_poly.len() != 1 << config.log_final_poly_len(log_max_height)
+    {
+        return Err(StirError::InvalidProofShape);
+    }
+
+    // The points and evaluations which the current round's polynomial is a degree corrected
+    // quotient by.
+    let mut quotient: Option<Quotient<Challenge>> = None;
+
+    for round in 0..=num_rounds {
+        let log_height = log_max_height - round;
+        let r_fold: Challenge = challenger.sample_ext_element();
+
+        let mut r_ood = None;
+        if round < num_rounds {
+            challenger.observe(proof.commitments[round].clone());
+            r_ood = Some(challenger.sample_ext_element::<Challenge>());
+            challenger.observe_ext_element(proof.ood_answers[round]);
+        } else {
+            for &x in &proof.final_poly {
+                challenger.observe_ext_element(x);
+            }
+        }
+
+        if !challenger.check_witness(config.proof_of_work_bits, proof.pow_witnesses[round]) {
+            return Err(StirError::InvalidPowWitness);
+        }
+
+        let log_num_rows = log_height - log_k;
+        let rows = (0..config.num_queries(round))
+            .map(|_| challenger.sample_bits(log_num_rows))
+            .collect_vec();
+
+        let opened_rows = if round == 0 {
+            // The first round folds the input itself, opened at every index of each queried row.
+            let indices = rows
+                .iter()
+                .flat_map(|&row| (row << log_k)..((row + 1) << log_k))
+                .collect_vec();
+            let input_values =
+                open_input(&indices, &proof.input_proof).map_err(StirError::InputError)?;
+            if input_values.len() != indices.len() {
+                return Err(StirError::InvalidProofShape);
+            }
+            input_values
+                .chunks_exact(1 << log_k)
+                .map(<[_]>::to_vec)
+                .collect_vec()
+        } else {
+            let openings = &proof.query_openings[round - 1];
+            if openings.rows.len() != rows.len()
+                || openings.rows.iter().any(|row| row.len() != 1 << log_k)
+            {
+                return Err(StirError::InvalidProofShape);
+            }
+            let dims = &[Dimensions {
+                width: 1 << log_k,
+                height: 1 << log_num_rows,
+            }];
+            let opened_values = openings
+                .rows
+                .iter()
+                .map(|row| vec![row.clone()])
+                .collect_vec();
+            config
+                .mmcs
+                .verify_multi_batch(
+                    &proof.commitments[round - 1],
+                    dims,
+                    &rows,
+                    &opened_values,
+                    &openings.opening_proof,
+                )
+                .map_err(StirError::MmcsError)?;
+            openings.rows.clone()
+        };
+
+        // Evaluate this round's polynomial on each queried row, and fold it.
+        let folds = izip!(&rows, &opened_rows)
+            .map(|(&row, opened_row)| {
+                let (xs, y) = row_points(Val::GENERATOR, log_height, log_k, row);
+                let xs = xs.into_iter().map(Challenge::from_base).collect_vec();
+                let evals = match &quotient {
+                    None => opened_row.clone(),
+                    Some(quotient) => izip!(&xs, opened_row)
+                        .map(|(&x, &eval)| quotient.eval(x, eval))
+                        .collect(),
+                };
+                (row, (Challenge::from_base(y), fold_row(xs, evals, r_fold)))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        match r_ood {
+            Some(r_ood) => {
+                let r_comb: Challenge = challenger.sample_ext_element();
+                let (points, evals) = folds.into_values().unzip::<_, _, Vec<_>, Vec<_>>();
+                quotient = Some(Quotient::new(
+                    [r_ood].into_iter().chain(points).collect(),
+                    [proof.ood_answers[round]]
+                        .into_iter()
+                        .chain(evals)
+                        .collect(),
+                    r_comb,
+                ));
+            }
+            None => {
+                for (y, fold) in folds.into_values() {
+                    if eval_poly(&proof.final_poly, y) != fold {
+                        return Err(StirError::FinalPolyMismatch);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The quotient `(g - A) / V` of a polynomial `g` by the vanishing polynomial `V` of a set of
+/// points, where `A` interpolates the claimed evaluations of `g` at those points, multiplied by
+/// the degree correction factor `sum_{i <= e} (r_comb x)^i` for `e` points.
+struct Quotient<F> {
+    points: Vec<F>,
+    /// The evaluations at each point, scaled by its barycentric weight.
+    weighted_evals: Vec<F>,
+    r_comb: F,
+}
+
+impl<F: Field> Quotient<F> {
+    fn new(points: Vec<F>, evals: Vec<F>, r_comb: F) -> Self {
+        let weights = batch_multiplicative_inverse(
+            &points
+                .iter()
+                .enumerate()
+                .map(|(i, &z)| {
+                    points
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, &w)| z - w)
+                        .product::<F>()
+                })
+                .collect_vec(),
+        );
+        let weighted_evals = izip!(weights, evals)
+            .map(|(weight, eval)| weight * eval)
+            .collect();
+        Self {
+            points,
+            weighted_evals,
+            r_comb,
+        }
+    }
+
+    /// Evaluate the corrected quotient at `x`, given `g(x)`. By the barycentric formula,
+    /// `A(x) / V(x) = sum_z w_z A(z) / (x - z)`.
+    fn eval(&self, x: F, g_at_x: F) -> F {
+        let inv_denoms =
+            batch_multiplicative_inverse(&self.points.iter().map(|&z| x - z).collect_vec());
+        let inv_vanishing: F = inv_denoms.iter().copied().product();
+        let quotient = g_at_x * inv_vanishing
+            - izip!(&self.weighted_evals, inv_denoms)
+                .map(|(&weighted_eval, inv_denom)| weighted_eval * inv_denom)
+                .sum::<F>();
+        quotient * degree_correction_at(self.r_comb, self.points.len(), x)
+    }
+}
@@ -0,0 +1,332 @@
+use itertools::{izip, Itertools};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{CanObserve, DuplexChallenger, FieldChallenger};
+use p3_commit::{ExtensionMmcs, Pcs, PolynomialSpace};
+use p3_dft::{Radix2Dit, Radix2DitParallel, TwoAdicSubgroupDft};
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_stir::verifier::StirError;
+use p3_stir::{
+    create_benchmark_stir_config, create_test_stir_config, prover, verifier, StirConfig, StirPcs,
+};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_util::reverse_slice_index_bits;
+use rand::distributions::{Distribution, Standard};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+fn seeded_rng() -> impl Rng {
+    ChaCha20Rng::seed_from_u64(0)
+}
+
+fn do_test_stir_pcs<Val, Challenge, Challenger, P>(
+    (pcs, challenger): &(P, Challenger),
+    log_degrees_by_round: &[&[usize]],
+) where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Val: Field,
+    Standard: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+    Challenger: Clone + CanObserve<P::Commitment> + FieldChallenger<Val>,
+{
+    let num_rounds = log_degrees_by_round.len();
+    let mut rng = seeded_rng();
+
+    let mut p_challenger = challenger.clone();
+
+    let domains_and_polys_by_round = log_degrees_by_round
+        .iter()
+        .map(|log_degrees| {
+            log_degrees
+                .iter()
+                .map(|&log_degree| {
+                    let d = 1 << log_degree;
+                    // random width 5-15
+                    let width = 5 + rng.gen_range(0..=10);
+                    (
+                        pcs.natural_domain_for_degree(d),
+                        RowMajorMatrix::<Val>::rand(&mut rng, d, width),
+                    )
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let (commits_by_round, data_by_round): (Vec<_>, Vec<_>) = domains_and_polys_by_round
+        .iter()
+        .map(|domains_and_polys| pcs.commit(domains_and_polys.clone()))
+        .unzip();
+    assert_eq!(commits_by_round.len(), num_rounds);
+    assert_eq!(data_by_round.len(), num_rounds);
+    p_challenger.observe_slice(&commits_by_round);
+
+    let zeta: Challenge = p_challenger.sample_ext_element();
+
+    let points_by_round = log_degrees_by_round
+        .iter()
+        .map(|log_degrees| vec![vec![zeta]; log_degrees.len()])
+        .collect_vec();
+    let data_and_points = data_by_round.iter().zip(points_by_round).collect();
+    let (opening_by_round, proof) = pcs.open(data_and_points, &mut p_challenger);
+    assert_eq!(opening_by_round.len(), num_rounds);
+
+    // Verify the proof.
+    let mut v_challenger = challenger.clone();
+    v_challenger.observe_slice(&commits_by_round);
+    let verifier_zeta: Challenge = v_challenger.sample_ext_element();
+    assert_eq!(verifier_zeta, zeta);
+
+    let commits_and_claims_by_round = izip!(
+        commits_by_round,
+        domains_and_polys_by_round,
+        opening_by_round
+    )
+    .map(|(commit, domains_and_polys, openings)| {
+        let claims = domains_and_polys
+            .iter()
+            .zip(openings)
+            .map(|((domain, _), mat_openings)| (*domain, vec![(zeta, mat_openings[0].clone())]))
+            .collect_vec();
+        (commit, claims)
+    })
+    .collect_vec();
+    assert_eq!(commits_and_claims_by_round.len(), num_rounds);
+
+    pcs.verify(commits_and_claims_by_round, &proof, &mut v_challenger)
+        .unwrap()
+}
+
+// Set it up so we create tests inside a module for each pcs, so we get nice error reports
+// specific to a failing PCS.
+macro_rules! make_tests_for_pcs {
+    ($p:expr) => {
+        #[test]
+        fn single() {
+            let p = $p;
+            for i in 3..6 {
+                $crate::do_test_stir_pcs(&p, &[&[i]]);
+            }
+        }
+
+        #[test]
+        fn many_equal() {
+            let p = $p;
+            for i in 5..8 {
+                $crate::do_test_stir_pcs(&p, &[&[i; 5]]);
+            }
+        }
+
+        #[test]
+        fn many_different() {
+            let p = $p;
+            for i in 3..8 {
+                let degrees = (3..3 + i).collect::<Vec<_>>();
+                $crate::do_test_stir_pcs(&p, &[&degrees]);
+            }
+        }
+
+        #[test]
+        fn many_different_rev() {
+            let p = $p;
+            for i in 3..8 {
+                let degrees = (3..3 + i).rev().collect::<Vec<_>>();
+                $crate::do_test_stir_pcs(&p, &[&degrees]);
+            }
+        }
+
+        #[test]
+        fn multiple_rounds() {
+            let p = $p;
+            $crate::do_test_stir_pcs(&p, &[&[3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3], &[3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3], &[2]]);
+            $crate::do_test_stir_pcs(&p, &[&[2], &[3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3, 4], &[3, 4]]);
+            $crate::do_test_stir_pcs(&p, &[&[4, 2], &[4, 2]]);
+            $crate::do_test_stir_pcs(&p, &[&[2, 2], &[3, 3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3, 3], &[2, 2]]);
+            $crate::do_test_stir_pcs(&p, &[&[2], &[3, 3]]);
+        }
+    };
+}
+
+mod babybear_stir_pcs {
+    use super::*;
+
+    pub(super) type Val = BabyBear;
+    pub(super) type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    pub(super) type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    pub(super) type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    pub(super) type MyPcs = StirPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+
+    pub(super) fn get_pcs(log_blowup: usize, log_folding_factor: usize) -> (MyPcs, Challenger) {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = ValMmcs::new(hash, compress);
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let stir_config = StirConfig {
+            log_blowup,
+            log_folding_factor,
+            log_final_poly_len: 0,
+            security_bits: 40,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, stir_config);
+        (pcs, Challenger::new(perm.clone()))
+    }
+
+    /// A PCS at a realistic security level, with `create_benchmark_stir_config`.
+    pub(super) fn get_benchmark_pcs() -> (MyPcs, Challenger) {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let val_mmcs = ValMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm.clone()));
+        let stir_config = create_benchmark_stir_config(ChallengeMmcs::new(val_mmcs.clone()));
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, stir_config);
+        (pcs, Challenger::new(perm))
+    }
+
+    /// A STIR config with few enough queries that every quotient is by fewer points than the
+    /// degree bound of the polynomial it's taken of.
+    pub(super) fn get_test_stir_config() -> (StirConfig<ChallengeMmcs>, Challenger) {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let val_mmcs = ValMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm.clone()));
+        let stir_config = create_test_stir_config(ChallengeMmcs::new(val_mmcs));
+        (stir_config, Challenger::new(perm))
+    }
+
+    mod blowup_1_folding_4 {
+        make_tests_for_pcs!(super::get_pcs(1, 2));
+    }
+    mod blowup_2_folding_4 {
+        make_tests_for_pcs!(super::get_pcs(2, 2));
+    }
+    mod blowup_1_folding_8 {
+        make_tests_for_pcs!(super::get_pcs(1, 3));
+    }
+}
+
+#[test]
+fn rejects_wrong_opening() {
+    do_test_rejects_wrong_opening(&babybear_stir_pcs::get_pcs(1, 2));
+}
+
+#[test]
+fn benchmark_security_level() {
+    let p = babybear_stir_pcs::get_benchmark_pcs();
+    do_test_stir_pcs(&p, &[&[12, 9], &[10]]);
+    do_test_rejects_wrong_opening(&p);
+}
+
+fn do_test_rejects_wrong_opening(
+    (pcs, challenger): &(babybear_stir_pcs::MyPcs, babybear_stir_pcs::Challenger),
+) {
+    use babybear_stir_pcs::*;
+
+    let mut rng = seeded_rng();
+    let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(pcs, 1 << 6);
+    let (commit, data) = <MyPcs as Pcs<Challenge, Challenger>>::commit(
+        pcs,
+        vec![(domain, RowMajorMatrix::<Val>::rand(&mut rng, 1 << 6, 4))],
+    );
+
+    let mut p_challenger = challenger.clone();
+    p_challenger.observe(commit.clone());
+    let zeta: Challenge = p_challenger.sample_ext_element();
+    let (mut openings, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut p_challenger);
+
+    openings[0][0][0][0] += Challenge::ONE;
+    let mut v_challenger = challenger.clone();
+    v_challenger.observe(commit.clone());
+    let claims = vec![(
+        commit,
+        vec![(domain, vec![(zeta, openings[0][0][0].clone())])],
+    )];
+    assert!(pcs.verify(claims, &proof, &mut v_challenger).is_err());
+}
+
+#[test]
+fn rejects_missing_input_openings() {
+    use babybear_stir_pcs::*;
+
+    let (pcs, challenger) = get_pcs(1, 2);
+    let mut rng = seeded_rng();
+    let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 1 << 6);
+    let (commit, data) = <MyPcs as Pcs<Challenge, Challenger>>::commit(
+        &pcs,
+        vec![(domain, RowMajorMatrix::<Val>::rand(&mut rng, 1 << 6, 4))],
+    );
+
+    let mut p_challenger = challenger.clone();
+    p_challenger.observe(commit.clone());
+    let zeta: Challenge = p_challenger.sample_ext_element();
+    let (openings, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut p_challenger);
+    let claims = vec![(
+        commit.clone(),
+        vec![(domain, vec![(zeta, openings[0][0][0].clone())])],
+    )];
+    let verify = |proof: &<MyPcs as Pcs<Challenge, Challenger>>::Proof| {
+        let mut v_challenger = challenger.clone();
+        v_challenger.observe(commit.clone());
+        let _: Challenge = v_challenger.sample_ext_element();
+        pcs.verify(claims.clone(), proof, &mut v_challenger)
+    };
+    assert!(verify(&proof).is_ok());
+
+    // Without any input openings, every query's combined input would be zero, which a prover
+    // could match by committing to the zero codeword.
+    let mut stripped = proof;
+    stripped.input_proof.clear();
+    assert!(matches!(
+        verify(&stripped),
+        Err(StirError::InvalidProofShape)
+    ));
+}
+
+#[test]
+fn rejects_high_degree_quotient() {
+    use babybear_stir_pcs::*;
+
+    let (config, challenger) = get_test_stir_config();
+    let mut rng = seeded_rng();
+    let log_height = 9;
+
+    // The input has one coefficient too many. Folding keeps it one too many, and only degree
+    // correction stops the quotient of the folded polynomial from hiding it.
+    let mut coeffs: Vec<Challenge> = (0..=1 << (log_height - config.log_blowup))
+        .map(|_| rng.gen())
+        .collect();
+    coeffs.resize(1 << log_height, Challenge::ZERO);
+    let mut input = Radix2Dit::default().coset_dft(coeffs, Challenge::from_base(Val::GENERATOR));
+    reverse_slice_index_bits(&mut input);
+
+    let proof = prover::prove::<Val, _, _, _, _>(
+        &config,
+        input.clone(),
+        &mut challenger.clone(),
+        |indices| indices.iter().map(|&index| input[index]).collect_vec(),
+    );
+    let result = verifier::verify::<Val, _, _, _, _, ()>(
+        &config,
+        log_height,
+        &proof,
+        &mut challenger.clone(),
+        |_indices, input_values| Ok(input_values.clone()),
+    );
+    assert!(matches!(result, Err(StirError::FinalPolyMismatch)));
+}
@@ -0,0 +1,148 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use p3_air::{Air, BaseAir};
+use p3_baby_bear::{BabyBear, GenericPoseidon2LinearLayersBabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, FieldAlgebra, PackedValue};
+use p3_fri::TwoAdicFriPcs;
+use p3_keccak_air::KeccakAir;
+use p3_matrix::dense::RowMajorMatrixView;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_poseidon2_air::{RoundConstants, VectorizedPoseidon2Air};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    get_symbolic_constraints, CompiledConstraints, PackedChallenge, PackedVal,
+    ProverConstraintFolder, StarkConfig, SymbolicAirBuilder,
+};
+use rand::{thread_rng, Rng};
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+type Poseidon2Air =
+    VectorizedPoseidon2Air<Val, GenericPoseidon2LinearLayersBabyBear, 16, 7, 1, 4, 13, 8>;
+
+/// The number of packed rows evaluated in each benchmark iteration.
+const NUM_ROWS: usize = 8;
+
+/// The number of packed rows whose compiled constraints are evaluated together, as in the prover.
+const BATCH_SIZE: usize = 4;
+
+/// Compare evaluating the constraints of `air` on `NUM_ROWS` packed rows by running `Air::eval`
+/// with evaluating its compiled constraints, one row at a time and in a batch.
+fn bench_constraint_eval<A>(c: &mut Criterion, name: &str, air: &A)
+where
+    A: Air<SymbolicAirBuilder<Val>> + for<'a> Air<ProverConstraintFolder<'a, MyConfig>>,
+{
+    let constraints = get_symbolic_constraints::<Val, A>(air, 0, 0);
+    let compiled = CompiledConstraints::new(&constraints);
+
+    let mut rng = thread_rng();
+    let width = <A as BaseAir<Val>>::width(air);
+    let mains: [Vec<PackedVal<MyConfig>>; NUM_ROWS] = core::array::from_fn(|_| {
+        (0..2 * width)
+            .map(|_| PackedVal::<MyConfig>::from_fn(|_| rng.gen()))
+            .collect()
+    });
+    let selectors: [PackedVal<MyConfig>; 3] =
+        core::array::from_fn(|_| PackedVal::<MyConfig>::from_fn(|_| rng.gen()));
+    let periodic_values = (0..<A as BaseAir<Val>>::periodic_columns(air).len())
+        .map(|_| PackedVal::<MyConfig>::from_fn(|_| rng.gen()))
+        .collect::<Vec<_>>();
+    let alpha_powers = (0..constraints.len())
+        .map(|_| PackedChallenge::<MyConfig>::from_f(rng.gen::<Challenge>()))
+        .collect::<Vec<_>>();
+    let public_values = vec![];
+
+    let folders = || {
+        mains
+            .each_ref()
+            .map(|main| ProverConstraintFolder::<MyConfig> {
+                preprocessed: RowMajorMatrixView::new(&[], 0),
+                main: RowMajorMatrixView::new(main, width),
+                permutation: RowMajorMatrixView::new(&[], 0),
+                permutation_challenges: &[],
+                cumulative_sum: PackedChallenge::<MyConfig>::ZERO,
+                public_values: &public_values,
+                is_first_row: selectors[0],
+                is_last_row: selectors[1],
+                is_transition_windows: vec![selectors[2]],
+                is_rows: vec![],
+                periodic_values: periodic_values.clone(),
+                alpha_powers: &alpha_powers,
+                accumulator: PackedChallenge::<MyConfig>::ZERO,
+                constraint_index: 0,
+            })
+    };
+
+    let mut evaluated = folders();
+    for folder in &mut evaluated {
+        air.eval(folder);
+    }
+    let mut compiled_evaluated = folders();
+    for batch in compiled_evaluated.chunks_exact_mut(BATCH_SIZE) {
+        let batch: &mut [_; BATCH_SIZE] = batch.try_into().unwrap();
+        ProverConstraintFolder::eval_compiled_batch(batch, &compiled);
+    }
+    for (evaluated, compiled_evaluated) in evaluated.iter().zip(&compiled_evaluated) {
+        assert_eq!(evaluated.accumulator, compiled_evaluated.accumulator);
+    }
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(
+        (NUM_ROWS * PackedVal::<MyConfig>::WIDTH) as u64,
+    ));
+    group.bench_function("eval", |b| {
+        b.iter(|| {
+            let mut folders = folders();
+            for folder in &mut folders {
+                air.eval(folder);
+            }
+            folders.map(|folder| folder.accumulator)
+        })
+    });
+    group.bench_function("compiled", |b| {
+        b.iter(|| {
+            let mut folders = folders();
+            for folder in &mut folders {
+                folder.eval_compiled(&compiled);
+            }
+            folders.map(|folder| folder.accumulator)
+        })
+    });
+    group.bench_function("compiled batch", |b| {
+        b.iter(|| {
+            let mut folders = folders();
+            for batch in folders.chunks_exact_mut(BATCH_SIZE) {
+                let batch: &mut [_; BATCH_SIZE] = batch.try_into().unwrap();
+                ProverConstraintFolder::eval_compiled_batch(batch, &compiled);
+            }
+            folders.map(|folder| folder.accumulator)
+        })
+    });
+    group.finish();
+}
+
+fn bench_keccak(c: &mut Criterion) {
+    bench_constraint_eval(c, "keccak constraints", &KeccakAir {});
+}
+
+fn bench_poseidon2(c: &mut Criterion) {
+    let air = Poseidon2Air::new(RoundConstants::from_rng(&mut thread_rng()));
+    bench_constraint_eval(c, "poseidon2 constraints", &air);
+}
+
+criterion_group!(benches, bench_keccak, bench_poseidon2);
+criterion_main!(benches);
@@ -1,63 +1,408 @@
+use alloc::collections::BTreeSet;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues};
-use p3_field::Field;
+use itertools::Itertools;
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PeriodicAirBuilder,
+    PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field};
 use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
 use p3_matrix::stack::VerticalPair;
 use p3_matrix::Matrix;
 use tracing::instrument;
 
+use crate::{Entry, SymbolicAirBuilder, SymbolicExpression};
+
+/// Check that every row of `main`, along with the matching row of the after-challenge trace if
+/// there is one, satisfies the AIR's constraints, panicking on the first row which doesn't.
+#[cfg(debug_assertions)]
 #[instrument(name = "check constraints", skip_all)]
-pub(crate) fn check_constraints<F, A>(air: &A, main: &RowMajorMatrix<F>, public_values: &Vec<F>)
+pub(crate) fn check_constraints<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    after_challenge: Option<&RowMajorMatrix<EF>>,
+    challenges: &[EF],
+    public_values: &[F],
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let preprocessed = air.preprocessed_trace();
+    for_each_row(
+        air,
+        preprocessed.as_ref(),
+        main,
+        after_challenge,
+        challenges,
+        public_values,
+        |row, failures| {
+            if let Some(failure) = failures.into_iter().next() {
+                assert_eq!(
+                    failure.value,
+                    EF::ZERO,
+                    "constraints had nonzero value on row {row}: {}",
+                    ConstraintLabel(failure.constraint_index, failure.name.as_deref()),
+                );
+            }
+        },
+    );
+}
+
+/// Evaluate every constraint on every row of `main`, along with the matching row of the
+/// after-challenge trace if there is one, and report each nonzero evaluation.
+///
+/// Unlike the check the prover runs in debug builds, this doesn't stop at the first failure and
+/// is available in release builds. Each failure comes with the values of the trace cells, public
+/// values and challenges its constraint depends on.
+#[instrument(name = "check constraints report", skip_all)]
+pub fn check_constraints_report<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    after_challenge: Option<&RowMajorMatrix<EF>>,
+    challenges: &[EF],
+    public_values: &[F],
+) -> ConstraintReport<EF>
 where
     F: Field,
-    A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F>> + for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let height = main.height();
+    let preprocessed = air.preprocessed_trace();
+
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed.as_ref().map_or(0, |prep| prep.width),
+        main.width,
+        air.window_size(),
+        air.periodic_columns().len(),
+        after_challenge.map_or(0, |trace| trace.width),
+        challenges.len(),
+        public_values.len(),
+    );
+    air.eval(&mut builder);
+    let constraints = builder.constraints();
+    let involved_variables = constraints.iter().map(involved_variables).collect_vec();
+    let periodic_columns = air.periodic_columns();
+
+    let mut failures = vec![];
+    for_each_row(
+        air,
+        preprocessed.as_ref(),
+        main,
+        after_challenge,
+        challenges,
+        public_values,
+        |row, row_failures| {
+            let value_of = |entry, index| match entry {
+                Entry::Preprocessed { offset } => preprocessed
+                    .as_ref()
+                    .map(|prep| EF::from_base(prep.get((row + offset) % height, index))),
+                Entry::Main { offset } => {
+                    Some(EF::from_base(main.get((row + offset) % height, index)))
+                }
+                Entry::Permutation { offset } => {
+                    after_challenge.map(|trace| trace.get((row + offset) % height, index))
+                }
+                Entry::Public => Some(EF::from_base(public_values[index])),
+                Entry::Periodic => {
+                    let column = &periodic_columns[index];
+                    Some(EF::from_base(column[row % column.len()]))
+                }
+                Entry::Challenge => Some(challenges[index]),
+                Entry::CumulativeSum => None,
+            };
+            for failure in row_failures {
+                let involved = involved_variables
+                    .get(failure.constraint_index)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|&(entry, index)| {
+                        value_of(entry, index).map(|value| InvolvedValue {
+                            entry,
+                            index,
+                            value,
+                        })
+                    })
+                    .collect();
+                failures.push(ConstraintFailure {
+                    row,
+                    constraint_index: failure.constraint_index,
+                    name: failure.name,
+                    value: failure.value,
+                    involved,
+                });
+            }
+        },
+    );
+
+    ConstraintReport {
+        num_rows: height,
+        num_constraints: constraints.len(),
+        failures,
+    }
+}
+
+/// The result of `check_constraints_report`.
+#[derive(Clone, Debug)]
+pub struct ConstraintReport<EF> {
+    pub num_rows: usize,
+    pub num_constraints: usize,
+    /// Every nonzero constraint evaluation, ordered by row and then by constraint index.
+    pub failures: Vec<ConstraintFailure<EF>>,
+}
+
+impl<EF> ConstraintReport<EF> {
+    /// Whether every constraint holds on every row.
+    pub fn is_satisfied(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A constraint which evaluated to a nonzero value on some row.
+#[derive(Clone, Debug)]
+pub struct ConstraintFailure<EF> {
+    pub row: usize,
+    /// The position of the constraint in the order the AIR asserts its constraints.
+    pub constraint_index: usize,
+    /// The name given to the constraint with `assert_zero_named`, if any.
+    pub name: Option<String>,
+    pub value: EF,
+    /// The values of the variables the constraint depends on.
+    pub involved: Vec<InvolvedValue<EF>>,
+}
+
+/// The value of a variable on the row of a failed constraint.
+#[derive(Clone, Debug)]
+pub struct InvolvedValue<EF> {
+    pub entry: Entry,
+    pub index: usize,
+    pub value: EF,
+}
+
+impl<EF: fmt::Display> fmt::Display for ConstraintReport<EF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_satisfied() {
+            return write!(
+                f,
+                "all {} constraints hold on all {} rows",
+                self.num_constraints, self.num_rows
+            );
+        }
+        write!(
+            f,
+            "{} failed constraint evaluations over {} rows",
+            self.failures.len(),
+            self.num_rows
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<EF: fmt::Display> fmt::Display for ConstraintFailure<EF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row {}, {}: evaluated to {}",
+            self.row,
+            ConstraintLabel(self.constraint_index, self.name.as_deref()),
+            self.value
+        )?;
+        for (i, involved) in self.involved.iter().enumerate() {
+            let separator = if i == 0 { "; " } else { ", " };
+            write!(f, "{separator}{involved}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<EF: fmt::Display> fmt::Display for InvolvedValue<EF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let index = self.index;
+        match self.entry {
+            Entry::Preprocessed { offset } => write!(f, "preprocessed[row + {offset}][{index}]")?,
+            Entry::Main { offset } => write!(f, "main[row + {offset}][{index}]")?,
+            Entry::Permutation { offset } => write!(f, "after_challenge[row + {offset}][{index}]")?,
+            Entry::Public => write!(f, "public_values[{index}]")?,
+            Entry::Periodic => write!(f, "periodic[row][{index}]")?,
+            Entry::Challenge => write!(f, "challenges[{index}]")?,
+            Entry::CumulativeSum => write!(f, "cumulative_sum")?,
+        }
+        write!(f, " = {}", self.value)
+    }
+}
+
+/// Formats a constraint as its index, followed by its name if it has one.
+struct ConstraintLabel<'a>(usize, Option<&'a str>);
+
+impl fmt::Display for ConstraintLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(name) => write!(f, "constraint {} ({name:?})", self.0),
+            None => write!(f, "constraint {}", self.0),
+        }
+    }
+}
+
+/// The distinct variables a constraint depends on, in the order they're first reached.
+fn involved_variables<F>(constraint: &SymbolicExpression<F>) -> Vec<(Entry, usize)> {
+    // Subexpressions may be shared, so we only visit each once.
+    let mut visited = BTreeSet::new();
+    let mut variables = vec![];
+    let mut stack = vec![constraint];
+    while let Some(expr) = stack.pop() {
+        let children = match expr {
+            SymbolicExpression::Variable(v) => {
+                if !variables.contains(&(v.entry, v.index)) {
+                    variables.push((v.entry, v.index));
+                }
+                continue;
+            }
+            SymbolicExpression::Add { x, y, .. }
+            | SymbolicExpression::Sub { x, y, .. }
+            | SymbolicExpression::Mul { x, y, .. } => vec![y, x],
+            SymbolicExpression::Neg { x, .. } => vec![x],
+            _ => continue,
+        };
+        for child in children {
+            if visited.insert(Rc::as_ptr(child)) {
+                stack.push(child);
+            }
+        }
+    }
+    variables
+}
+
+/// A constraint which evaluated to a nonzero value, recorded by `DebugConstraintBuilder`.
+#[derive(Debug)]
+pub(crate) struct RowFailure<EF> {
+    constraint_index: usize,
+    name: Option<String>,
+    value: EF,
+}
+
+/// Evaluate the constraints on every row, passing the failures of each row to `on_row`.
+fn for_each_row<F, EF, A>(
+    air: &A,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    main: &RowMajorMatrix<F>,
+    after_challenge: Option<&RowMajorMatrix<EF>>,
+    challenges: &[EF],
+    public_values: &[F],
+    mut on_row: impl FnMut(usize, Vec<RowFailure<EF>>),
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
 {
     let height = main.height();
+    let window_size = air.window_size();
+    let periodic_columns = air.periodic_columns();
+
+    // The rows `i, i + 1, ..., i + window_size - 1` of `matrix`, wrapping around.
+    let window = |matrix: &RowMajorMatrix<F>, i: usize| {
+        (0..window_size)
+            .flat_map(|offset| matrix.row((i + offset) % height))
+            .collect_vec()
+    };
 
     (0..height).for_each(|i| {
         let i_next = (i + 1) % height;
 
-        let local = main.row_slice(i);
-        let next = main.row_slice(i_next);
-        let main = VerticalPair::new(
-            RowMajorMatrixView::new_row(&*local),
-            RowMajorMatrixView::new_row(&*next),
+        let preprocessed_rows = preprocessed.map_or_else(Vec::new, |prep| window(prep, i));
+        let preprocessed_rows = RowMajorMatrixView::new(
+            &preprocessed_rows,
+            preprocessed.map_or(0, |prep| prep.width),
+        );
+
+        let main_rows = window(main, i);
+        let main_rows = RowMajorMatrixView::new(&main_rows, main.width);
+
+        let (after_challenge_local, after_challenge_next) =
+            after_challenge.map_or((vec![], vec![]), |trace| {
+                (
+                    trace.row_slice(i).to_vec(),
+                    trace.row_slice(i_next).to_vec(),
+                )
+            });
+        let after_challenge_rows = VerticalPair::new(
+            RowMajorMatrixView::new_row(after_challenge_local.as_slice()),
+            RowMajorMatrixView::new_row(after_challenge_next.as_slice()),
         );
 
         let mut builder = DebugConstraintBuilder {
             row_index: i,
-            main,
+            height,
+            preprocessed: preprocessed_rows,
+            main: main_rows,
+            window_size,
+            after_challenge: after_challenge_rows,
+            challenges,
             public_values,
+            periodic_values: periodic_columns
+                .iter()
+                .map(|column| column[i % column.len()])
+                .collect(),
             is_first_row: F::from_bool(i == 0),
             is_last_row: F::from_bool(i == height - 1),
-            is_transition: F::from_bool(i != height - 1),
+            constraint_index: 0,
+            failures: vec![],
         };
 
         air.eval(&mut builder);
+        on_row(i, builder.failures);
     });
 }
 
-/// An `AirBuilder` which asserts that each constraint is zero, allowing any failed constraints to
-/// be detected early.
+/// An `AirBuilder` which evaluates the constraints on a single row, recording any which are
+/// nonzero.
 #[derive(Debug)]
-pub struct DebugConstraintBuilder<'a, F: Field> {
+pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F>> {
     row_index: usize,
-    main: VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>,
+    height: usize,
+    preprocessed: RowMajorMatrixView<'a, F>,
+    main: RowMajorMatrixView<'a, F>,
+    window_size: usize,
+    after_challenge: VerticalPair<RowMajorMatrixView<'a, EF>, RowMajorMatrixView<'a, EF>>,
+    challenges: &'a [EF],
     public_values: &'a [F],
+    periodic_values: Vec<F>,
     is_first_row: F,
     is_last_row: F,
-    is_transition: F,
+    constraint_index: usize,
+    failures: Vec<RowFailure<EF>>,
+}
+
+impl<F: Field, EF: ExtensionField<F>> DebugConstraintBuilder<'_, F, EF> {
+    fn record(&mut self, name: Option<&str>, value: EF) {
+        if !value.is_zero() {
+            self.failures.push(RowFailure {
+                constraint_index: self.constraint_index,
+                name: name.map(String::from),
+                value,
+            });
+        }
+        self.constraint_index += 1;
+    }
 }
 
-impl<'a, F> AirBuilder for DebugConstraintBuilder<'a, F>
+impl<'a, F, EF> AirBuilder for DebugConstraintBuilder<'a, F, EF>
 where
     F: Field,
+    EF: ExtensionField<F>,
 {
     type F = F;
     type Expr = F;
     type Var = F;
-    type M = VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>;
+    type M = RowMajorMatrixView<'a, F>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -72,37 +417,75 @@ where
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        assert!(
+            (2..=self.window_size).contains(&size),
+            "window of {size} rows exceeds the AIR's window size"
+        );
+        F::from_bool(self.row_index + size <= self.height)
+    }
+
+    fn is_row(&self, row: usize) -> Self::Expr {
+        assert!(row < self.height, "row {row} is outside the trace");
+        F::from_bool(self.row_index == row)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
-        assert_eq!(
-            x.into(),
-            F::ZERO,
-            "constraints had nonzero value on row {}",
-            self.row_index
-        );
+        self.record(None, EF::from_base(x.into()));
     }
 
-    fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
-        let x = x.into();
-        let y = y.into();
-        assert_eq!(
-            x, y,
-            "values didn't match on row {}: {} != {}",
-            self.row_index, x, y
-        );
+    fn assert_zero_named<I: Into<Self::Expr>>(&mut self, name: &str, x: I) {
+        self.record(Some(name), EF::from_base(x.into()));
     }
 }
 
-impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
+impl<F: Field, EF: ExtensionField<F>> AirBuilderWithPublicValues
+    for DebugConstraintBuilder<'_, F, EF>
+{
     type PublicVar = Self::F;
 
     fn public_values(&self) -> &[Self::F] {
         self.public_values
     }
 }
+
+impl<F: Field, EF: ExtensionField<F>> PeriodicAirBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type PeriodicVar = Self::F;
+
+    fn periodic_values(&self) -> &[Self::F] {
+        &self.periodic_values
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> PairBuilder for DebugConstraintBuilder<'_, F, EF> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type EF = EF;
+    type ExprEF = EF;
+    type VarEF = EF;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.record(None, x.into());
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> PermutationAirBuilder
+    for DebugConstraintBuilder<'a, F, EF>
+{
+    type MP = VerticalPair<RowMajorMatrixView<'a, EF>, RowMajorMatrixView<'a, EF>>;
+    type RandomVar = EF;
+
+    fn permutation(&self) -> Self::MP {
+        self.after_challenge
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.challenges
+    }
+}
@@ -0,0 +1,716 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use p3_field::{Field, FieldAlgebra};
+
+use crate::folder::row_selector;
+use crate::{
+    Entry, PackedChallenge, PackedVal, ProverConstraintFolder, StarkGenericConfig,
+    SymbolicExpression, Val,
+};
+
+/// A set of constraints compiled into a flat list of instructions, for fast evaluation over
+/// packed rows of the quotient domain.
+///
+/// Structurally equal subexpressions are computed once, however many constraints use them, and
+/// operations on constants are folded. Each constraint is folded into the accumulator as soon as
+/// it's computed, and registers are reused once their values are no longer needed, which keeps
+/// the register files small enough to stay in cache.
+#[derive(Clone, Debug)]
+pub struct CompiledConstraints<F> {
+    /// The values loaded into the first base field registers before the instructions run.
+    base_inputs: Vec<Input<F>>,
+    /// The values loaded into the first extension field registers before the instructions run.
+    ext_inputs: Vec<Input<F>>,
+    instructions: Vec<Instruction>,
+    num_base_registers: usize,
+    num_ext_registers: usize,
+    num_constraints: usize,
+}
+
+/// A value which doesn't depend on any other.
+#[derive(Clone, Copy, Debug)]
+enum Input<F> {
+    Variable(Entry, usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransitionWindow(usize),
+    IsRow(usize),
+    Constant(F),
+}
+
+/// An operation on registers. Registers are indices into the base field register file, unless
+/// the variant's name says otherwise.
+#[derive(Clone, Copy, Debug)]
+enum Instruction {
+    Add {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    Sub {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    Mul {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    Neg {
+        out: u32,
+        x: u32,
+    },
+    ExtAdd {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    ExtSub {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    ExtMul {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    ExtNeg {
+        out: u32,
+        x: u32,
+    },
+    /// `out` and `x` are extension field registers.
+    ExtAddBase {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    /// `out` and `x` are extension field registers.
+    ExtSubBase {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    /// `out` and `y` are extension field registers.
+    BaseSubExt {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    /// `out` and `x` are extension field registers.
+    ExtMulBase {
+        out: u32,
+        x: u32,
+        y: u32,
+    },
+    /// Add the register's value, times the next power of alpha, to the accumulator.
+    Accumulate(u32),
+    /// Add the extension field register's value, times the next power of alpha, to the
+    /// accumulator.
+    AccumulateExt(u32),
+}
+
+/// A register in either the base field or the extension field register file.
+///
+/// Values which depend on the after-challenge trace, the challenges or the cumulative sum are in
+/// the extension field, and all others in the base field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Register {
+    Base(usize),
+    Ext(usize),
+}
+
+/// An operation on virtual registers, each of which is written once.
+#[derive(Clone, Copy, Debug)]
+enum Op<F> {
+    Input(Input<F>),
+    Add(Register, Register),
+    Sub(Register, Register),
+    Mul(Register, Register),
+    Neg(Register),
+}
+
+/// An operation, with constants replaced by their index, used to find repeated operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum OpKey {
+    Variable(Entry, usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransitionWindow(usize),
+    IsRow(usize),
+    Constant(usize),
+    Add(Register, Register),
+    Sub(Register, Register),
+    Mul(Register, Register),
+    Neg(Register),
+}
+
+/// A step of the schedule, on virtual registers.
+#[derive(Clone, Copy, Debug)]
+enum Step<F> {
+    Compute(Op<F>, Register),
+    Accumulate(Register),
+}
+
+impl<F: Field> CompiledConstraints<F> {
+    /// Compile the given constraints, as returned by `get_symbolic_constraints` or one of its
+    /// variants.
+    pub fn new(constraints: &[SymbolicExpression<F>]) -> Self {
+        let mut compiler = Compiler {
+            ops: Vec::new(),
+            outputs: Vec::new(),
+            registers: BTreeMap::new(),
+            constants: Vec::new(),
+            constant_values: BTreeMap::new(),
+            shared: BTreeMap::new(),
+            virtual_registers: RegisterFiles::default(),
+        };
+        let roots = constraints
+            .iter()
+            .map(|constraint| compiler.compile(constraint))
+            .collect::<Vec<_>>();
+        compiler.finish(&roots)
+    }
+
+    /// The number of compiled constraints.
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// The number of instructions evaluated per row, not counting loads of inputs.
+    pub fn num_instructions(&self) -> usize {
+        self.instructions.len()
+    }
+}
+
+impl<F> Input<F> {
+    fn is_ext(&self) -> bool {
+        matches!(
+            self,
+            Self::Variable(
+                Entry::Permutation { .. } | Entry::Challenge | Entry::CumulativeSum,
+                _
+            )
+        )
+    }
+}
+
+impl Instruction {
+    /// Lower an operation on the registers it was assigned, writing to `out`.
+    fn new<F>(op: Op<F>, out: Register) -> Self {
+        use Register::{Base, Ext};
+        let index = |r: usize| u32::try_from(r).expect("too many registers");
+        match (op, out) {
+            (Op::Add(Base(x), Base(y)), Base(out)) => Self::Add {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Sub(Base(x), Base(y)), Base(out)) => Self::Sub {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Mul(Base(x), Base(y)), Base(out)) => Self::Mul {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Neg(Base(x)), Base(out)) => Self::Neg {
+                out: index(out),
+                x: index(x),
+            },
+            (Op::Add(Ext(x), Ext(y)), Ext(out)) => Self::ExtAdd {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Sub(Ext(x), Ext(y)), Ext(out)) => Self::ExtSub {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Mul(Ext(x), Ext(y)), Ext(out)) => Self::ExtMul {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Neg(Ext(x)), Ext(out)) => Self::ExtNeg {
+                out: index(out),
+                x: index(x),
+            },
+            (Op::Add(Ext(x), Base(y)) | Op::Add(Base(y), Ext(x)), Ext(out)) => Self::ExtAddBase {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Sub(Ext(x), Base(y)), Ext(out)) => Self::ExtSubBase {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Sub(Base(x), Ext(y)), Ext(out)) => Self::BaseSubExt {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            (Op::Mul(Ext(x), Base(y)) | Op::Mul(Base(y), Ext(x)), Ext(out)) => Self::ExtMulBase {
+                out: index(out),
+                x: index(x),
+                y: index(y),
+            },
+            _ => unreachable!("inputs are loaded, and results are in the field of their operands"),
+        }
+    }
+
+    fn accumulate(register: Register) -> Self {
+        match register {
+            Register::Base(x) => Self::Accumulate(x.try_into().expect("too many registers")),
+            Register::Ext(x) => Self::AccumulateExt(x.try_into().expect("too many registers")),
+        }
+    }
+}
+
+impl<F> Op<F> {
+    /// Whether the result is in the extension field.
+    fn is_ext(&self) -> bool {
+        match self {
+            Self::Input(input) => input.is_ext(),
+            _ => self
+                .operands()
+                .any(|operand| matches!(operand, Register::Ext(_))),
+        }
+    }
+
+    /// The distinct registers read by the operation.
+    fn operands(&self) -> impl Iterator<Item = Register> {
+        let (x, y) = match *self {
+            Self::Add(x, y) | Self::Sub(x, y) | Self::Mul(x, y) => {
+                (Some(x), Some(y).filter(|&y| y != x))
+            }
+            Self::Neg(x) => (Some(x), None),
+            Self::Input(_) => (None, None),
+        };
+        x.into_iter().chain(y)
+    }
+
+    fn map_operands(self, f: impl Fn(Register) -> Register) -> Self {
+        match self {
+            Self::Add(x, y) => Self::Add(f(x), f(y)),
+            Self::Sub(x, y) => Self::Sub(f(x), f(y)),
+            Self::Mul(x, y) => Self::Mul(f(x), f(y)),
+            Self::Neg(x) => Self::Neg(f(x)),
+            input @ Self::Input(_) => input,
+        }
+    }
+}
+
+/// The registers of one field, some of which may be free for reuse.
+#[derive(Default)]
+struct RegisterFile {
+    len: usize,
+    free: Vec<usize>,
+}
+
+#[derive(Default)]
+struct RegisterFiles {
+    base: RegisterFile,
+    ext: RegisterFile,
+}
+
+impl RegisterFiles {
+    /// A new register, never used before.
+    fn push(&mut self, is_ext: bool) -> Register {
+        let (file, register): (_, fn(usize) -> Register) = if is_ext {
+            (&mut self.ext, Register::Ext)
+        } else {
+            (&mut self.base, Register::Base)
+        };
+        file.len += 1;
+        register(file.len - 1)
+    }
+
+    /// A free register, if any, or else a new one.
+    fn reuse(&mut self, is_ext: bool) -> Register {
+        let free = if is_ext {
+            self.ext.free.pop().map(Register::Ext)
+        } else {
+            self.base.free.pop().map(Register::Base)
+        };
+        free.unwrap_or_else(|| self.push(is_ext))
+    }
+
+    fn free(&mut self, register: Register) {
+        match register {
+            Register::Base(r) => self.base.free.push(r),
+            Register::Ext(r) => self.ext.free.push(r),
+        }
+    }
+}
+
+/// Compiles constraints into operations on virtual registers, and then schedules them and assigns
+/// them to registers.
+struct Compiler<F> {
+    ops: Vec<Op<F>>,
+    /// The virtual register written by each operation.
+    outputs: Vec<Register>,
+    /// The virtual register holding the result of each distinct operation.
+    registers: BTreeMap<OpKey, Register>,
+    /// Each distinct constant, indexed as in `OpKey::Constant`.
+    constants: Vec<F>,
+    /// The value of each virtual register holding a constant.
+    constant_values: BTreeMap<Register, F>,
+    /// The virtual register holding each shared subexpression which was already compiled.
+    shared: BTreeMap<*const SymbolicExpression<F>, Register>,
+    virtual_registers: RegisterFiles,
+}
+
+impl<F: Field> Compiler<F> {
+    fn compile(&mut self, expr: &SymbolicExpression<F>) -> Register {
+        match expr {
+            SymbolicExpression::Variable(v) => self.push(
+                Op::Input(Input::Variable(v.entry, v.index)),
+                OpKey::Variable(v.entry, v.index),
+            ),
+            SymbolicExpression::IsFirstRow => {
+                self.push(Op::Input(Input::IsFirstRow), OpKey::IsFirstRow)
+            }
+            SymbolicExpression::IsLastRow => {
+                self.push(Op::Input(Input::IsLastRow), OpKey::IsLastRow)
+            }
+            SymbolicExpression::IsTransition => self.push(
+                Op::Input(Input::IsTransitionWindow(2)),
+                OpKey::IsTransitionWindow(2),
+            ),
+            SymbolicExpression::IsTransitionWindow(size) => self.push(
+                Op::Input(Input::IsTransitionWindow(*size)),
+                OpKey::IsTransitionWindow(*size),
+            ),
+            SymbolicExpression::IsRow(row) => {
+                self.push(Op::Input(Input::IsRow(*row)), OpKey::IsRow(*row))
+            }
+            SymbolicExpression::Constant(c) => self.constant(*c),
+            SymbolicExpression::Add { x, y, .. } => {
+                let (x, y) = (self.compile_shared(x), self.compile_shared(y));
+                match (self.constant_value(x), self.constant_value(y)) {
+                    (Some(x), Some(y)) => self.constant(x + y),
+                    (Some(x), _) if x.is_zero() => y,
+                    (_, Some(y)) if y.is_zero() => x,
+                    // Addition commutes, so we order the operands to find more repeats.
+                    _ => {
+                        let (x, y) = (x.min(y), x.max(y));
+                        self.push(Op::Add(x, y), OpKey::Add(x, y))
+                    }
+                }
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                let (x, y) = (self.compile_shared(x), self.compile_shared(y));
+                match (self.constant_value(x), self.constant_value(y)) {
+                    (Some(x), Some(y)) => self.constant(x - y),
+                    (Some(x), _) if x.is_zero() => self.push(Op::Neg(y), OpKey::Neg(y)),
+                    (_, Some(y)) if y.is_zero() => x,
+                    _ => self.push(Op::Sub(x, y), OpKey::Sub(x, y)),
+                }
+            }
+            SymbolicExpression::Mul { x, y, .. } => {
+                let (x, y) = (self.compile_shared(x), self.compile_shared(y));
+                match (self.constant_value(x), self.constant_value(y)) {
+                    (Some(x), Some(y)) => self.constant(x * y),
+                    (Some(c), _) | (_, Some(c)) if c.is_zero() => self.constant(F::ZERO),
+                    (Some(x), _) if x.is_one() => y,
+                    (_, Some(y)) if y.is_one() => x,
+                    // Multiplication commutes, so we order the operands to find more repeats.
+                    _ => {
+                        let (x, y) = (x.min(y), x.max(y));
+                        self.push(Op::Mul(x, y), OpKey::Mul(x, y))
+                    }
+                }
+            }
+            SymbolicExpression::Neg { x, .. } => {
+                let x = self.compile_shared(x);
+                match self.constant_value(x) {
+                    Some(x) => self.constant(-x),
+                    None => self.push(Op::Neg(x), OpKey::Neg(x)),
+                }
+            }
+        }
+    }
+
+    fn compile_shared(&mut self, expr: &Rc<SymbolicExpression<F>>) -> Register {
+        let key = Rc::as_ptr(expr);
+        if let Some(&register) = self.shared.get(&key) {
+            return register;
+        }
+        let register = self.compile(expr);
+        self.shared.insert(key, register);
+        register
+    }
+
+    fn constant(&mut self, c: F) -> Register {
+        let index = self
+            .constants
+            .iter()
+            .position(|&constant| constant == c)
+            .unwrap_or_else(|| {
+                self.constants.push(c);
+                self.constants.len() - 1
+            });
+        let register = self.push(Op::Input(Input::Constant(c)), OpKey::Constant(index));
+        self.constant_values.insert(register, c);
+        register
+    }
+
+    fn constant_value(&self, register: Register) -> Option<F> {
+        self.constant_values.get(&register).copied()
+    }
+
+    /// Append `op` unless an equal operation was already appended, and return the virtual
+    /// register holding its result.
+    fn push(&mut self, op: Op<F>, key: OpKey) -> Register {
+        if let Some(&register) = self.registers.get(&key) {
+            return register;
+        }
+        let register = self.virtual_registers.push(op.is_ext());
+        self.ops.push(op);
+        self.outputs.push(register);
+        self.registers.insert(key, register);
+        register
+    }
+
+    /// Schedule the operations, with each constraint accumulated as soon as it and all prior
+    /// constraints are computed, and assign the virtual registers to as few registers as possible.
+    fn finish(self, roots: &[Register]) -> CompiledConstraints<F> {
+        // Drop the operations which no constraint depends on, such as the operands of a product
+        // which was folded to zero.
+        let mut live = roots.iter().copied().collect::<BTreeSet<_>>();
+        for (op, output) in self.ops.iter().zip(&self.outputs).rev() {
+            if live.contains(output) {
+                live.extend(op.operands());
+            }
+        }
+
+        // Inputs are loaded into registers of their own, which are never reused.
+        let mut registers = RegisterFiles::default();
+        let (mut base_inputs, mut ext_inputs) = (Vec::new(), Vec::new());
+        let mut assigned = BTreeMap::new();
+        for (&op, &out) in self.ops.iter().zip(&self.outputs) {
+            if let Op::Input(input) = op {
+                if live.contains(&out) {
+                    assigned.insert(out, registers.push(input.is_ext()));
+                    if input.is_ext() {
+                        ext_inputs.push(input);
+                    } else {
+                        base_inputs.push(input);
+                    }
+                }
+            }
+        }
+
+        let defined_at = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(i, &output)| (output, i))
+            .collect::<BTreeMap<_, _>>();
+        let mut pending = roots.iter().peekable();
+        let mut schedule = Vec::with_capacity(live.len() + roots.len());
+        for (i, (&op, &out)) in self.ops.iter().zip(&self.outputs).enumerate() {
+            if live.contains(&out) && !matches!(op, Op::Input(_)) {
+                schedule.push(Step::Compute(op, out));
+            }
+            while let Some(&root) = pending.next_if(|&&root| defined_at[&root] <= i) {
+                schedule.push(Step::Accumulate(root));
+            }
+        }
+
+        let mut last_use = BTreeMap::new();
+        for (i, step) in schedule.iter().enumerate() {
+            match step {
+                Step::Compute(op, _) => last_use.extend(op.operands().map(|operand| (operand, i))),
+                Step::Accumulate(register) => {
+                    last_use.insert(*register, i);
+                }
+            }
+        }
+
+        // Each other virtual register is assigned a register which is free from its definition
+        // to its last use. An operation's operands are freed before its result is assigned, since
+        // they're read before it's written.
+        let inputs = assigned.keys().copied().collect::<BTreeSet<_>>();
+        let mut instructions = Vec::with_capacity(schedule.len());
+        for (i, step) in schedule.into_iter().enumerate() {
+            let read = match step {
+                Step::Compute(op, _) => op.operands().collect::<Vec<_>>(),
+                Step::Accumulate(register) => Vec::from([register]),
+            };
+            let assigned_read = read.iter().map(|r| assigned[r]).collect::<Vec<_>>();
+            for (register, &assigned_register) in read.iter().zip(&assigned_read) {
+                if last_use[register] == i && !inputs.contains(register) {
+                    registers.free(assigned_register);
+                }
+            }
+            instructions.push(match step {
+                Step::Compute(op, out) => {
+                    let op = op.map_operands(|operand| assigned[&operand]);
+                    let register = registers.reuse(matches!(out, Register::Ext(_)));
+                    assigned.insert(out, register);
+                    Instruction::new(op, register)
+                }
+                Step::Accumulate(register) => Instruction::accumulate(assigned[&register]),
+            });
+        }
+
+        CompiledConstraints {
+            base_inputs,
+            ext_inputs,
+            instructions,
+            num_base_registers: registers.base.len,
+            num_ext_registers: registers.ext.len,
+            num_constraints: roots.len(),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> ProverConstraintFolder<'_, SC> {
+    /// Evaluate compiled constraints on the folder's rows, folding them into the accumulator as
+    /// `Air::eval` would, but without running the AIR's evaluation code.
+    pub fn eval_compiled(&mut self, compiled: &CompiledConstraints<Val<SC>>) {
+        Self::eval_compiled_batch(core::array::from_mut(self), compiled);
+    }
+
+    /// Evaluate compiled constraints on the rows of each of the folders, which must be at the
+    /// same constraint index. Each instruction is run on all `N` folders' rows before the next,
+    /// so the cost of interpreting it is shared between them.
+    pub fn eval_compiled_batch<const N: usize>(
+        folders: &mut [Self; N],
+        compiled: &CompiledConstraints<Val<SC>>,
+    ) {
+        let constraint_index = folders.first().map_or(0, |folder| folder.constraint_index);
+        debug_assert!(folders
+            .iter()
+            .all(|folder| folder.constraint_index == constraint_index));
+
+        let mut base = Vec::with_capacity(compiled.num_base_registers);
+        base.extend(
+            compiled
+                .base_inputs
+                .iter()
+                .map(|&input| core::array::from_fn::<_, N, _>(|i| folders[i].base_input(input))),
+        );
+        base.resize(compiled.num_base_registers, [PackedVal::<SC>::ZERO; N]);
+        let mut ext = Vec::with_capacity(compiled.num_ext_registers);
+        ext.extend(
+            compiled
+                .ext_inputs
+                .iter()
+                .map(|&input| core::array::from_fn::<_, N, _>(|i| folders[i].ext_input(input))),
+        );
+        ext.resize(compiled.num_ext_registers, [PackedChallenge::<SC>::ZERO; N]);
+
+        let mut accumulators = [PackedChallenge::<SC>::ZERO; N];
+        let mut alpha_powers = folders
+            .first()
+            .map_or(&[][..], |folder| &folder.alpha_powers[constraint_index..])
+            .iter()
+            .copied();
+        for &instruction in &compiled.instructions {
+            match instruction {
+                Instruction::Add { out, x, y } => {
+                    base[out as usize] = zip_with(base[x as usize], base[y as usize], |x, y| x + y)
+                }
+                Instruction::Sub { out, x, y } => {
+                    base[out as usize] = zip_with(base[x as usize], base[y as usize], |x, y| x - y)
+                }
+                Instruction::Mul { out, x, y } => {
+                    base[out as usize] = zip_with(base[x as usize], base[y as usize], |x, y| x * y)
+                }
+                Instruction::Neg { out, x } => base[out as usize] = base[x as usize].map(|x| -x),
+                Instruction::ExtAdd { out, x, y } => {
+                    ext[out as usize] = zip_with(ext[x as usize], ext[y as usize], |x, y| x + y)
+                }
+                Instruction::ExtSub { out, x, y } => {
+                    ext[out as usize] = zip_with(ext[x as usize], ext[y as usize], |x, y| x - y)
+                }
+                Instruction::ExtMul { out, x, y } => {
+                    ext[out as usize] = zip_with(ext[x as usize], ext[y as usize], |x, y| x * y)
+                }
+                Instruction::ExtNeg { out, x } => ext[out as usize] = ext[x as usize].map(|x| -x),
+                Instruction::ExtAddBase { out, x, y } => {
+                    ext[out as usize] = zip_with(ext[x as usize], base[y as usize], |x, y| x + y)
+                }
+                Instruction::ExtSubBase { out, x, y } => {
+                    ext[out as usize] = zip_with(ext[x as usize], base[y as usize], |x, y| x - y)
+                }
+                Instruction::BaseSubExt { out, x, y } => {
+                    ext[out as usize] = zip_with(base[x as usize], ext[y as usize], |x, y| {
+                        PackedChallenge::<SC>::from(x) - y
+                    })
+                }
+                Instruction::ExtMulBase { out, x, y } => {
+                    ext[out as usize] = zip_with(ext[x as usize], base[y as usize], |x, y| x * y)
+                }
+                Instruction::Accumulate(x) => {
+                    let alpha_power = alpha_powers.next().expect("too few powers of alpha");
+                    for (accumulator, x) in accumulators.iter_mut().zip(base[x as usize]) {
+                        *accumulator += alpha_power * x;
+                    }
+                }
+                Instruction::AccumulateExt(x) => {
+                    let alpha_power = alpha_powers.next().expect("too few powers of alpha");
+                    for (accumulator, x) in accumulators.iter_mut().zip(ext[x as usize]) {
+                        *accumulator += alpha_power * x;
+                    }
+                }
+            }
+        }
+
+        for (folder, accumulator) in folders.iter_mut().zip(accumulators) {
+            folder.accumulator += accumulator;
+            folder.constraint_index += compiled.num_constraints;
+        }
+    }
+
+    fn base_input(&self, input: Input<Val<SC>>) -> PackedVal<SC> {
+        match input {
+            Input::Variable(Entry::Preprocessed { offset }, index) => {
+                self.preprocessed.values[offset * self.preprocessed.width + index]
+            }
+            Input::Variable(Entry::Main { offset }, index) => {
+                self.main.values[offset * self.main.width + index]
+            }
+            Input::Variable(Entry::Public, index) => {
+                PackedVal::<SC>::from_f(self.public_values[index])
+            }
+            Input::Variable(Entry::Periodic, index) => self.periodic_values[index],
+            Input::IsFirstRow => self.is_first_row,
+            Input::IsLastRow => self.is_last_row,
+            Input::IsTransitionWindow(size) => self.is_transition_windows[size - 2],
+            Input::IsRow(row) => row_selector(&self.is_rows, row),
+            Input::Constant(c) => PackedVal::<SC>::from_f(c),
+            Input::Variable(..) => unreachable!("extension field input"),
+        }
+    }
+
+    fn ext_input(&self, input: Input<Val<SC>>) -> PackedChallenge<SC> {
+        match input {
+            Input::Variable(Entry::Permutation { offset }, index) => {
+                self.permutation.values[offset * self.permutation.width + index]
+            }
+            Input::Variable(Entry::Challenge, index) => self.permutation_challenges[index],
+            Input::Variable(Entry::CumulativeSum, _) => self.cumulative_sum,
+            _ => unreachable!("base field input"),
+        }
+    }
+}
+
+#[inline(always)]
+fn zip_with<T: Copy, U: Copy, V, const N: usize>(
+    x: [T; N],
+    y: [U; N],
+    f: impl Fn(T, U) -> V,
+) -> [V; N] {
+    core::array::from_fn(|i| f(x[i], y[i]))
+}
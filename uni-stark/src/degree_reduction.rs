@@ -0,0 +1,464 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir, PairBuilder, PeriodicAirBuilder};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::*;
+
+use crate::{
+    get_symbolic_constraints, Entry, SymbolicAirBuilder, SymbolicExpression, SymbolicVariable,
+};
+
+/// An AIR whose constraints are rewritten to have degree at most a given maximum, by moving
+/// high-degree subexpressions into auxiliary columns appended to the main trace.
+///
+/// Each auxiliary column has a defining constraint, equating it to the subexpression it replaces.
+/// Traces of the wrapped AIR are extended with the auxiliary columns by `extend_trace`.
+///
+/// Subexpressions involving selectors can't be moved into columns, so products involving them are
+/// reassociated to multiply the selectors by auxiliary columns instead. The AIR's after-challenge
+/// variables aren't supported.
+pub struct DegreeReducedAir<F, A> {
+    air: A,
+    /// The rewritten constraints of the wrapped AIR, followed by the defining constraints of the
+    /// auxiliary columns.
+    constraints: ExpressionDag<F>,
+    /// The value of each auxiliary column, in terms of the wrapped AIR's columns and the earlier
+    /// auxiliary columns.
+    auxiliary_columns: Vec<ExpressionDag<F>>,
+}
+
+/// Expressions stored as a list of nodes, each of whose operands are earlier nodes.
+struct ExpressionDag<F> {
+    nodes: Vec<Node<F>>,
+    /// The node of each expression.
+    roots: Vec<usize>,
+}
+
+enum Node<F> {
+    Variable(Entry, usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    IsRow(usize),
+    Constant(F),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    Neg(usize),
+}
+
+impl<F: Field, A: Air<SymbolicAirBuilder<F>>> DegreeReducedAir<F, A> {
+    /// Wrap `air`, for proofs with `num_public_values` public values, rewriting its constraints to
+    /// have degree at most `max_degree`, which must be at least 2.
+    pub fn new(air: A, num_public_values: usize, max_degree: usize) -> Self {
+        assert!(max_degree >= 2, "the maximum degree must be at least 2");
+        let constraints =
+            get_symbolic_constraints(&air, air.preprocessed_width(), num_public_values)
+                .into_iter()
+                .map(Rc::new)
+                .collect::<Vec<_>>();
+
+        let mut reducer = Reducer {
+            width: air.width(),
+            max_degree,
+            columns: BTreeMap::new(),
+            auxiliary_columns: Vec::new(),
+            has_selector: BTreeMap::new(),
+            products: Vec::new(),
+        };
+        let mut reduced = constraints
+            .iter()
+            .map(|constraint| reducer.reduce(constraint, max_degree))
+            .collect::<Vec<_>>();
+        reduced.extend(
+            reducer
+                .auxiliary_columns
+                .iter()
+                .enumerate()
+                .map(|(i, definition)| reducer.column(i) - definition.clone()),
+        );
+
+        Self {
+            air,
+            constraints: ExpressionDag::new(&reduced),
+            auxiliary_columns: reducer
+                .auxiliary_columns
+                .iter()
+                .map(|definition| ExpressionDag::new(core::slice::from_ref(definition)))
+                .collect(),
+        }
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> DegreeReducedAir<F, A> {
+    /// The wrapped AIR.
+    pub fn inner(&self) -> &A {
+        &self.air
+    }
+
+    /// The number of auxiliary columns appended to the wrapped AIR's main trace.
+    pub fn num_auxiliary_columns(&self) -> usize {
+        self.auxiliary_columns.len()
+    }
+
+    /// Extend a trace of the wrapped AIR with the auxiliary columns.
+    pub fn extend_trace(
+        &self,
+        trace: &RowMajorMatrix<F>,
+        public_values: &[F],
+    ) -> RowMajorMatrix<F> {
+        let width = self.air.width();
+        assert_eq!(
+            trace.width(),
+            width,
+            "trace width must match the wrapped AIR's width"
+        );
+        let height = trace.height();
+        let extended_width = <Self as BaseAir<F>>::width(self);
+        let preprocessed = self.air.preprocessed_trace();
+        let periodic_columns = self.air.periodic_columns();
+
+        let mut values = F::zero_vec(height * extended_width);
+        for (extended_row, row) in values
+            .chunks_exact_mut(extended_width)
+            .zip(trace.values.chunks_exact(width))
+        {
+            extended_row[..width].copy_from_slice(row);
+        }
+
+        // Each auxiliary column may depend on earlier ones, on any row of the window, so we fill
+        // them one at a time.
+        for (i, definition) in self.auxiliary_columns.iter().enumerate() {
+            let column = (0..height)
+                .into_par_iter()
+                .map(|row| {
+                    definition.evaluate(|node| match *node {
+                        Node::Variable(Entry::Main { offset }, index) => {
+                            values[((row + offset) % height) * extended_width + index]
+                        }
+                        Node::Variable(Entry::Preprocessed { offset }, index) => preprocessed
+                            .as_ref()
+                            .expect("the wrapped AIR has no preprocessed trace")
+                            .get((row + offset) % height, index),
+                        Node::Variable(Entry::Public, index) => public_values[index],
+                        Node::Variable(Entry::Periodic, index) => {
+                            let column = &periodic_columns[index];
+                            column[row % column.len()]
+                        }
+                        Node::Constant(c) => c,
+                        _ => unreachable!("auxiliary columns only involve variables and constants"),
+                    })[0]
+                })
+                .collect::<Vec<_>>();
+            for (row, value) in column.into_iter().enumerate() {
+                values[row * extended_width + width + i] = value;
+            }
+        }
+
+        RowMajorMatrix::new(values, extended_width)
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> BaseAir<F> for DegreeReducedAir<F, A> {
+    fn width(&self) -> usize {
+        self.air.width() + self.auxiliary_columns.len()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.air.preprocessed_trace()
+    }
+
+    fn preprocessed_width(&self) -> usize {
+        self.air.preprocessed_width()
+    }
+
+    fn window_size(&self) -> usize {
+        self.air.window_size()
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        self.air.periodic_columns()
+    }
+}
+
+impl<F, A, AB> Air<AB> for DegreeReducedAir<F, A>
+where
+    F: Field,
+    A: BaseAir<F>,
+    AB: AirBuilderWithPublicValues<F = F> + PairBuilder + PeriodicAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let window_size = self.air.window_size();
+        let main = builder.main();
+        let main_rows = (0..window_size)
+            .map(|offset| main.row_slice(offset).to_vec())
+            .collect::<Vec<_>>();
+        let preprocessed = builder.preprocessed();
+        let preprocessed_rows = (0..window_size)
+            .map(|offset| preprocessed.row_slice(offset).to_vec())
+            .collect::<Vec<_>>();
+        let public_values = builder.public_values().to_vec();
+        let periodic_values = builder.periodic_values().to_vec();
+
+        let constraints = self.constraints.evaluate::<AB::Expr>(|node| match *node {
+            Node::Variable(Entry::Main { offset }, index) => main_rows[offset][index].into(),
+            Node::Variable(Entry::Preprocessed { offset }, index) => {
+                preprocessed_rows[offset][index].into()
+            }
+            Node::Variable(Entry::Public, index) => public_values[index].into(),
+            Node::Variable(Entry::Periodic, index) => periodic_values[index].into(),
+            Node::Variable(..) => unreachable!("after-challenge variables aren't supported"),
+            Node::IsFirstRow => builder.is_first_row(),
+            Node::IsLastRow => builder.is_last_row(),
+            Node::IsTransition => builder.is_transition(),
+            Node::IsTransitionWindow(size) => builder.is_transition_window(size),
+            Node::IsRow(row) => builder.is_row(row),
+            Node::Constant(c) => c.into(),
+            _ => unreachable!("not a leaf"),
+        });
+        for constraint in constraints {
+            builder.assert_zero(constraint);
+        }
+    }
+}
+
+/// Rewrites expressions to a given degree, moving subexpressions into auxiliary columns.
+struct Reducer<F> {
+    /// The width of the wrapped AIR's main trace.
+    width: usize,
+    max_degree: usize,
+    /// The auxiliary column holding each subexpression which was moved into one.
+    columns: BTreeMap<*const SymbolicExpression<F>, usize>,
+    auxiliary_columns: Vec<SymbolicExpression<F>>,
+    /// Whether each shared subexpression which was checked involves selectors.
+    has_selector: BTreeMap<*const SymbolicExpression<F>, bool>,
+    /// Products built while reassociating, kept alive so their addresses stay unique keys.
+    products: Vec<Rc<SymbolicExpression<F>>>,
+}
+
+impl<F: Field> Reducer<F> {
+    /// Rewrite `expr` to have degree at most `max_degree`, which must be at least 1.
+    fn reduce(
+        &mut self,
+        expr: &Rc<SymbolicExpression<F>>,
+        max_degree: usize,
+    ) -> SymbolicExpression<F> {
+        let degree = expr.degree_multiple();
+        if degree <= max_degree {
+            return (**expr).clone();
+        }
+        if max_degree == 1 && !self.has_selector(expr) {
+            return self.move_to_column(expr);
+        }
+        match &**expr {
+            SymbolicExpression::Add { x, y, .. } => {
+                self.reduce(x, max_degree) + self.reduce(y, max_degree)
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                self.reduce(x, max_degree) - self.reduce(y, max_degree)
+            }
+            SymbolicExpression::Neg { x, .. } => -self.reduce(x, max_degree),
+            SymbolicExpression::Mul { .. } if self.has_selector(expr) => {
+                self.reduce_selector_product(expr, max_degree)
+            }
+            SymbolicExpression::Mul { x, y, .. } => {
+                // Give each factor the degree it has if the other can do with what's left, and
+                // otherwise split the degree between them.
+                let (x_degree, y_degree) = (x.degree_multiple(), y.degree_multiple());
+                let (x_max, y_max) = if x_degree < max_degree {
+                    (x_degree, max_degree - x_degree)
+                } else if y_degree < max_degree {
+                    (max_degree - y_degree, y_degree)
+                } else {
+                    (max_degree - max_degree / 2, max_degree / 2)
+                };
+                self.reduce(x, x_max) * self.reduce(y, y_max)
+            }
+            _ => unreachable!("leaves have degree at most 1"),
+        }
+    }
+
+    /// Rewrite a product involving selectors to have degree at most `max_degree`.
+    ///
+    /// Selectors can't be moved into columns, so rather than splitting the product where it was
+    /// built, we multiply its factors involving selectors by the product of the other factors,
+    /// which is reduced on its own and may end up in a single auxiliary column.
+    fn reduce_selector_product(
+        &mut self,
+        expr: &Rc<SymbolicExpression<F>>,
+        max_degree: usize,
+    ) -> SymbolicExpression<F> {
+        let mut factors = Vec::new();
+        collect_factors(expr, &mut factors);
+        let (mut selector_factors, mut other_factors) = (Vec::new(), Vec::new());
+        for factor in factors {
+            if self.has_selector(&factor) {
+                selector_factors.push(factor);
+            } else {
+                other_factors.push(factor);
+            }
+        }
+
+        let degree = |factors: &[Rc<SymbolicExpression<F>>]| {
+            factors
+                .iter()
+                .map(|factor| factor.degree_multiple())
+                .sum::<usize>()
+        };
+        let (selector_degree, other_degree) = (degree(&selector_factors), degree(&other_factors));
+        // The other factors get whatever the selectors leave, but at least degree 1 if they aren't
+        // constant.
+        let other_max = other_degree.min(max_degree.saturating_sub(selector_degree).max(1));
+        let mut selector_max = max_degree
+            .checked_sub(other_max)
+            .filter(|&selector_max| selector_max >= selector_factors.len())
+            .expect("a product of selectors has degree above the maximum");
+
+        let other = match other_factors.len() {
+            0 => SymbolicExpression::Constant(F::ONE),
+            1 => self.reduce(&other_factors[0], other_max),
+            _ => {
+                let product = Rc::new(
+                    other_factors
+                        .iter()
+                        .map(|factor| (**factor).clone())
+                        .reduce(|acc, factor| acc * factor)
+                        .unwrap(),
+                );
+                self.products.push(product.clone());
+                self.reduce(&product, other_max)
+            }
+        };
+
+        // Each selector factor needs degree at least 1; hand out the rest in order.
+        selector_max -= selector_factors.len();
+        selector_factors.iter().fold(other, |acc, factor| {
+            let extra = (factor.degree_multiple() - 1).min(selector_max);
+            selector_max -= extra;
+            self.reduce(factor, 1 + extra) * acc
+        })
+    }
+
+    /// Move `expr` into an auxiliary column, unless it already was, and return the column.
+    fn move_to_column(&mut self, expr: &Rc<SymbolicExpression<F>>) -> SymbolicExpression<F> {
+        let key = Rc::as_ptr(expr);
+        if let Some(&i) = self.columns.get(&key) {
+            return self.column(i);
+        }
+        let definition = self.reduce(expr, self.max_degree);
+        self.auxiliary_columns.push(definition);
+        let i = self.auxiliary_columns.len() - 1;
+        self.columns.insert(key, i);
+        self.column(i)
+    }
+
+    fn has_selector(&mut self, expr: &Rc<SymbolicExpression<F>>) -> bool {
+        let key = Rc::as_ptr(expr);
+        if let Some(&has_selector) = self.has_selector.get(&key) {
+            return has_selector;
+        }
+        let has_selector = match &**expr {
+            SymbolicExpression::IsFirstRow
+            | SymbolicExpression::IsLastRow
+            | SymbolicExpression::IsTransition
+            | SymbolicExpression::IsTransitionWindow(_)
+            | SymbolicExpression::IsRow(_) => true,
+            SymbolicExpression::Variable(_) | SymbolicExpression::Constant(_) => false,
+            SymbolicExpression::Add { x, y, .. }
+            | SymbolicExpression::Sub { x, y, .. }
+            | SymbolicExpression::Mul { x, y, .. } => self.has_selector(x) || self.has_selector(y),
+            SymbolicExpression::Neg { x, .. } => self.has_selector(x),
+        };
+        self.has_selector.insert(key, has_selector);
+        has_selector
+    }
+
+    /// The `i`th auxiliary column, on the current row.
+    fn column(&self, i: usize) -> SymbolicExpression<F> {
+        SymbolicVariable::new(Entry::Main { offset: 0 }, self.width + i).into()
+    }
+}
+
+/// Collect the factors of `expr`, looking through nested products.
+fn collect_factors<F>(
+    expr: &Rc<SymbolicExpression<F>>,
+    factors: &mut Vec<Rc<SymbolicExpression<F>>>,
+) {
+    match &**expr {
+        SymbolicExpression::Mul { x, y, .. } => {
+            collect_factors(x, factors);
+            collect_factors(y, factors);
+        }
+        _ => factors.push(expr.clone()),
+    }
+}
+
+impl<F: Field> ExpressionDag<F> {
+    fn new(exprs: &[SymbolicExpression<F>]) -> Self {
+        let mut dag = Self {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        };
+        let mut shared = BTreeMap::new();
+        dag.roots = exprs
+            .iter()
+            .map(|expr| dag.push(expr, &mut shared))
+            .collect();
+        dag
+    }
+
+    fn push(
+        &mut self,
+        expr: &SymbolicExpression<F>,
+        shared: &mut BTreeMap<*const SymbolicExpression<F>, usize>,
+    ) -> usize {
+        let mut push_shared = |expr: &Rc<SymbolicExpression<F>>| {
+            let key = Rc::as_ptr(expr);
+            if let Some(&node) = shared.get(&key) {
+                return node;
+            }
+            let node = self.push(expr, shared);
+            shared.insert(key, node);
+            node
+        };
+        let node = match expr {
+            SymbolicExpression::Variable(v) => Node::Variable(v.entry, v.index),
+            SymbolicExpression::IsFirstRow => Node::IsFirstRow,
+            SymbolicExpression::IsLastRow => Node::IsLastRow,
+            SymbolicExpression::IsTransition => Node::IsTransition,
+            SymbolicExpression::IsTransitionWindow(size) => Node::IsTransitionWindow(*size),
+            SymbolicExpression::IsRow(row) => Node::IsRow(*row),
+            SymbolicExpression::Constant(c) => Node::Constant(*c),
+            SymbolicExpression::Add { x, y, .. } => Node::Add(push_shared(x), push_shared(y)),
+            SymbolicExpression::Sub { x, y, .. } => Node::Sub(push_shared(x), push_shared(y)),
+            SymbolicExpression::Mul { x, y, .. } => Node::Mul(push_shared(x), push_shared(y)),
+            SymbolicExpression::Neg { x, .. } => Node::Neg(push_shared(x)),
+        };
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Evaluate the expressions, given the value of each leaf.
+    fn evaluate<E: FieldAlgebra>(&self, leaf: impl Fn(&Node<F>) -> E) -> Vec<E> {
+        let mut values = Vec::<E>::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match *node {
+                Node::Add(x, y) => values[x].clone() + values[y].clone(),
+                Node::Sub(x, y) => values[x].clone() - values[y].clone(),
+                Node::Mul(x, y) => values[x].clone() * values[y].clone(),
+                Node::Neg(x) => -values[x].clone(),
+                _ => leaf(node),
+            };
+            values.push(value);
+        }
+        self.roots
+            .iter()
+            .map(|&root| values[root].clone())
+            .collect()
+    }
+}
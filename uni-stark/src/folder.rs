@@ -1,7 +1,9 @@
 use alloc::vec::Vec;
 
-use p3_air::{AirBuilder, AirBuilderWithPublicValues};
-use p3_field::FieldAlgebra;
+use p3_air::{
+    AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, InteractionAirBuilder, PairBuilder,
+    PeriodicAirBuilder, PermutationAirBuilder,
+};
 use p3_matrix::dense::RowMajorMatrixView;
 use p3_matrix::stack::VerticalPair;
 
@@ -9,25 +11,61 @@ use crate::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 
 #[derive(Debug)]
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
+    pub preprocessed: RowMajorMatrixView<'a, PackedVal<SC>>,
     pub main: RowMajorMatrixView<'a, PackedVal<SC>>,
+    pub permutation: RowMajorMatrixView<'a, PackedChallenge<SC>>,
+    pub permutation_challenges: &'a [PackedChallenge<SC>],
+    pub cumulative_sum: PackedChallenge<SC>,
     pub public_values: &'a Vec<Val<SC>>,
     pub is_first_row: PackedVal<SC>,
     pub is_last_row: PackedVal<SC>,
-    pub is_transition: PackedVal<SC>,
-    pub alpha_powers: &'a [SC::Challenge],
+    /// The transition selectors for windows of 2, 3, ..., `window_size` rows.
+    pub is_transition_windows: Vec<PackedVal<SC>>,
+    /// The selectors for the rows of boundary constraints, paired with those rows. This must
+    /// include every row the AIR passes to `is_row`.
+    pub is_rows: Vec<(usize, PackedVal<SC>)>,
+    /// The current row of each periodic column.
+    pub periodic_values: Vec<PackedVal<SC>>,
+    /// The powers of alpha which each constraint is scaled by, broadcast into every lane.
+    pub alpha_powers: &'a [PackedChallenge<SC>],
     pub accumulator: PackedChallenge<SC>,
     pub constraint_index: usize,
 }
 
+/// Look up the selector of `row` among the boundary rows' selectors.
+///
+/// The folders' selectors are those of the instance's `boundary_rows`, which are found by
+/// evaluating the same AIR symbolically, so every row the AIR passes to `is_row` is among them.
+/// This panics if it isn't, which can only happen if the instance was computed from another AIR,
+/// e.g. a verifying key used with the wrong AIR.
+#[inline]
+pub(crate) fn row_selector<T: Copy>(is_rows: &[(usize, T)], row: usize) -> T {
+    is_rows
+        .iter()
+        .find(|&&(r, _)| r == row)
+        .unwrap_or_else(|| panic!("row {row} isn't one of the AIR's boundary rows"))
+        .1
+}
+
 type ViewPair<'a, T> = VerticalPair<RowMajorMatrixView<'a, T>, RowMajorMatrixView<'a, T>>;
 
 #[derive(Debug)]
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
-    pub main: ViewPair<'a, SC::Challenge>,
+    pub preprocessed: RowMajorMatrixView<'a, SC::Challenge>,
+    pub main: RowMajorMatrixView<'a, SC::Challenge>,
+    pub permutation: ViewPair<'a, SC::Challenge>,
+    pub permutation_challenges: &'a [SC::Challenge],
+    pub cumulative_sum: SC::Challenge,
     pub public_values: &'a Vec<Val<SC>>,
     pub is_first_row: SC::Challenge,
     pub is_last_row: SC::Challenge,
-    pub is_transition: SC::Challenge,
+    /// The transition selectors for windows of 2, 3, ..., `window_size` rows.
+    pub is_transition_windows: Vec<SC::Challenge>,
+    /// The selectors for the rows of boundary constraints, paired with those rows. This must
+    /// include every row the AIR passes to `is_row`.
+    pub is_rows: Vec<(usize, SC::Challenge)>,
+    /// Each periodic column's interpolant, evaluated at the opening point.
+    pub periodic_values: Vec<SC::Challenge>,
     pub alpha: SC::Challenge,
     pub accumulator: SC::Challenge,
 }
@@ -55,18 +93,22 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for ProverConstraintFolder<'a, SC> {
 
     #[inline]
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        assert!(
+            (2..self.is_transition_windows.len() + 2).contains(&size),
+            "window of {size} rows exceeds the AIR's window size"
+        );
+        self.is_transition_windows[size - 2]
+    }
+
+    #[inline]
+    fn is_row(&self, row: usize) -> Self::Expr {
+        row_selector(&self.is_rows, row)
     }
 
     #[inline]
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         let x: PackedVal<SC> = x.into();
-        let alpha_power = self.alpha_powers[self.constraint_index];
-        self.accumulator += PackedChallenge::<SC>::from_f(alpha_power) * x;
+        self.accumulator += self.alpha_powers[self.constraint_index] * x;
         self.constraint_index += 1;
     }
 }
@@ -80,11 +122,65 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for ProverConstraintFold
     }
 }
 
+impl<SC: StarkGenericConfig> PeriodicAirBuilder for ProverConstraintFolder<'_, SC> {
+    type PeriodicVar = PackedVal<SC>;
+
+    #[inline]
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
+impl<SC: StarkGenericConfig> PairBuilder for ProverConstraintFolder<'_, SC> {
+    #[inline]
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> ExtensionBuilder for ProverConstraintFolder<'a, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = PackedChallenge<SC>;
+    type VarEF = PackedChallenge<SC>;
+
+    #[inline]
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        let x: PackedChallenge<SC> = x.into();
+        self.accumulator += self.alpha_powers[self.constraint_index] * x;
+        self.constraint_index += 1;
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for ProverConstraintFolder<'a, SC> {
+    type MP = RowMajorMatrixView<'a, PackedChallenge<SC>>;
+    type RandomVar = PackedChallenge<SC>;
+
+    #[inline]
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    #[inline]
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+impl<SC: StarkGenericConfig> InteractionAirBuilder for ProverConstraintFolder<'_, SC> {
+    #[inline]
+    fn cumulative_sum(&self) -> Self::RandomVar {
+        self.cumulative_sum
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC> {
     type F = Val<SC>;
     type Expr = SC::Challenge;
     type Var = SC::Challenge;
-    type M = ViewPair<'a, SC::Challenge>;
+    type M = RowMajorMatrixView<'a, SC::Challenge>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -99,11 +195,15 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC>
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        assert!(
+            (2..self.is_transition_windows.len() + 2).contains(&size),
+            "window of {size} rows exceeds the AIR's window size"
+        );
+        self.is_transition_windows[size - 2]
+    }
+
+    fn is_row(&self, row: usize) -> Self::Expr {
+        row_selector(&self.is_rows, row)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
@@ -120,3 +220,50 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for VerifierConstraintFo
         self.public_values
     }
 }
+
+impl<SC: StarkGenericConfig> PeriodicAirBuilder for VerifierConstraintFolder<'_, SC> {
+    type PeriodicVar = SC::Challenge;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
+impl<SC: StarkGenericConfig> PairBuilder for VerifierConstraintFolder<'_, SC> {
+    #[inline]
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> ExtensionBuilder for VerifierConstraintFolder<'a, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = SC::Challenge;
+    type VarEF = SC::Challenge;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.assert_zero(x);
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for VerifierConstraintFolder<'a, SC> {
+    type MP = ViewPair<'a, SC::Challenge>;
+    type RandomVar = SC::Challenge;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+impl<SC: StarkGenericConfig> InteractionAirBuilder for VerifierConstraintFolder<'_, SC> {
+    fn cumulative_sum(&self) -> Self::RandomVar {
+        self.cumulative_sum
+    }
+}
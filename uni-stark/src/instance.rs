@@ -0,0 +1,185 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use p3_air::BaseAir;
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::Pcs;
+use p3_field::{Field, FieldAlgebra};
+use p3_keccak::Keccak256Hash;
+use p3_symmetric::CryptographicHasher;
+use serde::{Deserialize, Serialize};
+
+use crate::symbolic_builder::{boundary_rows, log_quotient_degree_of};
+use crate::{Entry, StarkGenericConfig, SymbolicExpression, Val};
+
+/// The number of bytes in a constraint digest.
+pub const CONSTRAINT_DIGEST_LEN: usize = 32;
+
+/// A canonical description of an AIR instance, which the prover and verifier both absorb into the
+/// transcript before sampling any challenge, so that a proof can't be replayed against another AIR.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceDescriptor<F> {
+    /// The number of preprocessed columns.
+    pub preprocessed_width: usize,
+    /// The number of main trace columns.
+    pub width: usize,
+    /// The number of consecutive rows the constraints can access.
+    pub window_size: usize,
+    /// The number of challenges sampled before the after-challenge trace is committed to.
+    pub num_challenges: usize,
+    /// The width of the after-challenge trace, or zero if there is none.
+    pub after_challenge_width: usize,
+    pub num_public_values: usize,
+    pub num_constraints: usize,
+    /// The log of the number of quotient chunks.
+    pub log_quotient_degree: usize,
+    /// A hash of the constraints, see `constraint_digest`.
+    pub constraint_digest: [u8; CONSTRAINT_DIGEST_LEN],
+    /// The AIR's periodic columns, which the verifier evaluates itself.
+    pub periodic_columns: Vec<Vec<F>>,
+    /// The rows which boundary constraints select with `AirBuilder::is_row`, in increasing order.
+    pub boundary_rows: Vec<usize>,
+}
+
+impl<F: Field> InstanceDescriptor<F> {
+    pub(crate) fn new<A: BaseAir<F>>(
+        air: &A,
+        constraints: &[SymbolicExpression<F>],
+        num_public_values: usize,
+        num_challenges: usize,
+        after_challenge_width: usize,
+        is_zk: bool,
+    ) -> Self {
+        let periodic_columns = air.periodic_columns();
+        assert!(
+            periodic_columns
+                .iter()
+                .all(|column| column.len().is_power_of_two()),
+            "periodic column lengths must be powers of two"
+        );
+        Self {
+            preprocessed_width: air.preprocessed_width(),
+            width: air.width(),
+            window_size: air.window_size(),
+            num_challenges,
+            after_challenge_width,
+            num_public_values,
+            num_constraints: constraints.len(),
+            log_quotient_degree: log_quotient_degree_of(constraints, is_zk),
+            constraint_digest: constraint_digest(constraints),
+            periodic_columns,
+            boundary_rows: boundary_rows(constraints),
+        }
+    }
+
+    pub(crate) fn observe<Challenger: FieldChallenger<F>>(&self, challenger: &mut Challenger) {
+        for n in [
+            self.preprocessed_width,
+            self.width,
+            self.window_size,
+            self.num_challenges,
+            self.after_challenge_width,
+            self.num_public_values,
+            self.num_constraints,
+            self.log_quotient_degree,
+        ] {
+            challenger.observe(F::from_canonical_usize(n));
+        }
+        for &byte in &self.constraint_digest {
+            challenger.observe(F::from_canonical_u8(byte));
+        }
+        challenger.observe(F::from_canonical_usize(self.periodic_columns.len()));
+        for column in &self.periodic_columns {
+            challenger.observe(F::from_canonical_usize(column.len()));
+            challenger.observe_slice(column);
+        }
+        challenger.observe(F::from_canonical_usize(self.boundary_rows.len()));
+        for &row in &self.boundary_rows {
+            challenger.observe(F::from_canonical_usize(row));
+        }
+    }
+}
+
+/// Observe the config's domain separator and PCS parameters, each prefixed by its length.
+pub(crate) fn observe_config<SC: StarkGenericConfig>(config: &SC, challenger: &mut SC::Challenger) {
+    let domain_separator = config.domain_separator();
+    challenger.observe(Val::<SC>::from_canonical_usize(domain_separator.len()));
+    for &byte in domain_separator {
+        challenger.observe(Val::<SC>::from_canonical_u8(byte));
+    }
+
+    let parameters = config.pcs().parameters();
+    challenger.observe(Val::<SC>::from_canonical_usize(parameters.len()));
+    for parameter in parameters {
+        challenger.observe(Val::<SC>::from_canonical_usize(parameter));
+    }
+}
+
+/// A digest of a set of constraints: the Keccak-256 hash of a canonical serialization of their
+/// symbolic expression DAG.
+///
+/// Each node of the DAG is serialized once, after its operands, which it refers to by their
+/// positions in the serialization, and the constraints follow as the positions of their roots.
+/// Shared subexpressions are serialized once, so the serialization follows how the AIR builds its
+/// constraints, which is deterministic.
+pub(crate) fn constraint_digest<F: Field>(
+    constraints: &[SymbolicExpression<F>],
+) -> [u8; CONSTRAINT_DIGEST_LEN] {
+    let mut nodes = Vec::new();
+    let mut positions = BTreeMap::new();
+    let roots = constraints
+        .iter()
+        .map(|constraint| serialize_node(constraint, &mut nodes, &mut positions))
+        .collect::<Vec<_>>();
+    let bytes = postcard::to_allocvec(&(nodes, roots)).expect("serializing constraints can't fail");
+    Keccak256Hash.hash_iter(bytes)
+}
+
+/// A node of a serialized constraint DAG, whose operands are the positions of earlier nodes.
+#[derive(Serialize)]
+enum Node<F> {
+    Variable { entry: Entry, index: usize },
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    IsRow(usize),
+    Constant(F),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    Neg(usize),
+}
+
+/// Append `expr` and any of its subexpressions not already in `nodes`, and return its position.
+fn serialize_node<F: Field>(
+    expr: &SymbolicExpression<F>,
+    nodes: &mut Vec<Node<F>>,
+    positions: &mut BTreeMap<*const SymbolicExpression<F>, usize>,
+) -> usize {
+    let key: *const SymbolicExpression<F> = expr;
+    if let Some(&position) = positions.get(&key) {
+        return position;
+    }
+    let mut operand = |x: &Rc<SymbolicExpression<F>>| serialize_node(x, nodes, positions);
+    let node = match expr {
+        SymbolicExpression::Variable(v) => Node::Variable {
+            entry: v.entry,
+            index: v.index,
+        },
+        SymbolicExpression::IsFirstRow => Node::IsFirstRow,
+        SymbolicExpression::IsLastRow => Node::IsLastRow,
+        SymbolicExpression::IsTransition => Node::IsTransition,
+        SymbolicExpression::IsTransitionWindow(size) => Node::IsTransitionWindow(*size),
+        SymbolicExpression::IsRow(row) => Node::IsRow(*row),
+        SymbolicExpression::Constant(c) => Node::Constant(*c),
+        SymbolicExpression::Add { x, y, .. } => Node::Add(operand(x), operand(y)),
+        SymbolicExpression::Sub { x, y, .. } => Node::Sub(operand(x), operand(y)),
+        SymbolicExpression::Mul { x, y, .. } => Node::Mul(operand(x), operand(y)),
+        SymbolicExpression::Neg { x, .. } => Node::Neg(operand(x)),
+    };
+    nodes.push(node);
+    positions.insert(key, nodes.len() - 1);
+    nodes.len() - 1
+}
@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+
+use p3_air::Air;
+use p3_commit::Pcs;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::instance::InstanceDescriptor;
+use crate::preprocessed::commit_preprocessed;
+use crate::{
+    get_symbolic_constraints, CompiledConstraints, PreprocessedProverData, PreprocessedVerifierKey,
+    StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val,
+};
+
+/// Everything the prover needs to know about an AIR, computed once by `setup_keys` and reused
+/// across proofs.
+pub struct StarkProvingKey<SC: StarkGenericConfig> {
+    /// The AIR's widths, quotient degree and constraint digest.
+    pub instance: InstanceDescriptor<Val<SC>>,
+    /// The committed preprocessed trace, if the AIR has preprocessed columns.
+    pub preprocessed: Option<PreprocessedProverData<SC>>,
+    /// The AIR's constraints, compiled for evaluation over the quotient domain.
+    pub constraints: CompiledConstraints<Val<SC>>,
+}
+
+/// Everything the verifier needs to know about an AIR, besides how to evaluate its constraints.
+///
+/// This is computed once by `setup_keys`, and may be stored and later used to verify proofs
+/// without analyzing the AIR's constraints again.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct StarkVerifyingKey<SC: StarkGenericConfig> {
+    /// The AIR's widths, quotient degree and constraint digest.
+    pub instance: InstanceDescriptor<Val<SC>>,
+    /// The commitment to the preprocessed trace, if the AIR has preprocessed columns.
+    pub preprocessed: Option<PreprocessedVerifierKey<SC>>,
+}
+
+/// Analyze the constraints of `air` and commit to its preprocessed trace, if any, for proofs with
+/// `num_public_values` public values.
+///
+/// The preprocessed trace must have the same height as any main trace it is later proven with.
+#[instrument(skip_all)]
+pub fn setup_keys<SC, A>(
+    config: &SC,
+    air: &A,
+    num_public_values: usize,
+) -> (StarkProvingKey<SC>, StarkVerifyingKey<SC>)
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let (instance, constraints) = analyze_air::<SC, A>(air, num_public_values);
+    let (preprocessed_data, preprocessed_vk) = commit_preprocessed(config, air).unzip();
+    let proving_key = StarkProvingKey {
+        instance: instance.clone(),
+        preprocessed: preprocessed_data,
+        constraints: CompiledConstraints::new(&constraints),
+    };
+    let verifying_key = StarkVerifyingKey {
+        instance,
+        preprocessed: preprocessed_vk,
+    };
+    (proving_key, verifying_key)
+}
+
+/// Describe `air`, for an AIR without an after-challenge trace.
+pub(crate) fn instance_descriptor<SC, A>(
+    air: &A,
+    num_public_values: usize,
+) -> InstanceDescriptor<Val<SC>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    analyze_air::<SC, A>(air, num_public_values).0
+}
+
+/// A proving key for `air`, for an AIR without preprocessed columns or an after-challenge trace.
+pub(crate) fn proving_key_without_preprocessed<SC, A>(
+    air: &A,
+    num_public_values: usize,
+) -> StarkProvingKey<SC>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let (instance, constraints) = analyze_air::<SC, A>(air, num_public_values);
+    StarkProvingKey {
+        instance,
+        preprocessed: None,
+        constraints: CompiledConstraints::new(&constraints),
+    }
+}
+
+/// Describe `air` and return its symbolic constraints, for an AIR without an after-challenge
+/// trace.
+fn analyze_air<SC, A>(
+    air: &A,
+    num_public_values: usize,
+) -> (
+    InstanceDescriptor<Val<SC>>,
+    Vec<SymbolicExpression<Val<SC>>>,
+)
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let symbolic_constraints =
+        get_symbolic_constraints::<Val<SC>, A>(air, air.preprocessed_width(), num_public_values);
+    let instance = InstanceDescriptor::new(
+        air,
+        &symbolic_constraints,
+        num_public_values,
+        0,
+        0,
+        <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK,
+    );
+    (instance, symbolic_constraints)
+}
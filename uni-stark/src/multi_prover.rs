@@ -0,0 +1,307 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::{izip, Itertools};
+use p3_air::{generate_permutation_trace, Air, InteractionAir};
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::FieldAlgebra;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_util::log2_strict_usize;
+use tracing::{info_span, instrument};
+
+use crate::instance::{observe_config, InstanceDescriptor};
+use crate::{
+    get_symbolic_constraints_with_interactions, quotient_values, Commitments, CompiledConstraints,
+    MultiProof, OpenedValues, StarkGenericConfig, SymbolicAirBuilder, Val,
+};
+
+/// Prove several AIR instances, or "tables", at once.
+///
+/// The traces may have different heights. All traces are committed to in a single PCS commitment,
+/// each table's quotient is computed over its own quotient domain, all quotient chunks are
+/// committed to together, and everything is opened with a single batched PCS opening.
+///
+/// Tables may interact with each other through buses, as declared by `InteractionAir`. After
+/// the main traces are committed to, LogUp randomness is sampled and a permutation trace is
+/// committed to for each table with interactions.
+///
+/// All tables share the AIR type `A`. Tables with different AIRs can be combined by wrapping
+/// them in an enum which dispatches to the underlying AIRs.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_multi<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    airs: &[A],
+    challenger: &mut SC::Challenger,
+    traces: Vec<RowMajorMatrix<Val<SC>>>,
+    public_values: &[Vec<Val<SC>>],
+) -> MultiProof<SC>
+where
+    SC: StarkGenericConfig,
+    A: InteractionAir<Val<SC>> + Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    assert!(!airs.is_empty(), "at least one table is required");
+    assert_eq!(airs.len(), traces.len(), "expected one trace per AIR");
+    assert_eq!(
+        airs.len(),
+        public_values.len(),
+        "expected one set of public values per AIR"
+    );
+    for air in airs {
+        assert_eq!(
+            air.preprocessed_width(),
+            0,
+            "preprocessed columns are not supported by the multi-table prover"
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    for (air, trace, public_values) in izip!(airs, &traces, public_values) {
+        crate::check_constraints::check_constraints::<_, SC::Challenge, _>(
+            air,
+            trace,
+            None,
+            &[],
+            public_values,
+        );
+    }
+
+    let degree_bits = traces
+        .iter()
+        .map(|trace| log2_strict_usize(trace.height()))
+        .collect_vec();
+    let sends = airs.iter().map(|air| air.sends()).collect_vec();
+    let receives = airs.iter().map(|air| air.receives()).collect_vec();
+    let has_interactions = izip!(&sends, &receives)
+        .map(|(sends, receives)| !sends.is_empty() || !receives.is_empty())
+        .collect_vec();
+    let (instances, constraints): (Vec<_>, Vec<_>) = izip!(airs, public_values, &has_interactions)
+        .map(|(air, public_values, &has_interactions)| {
+            let symbolic_constraints = get_symbolic_constraints_with_interactions::<Val<SC>, A>(
+                air,
+                0,
+                public_values.len(),
+            );
+            let instance = InstanceDescriptor::new(
+                air,
+                &symbolic_constraints,
+                public_values.len(),
+                if has_interactions { 2 } else { 0 },
+                air.permutation_width(),
+                <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK,
+            );
+            (instance, CompiledConstraints::new(&symbolic_constraints))
+        })
+        .unzip();
+
+    // The permutation traces are generated after the main traces are committed to, so we keep a
+    // copy of the main traces which have interactions.
+    let interacting_traces = izip!(&traces, &has_interactions)
+        .map(|(trace, &has_interactions)| has_interactions.then(|| trace.clone()))
+        .collect_vec();
+
+    let pcs = config.pcs();
+    let trace_domains = traces
+        .iter()
+        .map(|trace| pcs.natural_domain_for_degree(trace.height()))
+        .collect_vec();
+
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.commit(izip!(trace_domains.clone(), traces).collect_vec()));
+
+    // Observe the instance.
+    observe_config(config, challenger);
+    challenger.observe(Val::<SC>::from_canonical_usize(airs.len()));
+    for (instance, &log_degree) in izip!(&instances, &degree_bits) {
+        instance.observe(challenger);
+        challenger.observe(Val::<SC>::from_canonical_usize(log_degree));
+    }
+
+    challenger.observe(trace_commit.clone());
+    for public_values in public_values {
+        challenger.observe_slice(public_values);
+    }
+
+    let any_interactions = has_interactions.iter().any(|&b| b);
+    let permutation_challenges: Vec<SC::Challenge> = if any_interactions {
+        (0..2).map(|_| challenger.sample_ext_element()).collect()
+    } else {
+        vec![]
+    };
+
+    let permutation_traces = info_span!("generate permutation traces").in_scope(|| {
+        izip!(&sends, &receives, interacting_traces)
+            .map(|(sends, receives, trace)| {
+                trace.and_then(|trace| {
+                    generate_permutation_trace(
+                        sends,
+                        receives,
+                        None,
+                        &trace,
+                        &permutation_challenges,
+                    )
+                })
+            })
+            .collect_vec()
+    });
+    let cumulative_sums = permutation_traces
+        .iter()
+        .map(|trace| trace.as_ref().map(|trace| *trace.values.last().unwrap()))
+        .collect_vec();
+    debug_assert_eq!(
+        cumulative_sums
+            .iter()
+            .flatten()
+            .copied()
+            .sum::<SC::Challenge>(),
+        SC::Challenge::ZERO,
+        "interactions are unbalanced"
+    );
+
+    // Index of each table's permutation trace within the permutation commitment.
+    let mut permutation_indices = Vec::with_capacity(airs.len());
+    let mut permutation_domains_and_traces = vec![];
+    for (&domain, trace) in izip!(&trace_domains, permutation_traces) {
+        permutation_indices.push(trace.map(|trace| {
+            permutation_domains_and_traces.push((domain, trace.flatten_to_base()));
+            permutation_domains_and_traces.len() - 1
+        }));
+    }
+    let permutation_commit_and_data = any_interactions.then(|| {
+        info_span!("commit to permutation traces")
+            .in_scope(|| pcs.commit(permutation_domains_and_traces))
+    });
+    if let Some((permutation_commit, _)) = &permutation_commit_and_data {
+        challenger.observe(permutation_commit.clone());
+        for &cumulative_sum in cumulative_sums.iter().flatten() {
+            challenger.observe_ext_element(cumulative_sum);
+        }
+    }
+
+    let alpha: SC::Challenge = challenger.sample_ext_element();
+
+    let mut quotient_degrees = Vec::with_capacity(airs.len());
+    let mut quotients = Vec::with_capacity(airs.len());
+    for (i, (air, public_values, instance, &trace_domain, &log_degree)) in izip!(
+        airs,
+        public_values,
+        &instances,
+        &trace_domains,
+        &degree_bits
+    )
+    .enumerate()
+    {
+        let _span = info_span!("compute quotient for table", table = i).entered();
+
+        let log_quotient_degree = instance.log_quotient_degree;
+        let quotient_degree = 1 << log_quotient_degree;
+
+        let quotient_domain =
+            trace_domain.create_disjoint_domain(1 << (log_degree + log_quotient_degree));
+        let trace_on_quotient_domain =
+            pcs.get_evaluations_on_domain(&trace_data, i, quotient_domain);
+        let permutation_on_quotient_domain = permutation_indices[i].map(|index| {
+            let (_, permutation_data) = permutation_commit_and_data.as_ref().unwrap();
+            pcs.get_evaluations_on_domain(permutation_data, index, quotient_domain)
+        });
+
+        let quotient_values = quotient_values::<SC, _, _, _>(
+            &constraints[i],
+            air.window_size(),
+            public_values,
+            &instances[i],
+            trace_domain,
+            quotient_domain,
+            None::<RowMajorMatrix<Val<SC>>>,
+            trace_on_quotient_domain,
+            permutation_on_quotient_domain,
+            &permutation_challenges,
+            cumulative_sums[i].unwrap_or_default(),
+            alpha,
+        );
+        let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
+        quotients.push((quotient_domain, quotient_flat, quotient_degree));
+        quotient_degrees.push(quotient_degree);
+    }
+
+    let (quotient_commit, quotient_data) =
+        info_span!("commit to quotient poly chunks").in_scope(|| pcs.commit_quotients(quotients));
+    challenger.observe(quotient_commit.clone());
+
+    let (permutation_commit, permutation_data) = permutation_commit_and_data.unzip();
+    let commitments = Commitments {
+        trace: trace_commit,
+        permutation: permutation_commit,
+        quotient_chunks: quotient_commit,
+    };
+
+    let zeta: SC::Challenge = challenger.sample();
+
+    // Each table's trace is opened at every row of its AIR's window.
+    let trace_points = izip!(airs, &trace_domains)
+        .map(|(air, domain)| {
+            iter::successors(Some(zeta), |&point| domain.next_point(point))
+                .take(air.window_size())
+                .collect_vec()
+        })
+        .collect_vec();
+    // Open every quotient chunk of every table at zeta.
+    let total_quotient_chunks = quotient_degrees.iter().sum();
+    let quotient_points = (0..total_quotient_chunks).map(|_| vec![zeta]).collect_vec();
+
+    let mut rounds = vec![
+        (&trace_data, trace_points),
+        (&quotient_data, quotient_points),
+    ];
+    if let Some(permutation_data) = &permutation_data {
+        let permutation_points = izip!(&trace_domains, &permutation_indices)
+            .filter(|(_, index)| index.is_some())
+            .map(|(domain, _)| vec![zeta, domain.next_point(zeta).unwrap()])
+            .collect_vec();
+        rounds.push((permutation_data, permutation_points));
+    }
+
+    let (opened_values, opening_proof) =
+        info_span!("open").in_scope(|| pcs.open(rounds, challenger));
+
+    let mut quotient_openings = opened_values[1].iter();
+    let opened_values = izip!(&opened_values[0], &quotient_degrees, &permutation_indices)
+        .map(|(trace_openings, &quotient_degree, permutation_index)| {
+            let (permutation_local, permutation_next) =
+                permutation_index.map_or((vec![], vec![]), |index| {
+                    let openings = &opened_values[2][index];
+                    (openings[0].clone(), openings[1].clone())
+                });
+            OpenedValues {
+                preprocessed_local: vec![],
+                preprocessed_next: vec![],
+                preprocessed_after_next: vec![],
+                trace_local: trace_openings[0].clone(),
+                trace_next: trace_openings[1].clone(),
+                trace_after_next: trace_openings[2..].to_vec(),
+                permutation_local,
+                permutation_next,
+                quotient_chunks: quotient_openings
+                    .by_ref()
+                    .take(quotient_degree)
+                    .map(|v| v[0].clone())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    MultiProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+        cumulative_sums,
+    }
+}
@@ -0,0 +1,234 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::{izip, Itertools};
+use p3_air::{Air, BaseAir, InteractionAir};
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{FieldAlgebra, FieldExtensionAlgebra};
+use tracing::instrument;
+
+use crate::instance::{observe_config, InstanceDescriptor};
+use crate::symbolic_builder::{get_symbolic_constraints_with_interactions, SymbolicAirBuilder};
+use crate::verifier::{verify_constraints, window_openings};
+use crate::{
+    MultiProof, PcsError, StarkGenericConfig, Val, VerificationError, VerifierConstraintFolder,
+};
+
+/// Verify a proof of several AIR instances produced by `prove_multi`.
+#[instrument(skip_all)]
+pub fn verify_multi<SC, A>(
+    config: &SC,
+    airs: &[A],
+    challenger: &mut SC::Challenger,
+    proof: &MultiProof<SC>,
+    public_values: &[Vec<Val<SC>>],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: InteractionAir<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let MultiProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+        cumulative_sums,
+    } = proof;
+
+    if airs.is_empty()
+        || opened_values.len() != airs.len()
+        || degree_bits.len() != airs.len()
+        || cumulative_sums.len() != airs.len()
+        || public_values.len() != airs.len()
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    if airs.iter().any(|air| air.preprocessed_width() != 0) {
+        return Err(VerificationError::PreprocessedWidthMismatch);
+    }
+
+    let sends = airs.iter().map(|air| air.sends()).collect_vec();
+    let receives = airs.iter().map(|air| air.receives()).collect_vec();
+    let any_interactions = cumulative_sums.iter().any(Option::is_some);
+    if commitments.permutation.is_some() != any_interactions {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let pcs = config.pcs();
+    let mut instances = Vec::with_capacity(airs.len());
+    let mut trace_domains = Vec::with_capacity(airs.len());
+    let mut quotient_chunks_domains = Vec::with_capacity(airs.len());
+    for (air, opened_values, public_values, &degree_bits, cumulative_sum) in izip!(
+        airs,
+        opened_values,
+        public_values,
+        degree_bits,
+        cumulative_sums
+    ) {
+        let permutation_width = air.permutation_width();
+        let symbolic_constraints =
+            get_symbolic_constraints_with_interactions::<Val<SC>, A>(air, 0, public_values.len());
+        let instance = InstanceDescriptor::new(
+            air,
+            &symbolic_constraints,
+            public_values.len(),
+            if permutation_width > 0 { 2 } else { 0 },
+            permutation_width,
+            <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK,
+        );
+        let log_quotient_degree = instance.log_quotient_degree;
+        let quotient_degree = 1 << log_quotient_degree;
+
+        let trace_domain = pcs.natural_domain_for_degree(1 << degree_bits);
+        let quotient_domain =
+            trace_domain.create_disjoint_domain(1 << (degree_bits + log_quotient_degree));
+
+        let air_width = <A as BaseAir<Val<SC>>>::width(air);
+        let window_size = air.window_size();
+        let valid_shape = instance.periodic_columns.iter().all(|column| {
+            !column.is_empty() && column.len().is_power_of_two() && column.len() <= 1 << degree_bits
+        }) && instance
+            .boundary_rows
+            .iter()
+            .all(|&row| row < 1 << degree_bits)
+            && opened_values.preprocessed_local.is_empty()
+            && opened_values.preprocessed_next.is_empty()
+            && opened_values.preprocessed_after_next.is_empty()
+            && opened_values.trace_local.len() == air_width
+            && opened_values.trace_next.len() == air_width
+            && opened_values.trace_after_next.len() == window_size - 2
+            && opened_values
+                .trace_after_next
+                .iter()
+                .all(|row| row.len() == air_width)
+            && cumulative_sum.is_some() == (permutation_width > 0)
+            && opened_values.permutation_local.len() == permutation_width * ext_degree
+            && opened_values.permutation_next.len() == permutation_width * ext_degree
+            && opened_values.quotient_chunks.len() == quotient_degree
+            && opened_values
+                .quotient_chunks
+                .iter()
+                .all(|qc| qc.len() == <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D);
+        if !valid_shape {
+            return Err(VerificationError::InvalidProofShape);
+        }
+
+        instances.push(instance);
+        trace_domains.push(trace_domain);
+        quotient_chunks_domains.push(quotient_domain.split_domains(quotient_degree));
+    }
+
+    // Observe the instance.
+    observe_config(config, challenger);
+    challenger.observe(Val::<SC>::from_canonical_usize(airs.len()));
+    for (instance, &log_degree) in izip!(&instances, degree_bits) {
+        instance.observe(challenger);
+        challenger.observe(Val::<SC>::from_canonical_usize(log_degree));
+    }
+
+    challenger.observe(commitments.trace.clone());
+    for public_values in public_values {
+        challenger.observe_slice(public_values);
+    }
+
+    let permutation_challenges: Vec<SC::Challenge> = if any_interactions {
+        (0..2).map(|_| challenger.sample_ext_element()).collect()
+    } else {
+        vec![]
+    };
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+        for &cumulative_sum in cumulative_sums.iter().flatten() {
+            challenger.observe_ext_element(cumulative_sum);
+        }
+    }
+    if cumulative_sums
+        .iter()
+        .flatten()
+        .copied()
+        .sum::<SC::Challenge>()
+        != SC::Challenge::ZERO
+    {
+        return Err(VerificationError::UnbalancedInteractions);
+    }
+
+    let alpha: SC::Challenge = challenger.sample_ext_element();
+    challenger.observe(commitments.quotient_chunks.clone());
+
+    let zeta: SC::Challenge = challenger.sample();
+
+    let trace_round = izip!(airs, &trace_domains, opened_values)
+        .map(|(air, domain, opened_values)| {
+            let window_points = iter::successors(Some(zeta), |&point| domain.next_point(point))
+                .take(air.window_size())
+                .collect_vec();
+            (
+                *domain,
+                window_openings(
+                    &window_points,
+                    &opened_values.trace_local,
+                    &opened_values.trace_next,
+                    &opened_values.trace_after_next,
+                ),
+            )
+        })
+        .collect_vec();
+    let permutation_round = izip!(&trace_domains, opened_values, cumulative_sums)
+        .filter(|(_, _, cumulative_sum)| cumulative_sum.is_some())
+        .map(|(domain, opened_values, _)| {
+            (
+                *domain,
+                vec![
+                    (zeta, opened_values.permutation_local.clone()),
+                    (
+                        domain.next_point(zeta).unwrap(),
+                        opened_values.permutation_next.clone(),
+                    ),
+                ],
+            )
+        })
+        .collect_vec();
+    let quotient_round = izip!(&quotient_chunks_domains, opened_values)
+        .flat_map(|(domains, opened_values)| {
+            izip!(domains, &opened_values.quotient_chunks)
+                .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
+        })
+        .collect_vec();
+
+    let mut rounds = vec![
+        (commitments.trace.clone(), trace_round),
+        (commitments.quotient_chunks.clone(), quotient_round),
+    ];
+    if let Some(permutation_commit) = &commitments.permutation {
+        rounds.push((permutation_commit.clone(), permutation_round));
+    }
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    for (i, (air, opened_values, public_values)) in
+        izip!(airs, opened_values, public_values).enumerate()
+    {
+        verify_constraints::<SC, A>(
+            air,
+            opened_values,
+            trace_domains[i],
+            &quotient_chunks_domains[i],
+            &permutation_challenges,
+            cumulative_sums[i].unwrap_or_default(),
+            &sends[i],
+            &receives[i],
+            zeta,
+            alpha,
+            public_values,
+            &instances[i],
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use alloc::vec;
+
+use p3_air::BaseAir;
+use p3_commit::Pcs;
+use p3_matrix::Matrix;
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, instrument};
+
+use crate::{Com, PcsProverData, StarkGenericConfig, Val};
+
+/// The prover's view of a committed preprocessed trace, computed once by `setup_keys` and reused
+/// across proofs.
+pub struct PreprocessedProverData<SC: StarkGenericConfig> {
+    /// The number of preprocessed columns.
+    pub width: usize,
+    /// The log of the number of rows in the preprocessed trace.
+    pub degree_bits: usize,
+    /// The commitment to the preprocessed trace.
+    pub commitment: Com<SC>,
+    /// The PCS data needed to open the preprocessed trace.
+    pub prover_data: PcsProverData<SC>,
+}
+
+/// The verifier's view of a committed preprocessed trace.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PreprocessedVerifierKey<SC: StarkGenericConfig> {
+    /// The number of preprocessed columns.
+    pub width: usize,
+    /// The log of the number of rows in the preprocessed trace.
+    pub degree_bits: usize,
+    /// The commitment to the preprocessed trace.
+    pub commitment: Com<SC>,
+}
+
+/// Commit to the preprocessed trace of `air`, returning `None` if it has no preprocessed columns.
+///
+/// The preprocessed trace must have the same height as any main trace it is later proven with.
+#[instrument(skip_all)]
+pub(crate) fn commit_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+) -> Option<(PreprocessedProverData<SC>, PreprocessedVerifierKey<SC>)>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+{
+    let preprocessed = air.preprocessed_trace()?;
+    let width = preprocessed.width();
+    if width == 0 {
+        return None;
+    }
+
+    let degree = preprocessed.height();
+    let degree_bits = log2_strict_usize(degree);
+
+    let pcs = config.pcs();
+    let domain = pcs.natural_domain_for_degree(degree);
+    let (commitment, prover_data) = info_span!("commit to preprocessed trace")
+        .in_scope(|| pcs.commit(vec![(domain, preprocessed)]));
+
+    let prover_data = PreprocessedProverData {
+        width,
+        degree_bits,
+        commitment: commitment.clone(),
+        prover_data,
+    };
+    let verifier_key = PreprocessedVerifierKey {
+        width,
+        degree_bits,
+        commitment,
+    };
+    Some((prover_data, verifier_key))
+}
@@ -3,12 +3,8 @@ use alloc::vec::Vec;
 use p3_commit::Pcs;
 use serde::{Deserialize, Serialize};
 
-use crate::StarkGenericConfig;
+use crate::{Com, StarkGenericConfig};
 
-type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
-    <SC as StarkGenericConfig>::Challenge,
-    <SC as StarkGenericConfig>::Challenger,
->>::Commitment;
 type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
@@ -23,15 +19,36 @@ pub struct Proof<SC: StarkGenericConfig> {
     pub(crate) degree_bits: usize,
 }
 
+/// A proof of several AIR instances, produced by `prove_multi`.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MultiProof<SC: StarkGenericConfig> {
+    pub(crate) commitments: Commitments<Com<SC>>,
+    pub(crate) opened_values: Vec<OpenedValues<SC::Challenge>>,
+    pub(crate) opening_proof: PcsProof<SC>,
+    pub(crate) degree_bits: Vec<usize>,
+    /// The claimed LogUp sum of each table with interactions, which must sum to zero.
+    pub(crate) cumulative_sums: Vec<Option<SC::Challenge>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Commitments<Com> {
     pub(crate) trace: Com,
+    pub(crate) permutation: Option<Com>,
     pub(crate) quotient_chunks: Com,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenedValues<Challenge> {
+    pub(crate) preprocessed_local: Vec<Challenge>,
+    pub(crate) preprocessed_next: Vec<Challenge>,
+    /// The preprocessed rows after `preprocessed_next`, for AIRs with a window of more than 2 rows.
+    pub(crate) preprocessed_after_next: Vec<Vec<Challenge>>,
     pub(crate) trace_local: Vec<Challenge>,
     pub(crate) trace_next: Vec<Challenge>,
+    /// The trace rows after `trace_next`, for AIRs with a window of more than 2 rows.
+    pub(crate) trace_after_next: Vec<Vec<Challenge>>,
+    pub(crate) permutation_local: Vec<Challenge>,
+    pub(crate) permutation_next: Vec<Challenge>,
     pub(crate) quotient_chunks: Vec<Vec<Challenge>>,
 }
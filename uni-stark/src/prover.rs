@@ -1,27 +1,31 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::cmp::min;
+use core::iter;
 
 use itertools::{izip, Itertools};
-use p3_air::Air;
+use p3_air::{Air, BaseAir, MultiPhaseBaseAir, PaddedBaseAir, PaddingRule};
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
 use p3_field::{FieldAlgebra, FieldExtensionAlgebra, PackedValue};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
-use p3_util::{log2_ceil_usize, log2_strict_usize};
+use p3_util::log2_strict_usize;
 use tracing::{info_span, instrument};
 
+use crate::instance::{observe_config, InstanceDescriptor};
+use crate::keys::proving_key_without_preprocessed;
 use crate::{
-    get_symbolic_constraints, Commitments, Domain, OpenedValues, PackedChallenge, PackedVal, Proof,
-    ProverConstraintFolder, StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val,
+    get_multi_phase_symbolic_constraints, Commitments, CompiledConstraints, Domain, OpenedValues,
+    PackedChallenge, PackedVal, Proof, ProverConstraintFolder, StarkGenericConfig, StarkProvingKey,
+    SymbolicAirBuilder, Val,
 };
 
-#[instrument(skip_all)]
 #[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
 pub fn prove<
     SC,
-    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>>>,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
     #[cfg(not(debug_assertions))] A,
 >(
     config: &SC,
@@ -32,87 +36,374 @@ pub fn prove<
 ) -> Proof<SC>
 where
     SC: StarkGenericConfig,
-    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<ProverConstraintFolder<'a, SC>>,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
 {
-    #[cfg(debug_assertions)]
-    crate::check_constraints::check_constraints(air, &trace, public_values);
+    let proving_key = proving_key_without_preprocessed::<SC, A>(air, public_values.len());
+    prove_phases(
+        config,
+        &proving_key,
+        air,
+        challenger,
+        trace,
+        public_values,
+        None,
+        None::<AfterChallengeFn<SC>>,
+    )
+}
+
+/// Like `prove`, but using a proving key computed beforehand by `setup_keys`, so the AIR's
+/// constraints aren't analyzed again. AIRs with preprocessed columns must be proven this way.
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_with_key<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    proving_key: &StarkProvingKey<SC>,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+{
+    prove_phases(
+        config,
+        proving_key,
+        air,
+        challenger,
+        trace,
+        public_values,
+        None,
+        None::<AfterChallengeFn<SC>>,
+    )
+}
+
+/// Like `prove`, but for an AIR with an after-challenge trace, which is generated once the main
+/// trace has been committed to and the AIR's challenges have been sampled.
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_multi_phase<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: MultiPhaseBaseAir<Val<SC>> + Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    assert!(
+        air.after_challenge_width() > 0,
+        "the after-challenge trace must have at least one column"
+    );
+    let symbolic_constraints =
+        get_multi_phase_symbolic_constraints::<Val<SC>, A>(air, 0, public_values.len());
+    let instance = InstanceDescriptor::new(
+        air,
+        &symbolic_constraints,
+        public_values.len(),
+        air.num_challenges(),
+        air.after_challenge_width(),
+        <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK,
+    );
+    let proving_key = StarkProvingKey {
+        instance,
+        preprocessed: None,
+        constraints: CompiledConstraints::new(&symbolic_constraints),
+    };
+    let generate_after_challenge =
+        |main: &RowMajorMatrix<Val<SC>>, challenges: &[SC::Challenge]| {
+            let after_challenge = air.generate_after_challenge_trace(main, challenges);
+            assert_eq!(
+                after_challenge.width(),
+                air.after_challenge_width(),
+                "after-challenge trace width must match the AIR's after-challenge width"
+            );
+            after_challenge
+        };
+    prove_phases(
+        config,
+        &proving_key,
+        air,
+        challenger,
+        trace,
+        public_values,
+        None,
+        Some(generate_after_challenge),
+    )
+}
 
+/// Like `prove`, but for a trace of any nonzero height, which is padded to the next power of two
+/// according to the AIR's padding rule.
+///
+/// The unpadded height is bound into the transcript, so the proof must be checked with
+/// `verify_padded` given the same height.
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_padded<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: PaddedBaseAir<Val<SC>> + Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let proving_key = proving_key_without_preprocessed::<SC, A>(air, public_values.len());
+    prove_phases(
+        config,
+        &proving_key,
+        air,
+        challenger,
+        trace,
+        public_values,
+        Some(&air.padding_rule()),
+        None::<AfterChallengeFn<SC>>,
+    )
+}
+
+type AfterChallengeFn<SC> = fn(
+    &RowMajorMatrix<Val<SC>>,
+    &[<SC as StarkGenericConfig>::Challenge],
+) -> RowMajorMatrix<<SC as StarkGenericConfig>::Challenge>;
+
+/// Prove `air`, which is described by `proving_key`.
+///
+/// If `padding` is given, the trace is first padded to a power of two with it.
+///
+/// If `after_challenge` is given, it generates the after-challenge trace from the main trace and
+/// the `num_challenges` challenges sampled once the main trace is committed to.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+#[allow(clippy::too_many_arguments)]
+fn prove_phases<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+    G,
+>(
+    config: &SC,
+    proving_key: &StarkProvingKey<SC>,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    mut trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    padding: Option<&PaddingRule<Val<SC>>>,
+    after_challenge: Option<G>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+    G: FnOnce(&RowMajorMatrix<Val<SC>>, &[SC::Challenge]) -> RowMajorMatrix<SC::Challenge>,
+{
+    let instance = &proving_key.instance;
+    let preprocessed = proving_key.preprocessed.as_ref();
+    let real_height = trace.height();
+    if let Some(padding) = padding {
+        padding.pad(&mut trace);
+    }
     let degree = trace.height();
     let log_degree = log2_strict_usize(degree);
+    assert_eq!(
+        public_values.len(),
+        instance.num_public_values,
+        "the number of public values must match the instance's"
+    );
 
-    let symbolic_constraints = get_symbolic_constraints::<Val<SC>, A>(air, 0, public_values.len());
-    let constraint_count = symbolic_constraints.len();
-    let constraint_degree = symbolic_constraints
-        .iter()
-        .map(SymbolicExpression::degree_multiple)
-        .max()
-        .unwrap_or(0);
-    let log_quotient_degree = log2_ceil_usize(constraint_degree - 1);
+    let preprocessed_width = preprocessed.map_or(0, |p| p.width);
+    assert_eq!(
+        preprocessed_width,
+        air.preprocessed_width(),
+        "preprocessed data width must match the AIR's preprocessed width"
+    );
+    assert!(
+        instance.boundary_rows.iter().all(|&row| row < degree),
+        "boundary constraints must be on rows of the trace"
+    );
+    if let Some(preprocessed) = preprocessed {
+        assert_eq!(
+            preprocessed.degree_bits, log_degree,
+            "preprocessed trace height must match the main trace height"
+        );
+    }
+
+    // With an after-challenge trace, the constraints can only be checked once it's generated.
+    #[cfg(debug_assertions)]
+    if after_challenge.is_none() {
+        crate::check_constraints::check_constraints::<_, SC::Challenge, _>(
+            air,
+            &trace,
+            None,
+            &[],
+            public_values,
+        );
+    }
+
+    let log_quotient_degree = instance.log_quotient_degree;
     let quotient_degree = 1 << log_quotient_degree;
 
     let pcs = config.pcs();
     let trace_domain = pcs.natural_domain_for_degree(degree);
 
+    // The after-challenge trace is generated from the main trace once it's committed to.
+    let main_trace = after_challenge.is_some().then(|| trace.clone());
+
     let (trace_commit, trace_data) =
         info_span!("commit to trace data").in_scope(|| pcs.commit(vec![(trace_domain, trace)]));
 
     // Observe the instance.
+    observe_config(config, challenger);
+    instance.observe(challenger);
     challenger.observe(Val::<SC>::from_canonical_usize(log_degree));
-    // TODO: Might be best practice to include other instance data here; see verifier comment.
+    challenger.observe(Val::<SC>::from_canonical_usize(real_height));
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     challenger.observe(trace_commit.clone());
     challenger.observe_slice(public_values);
+
+    let mut permutation_challenges = vec![];
+    let mut permutation_commit_and_data = None;
+    if let Some((generate_after_challenge, main)) = after_challenge.zip(main_trace) {
+        permutation_challenges = (0..instance.num_challenges)
+            .map(|_| challenger.sample_ext_element())
+            .collect_vec();
+        let after_challenge_trace = info_span!("generate after-challenge trace")
+            .in_scope(|| generate_after_challenge(&main, &permutation_challenges));
+        assert_eq!(
+            after_challenge_trace.height(),
+            degree,
+            "after-challenge trace height must match the main trace height"
+        );
+
+        #[cfg(debug_assertions)]
+        crate::check_constraints::check_constraints(
+            air,
+            &main,
+            Some(&after_challenge_trace),
+            &permutation_challenges,
+            public_values,
+        );
+
+        let (permutation_commit, permutation_data) = info_span!("commit to after-challenge trace")
+            .in_scope(|| {
+                pcs.commit(vec![(
+                    trace_domain,
+                    after_challenge_trace.flatten_to_base(),
+                )])
+            });
+        challenger.observe(permutation_commit.clone());
+        permutation_commit_and_data = Some((permutation_commit, permutation_data));
+    }
+    let (permutation_commit, permutation_data) = permutation_commit_and_data.unzip();
+
     let alpha: SC::Challenge = challenger.sample_ext_element();
 
     let quotient_domain =
         trace_domain.create_disjoint_domain(1 << (log_degree + log_quotient_degree));
 
     let trace_on_quotient_domain = pcs.get_evaluations_on_domain(&trace_data, 0, quotient_domain);
+    let preprocessed_on_quotient_domain =
+        preprocessed.map(|p| pcs.get_evaluations_on_domain(&p.prover_data, 0, quotient_domain));
+    let permutation_on_quotient_domain = permutation_data
+        .as_ref()
+        .map(|data| pcs.get_evaluations_on_domain(data, 0, quotient_domain));
 
-    let quotient_values = quotient_values(
-        air,
+    let quotient_values = quotient_values::<SC, _, _, _>(
+        &proving_key.constraints,
+        air.window_size(),
         public_values,
+        instance,
         trace_domain,
         quotient_domain,
+        preprocessed_on_quotient_domain,
         trace_on_quotient_domain,
+        permutation_on_quotient_domain,
+        &permutation_challenges,
+        SC::Challenge::ZERO,
         alpha,
-        constraint_count,
     );
     let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
-    let quotient_chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
-    let qc_domains = quotient_domain.split_domains(quotient_degree);
 
     let (quotient_commit, quotient_data) = info_span!("commit to quotient poly chunks")
-        .in_scope(|| pcs.commit(izip!(qc_domains, quotient_chunks).collect_vec()));
+        .in_scope(|| pcs.commit_quotients(vec![(quotient_domain, quotient_flat, quotient_degree)]));
     challenger.observe(quotient_commit.clone());
 
     let commitments = Commitments {
         trace: trace_commit,
+        permutation: permutation_commit,
         quotient_chunks: quotient_commit,
     };
 
     let zeta: SC::Challenge = challenger.sample();
     let zeta_next = trace_domain.next_point(zeta).unwrap();
+    // The main and preprocessed traces are opened at every row of the AIR's window.
+    let window_points = iter::successors(Some(zeta), |&point| trace_domain.next_point(point))
+        .take(air.window_size())
+        .collect_vec();
 
-    let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
-        pcs.open(
-            vec![
-                (&trace_data, vec![vec![zeta, zeta_next]]),
-                (
-                    &quotient_data,
-                    // open every chunk at zeta
-                    (0..quotient_degree).map(|_| vec![zeta]).collect_vec(),
-                ),
-            ],
-            challenger,
-        )
-    });
+    let mut rounds = vec![
+        (&trace_data, vec![window_points.clone()]),
+        (
+            &quotient_data,
+            // open every chunk at zeta
+            (0..quotient_degree).map(|_| vec![zeta]).collect_vec(),
+        ),
+    ];
+    if let Some(preprocessed) = preprocessed {
+        rounds.push((&preprocessed.prover_data, vec![window_points]));
+    }
+    if let Some(permutation_data) = &permutation_data {
+        rounds.push((permutation_data, vec![vec![zeta, zeta_next]]));
+    }
+
+    let (opened_values, opening_proof) =
+        info_span!("open").in_scope(|| pcs.open(rounds, challenger));
     let trace_local = opened_values[0][0][0].clone();
     let trace_next = opened_values[0][0][1].clone();
+    let trace_after_next = opened_values[0][0][2..].to_vec();
     let quotient_chunks = opened_values[1].iter().map(|v| v[0].clone()).collect_vec();
+    let mut extra_rounds = opened_values[2..].iter();
+    let (preprocessed_local, preprocessed_next, preprocessed_after_next) = if preprocessed.is_some()
+    {
+        let openings = &extra_rounds.next().unwrap()[0];
+        (
+            openings[0].clone(),
+            openings[1].clone(),
+            openings[2..].to_vec(),
+        )
+    } else {
+        (vec![], vec![], vec![])
+    };
+    let (permutation_local, permutation_next) = if permutation_data.is_some() {
+        let openings = &extra_rounds.next().unwrap()[0];
+        (openings[0].clone(), openings[1].clone())
+    } else {
+        (vec![], vec![])
+    };
     let opened_values = OpenedValues {
+        preprocessed_local,
+        preprocessed_next,
+        preprocessed_after_next,
         trace_local,
         trace_next,
+        trace_after_next,
+        permutation_local,
+        permutation_next,
         quotient_chunks,
     };
     Proof {
@@ -123,76 +414,176 @@ where
     }
 }
 
+/// The number of packed rows of the quotient domain whose constraints are evaluated together, so
+/// they share the cost of interpreting the compiled constraints.
+const QUOTIENT_BATCH_SIZE: usize = 4;
+
+/// Evaluate the quotient on the quotient domain, given the trace's compiled constraints and the
+/// number of rows they access.
 #[instrument(name = "compute quotient polynomial", skip_all)]
-fn quotient_values<SC, A, Mat>(
-    air: &A,
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn quotient_values<SC, PrepMat, Mat, PermMat>(
+    constraints: &CompiledConstraints<Val<SC>>,
+    window_size: usize,
     public_values: &Vec<Val<SC>>,
+    instance: &InstanceDescriptor<Val<SC>>,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
+    preprocessed_on_quotient_domain: Option<PrepMat>,
     trace_on_quotient_domain: Mat,
+    permutation_on_quotient_domain: Option<PermMat>,
+    permutation_challenges: &[SC::Challenge],
+    cumulative_sum: SC::Challenge,
     alpha: SC::Challenge,
-    constraint_count: usize,
 ) -> Vec<SC::Challenge>
 where
     SC: StarkGenericConfig,
-    A: for<'a> Air<ProverConstraintFolder<'a, SC>>,
+    PrepMat: Matrix<Val<SC>> + Sync,
     Mat: Matrix<Val<SC>> + Sync,
+    PermMat: Matrix<Val<SC>> + Sync,
 {
     let quotient_size = quotient_domain.size();
+    let preprocessed_width = preprocessed_on_quotient_domain
+        .as_ref()
+        .map_or(0, Matrix::width);
     let width = trace_on_quotient_domain.width();
+    // The permutation trace was committed to as a flattened matrix of base field elements.
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let permutation_width = permutation_on_quotient_domain
+        .as_ref()
+        .map_or(0, |m| m.width() / ext_degree);
+    let permutation_challenges = permutation_challenges
+        .iter()
+        .map(|&c| PackedChallenge::<SC>::from_f(c))
+        .collect_vec();
+    let cumulative_sum = PackedChallenge::<SC>::from_f(cumulative_sum);
     let mut sels = trace_domain.selectors_on_coset(quotient_domain);
+    // Selectors for windows of more than 2 rows; the 2-row window uses `sels.is_transition`.
+    let mut window_sels = (3..=window_size)
+        .map(|size| trace_domain.transition_window_selector_on_coset(size, quotient_domain))
+        .collect_vec();
+    let mut periodic = instance
+        .periodic_columns
+        .iter()
+        .map(|column| trace_domain.periodic_column_on_coset(column, quotient_domain))
+        .collect_vec();
+    let mut row_sels = instance
+        .boundary_rows
+        .iter()
+        .map(|&row| trace_domain.row_selector_on_coset(row, quotient_domain))
+        .collect_vec();
 
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
-    // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
-    // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
-    for _ in quotient_size..PackedVal::<SC>::WIDTH {
+    // We take QUOTIENT_BATCH_SIZE * PackedVal::<SC>::WIDTH worth of values at a time from a
+    // quotient_size slice, so we need to pad with default values in the case where quotient_size
+    // is smaller than that. The trace windows wrap around, so padding rows are evaluated on
+    // arbitrary rows and then discarded.
+    let batch_width = QUOTIENT_BATCH_SIZE * PackedVal::<SC>::WIDTH;
+    for _ in quotient_size..batch_width {
         sels.is_first_row.push(Val::<SC>::default());
         sels.is_last_row.push(Val::<SC>::default());
         sels.is_transition.push(Val::<SC>::default());
         sels.inv_zeroifier.push(Val::<SC>::default());
+        for sel in window_sels
+            .iter_mut()
+            .chain(&mut periodic)
+            .chain(&mut row_sels)
+        {
+            sel.push(Val::<SC>::default());
+        }
     }
 
-    let mut alpha_powers = alpha.powers().take(constraint_count).collect_vec();
+    let mut alpha_powers = alpha
+        .powers()
+        .take(constraints.num_constraints())
+        .map(PackedChallenge::<SC>::from_f)
+        .collect_vec();
     alpha_powers.reverse();
 
     (0..quotient_size)
         .into_par_iter()
-        .step_by(PackedVal::<SC>::WIDTH)
-        .flat_map_iter(|i_start| {
-            let i_range = i_start..i_start + PackedVal::<SC>::WIDTH;
-
-            let is_first_row = *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
-            let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
-            let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
-            let inv_zeroifier = *PackedVal::<SC>::from_slice(&sels.inv_zeroifier[i_range.clone()]);
-
-            let main = RowMajorMatrix::new(
-                trace_on_quotient_domain.vertically_packed_row_pair(i_start, next_step),
-                width,
-            );
+        .step_by(batch_width)
+        .flat_map_iter(|batch_start| {
+            let i_starts: [usize; QUOTIENT_BATCH_SIZE] =
+                core::array::from_fn(|j| batch_start + j * PackedVal::<SC>::WIDTH);
+
+            let preprocessed = i_starts.map(|i_start| {
+                RowMajorMatrix::new(
+                    preprocessed_on_quotient_domain
+                        .as_ref()
+                        .map_or_else(Vec::new, |m| {
+                            m.vertically_packed_row_window(i_start, next_step, window_size)
+                        }),
+                    preprocessed_width,
+                )
+            });
+            let main = i_starts.map(|i_start| {
+                RowMajorMatrix::new(
+                    trace_on_quotient_domain.vertically_packed_row_window(
+                        i_start,
+                        next_step,
+                        window_size,
+                    ),
+                    width,
+                )
+            });
+            let permutation = i_starts.map(|i_start| {
+                RowMajorMatrix::new(
+                    permutation_on_quotient_domain
+                        .as_ref()
+                        .map_or_else(Vec::new, |m| {
+                            m.vertically_packed_row_pair::<PackedVal<SC>>(i_start, next_step)
+                                .chunks_exact(ext_degree)
+                                .map(PackedChallenge::<SC>::from_base_slice)
+                                .collect()
+                        }),
+                    permutation_width,
+                )
+            });
 
-            let accumulator = PackedChallenge::<SC>::ZERO;
-            let mut folder = ProverConstraintFolder {
-                main: main.as_view(),
-                public_values,
-                is_first_row,
-                is_last_row,
-                is_transition,
-                alpha_powers: &alpha_powers,
-                accumulator,
-                constraint_index: 0,
+            let packed_sel = |sel: &[Val<SC>], i_start: usize| {
+                *PackedVal::<SC>::from_slice(&sel[i_start..i_start + PackedVal::<SC>::WIDTH])
             };
-            air.eval(&mut folder);
+            let mut folders: [_; QUOTIENT_BATCH_SIZE] =
+                core::array::from_fn(|j| ProverConstraintFolder::<SC> {
+                    preprocessed: preprocessed[j].as_view(),
+                    main: main[j].as_view(),
+                    permutation: permutation[j].as_view(),
+                    permutation_challenges: &permutation_challenges,
+                    cumulative_sum,
+                    public_values,
+                    is_first_row: packed_sel(&sels.is_first_row, i_starts[j]),
+                    is_last_row: packed_sel(&sels.is_last_row, i_starts[j]),
+                    is_transition_windows: iter::once(&sels.is_transition)
+                        .chain(&window_sels)
+                        .map(|sel| packed_sel(sel, i_starts[j]))
+                        .collect_vec(),
+                    is_rows: izip!(&instance.boundary_rows, &row_sels)
+                        .map(|(&row, sel)| (row, packed_sel(sel, i_starts[j])))
+                        .collect_vec(),
+                    periodic_values: periodic
+                        .iter()
+                        .map(|column| packed_sel(column, i_starts[j]))
+                        .collect_vec(),
+                    alpha_powers: &alpha_powers,
+                    accumulator: PackedChallenge::<SC>::ZERO,
+                    constraint_index: 0,
+                });
+            ProverConstraintFolder::eval_compiled_batch(&mut folders, constraints);
 
             // quotient(x) = constraints(x) / Z_H(x)
-            let quotient = folder.accumulator * inv_zeroifier;
+            let quotients: [PackedChallenge<SC>; QUOTIENT_BATCH_SIZE] = core::array::from_fn(|j| {
+                folders[j].accumulator * packed_sel(&sels.inv_zeroifier, i_starts[j])
+            });
 
             // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
-            (0..core::cmp::min(quotient_size, PackedVal::<SC>::WIDTH)).map(move |idx_in_packing| {
-                SC::Challenge::from_base_fn(|coeff_idx| {
-                    quotient.as_base_slice()[coeff_idx].as_slice()[idx_in_packing]
+            izip!(i_starts, quotients).flat_map(move |(i_start, quotient)| {
+                (i_start..min(quotient_size, i_start + PackedVal::<SC>::WIDTH)).map(move |i| {
+                    SC::Challenge::from_base_fn(|coeff_idx| {
+                        quotient.as_base_slice()[coeff_idx].as_slice()[i - i_start]
+                    })
                 })
             })
         })
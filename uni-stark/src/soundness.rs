@@ -0,0 +1,94 @@
+use p3_air::Air;
+use p3_commit::{security_bits, Pcs, PcsSoundness, ProximityRegime};
+use p3_field::{Field, FieldExtensionAlgebra};
+use p3_util::log2_f64;
+
+use crate::symbolic_builder::log_quotient_degree_of;
+use crate::{get_symbolic_constraints, StarkGenericConfig, SymbolicAirBuilder, Val};
+
+/// A breakdown of the proven soundness of a STARK, as bits of security (the negated log of each
+/// step's error).
+#[derive(Copy, Clone, Debug)]
+pub struct StarkSoundness {
+    /// Combining the constraints with powers of a random challenge.
+    pub constraint_batching_bits: f64,
+    /// Checking the combined constraints against the quotient at a random out-of-domain point.
+    pub ood_bits: f64,
+    /// Opening the committed polynomials at the out-of-domain point.
+    pub pcs: PcsSoundness,
+}
+
+impl StarkSoundness {
+    /// The bits of security of the STARK as a whole.
+    pub fn bits(&self) -> f64 {
+        security_bits(&[
+            self.constraint_batching_bits,
+            self.ood_bits,
+            self.pcs.batching_bits,
+            self.pcs.commit_phase_bits,
+            self.pcs.query_phase_bits,
+        ])
+    }
+}
+
+/// Estimate the proven soundness of proving `air` over a trace of height `2^log_degree` with
+/// `config`, in the given proximity regime. Returns `None` if the PCS has no soundness estimate.
+///
+/// This follows the DEEP-ALI analysis of the [ethSTARK](https://eprint.iacr.org/2021/582)
+/// documentation: each check the verifier makes at a random point errs once for every polynomial
+/// consistent with the commitments, as well as the usual error of that check. The challenge field
+/// size is rounded down to a power of two.
+pub fn proven_soundness<SC, A>(
+    config: &SC,
+    air: &A,
+    log_degree: usize,
+    num_public_values: usize,
+    regime: ProximityRegime,
+) -> Option<StarkSoundness>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let is_zk = <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK;
+    let constraints =
+        get_symbolic_constraints::<Val<SC>, A>(air, air.preprocessed_width(), num_public_values);
+    let constraint_degree = constraints
+        .iter()
+        .map(|constraint| constraint.degree_multiple())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    // A hiding PCS randomizes the committed traces to twice their degree.
+    let log_max_degree = log_degree + is_zk as usize;
+    // The trace columns are opened at each row of the window, and the quotient chunks, flattened
+    // into base field columns, at the out-of-domain point alone.
+    let num_quotient_columns = (1 << log_quotient_degree_of(&constraints, is_zk))
+        * <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let num_polys =
+        (air.preprocessed_width() + air.width()) * air.window_size() + num_quotient_columns;
+
+    let challenge_bits = (SC::Challenge::bits() - 1) as f64;
+    let pcs = config
+        .pcs()
+        .proven_soundness(regime, challenge_bits, log_max_degree, num_polys)?;
+
+    // A false constraint survives the random linear combination with probability `C / |F|`, and
+    // the out-of-domain check of a constraint polynomial of degree about `d n` errs with
+    // probability `d n / |F|`, each scaled by the list size.
+    let constraint_batching_bits = if constraints.is_empty() {
+        f64::INFINITY
+    } else {
+        challenge_bits - pcs.log_list_size - log2_f64(constraints.len() as f64)
+    };
+    let ood_bits = challenge_bits
+        - pcs.log_list_size
+        - log2_f64(constraint_degree as f64)
+        - log_max_degree as f64;
+
+    Some(StarkSoundness {
+        constraint_batching_bits,
+        ood_bits,
+        pcs,
+    })
+}
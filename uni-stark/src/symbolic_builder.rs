@@ -1,7 +1,15 @@
+use alloc::collections::BTreeSet;
+use alloc::rc::Rc;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, PairBuilder};
+use itertools::Itertools;
+
+use p3_air::{
+    eval_interactions, Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder,
+    InteractionAir, InteractionAirBuilder, MultiPhaseBaseAir, PairBuilder, PeriodicAirBuilder,
+    PermutationAirBuilder,
+};
 use p3_field::Field;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_ceil_usize;
@@ -16,19 +24,43 @@ pub fn get_log_quotient_degree<F, A>(
     air: &A,
     preprocessed_width: usize,
     num_public_values: usize,
+    is_zk: bool,
 ) -> usize
 where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
+    log_quotient_degree_of(
+        &get_symbolic_constraints(air, preprocessed_width, num_public_values),
+        is_zk,
+    )
+}
+
+/// The log of the quotient degree of the given constraints, i.e. of the number of trace-sized
+/// quotient chunks.
+pub(crate) fn log_quotient_degree_of<F: Field>(
+    constraints: &[SymbolicExpression<F>],
+    is_zk: bool,
+) -> usize {
     // We pad to at least degree 2, since a quotient argument doesn't make sense with smaller degrees.
-    let constraint_degree =
-        get_max_constraint_degree(air, preprocessed_width, num_public_values).max(2);
+    let constraint_degree = constraints
+        .iter()
+        .map(SymbolicExpression::degree_multiple)
+        .max()
+        .unwrap_or(0)
+        .max(2);
 
     // The quotient's actual degree is approximately (max_constraint_degree - 1) n,
     // where subtracting 1 comes from division by the zerofier.
     // But we pad it to a power of two so that we can efficiently decompose the quotient.
-    log2_ceil_usize(constraint_degree - 1)
+    //
+    // In ZK mode, the committed traces are randomized to degree 2n, so the quotient's degree is
+    // approximately (2 max_constraint_degree - 1) n instead.
+    if is_zk {
+        log2_ceil_usize(constraint_degree) + 1
+    } else {
+        log2_ceil_usize(constraint_degree - 1)
+    }
 }
 
 #[instrument(name = "infer constraint degree", skip_all, level = "debug")]
@@ -58,7 +90,136 @@ where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
-    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values);
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed_width,
+        air.width(),
+        air.window_size(),
+        air.periodic_columns().len(),
+        0,
+        0,
+        num_public_values,
+    );
+    air.eval(&mut builder);
+    builder.constraints()
+}
+
+/// A constraint which only applies on a single row, i.e. which was asserted under
+/// `AirBuilder::when_row`.
+#[derive(Clone, Debug)]
+pub struct BoundaryConstraint<F> {
+    /// The row the constraint applies on.
+    pub row: usize,
+    /// The position of the constraint in the order the AIR asserts its constraints.
+    pub constraint_index: usize,
+    pub constraint: SymbolicExpression<F>,
+}
+
+/// The constraints of `air` which apply on a single row given by `AirBuilder::is_row`, i.e. those
+/// which are a product with a row selector.
+pub fn get_boundary_constraints<F, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> Vec<BoundaryConstraint<F>>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    get_symbolic_constraints(air, preprocessed_width, num_public_values)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(constraint_index, constraint)| {
+            selected_row(&constraint).map(|row| BoundaryConstraint {
+                row,
+                constraint_index,
+                constraint,
+            })
+        })
+        .collect()
+}
+
+/// The row whose selector `expr` is a product with, if any.
+fn selected_row<F>(expr: &SymbolicExpression<F>) -> Option<usize> {
+    match expr {
+        SymbolicExpression::IsRow(row) => Some(*row),
+        SymbolicExpression::Mul { x, y, .. } => selected_row(x).or_else(|| selected_row(y)),
+        _ => None,
+    }
+}
+
+/// The distinct rows selected with `AirBuilder::is_row` anywhere in `constraints`, in increasing
+/// order.
+pub(crate) fn boundary_rows<F>(constraints: &[SymbolicExpression<F>]) -> Vec<usize> {
+    // Subexpressions may be shared, so we only visit each once.
+    let mut visited = BTreeSet::new();
+    let mut rows = BTreeSet::new();
+    let mut stack = constraints.iter().collect_vec();
+    while let Some(expr) = stack.pop() {
+        let children = match expr {
+            SymbolicExpression::IsRow(row) => {
+                rows.insert(*row);
+                continue;
+            }
+            SymbolicExpression::Add { x, y, .. }
+            | SymbolicExpression::Sub { x, y, .. }
+            | SymbolicExpression::Mul { x, y, .. } => vec![x, y],
+            SymbolicExpression::Neg { x, .. } => vec![x],
+            _ => continue,
+        };
+        for child in children {
+            if visited.insert(Rc::as_ptr(child)) {
+                stack.push(child);
+            }
+        }
+    }
+    rows.into_iter().collect()
+}
+
+/// Like `get_symbolic_constraints`, followed by the LogUp constraints for `air`'s interactions.
+#[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
+pub fn get_symbolic_constraints_with_interactions<F, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> Vec<SymbolicExpression<F>>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>> + InteractionAir<F>,
+{
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed_width,
+        air.width(),
+        air.window_size(),
+        air.periodic_columns().len(),
+        air.permutation_width(),
+        2,
+        num_public_values,
+    );
+    air.eval(&mut builder);
+    eval_interactions(&mut builder, &air.sends(), &air.receives());
+    builder.constraints()
+}
+
+/// Like `get_symbolic_constraints`, for an AIR with an after-challenge trace.
+#[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
+pub fn get_multi_phase_symbolic_constraints<F, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> Vec<SymbolicExpression<F>>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>> + MultiPhaseBaseAir<F>,
+{
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed_width,
+        air.width(),
+        air.window_size(),
+        air.periodic_columns().len(),
+        air.after_challenge_width(),
+        air.num_challenges(),
+        num_public_values,
+    );
     air.eval(&mut builder);
     builder.constraints()
 }
@@ -68,31 +229,59 @@ where
 pub struct SymbolicAirBuilder<F: Field> {
     preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
+    window_size: usize,
+    periodic_values: Vec<SymbolicVariable<F>>,
+    permutation: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation_challenges: Vec<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
     constraints: Vec<SymbolicExpression<F>>,
 }
 
 impl<F: Field> SymbolicAirBuilder<F> {
-    pub(crate) fn new(preprocessed_width: usize, width: usize, num_public_values: usize) -> Self {
-        let prep_values = [0, 1]
-            .into_iter()
+    pub(crate) fn new(
+        preprocessed_width: usize,
+        width: usize,
+        window_size: usize,
+        num_periodic_columns: usize,
+        permutation_width: usize,
+        num_permutation_challenges: usize,
+        num_public_values: usize,
+    ) -> Self {
+        assert!(window_size >= 2, "windows must have at least 2 rows");
+        let prep_values = (0..window_size)
             .flat_map(|offset| {
                 (0..preprocessed_width)
                     .map(move |index| SymbolicVariable::new(Entry::Preprocessed { offset }, index))
             })
             .collect();
-        let main_values = [0, 1]
-            .into_iter()
+        let main_values = (0..window_size)
             .flat_map(|offset| {
                 (0..width).map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
             })
             .collect();
+        let periodic_values = (0..num_periodic_columns)
+            .map(|index| SymbolicVariable::new(Entry::Periodic, index))
+            .collect();
+        let permutation_values = [0, 1]
+            .into_iter()
+            .flat_map(|offset| {
+                (0..permutation_width)
+                    .map(move |index| SymbolicVariable::new(Entry::Permutation { offset }, index))
+            })
+            .collect();
+        let permutation_challenges = (0..num_permutation_challenges)
+            .map(|index| SymbolicVariable::new(Entry::Challenge, index))
+            .collect();
         let public_values = (0..num_public_values)
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
         Self {
             preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
             main: RowMajorMatrix::new(main_values, width),
+            window_size,
+            periodic_values,
+            permutation: RowMajorMatrix::new(permutation_values, permutation_width),
+            permutation_challenges,
             public_values,
             constraints: vec![],
         }
@@ -122,13 +311,21 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
+        assert!(
+            (2..=self.window_size).contains(&size),
+            "window of {size} rows exceeds the AIR's window size"
+        );
         if size == 2 {
             SymbolicExpression::IsTransition
         } else {
-            panic!("uni-stark only supports a window size of 2")
+            SymbolicExpression::IsTransitionWindow(size)
         }
     }
 
+    fn is_row(&self, row: usize) -> Self::Expr {
+        SymbolicExpression::IsRow(row)
+    }
+
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.constraints.push(x.into());
     }
@@ -141,8 +338,47 @@ impl<F: Field> AirBuilderWithPublicValues for SymbolicAirBuilder<F> {
     }
 }
 
+impl<F: Field> PeriodicAirBuilder for SymbolicAirBuilder<F> {
+    type PeriodicVar = SymbolicVariable<F>;
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
 impl<F: Field> PairBuilder for SymbolicAirBuilder<F> {
     fn preprocessed(&self) -> Self::M {
         self.preprocessed.clone()
     }
 }
+
+impl<F: Field> ExtensionBuilder for SymbolicAirBuilder<F> {
+    type EF = F;
+    type ExprEF = SymbolicExpression<F>;
+    type VarEF = SymbolicVariable<F>;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.constraints.push(x.into());
+    }
+}
+
+impl<F: Field> PermutationAirBuilder for SymbolicAirBuilder<F> {
+    type MP = RowMajorMatrix<Self::VarEF>;
+    type RandomVar = SymbolicVariable<F>;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation.clone()
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        &self.permutation_challenges
+    }
+}
+
+impl<F: Field> InteractionAirBuilder for SymbolicAirBuilder<F> {
+    fn cumulative_sum(&self) -> Self::RandomVar {
+        SymbolicVariable::new(Entry::CumulativeSum, 0)
+    }
+}
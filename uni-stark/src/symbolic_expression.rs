@@ -15,6 +15,11 @@ pub enum SymbolicExpression<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    /// The selector for transitions over a window of the given number of rows, for windows of
+    /// more than 2 rows.
+    IsTransitionWindow(usize),
+    /// The selector for the row with the given index, used by boundary constraints.
+    IsRow(usize),
     Constant(F),
     Add {
         x: Rc<Self>,
@@ -45,6 +50,9 @@ impl<F> SymbolicExpression<F> {
             SymbolicExpression::IsFirstRow => 1,
             SymbolicExpression::IsLastRow => 1,
             SymbolicExpression::IsTransition => 0,
+            // The selector has degree `window_size - 1`, which we bound by `n`.
+            SymbolicExpression::IsTransitionWindow(_) => 1,
+            SymbolicExpression::IsRow(_) => 1,
             SymbolicExpression::Constant(_) => 0,
             SymbolicExpression::Add {
                 degree_multiple, ..
@@ -1,19 +1,24 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
 use itertools::Itertools;
-use p3_air::{Air, BaseAir};
+use p3_air::{eval_interactions, Air, BaseAir, Interaction, MultiPhaseBaseAir};
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
 use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra};
-use p3_matrix::dense::RowMajorMatrixView;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
 use p3_matrix::stack::VerticalPair;
 use tracing::instrument;
 
-use crate::symbolic_builder::{get_log_quotient_degree, SymbolicAirBuilder};
-use crate::{PcsError, Proof, StarkGenericConfig, Val, VerifierConstraintFolder};
+use crate::instance::{observe_config, InstanceDescriptor};
+use crate::keys::instance_descriptor;
+use crate::symbolic_builder::{get_multi_phase_symbolic_constraints, SymbolicAirBuilder};
+use crate::{
+    Domain, OpenedValues, PcsError, PreprocessedVerifierKey, Proof, StarkGenericConfig,
+    StarkVerifyingKey, Val, VerifierConstraintFolder,
+};
 
-#[instrument(skip_all)]
 pub fn verify<SC, A>(
     config: &SC,
     air: &A,
@@ -24,6 +29,135 @@ pub fn verify<SC, A>(
 where
     SC: StarkGenericConfig,
     A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let instance = instance_descriptor::<SC, A>(air, public_values.len());
+    verify_phases(
+        config,
+        None,
+        air,
+        challenger,
+        proof,
+        public_values,
+        &instance,
+        None,
+    )
+}
+
+/// Verify a proof produced by `prove_padded` from a trace of `real_height` rows.
+///
+/// The height is part of the statement: it's bound into the transcript on both sides, so the
+/// proof only verifies against the height the prover padded from. Nothing else ties it to the
+/// trace, so an AIR that cares how many rows are real must constrain its padding rows itself,
+/// e.g. with an `is_real` column.
+pub fn verify_padded<SC, A>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+    real_height: usize,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let instance = instance_descriptor::<SC, A>(air, public_values.len());
+    verify_phases(
+        config,
+        None,
+        air,
+        challenger,
+        proof,
+        public_values,
+        &instance,
+        Some(real_height),
+    )
+}
+
+/// Like `verify`, but using a verifying key computed beforehand by `setup_keys`, so the AIR's
+/// constraints aren't analyzed again. AIRs with preprocessed columns must be verified this way.
+///
+/// The key must have been computed from `air`, which is still needed to evaluate the constraints.
+/// This panics if `air` has boundary constraints on rows the key doesn't know of.
+pub fn verify_with_key<SC, A>(
+    config: &SC,
+    verifying_key: &StarkVerifyingKey<SC>,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    verify_phases(
+        config,
+        verifying_key.preprocessed.as_ref(),
+        air,
+        challenger,
+        proof,
+        public_values,
+        &verifying_key.instance,
+        None,
+    )
+}
+
+/// Verify a proof of an AIR with an after-challenge trace produced by `prove_multi_phase`.
+pub fn verify_multi_phase<SC, A>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: MultiPhaseBaseAir<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let symbolic_constraints =
+        get_multi_phase_symbolic_constraints::<Val<SC>, A>(air, 0, public_values.len());
+    let instance = InstanceDescriptor::new(
+        air,
+        &symbolic_constraints,
+        public_values.len(),
+        air.num_challenges(),
+        air.after_challenge_width(),
+        <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK,
+    );
+    verify_phases(
+        config,
+        None,
+        air,
+        challenger,
+        proof,
+        public_values,
+        &instance,
+        None,
+    )
+}
+
+/// Verify a proof of `air`, which is described by `instance`.
+///
+/// The proof has an after-challenge trace if and only if `instance.after_challenge_width` is
+/// nonzero. `real_height` is the height of the trace before padding, if it was padded.
+#[instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+fn verify_phases<SC, A>(
+    config: &SC,
+    preprocessed_vk: Option<&PreprocessedVerifierKey<SC>>,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+    instance: &InstanceDescriptor<Val<SC>>,
+    real_height: Option<usize>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
 {
     let Proof {
         commitments,
@@ -32,8 +166,24 @@ where
         degree_bits,
     } = proof;
 
+    if public_values.len() != instance.num_public_values {
+        return Err(VerificationError::PublicValuesLengthMismatch);
+    }
+
     let degree = 1 << degree_bits;
-    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len());
+    let real_height = real_height.unwrap_or(degree);
+    // The trace shape comes from the instance, which the keyed path takes from the verifying key
+    // rather than regenerating it from the AIR. Only a cheap check ties the AIR to the instance.
+    if <A as BaseAir<Val<SC>>>::width(air) != instance.width
+        || air.window_size() != instance.window_size
+    {
+        return Err(VerificationError::AirMismatch);
+    }
+    let preprocessed_width = instance.preprocessed_width;
+    if preprocessed_vk.map_or(0, |vk| vk.width) != preprocessed_width {
+        return Err(VerificationError::PreprocessedWidthMismatch);
+    }
+    let log_quotient_degree = instance.log_quotient_degree;
     let quotient_degree = 1 << log_quotient_degree;
 
     let pcs = config.pcs();
@@ -42,60 +192,195 @@ where
         trace_domain.create_disjoint_domain(1 << (degree_bits + log_quotient_degree));
     let quotient_chunks_domains = quotient_domain.split_domains(quotient_degree);
 
-    let air_width = <A as BaseAir<Val<SC>>>::width(air);
-    let valid_shape = opened_values.trace_local.len() == air_width
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let air_width = instance.width;
+    let after_challenge_width = instance.after_challenge_width;
+    let window_size = instance.window_size;
+    let preprocessed_rows_after_next = if preprocessed_vk.is_some() {
+        window_size - 2
+    } else {
+        0
+    };
+    let valid_shape = real_height > 0
+        && real_height.next_power_of_two() == degree
+        && instance.periodic_columns.iter().all(|column| {
+            !column.is_empty() && column.len().is_power_of_two() && column.len() <= degree
+        })
+        && instance.boundary_rows.iter().all(|&row| row < degree)
+        && preprocessed_vk.is_none_or(|vk| vk.degree_bits == *degree_bits)
+        && commitments.permutation.is_some() == (after_challenge_width > 0)
+        && opened_values.permutation_local.len() == after_challenge_width * ext_degree
+        && opened_values.permutation_next.len() == after_challenge_width * ext_degree
+        && opened_values.preprocessed_local.len() == preprocessed_width
+        && opened_values.preprocessed_next.len() == preprocessed_width
+        && opened_values.preprocessed_after_next.len() == preprocessed_rows_after_next
+        && opened_values
+            .preprocessed_after_next
+            .iter()
+            .all(|row| row.len() == preprocessed_width)
+        && opened_values.trace_local.len() == air_width
         && opened_values.trace_next.len() == air_width
+        && opened_values.trace_after_next.len() == window_size - 2
+        && opened_values
+            .trace_after_next
+            .iter()
+            .all(|row| row.len() == air_width)
         && opened_values.quotient_chunks.len() == quotient_degree
         && opened_values
             .quotient_chunks
             .iter()
-            .all(|qc| qc.len() == <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D);
+            .all(|qc| qc.len() == ext_degree);
     if !valid_shape {
         return Err(VerificationError::InvalidProofShape);
     }
 
     // Observe the instance.
+    observe_config(config, challenger);
+    instance.observe(challenger);
     challenger.observe(Val::<SC>::from_canonical_usize(proof.degree_bits));
-    // TODO: Might be best practice to include other instance data here in the transcript, like some
-    // encoding of the AIR. This protects against transcript collisions between distinct instances.
-    // Practically speaking though, the only related known attack is from failing to include public
-    // values. It's not clear if failing to include other instance data could enable a transcript
-    // collision, since most such changes would completely change the set of satisfying witnesses.
+    challenger.observe(Val::<SC>::from_canonical_usize(real_height));
+    if let Some(vk) = preprocessed_vk {
+        challenger.observe(vk.commitment.clone());
+    }
 
     challenger.observe(commitments.trace.clone());
     challenger.observe_slice(public_values);
+
+    let permutation_challenges: Vec<SC::Challenge> = (0..instance.num_challenges)
+        .map(|_| challenger.sample_ext_element())
+        .collect();
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+    }
+
     let alpha: SC::Challenge = challenger.sample_ext_element();
     challenger.observe(commitments.quotient_chunks.clone());
 
     let zeta: SC::Challenge = challenger.sample();
     let zeta_next = trace_domain.next_point(zeta).unwrap();
+    let window_points = iter::successors(Some(zeta), |&point| trace_domain.next_point(point))
+        .take(window_size)
+        .collect_vec();
 
-    pcs.verify(
-        vec![
-            (
-                commitments.trace.clone(),
-                vec![(
-                    trace_domain,
-                    vec![
-                        (zeta, opened_values.trace_local.clone()),
-                        (zeta_next, opened_values.trace_next.clone()),
-                    ],
-                )],
-            ),
-            (
-                commitments.quotient_chunks.clone(),
-                quotient_chunks_domains
-                    .iter()
-                    .zip(&opened_values.quotient_chunks)
-                    .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
-                    .collect_vec(),
-            ),
-        ],
-        opening_proof,
-        challenger,
+    let mut rounds = vec![
+        (
+            commitments.trace.clone(),
+            vec![(
+                trace_domain,
+                window_openings(
+                    &window_points,
+                    &opened_values.trace_local,
+                    &opened_values.trace_next,
+                    &opened_values.trace_after_next,
+                ),
+            )],
+        ),
+        (
+            commitments.quotient_chunks.clone(),
+            quotient_chunks_domains
+                .iter()
+                .zip(&opened_values.quotient_chunks)
+                .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
+                .collect_vec(),
+        ),
+    ];
+    if let Some(vk) = preprocessed_vk {
+        rounds.push((
+            vk.commitment.clone(),
+            vec![(
+                trace_domain,
+                window_openings(
+                    &window_points,
+                    &opened_values.preprocessed_local,
+                    &opened_values.preprocessed_next,
+                    &opened_values.preprocessed_after_next,
+                ),
+            )],
+        ));
+    }
+    if let Some(permutation_commit) = &commitments.permutation {
+        rounds.push((
+            permutation_commit.clone(),
+            vec![(
+                trace_domain,
+                vec![
+                    (zeta, opened_values.permutation_local.clone()),
+                    (zeta_next, opened_values.permutation_next.clone()),
+                ],
+            )],
+        ));
+    }
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    verify_constraints::<SC, A>(
+        air,
+        opened_values,
+        trace_domain,
+        &quotient_chunks_domains,
+        &permutation_challenges,
+        SC::Challenge::ZERO,
+        &[],
+        &[],
+        zeta,
+        alpha,
+        public_values,
+        instance,
     )
-    .map_err(VerificationError::InvalidOpeningArgument)?;
+}
+
+/// Pair each point of a window with the rows opened there.
+pub(crate) fn window_openings<F: Clone, P: Copy>(
+    points: &[P],
+    local: &[F],
+    next: &[F],
+    after_next: &[Vec<F>],
+) -> Vec<(P, Vec<F>)> {
+    let rows = [local.to_vec(), next.to_vec()]
+        .into_iter()
+        .chain(after_next.iter().cloned());
+    points.iter().copied().zip(rows).collect()
+}
+
+/// Stack the rows opened over a window into a matrix.
+fn window_matrix<F: Clone + Send + Sync>(
+    local: &[F],
+    next: &[F],
+    after_next: &[Vec<F>],
+) -> RowMajorMatrix<F> {
+    let values = [local, next]
+        .into_iter()
+        .chain(after_next.iter().map(Vec::as_slice))
+        .flatten()
+        .cloned()
+        .collect();
+    RowMajorMatrix::new(values, local.len())
+}
 
+/// Check that the opened trace values satisfy the AIR's constraints at `zeta`, i.e. that the
+/// folded constraints divided by the vanishing polynomial agree with the opened quotient.
+///
+/// The LogUp constraints for `sends` and `receives` are checked after the AIR's own constraints.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_constraints<SC, A>(
+    air: &A,
+    opened_values: &OpenedValues<SC::Challenge>,
+    trace_domain: Domain<SC>,
+    quotient_chunks_domains: &[Domain<SC>],
+    permutation_challenges: &[SC::Challenge],
+    cumulative_sum: SC::Challenge,
+    sends: &[Interaction<Val<SC>>],
+    receives: &[Interaction<Val<SC>>],
+    zeta: SC::Challenge,
+    alpha: SC::Challenge,
+    public_values: &Vec<Val<SC>>,
+    instance: &InstanceDescriptor<Val<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
     let zps = quotient_chunks_domains
         .iter()
         .enumerate()
@@ -126,21 +411,70 @@ where
 
     let sels = trace_domain.selectors_at_point(zeta);
 
-    let main = VerticalPair::new(
-        RowMajorMatrixView::new_row(&opened_values.trace_local),
-        RowMajorMatrixView::new_row(&opened_values.trace_next),
+    let is_transition_windows = (2..=instance.window_size)
+        .map(|size| trace_domain.transition_window_selector_at_point(size, zeta))
+        .collect_vec();
+    let is_rows = instance
+        .boundary_rows
+        .iter()
+        .map(|&row| (row, trace_domain.row_selector_at_point(row, zeta)))
+        .collect_vec();
+    let periodic_values = instance
+        .periodic_columns
+        .iter()
+        .map(|column| trace_domain.periodic_column_at_point(column, zeta))
+        .collect_vec();
+
+    let preprocessed = window_matrix(
+        &opened_values.preprocessed_local,
+        &opened_values.preprocessed_next,
+        &opened_values.preprocessed_after_next,
+    );
+    let main = window_matrix(
+        &opened_values.trace_local,
+        &opened_values.trace_next,
+        &opened_values.trace_after_next,
+    );
+
+    // Each permutation column was committed to as `D` base field columns, so we recombine their
+    // openings into extension field values.
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let recompose = |values: &[SC::Challenge]| {
+        values
+            .chunks_exact(ext_degree)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(e_i, &c)| SC::Challenge::monomial(e_i) * c)
+                    .sum::<SC::Challenge>()
+            })
+            .collect_vec()
+    };
+    let permutation_local = recompose(&opened_values.permutation_local);
+    let permutation_next = recompose(&opened_values.permutation_next);
+    let permutation = VerticalPair::new(
+        RowMajorMatrixView::new_row(&permutation_local),
+        RowMajorMatrixView::new_row(&permutation_next),
     );
 
     let mut folder = VerifierConstraintFolder {
-        main,
+        preprocessed: preprocessed.as_view(),
+        main: main.as_view(),
+        permutation,
+        permutation_challenges,
+        cumulative_sum,
         public_values,
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
-        is_transition: sels.is_transition,
+        is_transition_windows,
+        is_rows,
+        periodic_values,
         alpha,
         accumulator: SC::Challenge::ZERO,
     };
     air.eval(&mut folder);
+    eval_interactions(&mut folder, sends, receives);
     let folded_constraints = folder.accumulator;
 
     // Finally, check that
@@ -155,8 +489,16 @@ where
 #[derive(Debug)]
 pub enum VerificationError<PcsErr> {
     InvalidProofShape,
+    /// The preprocessed verifier key's width doesn't match the AIR's preprocessed width.
+    PreprocessedWidthMismatch,
+    /// The AIR's trace width or window size doesn't match the verifying key's.
+    AirMismatch,
+    /// The number of public values doesn't match the verifying key's.
+    PublicValuesLengthMismatch,
     /// An error occurred while verifying the claimed openings.
     InvalidOpeningArgument(PcsErr),
+    /// The LogUp sums of all tables don't add up to zero, i.e. some bus is unbalanced.
+    UnbalancedInteractions,
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
     /// `quotient(zeta) Z_H(zeta)`.
     OodEvaluationMismatch,
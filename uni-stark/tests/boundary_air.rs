@@ -0,0 +1,186 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_commit::{PolynomialSpace, TwoAdicMultiplicativeCoset};
+use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    check_constraints_report, get_boundary_constraints, prove, verify, SymbolicExpression,
+    VerificationError,
+};
+use rand::thread_rng;
+
+use crate::common::{generate_trace, setup_config, Challenge, Challenger, Perm, Val};
+
+mod common;
+
+/// The row whose right column is pinned to a public value.
+const PINNED_ROW: usize = 5;
+
+/// A Fibonacci sequence in two columns, starting at `(a, b)`, whose right column is `x` at row
+/// `PINNED_ROW`, where `[a, b, x]` are the public values.
+pub struct PinnedFibonacciAir;
+
+impl<F> BaseAir<F> for PinnedFibonacciAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PinnedFibonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b, x) = (pis[0], pis[1], pis[2]);
+
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+
+        let mut when_first_row = builder.when_row(0);
+        when_first_row.assert_eq(local[0], a);
+        when_first_row.assert_eq(local[1], b);
+
+        let mut when_transition = builder.when_transition();
+        when_transition.assert_eq(next[0], local[1]);
+        when_transition.assert_eq(next[1], local[0] + local[1]);
+
+        builder.when_row(PINNED_ROW).assert_eq(local[1], x);
+    }
+}
+
+/// A `FibonacciAir` trace, with the last public value replaced by the right column of
+/// `PINNED_ROW`.
+fn generate_pinned_trace<F: Field>(log_height: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let (trace, mut public_values) = generate_trace(log_height);
+    public_values[2] = trace.get(PINNED_ROW, 1);
+    (trace, public_values)
+}
+
+#[test]
+fn test_boundary_constraints_reported_separately() {
+    let boundary_constraints = get_boundary_constraints::<Val, _>(&PinnedFibonacciAir, 0, 3);
+    let rows = boundary_constraints
+        .iter()
+        .map(|boundary| (boundary.row, boundary.constraint_index))
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![(0, 0), (0, 1), (PINNED_ROW, 4)]);
+    assert!(boundary_constraints
+        .iter()
+        .all(|boundary| matches!(boundary.constraint, SymbolicExpression::Mul { .. })));
+}
+
+#[test]
+fn test_prove_boundary() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+
+    for log_height in [3, 6] {
+        let (trace, public_values) = generate_pinned_trace::<Val>(log_height);
+
+        let mut challenger = Challenger::new(perm.clone());
+        let proof = prove(
+            &config,
+            &PinnedFibonacciAir,
+            &mut challenger,
+            trace,
+            &public_values,
+        );
+
+        let mut challenger = Challenger::new(perm.clone());
+        verify(
+            &config,
+            &PinnedFibonacciAir,
+            &mut challenger,
+            &proof,
+            &public_values,
+        )
+        .expect("verification failed");
+    }
+}
+
+#[test]
+fn test_boundary_incorrect_public_value() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (trace, mut public_values) = generate_pinned_trace::<Val>(4);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(
+        &config,
+        &PinnedFibonacciAir,
+        &mut challenger,
+        trace,
+        &public_values,
+    );
+
+    public_values[2] += Val::ONE;
+    let mut challenger = Challenger::new(perm);
+    let result = verify(
+        &config,
+        &PinnedFibonacciAir,
+        &mut challenger,
+        &proof,
+        &public_values,
+    );
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidOpeningArgument(_) | VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[test]
+fn test_report_boundary_failure() {
+    let (trace, mut public_values) = generate_pinned_trace::<Val>(4);
+    public_values[2] += Val::ONE;
+
+    let report = check_constraints_report::<_, Challenge, _>(
+        &PinnedFibonacciAir,
+        &trace,
+        None,
+        &[],
+        &public_values,
+    );
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint_index))
+        .collect::<Vec<_>>();
+    assert_eq!(failures, vec![(PINNED_ROW, 4)]);
+}
+
+#[test]
+fn test_row_selector_evaluations_agree() {
+    let trace_domain = TwoAdicMultiplicativeCoset {
+        log_n: 4,
+        shift: Val::ONE,
+    };
+    let quotient_domain = trace_domain.create_disjoint_domain(1 << 6);
+
+    for row in [0, 5, 15] {
+        // On the trace domain, the selector vanishes everywhere but on its row, where its formula
+        // is undefined.
+        for (i, x) in Val::two_adic_generator(4).powers().take(16).enumerate() {
+            if i != row {
+                let selector = trace_domain.row_selector_at_point(row, Challenge::from_base(x));
+                assert_eq!(selector, Challenge::ZERO);
+            }
+        }
+        // Row 0 gives the first-row selector.
+        let zeta = Challenge::from_canonical_u32(1234);
+        if row == 0 {
+            assert_eq!(
+                trace_domain.row_selector_at_point(row, zeta),
+                trace_domain.selectors_at_point(zeta).is_first_row
+            );
+        }
+        // On the quotient domain, it agrees with evaluating at each point.
+        let on_coset = trace_domain.row_selector_on_coset(row, quotient_domain);
+        let points = Val::two_adic_generator(6)
+            .shifted_powers(quotient_domain.shift)
+            .take(1 << 6);
+        for (value, x) in on_coset.into_iter().zip(points) {
+            assert_eq!(
+                Challenge::from_base(value),
+                trace_domain.row_selector_at_point(row, Challenge::from_base(x))
+            );
+        }
+    }
+}
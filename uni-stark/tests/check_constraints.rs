@@ -0,0 +1,104 @@
+use p3_field::FieldAlgebra;
+use p3_uni_stark::{check_constraints_report, Entry};
+
+use crate::common::{generate_trace, Challenge, FibonacciAir, Val};
+
+mod common;
+
+#[test]
+fn test_report_satisfied() {
+    let (trace, public_values) = generate_trace::<Val>(4);
+    let report = check_constraints_report::<_, Challenge, _>(
+        &FibonacciAir,
+        &trace,
+        None,
+        &[],
+        &public_values,
+    );
+    assert!(report.is_satisfied(), "{report}");
+    assert_eq!(report.num_rows, 16);
+    assert_eq!(report.num_constraints, 5);
+}
+
+#[test]
+fn test_report_bad_trace() {
+    let (mut trace, public_values) = generate_trace::<Val>(4);
+    // Breaks the right column of row 5, so the transitions into and out of row 5 both fail.
+    trace.values[11] += Val::ONE;
+
+    let report = check_constraints_report::<_, Challenge, _>(
+        &FibonacciAir,
+        &trace,
+        None,
+        &[],
+        &public_values,
+    );
+    assert!(!report.is_satisfied());
+
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| {
+            (
+                failure.row,
+                failure.constraint_index,
+                failure.name.as_deref(),
+                failure.value,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        vec![
+            (4, 3, Some("right' = left + right"), Challenge::ONE),
+            (5, 2, Some("left' = right"), Challenge::NEG_ONE),
+            (5, 3, Some("right' = left + right"), Challenge::NEG_ONE),
+        ]
+    );
+
+    // The second failure depends on the next row's left column and this row's right column.
+    let involved = report.failures[1]
+        .involved
+        .iter()
+        .map(|involved| (involved.entry, involved.index, involved.value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        involved,
+        vec![
+            (
+                Entry::Main { offset: 1 },
+                0,
+                Challenge::from_canonical_u32(8)
+            ),
+            (
+                Entry::Main { offset: 0 },
+                1,
+                Challenge::from_canonical_u32(9)
+            ),
+        ]
+    );
+
+    let message = report.to_string();
+    assert!(message.contains(
+        "row 5, constraint 2 (\"left' = right\"): evaluated to 2013265920; main[row + 1][0] = 8, main[row + 0][1] = 9"
+    ), "{message}");
+}
+
+#[test]
+fn test_report_unnamed_constraint() {
+    let (trace, mut public_values) = generate_trace::<Val>(4);
+    public_values[0] = Val::ONE;
+
+    let report = check_constraints_report::<_, Challenge, _>(
+        &FibonacciAir,
+        &trace,
+        None,
+        &[],
+        &public_values,
+    );
+    assert_eq!(report.failures.len(), 1);
+    let failure = &report.failures[0];
+    assert_eq!((failure.row, failure.constraint_index), (0, 0));
+    assert_eq!(failure.name, None);
+    assert_eq!(failure.involved.len(), 2);
+}
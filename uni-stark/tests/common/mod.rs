@@ -0,0 +1,82 @@
+//! The configuration shared by the uni-stark integration tests: BabyBear with Poseidon2 Merkle
+//! trees and a FRI PCS, along with a Fibonacci AIR several of them prove.
+#![allow(dead_code)]
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::Field;
+use p3_fri::{create_test_fri_config, TwoAdicFriPcs};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::StarkConfig;
+
+pub type Val = BabyBear;
+pub type Perm = Poseidon2BabyBear<16>;
+pub type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+pub type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+pub type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+pub type Challenge = BinomialExtensionField<Val, 4>;
+pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+pub type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+pub type Dft = Radix2DitParallel<Val>;
+pub type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+pub type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+pub fn setup_config(perm: &Perm) -> MyConfig {
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config);
+    MyConfig::new(pcs)
+}
+
+/// A Fibonacci sequence in two columns, starting at `(a, b)` and ending in `x`, where `[a, b, x]`
+/// are the public values. Its transition constraints are named.
+pub struct FibonacciAir;
+
+impl<F> BaseAir<F> for FibonacciAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for FibonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b, x) = (pis[0], pis[1], pis[2]);
+
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_eq(local[0], a);
+        when_first_row.assert_eq(local[1], b);
+
+        let mut when_transition = builder.when_transition();
+        when_transition.assert_eq_named("left' = right", next[0], local[1]);
+        when_transition.assert_eq_named("right' = left + right", next[1], local[0] + local[1]);
+
+        builder.when_last_row().assert_eq(local[1], x);
+    }
+}
+
+/// A `FibonacciAir` trace of `2^log_height` rows starting at `(0, 1)`, and its public values.
+pub fn generate_trace<F: Field>(log_height: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let (mut a, mut b) = (F::ZERO, F::ONE);
+    let mut values = Vec::with_capacity(2 << log_height);
+    for _ in 0..1 << log_height {
+        values.extend([a, b]);
+        (a, b) = (b, a + b);
+    }
+    let x = values[values.len() - 1];
+    (RowMajorMatrix::new(values, 2), vec![F::ZERO, F::ONE, x])
+}
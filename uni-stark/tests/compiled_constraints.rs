@@ -0,0 +1,110 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{FieldAlgebra, PackedValue};
+use p3_matrix::dense::RowMajorMatrixView;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    get_symbolic_constraints, CompiledConstraints, PackedChallenge, PackedVal,
+    ProverConstraintFolder,
+};
+use rand::{thread_rng, Rng};
+
+use crate::common::{Challenge, MyConfig, Val};
+
+mod common;
+
+/// Constraints on two columns `x` and `y` which repeat the subexpression `(x + y)^2`, once with
+/// its operands in the other order.
+pub struct RepeatedAir;
+
+impl<F> BaseAir<F> for RepeatedAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for RepeatedAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let (x, y): (AB::Expr, AB::Expr) = (local[0].into(), local[1].into());
+
+        builder.assert_zero((x.clone() + y.clone()) * (x.clone() + y.clone()) - x.clone());
+        builder.assert_zero((y.clone() + x.clone()) * (x.clone() + y.clone()) - y.clone());
+        builder.when_transition().assert_eq(
+            next[0],
+            x.clone() * y.clone() * AB::Expr::ONE + AB::Expr::ZERO,
+        );
+        builder.when_first_row().assert_eq(x, AB::Expr::TWO);
+    }
+}
+
+#[test]
+fn test_shared_subexpressions_are_computed_once() {
+    let constraints = get_symbolic_constraints::<Val, _>(&RepeatedAir, 0, 0);
+    let compiled = CompiledConstraints::new(&constraints);
+    assert_eq!(compiled.num_constraints(), 4);
+    // x + y, (x + y)^2, its differences with x and y, x * y, the transition difference and its
+    // product with the selector, x - 2 and its product with the selector, and an accumulation
+    // per constraint.
+    assert_eq!(compiled.num_instructions(), 13);
+}
+
+#[test]
+fn test_compiled_matches_eval() {
+    let constraints = get_symbolic_constraints::<Val, _>(&RepeatedAir, 0, 0);
+    let compiled = CompiledConstraints::new(&constraints);
+
+    let mut rng = thread_rng();
+    let mut random_packed = || PackedVal::<MyConfig>::from_fn(|_| rng.gen());
+    let mains: [[PackedVal<MyConfig>; 4]; 2] =
+        core::array::from_fn(|_| core::array::from_fn(|_| random_packed()));
+    let selectors: [PackedVal<MyConfig>; 3] = core::array::from_fn(|_| random_packed());
+    let alpha_powers = (0..constraints.len())
+        .map(|_| PackedChallenge::<MyConfig>::from_f(rng.gen::<Challenge>()))
+        .collect::<Vec<_>>();
+    let public_values = vec![];
+
+    let folders = || {
+        mains
+            .each_ref()
+            .map(|main| ProverConstraintFolder::<MyConfig> {
+                preprocessed: RowMajorMatrixView::new(&[], 0),
+                main: RowMajorMatrixView::new(main, 2),
+                permutation: RowMajorMatrixView::new(&[], 0),
+                permutation_challenges: &[],
+                cumulative_sum: PackedChallenge::<MyConfig>::ZERO,
+                public_values: &public_values,
+                is_first_row: selectors[0],
+                is_last_row: selectors[1],
+                is_transition_windows: vec![selectors[2]],
+                is_rows: vec![],
+                periodic_values: vec![],
+                alpha_powers: &alpha_powers,
+                accumulator: PackedChallenge::<MyConfig>::ZERO,
+                constraint_index: 0,
+            })
+    };
+
+    let mut evaluated = folders();
+    for folder in &mut evaluated {
+        RepeatedAir.eval(folder);
+    }
+    let mut compiled_evaluated = folders();
+    for folder in &mut compiled_evaluated {
+        folder.eval_compiled(&compiled);
+    }
+    let mut batch_evaluated = folders();
+    ProverConstraintFolder::eval_compiled_batch(&mut batch_evaluated, &compiled);
+
+    for ((evaluated, compiled_evaluated), batch_evaluated) in evaluated
+        .iter()
+        .zip(&compiled_evaluated)
+        .zip(&batch_evaluated)
+    {
+        assert_eq!(compiled_evaluated.accumulator, evaluated.accumulator);
+        assert_eq!(batch_evaluated.accumulator, evaluated.accumulator);
+        assert_eq!(compiled_evaluated.constraint_index, constraints.len());
+        assert_eq!(batch_evaluated.constraint_index, constraints.len());
+    }
+}
@@ -0,0 +1,163 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    check_constraints_report, get_max_constraint_degree, prove, verify, DegreeReducedAir,
+};
+use rand::thread_rng;
+
+use crate::common::{setup_config, Challenge, Challenger, Perm, Val};
+
+mod common;
+
+/// Two columns `x` and `y`, starting at the public values `[x_0, y]`, where `y` is constant and
+/// `x' = x^4 y`, a transition constraint of degree 5.
+pub struct PowerAir;
+
+impl<F> BaseAir<F> for PowerAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PowerAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (x_0, y_0) = (pis[0], pis[1]);
+
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (x, y) = (local[0], local[1]);
+
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_eq(x, x_0);
+        when_first_row.assert_eq(y, y_0);
+
+        let mut when_transition = builder.when_transition();
+        let x_squared = x * x;
+        when_transition.assert_eq(next[0], x_squared.clone() * x_squared * y);
+        when_transition.assert_eq(next[1], y);
+    }
+}
+
+fn generate_trace<F: Field>(log_height: usize, x_0: F, y: F) -> RowMajorMatrix<F> {
+    let mut values = Vec::with_capacity(2 << log_height);
+    let mut x = x_0;
+    for _ in 0..1 << log_height {
+        values.extend([x, y]);
+        x = x.exp_u64(4) * y;
+    }
+    RowMajorMatrix::new(values, 2)
+}
+
+#[test]
+fn test_reduced_degree() {
+    assert_eq!(get_max_constraint_degree::<Val, _>(&PowerAir, 0, 2), 5);
+
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 2, 3);
+    assert!(air.num_auxiliary_columns() > 0);
+    assert_eq!(
+        <DegreeReducedAir<Val, PowerAir> as BaseAir<Val>>::width(&air),
+        2 + air.num_auxiliary_columns()
+    );
+    assert!(get_max_constraint_degree::<Val, _>(&air, 0, 2) <= 3);
+
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 2, 2);
+    assert_eq!(get_max_constraint_degree::<Val, _>(&air, 0, 2), 2);
+}
+
+#[test]
+fn test_no_reduction_needed() {
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 2, 5);
+    assert_eq!(air.num_auxiliary_columns(), 0);
+    assert_eq!(get_max_constraint_degree::<Val, _>(&air, 0, 2), 5);
+}
+
+#[test]
+fn test_extended_trace_satisfies_constraints() {
+    let public_values = vec![Val::TWO, Val::from_canonical_u32(3)];
+    let trace = generate_trace(4, public_values[0], public_values[1]);
+
+    for max_degree in [2, 3, 4] {
+        let air = DegreeReducedAir::<Val, _>::new(PowerAir, 2, max_degree);
+        let extended = air.extend_trace(&trace, &public_values);
+        assert_eq!(extended.height(), trace.height());
+
+        let report =
+            check_constraints_report::<_, Challenge, _>(&air, &extended, None, &[], &public_values);
+        assert!(report.is_satisfied(), "{report}");
+    }
+}
+
+#[test]
+fn test_tampered_auxiliary_column() {
+    let public_values = vec![Val::TWO, Val::from_canonical_u32(3)];
+    let trace = generate_trace(4, public_values[0], public_values[1]);
+
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 2, 3);
+    let mut extended = air.extend_trace(&trace, &public_values);
+    let width = extended.width();
+    extended.values[5 * width + 2] += Val::ONE;
+
+    let report =
+        check_constraints_report::<_, Challenge, _>(&air, &extended, None, &[], &public_values);
+    assert!(!report.is_satisfied());
+    assert!(report.failures.iter().all(|failure| failure.row <= 5));
+}
+
+#[test]
+fn test_prove_reduced() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+
+    let public_values = vec![Val::TWO, Val::from_canonical_u32(3)];
+    let trace = generate_trace(5, public_values[0], public_values[1]);
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 2, 3);
+    let extended = air.extend_trace(&trace, &public_values);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, extended, &public_values);
+
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &air, &mut challenger, &proof, &public_values).expect("verification failed");
+}
+
+/// Two columns `a` and `b`, where `b` must be zero on the first row if `a` is nonzero there, via a
+/// constraint of degree 4 mixing a selector with other factors.
+pub struct GuardedAir;
+
+impl<F> BaseAir<F> for GuardedAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for GuardedAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (a, b) = (local[0], local[1]);
+        builder.when_first_row().when(a).assert_zero(b * b);
+    }
+}
+
+#[test]
+fn test_reduced_selector_product() {
+    let air = DegreeReducedAir::<Val, _>::new(GuardedAir, 0, 2);
+    assert_eq!(get_max_constraint_degree::<Val, _>(&air, 0, 0), 2);
+
+    let values = (0..8u32)
+        .flat_map(|i| [Val::ONE, Val::from_canonical_u32(i)])
+        .collect();
+    let trace = RowMajorMatrix::new(values, 2);
+    let extended = air.extend_trace(&trace, &[]);
+    let report = check_constraints_report::<_, Challenge, _>(&air, &extended, None, &[], &[]);
+    assert!(report.is_satisfied(), "{report}");
+
+    let mut tampered = trace;
+    tampered.values[1] = Val::ONE;
+    let extended = air.extend_trace(&tampered, &[]);
+    let report = check_constraints_report::<_, Challenge, _>(&air, &extended, None, &[], &[]);
+    assert!(!report.is_satisfied());
+}
@@ -1,19 +1,22 @@
 use std::borrow::Borrow;
 
 use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
-use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
-use p3_challenger::DuplexChallenger;
+use p3_baby_bear::BabyBear;
 use p3_commit::ExtensionMmcs;
-use p3_dft::Radix2DitParallel;
-use p3_field::extension::BinomialExtensionField;
 use p3_field::{Field, FieldAlgebra, PrimeField64};
-use p3_fri::{create_test_fri_config, TwoAdicFriPcs};
+use p3_fri::{create_test_fri_config, HidingFriPcs};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
-use p3_merkle_tree::MerkleTreeMmcs;
-use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
-use p3_uni_stark::{prove, verify, StarkConfig};
-use rand::thread_rng;
+use p3_merkle_tree::MerkleTreeHidingMmcs;
+use p3_uni_stark::{
+    prove, prove_with_key, setup_keys, verify, verify_with_key, StarkConfig, VerificationError,
+};
+use rand::rngs::StdRng;
+use rand::{thread_rng, SeedableRng};
+
+use crate::common::{setup_config, Challenge, Challenger, Dft, MyCompress, MyHash, Perm, Val};
+
+mod common;
 
 /// For testing the public values feature
 pub struct FibonacciAir {}
@@ -98,31 +101,11 @@ impl<F> Borrow<FibonacciRow<F>> for [F] {
     }
 }
 
-type Val = BabyBear;
-type Perm = Poseidon2BabyBear<16>;
-type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
-type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
-type ValMmcs =
-    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
-type Challenge = BinomialExtensionField<Val, 4>;
-type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
-type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
-type Dft = Radix2DitParallel<Val>;
-type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
-type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
-
 /// n-th Fibonacci number expected to be x
 fn test_public_value_impl(n: usize, x: u64) {
     let perm = Perm::new_from_rng_128(&mut thread_rng());
-    let hash = MyHash::new(perm.clone());
-    let compress = MyCompress::new(perm.clone());
-    let val_mmcs = ValMmcs::new(hash, compress);
-    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-    let dft = Dft::default();
+    let config = setup_config(&perm);
     let trace = generate_trace_rows::<Val>(0, 1, n);
-    let fri_config = create_test_fri_config(challenge_mmcs);
-    let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let config = MyConfig::new(pcs);
     let mut challenger = Challenger::new(perm.clone());
     let pis = vec![
         BabyBear::from_canonical_u64(0),
@@ -149,15 +132,8 @@ fn test_public_value() {
 #[should_panic(expected = "assertion `left == right` failed: constraints had nonzero value")]
 fn test_incorrect_public_value() {
     let perm = Perm::new_from_rng_128(&mut thread_rng());
-    let hash = MyHash::new(perm.clone());
-    let compress = MyCompress::new(perm.clone());
-    let val_mmcs = ValMmcs::new(hash, compress);
-    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-    let dft = Dft::default();
-    let fri_config = create_test_fri_config(challenge_mmcs);
+    let config = setup_config(&perm);
     let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
-    let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let config = MyConfig::new(pcs);
     let mut challenger = Challenger::new(perm.clone());
     let pis = vec![
         BabyBear::from_canonical_u64(0),
@@ -166,3 +142,196 @@ fn test_incorrect_public_value() {
     ];
     prove(&config, &FibonacciAir {}, &mut challenger, trace, &pis);
 }
+
+#[test]
+fn test_keys() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let pis = vec![
+        BabyBear::from_canonical_u64(0),
+        BabyBear::from_canonical_u64(1),
+        BabyBear::from_canonical_u64(21),
+    ];
+
+    let (proving_key, verifying_key) = setup_keys(&config, &FibonacciAir {}, pis.len());
+    assert!(verifying_key.preprocessed.is_none());
+
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_with_key(
+        &config,
+        &proving_key,
+        &FibonacciAir {},
+        &mut challenger,
+        trace,
+        &pis,
+    );
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify_with_key(
+        &config,
+        &verifying_key,
+        &FibonacciAir {},
+        &mut challenger,
+        &proof,
+        &pis,
+    )
+    .expect("verification failed");
+
+    // A proof made with a key also verifies without one.
+    let mut challenger = Challenger::new(perm.clone());
+    verify(&config, &FibonacciAir {}, &mut challenger, &proof, &pis).expect("verification failed");
+
+    let mut challenger = Challenger::new(perm.clone());
+    let result = verify_with_key(
+        &config,
+        &verifying_key,
+        &FibonacciAir {},
+        &mut challenger,
+        &proof,
+        &pis[..2].to_vec(),
+    );
+    assert!(matches!(
+        result,
+        Err(VerificationError::PublicValuesLengthMismatch)
+    ));
+
+    // The constraint digest is deterministic, and bound into the transcript, so a key for other
+    // constraints of the same shape is rejected.
+    let (_, mut other_key) = setup_keys(&config, &FibonacciAir {}, pis.len());
+    assert_eq!(other_key.instance, verifying_key.instance);
+    other_key.instance.constraint_digest[0] ^= 1;
+    let mut challenger = Challenger::new(perm.clone());
+    verify_with_key(
+        &config,
+        &other_key,
+        &FibonacciAir {},
+        &mut challenger,
+        &proof,
+        &pis,
+    )
+    .expect_err("verification should fail with another constraint digest");
+
+    // The trace shape is taken from the key, so a key for a different AIR is rejected.
+    let mut wrong_key = verifying_key;
+    wrong_key.instance.width += 1;
+    let mut challenger = Challenger::new(perm);
+    let result = verify_with_key(
+        &config,
+        &wrong_key,
+        &FibonacciAir {},
+        &mut challenger,
+        &proof,
+        &pis,
+    );
+    assert!(matches!(result, Err(VerificationError::AirMismatch)));
+}
+
+#[test]
+fn test_domain_separator_mismatch() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config =
+        |domain_separator: &[u8]| setup_config(&perm).with_domain_separator(domain_separator);
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let pis = vec![
+        BabyBear::from_canonical_u64(0),
+        BabyBear::from_canonical_u64(1),
+        BabyBear::from_canonical_u64(21),
+    ];
+
+    let prover_config = config(b"fibonacci");
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(
+        &prover_config,
+        &FibonacciAir {},
+        &mut challenger,
+        trace,
+        &pis,
+    );
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify(
+        &prover_config,
+        &FibonacciAir {},
+        &mut challenger,
+        &proof,
+        &pis,
+    )
+    .expect("verification failed");
+
+    // The proof can't be replayed in another application.
+    let mut challenger = Challenger::new(perm.clone());
+    verify(
+        &config(b"not fibonacci"),
+        &FibonacciAir {},
+        &mut challenger,
+        &proof,
+        &pis,
+    )
+    .expect_err("verification should fail with another domain separator");
+}
+
+type HidingValMmcs = MerkleTreeHidingMmcs<
+    <Val as Field>::Packing,
+    <Val as Field>::Packing,
+    MyHash,
+    MyCompress,
+    StdRng,
+    8,
+    4,
+>;
+type HidingChallengeMmcs = ExtensionMmcs<Val, Challenge, HidingValMmcs>;
+type HidingPcs = HidingFriPcs<Val, Dft, HidingValMmcs, HidingChallengeMmcs, StdRng>;
+type HidingConfig = StarkConfig<HidingPcs, Challenge, Challenger>;
+
+#[test]
+fn test_zk() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = HidingValMmcs::new(hash, compress, StdRng::seed_from_u64(1));
+    let challenge_mmcs = HidingChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs);
+    let pcs = HidingPcs::new(
+        Dft::default(),
+        val_mmcs,
+        fri_config,
+        4,
+        StdRng::seed_from_u64(2),
+    );
+    let config = HidingConfig::new(pcs);
+    let pis = vec![
+        BabyBear::from_canonical_u64(0),
+        BabyBear::from_canonical_u64(1),
+        BabyBear::from_canonical_u64(21),
+    ];
+
+    let proofs = (0..2)
+        .map(|_| {
+            let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+            let mut challenger = Challenger::new(perm.clone());
+            let proof = prove(&config, &FibonacciAir {}, &mut challenger, trace, &pis);
+
+            let mut challenger = Challenger::new(perm.clone());
+            verify(&config, &FibonacciAir {}, &mut challenger, &proof, &pis)
+                .expect("verification failed");
+
+            let mut incorrect_pis = pis.clone();
+            incorrect_pis[2] += Val::ONE;
+            let mut challenger = Challenger::new(perm.clone());
+            verify(
+                &config,
+                &FibonacciAir {},
+                &mut challenger,
+                &proof,
+                &incorrect_pis,
+            )
+            .expect_err("verification should fail with incorrect public values");
+
+            postcard::to_allocvec(&proof).expect("unable to serialize proof")
+        })
+        .collect::<Vec<_>>();
+
+    // The same witness must give different proofs, since the prover randomizes everything.
+    assert_ne!(proofs[0], proofs[1]);
+}
@@ -0,0 +1,217 @@
+use p3_air::{Air, AirBuilder, BaseAir, Interaction, InteractionAir, VirtualPairCol};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove_multi, verify_multi, VerificationError};
+use rand::thread_rng;
+
+use crate::common::{setup_config, Challenger, Perm, Val};
+
+mod common;
+
+const SQUARES_BUS: usize = 0;
+
+/// A table of `(x, y)` pairs, each of which is looked up in the squares table, thereby proving
+/// that `x` is in range and `y = x^2`.
+pub struct PairsAir {
+    /// Whether to send the pairs at all, to test proofs whose interactions differ from the AIR's.
+    send: bool,
+}
+
+impl<F> BaseAir<F> for PairsAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for PairsAir {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+/// The lookup table `(v, v^2)` for `v` counting up from zero, along with a multiplicity column.
+pub struct SquaresAir;
+
+impl<F> BaseAir<F> for SquaresAir {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SquaresAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+
+        builder.when_first_row().assert_zero(local[0]);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + AB::Expr::ONE);
+        builder.assert_eq(local[1], local[0] * local[0]);
+    }
+}
+
+pub enum TableAir {
+    Pairs(PairsAir),
+    Squares(SquaresAir),
+}
+
+impl<F> BaseAir<F> for TableAir {
+    fn width(&self) -> usize {
+        match self {
+            Self::Pairs(air) => <PairsAir as BaseAir<F>>::width(air),
+            Self::Squares(air) => <SquaresAir as BaseAir<F>>::width(air),
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for TableAir {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        match self {
+            Self::Pairs(PairsAir { send: true }) => vec![Interaction {
+                values: vec![
+                    VirtualPairCol::single_main(0),
+                    VirtualPairCol::single_main(1),
+                ],
+                multiplicity: VirtualPairCol::ONE,
+                bus: SQUARES_BUS,
+            }],
+            _ => vec![],
+        }
+    }
+
+    fn receives(&self) -> Vec<Interaction<F>> {
+        match self {
+            Self::Squares(_) => vec![Interaction {
+                values: vec![
+                    VirtualPairCol::single_main(0),
+                    VirtualPairCol::single_main(1),
+                ],
+                multiplicity: VirtualPairCol::single_main(2),
+                bus: SQUARES_BUS,
+            }],
+            _ => vec![],
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TableAir {
+    fn eval(&self, builder: &mut AB) {
+        match self {
+            Self::Pairs(air) => air.eval(builder),
+            Self::Squares(air) => air.eval(builder),
+        }
+    }
+}
+
+/// Generate a pairs trace for the given `x` values, along with the squares trace which serves
+/// all of their lookups.
+fn generate_traces<F: Field>(
+    xs: &[usize],
+    log_squares_height: usize,
+) -> (RowMajorMatrix<F>, RowMajorMatrix<F>) {
+    let pairs = xs
+        .iter()
+        .flat_map(|&x| [F::from_canonical_usize(x), F::from_canonical_usize(x * x)])
+        .collect();
+
+    let mut multiplicities = vec![0; 1 << log_squares_height];
+    for &x in xs {
+        multiplicities[x] += 1;
+    }
+    let squares = multiplicities
+        .into_iter()
+        .enumerate()
+        .flat_map(|(v, m)| {
+            [
+                F::from_canonical_usize(v),
+                F::from_canonical_usize(v * v),
+                F::from_canonical_usize(m),
+            ]
+        })
+        .collect();
+
+    (
+        RowMajorMatrix::new(pairs, 2),
+        RowMajorMatrix::new(squares, 3),
+    )
+}
+
+#[test]
+fn test_lookup() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let airs = [
+        TableAir::Pairs(PairsAir { send: true }),
+        TableAir::Squares(SquaresAir),
+    ];
+    let xs = (0..32).map(|i| (7 * i) % 16).collect::<Vec<_>>();
+    let (pairs, squares) = generate_traces::<Val>(&xs, 4);
+    let public_values = vec![vec![], vec![]];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(
+        &config,
+        &airs,
+        &mut challenger,
+        vec![pairs, squares],
+        &public_values,
+    );
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi(&config, &airs, &mut challenger, &proof, &public_values)
+        .expect("verification failed");
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "interactions are unbalanced")]
+fn test_lookup_missing_value() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let airs = [
+        TableAir::Pairs(PairsAir { send: true }),
+        TableAir::Squares(SquaresAir),
+    ];
+    // (3, 10) is not in the squares table.
+    let (mut pairs, squares) = generate_traces::<Val>(&[1, 2, 3, 4], 3);
+    pairs.values[5] = Val::from_canonical_usize(10);
+
+    let mut challenger = Challenger::new(perm);
+    prove_multi(
+        &config,
+        &airs,
+        &mut challenger,
+        vec![pairs, squares],
+        &[vec![], vec![]],
+    );
+}
+
+#[test]
+fn test_lookup_interactions_mismatch() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let airs = [
+        TableAir::Pairs(PairsAir { send: true }),
+        TableAir::Squares(SquaresAir),
+    ];
+    let (pairs, squares) = generate_traces::<Val>(&[1, 2, 3, 4], 3);
+    let public_values = vec![vec![], vec![]];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(
+        &config,
+        &airs,
+        &mut challenger,
+        vec![pairs, squares],
+        &public_values,
+    );
+
+    // The verifier expects the pairs table not to send anything.
+    let airs = [
+        TableAir::Pairs(PairsAir { send: false }),
+        TableAir::Squares(SquaresAir),
+    ];
+    let mut challenger = Challenger::new(perm);
+    let result = verify_multi(&config, &airs, &mut challenger, &proof, &public_values);
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
@@ -0,0 +1,131 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, InteractionAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove_multi, verify_multi, VerificationError};
+use rand::thread_rng;
+
+use crate::common::{generate_trace, setup_config, Challenger, FibonacciAir, Perm, Val};
+
+mod common;
+
+/// A counter column along with its cubes, so that its quotient degree differs from
+/// `FibonacciAir`'s.
+pub struct CubesAir;
+
+impl<F> BaseAir<F> for CubesAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CubesAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+
+        builder.when_first_row().assert_zero(local[0]);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + AB::Expr::ONE);
+        builder.assert_eq(local[1], local[0] * local[0] * local[0]);
+    }
+}
+
+/// Dispatches to one of the tables, since all tables of a multi-table proof share an AIR type.
+pub enum TableAir {
+    Fibonacci(FibonacciAir),
+    Cubes(CubesAir),
+}
+
+impl<F> BaseAir<F> for TableAir {
+    fn width(&self) -> usize {
+        match self {
+            Self::Fibonacci(air) => <FibonacciAir as BaseAir<F>>::width(air),
+            Self::Cubes(air) => <CubesAir as BaseAir<F>>::width(air),
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for TableAir {}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for TableAir {
+    fn eval(&self, builder: &mut AB) {
+        match self {
+            Self::Fibonacci(air) => air.eval(builder),
+            Self::Cubes(air) => air.eval(builder),
+        }
+    }
+}
+
+fn cubes_trace<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let values = (0..1 << log_height)
+        .flat_map(|i| {
+            let x = F::from_canonical_usize(i);
+            [x, x.cube()]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+/// Three tables of different heights and quotient degrees.
+fn tables() -> (Vec<TableAir>, Vec<RowMajorMatrix<Val>>, Vec<Vec<Val>>) {
+    let (fib_small, fib_small_pis) = generate_trace(3);
+    let (fib_large, fib_large_pis) = generate_trace(5);
+    let airs = vec![
+        TableAir::Fibonacci(FibonacciAir),
+        TableAir::Cubes(CubesAir),
+        TableAir::Fibonacci(FibonacciAir),
+    ];
+    let traces = vec![fib_small, cubes_trace(4), fib_large];
+    let public_values = vec![fib_small_pis, vec![], fib_large_pis];
+    (airs, traces, public_values)
+}
+
+#[test]
+fn test_multi_table() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (airs, traces, public_values) = tables();
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(&config, &airs, &mut challenger, traces, &public_values);
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi(&config, &airs, &mut challenger, &proof, &public_values)
+        .expect("verification failed");
+}
+
+#[test]
+fn test_multi_table_incorrect_public_value() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (airs, traces, mut public_values) = tables();
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(&config, &airs, &mut challenger, traces, &public_values);
+
+    public_values[2][2] += Val::ONE;
+    let mut challenger = Challenger::new(perm);
+    let result = verify_multi(&config, &airs, &mut challenger, &proof, &public_values);
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidOpeningArgument(_) | VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[test]
+fn test_multi_table_wrong_table_count() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (mut airs, traces, mut public_values) = tables();
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(&config, &airs, &mut challenger, traces, &public_values);
+
+    airs.pop();
+    public_values.pop();
+    let mut challenger = Challenger::new(perm);
+    let result = verify_multi(&config, &airs, &mut challenger, &proof, &public_values);
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
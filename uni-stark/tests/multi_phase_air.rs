@@ -0,0 +1,142 @@
+use core::iter;
+use p3_air::{Air, BaseAir, ExtensionBuilder, MultiPhaseBaseAir, PermutationAirBuilder};
+
+use p3_field::{ExtensionField, Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove_multi_phase, verify_multi_phase, VerificationError};
+use rand::thread_rng;
+
+use crate::common::{setup_config, Challenger, Perm, Val};
+
+mod common;
+
+/// Checks that column 1 is a permutation of column 0, using a grand product over a random
+/// challenge `gamma`.
+///
+/// The after-challenge trace holds the running product of `(gamma - a) / (gamma - b)`, which must
+/// end in one.
+pub struct PermutationCheckAir {
+    /// Unused after-challenge columns, to test proofs whose shape differs from the AIR's.
+    unused_columns: usize,
+}
+
+const AIR: PermutationCheckAir = PermutationCheckAir { unused_columns: 0 };
+
+impl<F> BaseAir<F> for PermutationCheckAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<F: Field> MultiPhaseBaseAir<F> for PermutationCheckAir {
+    fn num_challenges(&self) -> usize {
+        1
+    }
+
+    fn after_challenge_width(&self) -> usize {
+        1 + self.unused_columns
+    }
+
+    fn generate_after_challenge_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> RowMajorMatrix<EF> {
+        let gamma = challenges[0];
+        let mut product = EF::ONE;
+        let width = 1 + self.unused_columns;
+        let values = main
+            .rows()
+            .flat_map(|mut row| {
+                let (a, b) = (row.next().unwrap(), row.next().unwrap());
+                product *= (gamma - a) * (gamma - b).inverse();
+                iter::once(product).chain(iter::repeat_n(EF::ZERO, width - 1))
+            })
+            .collect();
+        RowMajorMatrix::new(values, width)
+    }
+}
+
+impl<AB: PermutationAirBuilder> Air<AB> for PermutationCheckAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (a_local, b_local): (AB::Expr, AB::Expr) = (local[0].into(), local[1].into());
+        let (a_next, b_next): (AB::Expr, AB::Expr) = (next[0].into(), next[1].into());
+
+        let product = builder.permutation();
+        let product_local: AB::ExprEF = product.row_slice(0)[0].into();
+        let product_next: AB::ExprEF = product.row_slice(1)[0].into();
+        let gamma: AB::ExprEF = builder.permutation_randomness()[0].into();
+
+        builder.when_first_row().assert_eq_ext(
+            product_local.clone() * (gamma.clone() - b_local),
+            gamma.clone() - a_local,
+        );
+        builder.when_transition().assert_eq_ext(
+            product_next * (gamma.clone() - b_next),
+            product_local.clone() * (gamma - a_next),
+        );
+        builder
+            .when_last_row()
+            .assert_eq_ext(product_local, AB::ExprEF::ONE);
+    }
+}
+
+/// Generate a trace whose second column is a rotation of its first.
+fn generate_trace<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let height = 1 << log_height;
+    let values = (0..height)
+        .flat_map(|i| {
+            [
+                F::from_canonical_usize(i * i),
+                F::from_canonical_usize(((i + 3) % height).pow(2)),
+            ]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+#[test]
+fn test_permutation_check() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let trace = generate_trace::<Val>(5);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi_phase(&config, &AIR, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi_phase(&config, &AIR, &mut challenger, &proof, &vec![])
+        .expect("verification failed");
+}
+
+#[test]
+fn test_permutation_check_after_challenge_width_mismatch() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let trace = generate_trace::<Val>(5);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi_phase(&config, &AIR, &mut challenger, trace, &vec![]);
+
+    // The verifier expects an extra after-challenge column.
+    let air = PermutationCheckAir { unused_columns: 1 };
+    let mut challenger = Challenger::new(perm);
+    let result = verify_multi_phase(&config, &air, &mut challenger, &proof, &vec![]);
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "constraints had nonzero value")]
+fn test_permutation_check_not_a_permutation() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let mut trace = generate_trace::<Val>(5);
+    trace.values[1] += Val::ONE;
+
+    let mut challenger = Challenger::new(perm);
+    prove_multi_phase(&config, &AIR, &mut challenger, trace, &vec![]);
+}
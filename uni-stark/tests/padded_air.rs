@@ -0,0 +1,170 @@
+use p3_air::{Air, AirBuilder, BaseAir, PaddedBaseAir, PaddingRule};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove_padded, verify, verify_padded, VerificationError};
+use rand::thread_rng;
+
+use crate::common::{setup_config, Challenger, Perm, Val};
+
+mod common;
+
+#[derive(Clone, Copy)]
+enum Padding {
+    RepeatLastRow,
+    DummyRows,
+    ZeroRows,
+}
+
+/// A counter which starts at zero and increases by zero or one on each row, along with an
+/// `is_real` column which is one on the rows of the unpadded trace.
+pub struct CounterAir {
+    padding: Padding,
+}
+
+impl<F> BaseAir<F> for CounterAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<F: Field> PaddedBaseAir<F> for CounterAir {
+    fn padding_rule(&self) -> PaddingRule<F> {
+        match self.padding {
+            Padding::RepeatLastRow => PaddingRule::RepeatLastRow,
+            // Keep counting, so the counter ends at the padded height minus one.
+            Padding::DummyRows => {
+                PaddingRule::DummyRows(Box::new(|i| vec![F::ONE, F::from_canonical_usize(i)]))
+            }
+            Padding::ZeroRows => PaddingRule::ZeroRows { is_real: 0 },
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CounterAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (is_real, x) = (local[0], local[1]);
+        let (next_is_real, next_x) = (next[0], next[1]);
+
+        builder.assert_bool(is_real);
+        builder.when_first_row().assert_one(is_real);
+        builder.when_first_row().assert_zero(x);
+
+        let mut when_transition = builder.when_transition();
+        // No real rows come after a padding row.
+        when_transition.when(next_is_real).assert_one(is_real);
+        when_transition.when(next_is_real).assert_bool(next_x - x);
+    }
+}
+
+fn generate_trace<F: Field>(height: usize) -> RowMajorMatrix<F> {
+    let values = (0..height)
+        .flat_map(|i| [F::ONE, F::from_canonical_usize(i)])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+fn do_test(padding: Padding, height: usize) {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = CounterAir { padding };
+    let trace = generate_trace::<Val>(height);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_padded(&config, &air, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::new(perm);
+    verify_padded(&config, &air, &mut challenger, &proof, &vec![], height)
+        .expect("verification failed");
+}
+
+#[test]
+fn test_padding_repeat_last_row() {
+    do_test(Padding::RepeatLastRow, 11);
+}
+
+#[test]
+fn test_padding_dummy_rows() {
+    do_test(Padding::DummyRows, 11);
+}
+
+#[test]
+fn test_padding_zero_rows() {
+    do_test(Padding::ZeroRows, 11);
+}
+
+#[test]
+fn test_padding_power_of_two_height() {
+    do_test(Padding::ZeroRows, 16);
+}
+
+#[test]
+fn test_padding_rejects_other_heights() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = CounterAir {
+        padding: Padding::ZeroRows,
+    };
+    let trace = generate_trace::<Val>(11);
+    let proof = prove_padded(
+        &config,
+        &air,
+        &mut Challenger::new(perm.clone()),
+        trace,
+        &vec![],
+    );
+
+    // The height is bound into the transcript, so another height of the same padded trace fails.
+    let result = verify_padded(
+        &config,
+        &air,
+        &mut Challenger::new(perm.clone()),
+        &proof,
+        &vec![],
+        12,
+    );
+    assert!(result.is_err());
+    // So does verifying it as an unpadded trace of 16 rows.
+    let result = verify(
+        &config,
+        &air,
+        &mut Challenger::new(perm.clone()),
+        &proof,
+        &vec![],
+    );
+    assert!(result.is_err());
+    // A height which doesn't pad to the proof's height is rejected outright.
+    let result = verify_padded(
+        &config,
+        &air,
+        &mut Challenger::new(perm),
+        &proof,
+        &vec![],
+        5,
+    );
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
+
+#[test]
+fn test_pad_trace() {
+    let mut trace = generate_trace::<Val>(3);
+    PaddingRule::RepeatLastRow.pad(&mut trace);
+    assert_eq!(trace.height(), 4);
+    assert_eq!(trace.row_slice(3).to_vec(), trace.row_slice(2).to_vec());
+
+    let mut trace = generate_trace::<Val>(5);
+    PaddingRule::ZeroRows { is_real: 0 }.pad(&mut trace);
+    assert_eq!(trace.height(), 8);
+    assert!(trace.values[10..].iter().all(|&x| x == Val::ZERO));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "the is_real column must be one")]
+fn test_padding_zero_rows_bad_is_real() {
+    let mut trace = generate_trace::<Val>(5);
+    trace.values[4] = Val::ZERO;
+    PaddingRule::ZeroRows { is_real: 0 }.pad(&mut trace);
+}
@@ -0,0 +1,198 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, PeriodicAirBuilder};
+use p3_commit::{PolynomialSpace, TwoAdicMultiplicativeCoset};
+use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    check_constraints_report, get_max_constraint_degree, prove, verify, VerificationError,
+};
+use rand::{thread_rng, Rng};
+
+use crate::common::{setup_config, Challenge, Challenger, Perm, Val};
+
+mod common;
+
+/// A single column `x`, starting at `a` and ending at `b`, where `[a, b]` are the public values,
+/// with `x' = m x + c` for periodic columns `m` and `c`, like the rounds of a hash function.
+pub struct RoundAir<F> {
+    multipliers: Vec<F>,
+    round_constants: Vec<F>,
+}
+
+impl<F: Field> RoundAir<F> {
+    fn new(round_constants: Vec<F>) -> Self {
+        Self {
+            multipliers: vec![F::TWO, F::from_canonical_u32(3)],
+            round_constants,
+        }
+    }
+
+    fn generate_trace(&self, log_height: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+        let a = F::from_canonical_u32(7);
+        let mut x = a;
+        let mut values = Vec::with_capacity(1 << log_height);
+        for row in 0..1 << log_height {
+            values.push(x);
+            x = self.multipliers[row % self.multipliers.len()] * x
+                + self.round_constants[row % self.round_constants.len()];
+        }
+        let b = values[values.len() - 1];
+        (RowMajorMatrix::new_col(values), vec![a, b])
+    }
+}
+
+impl<F: Field> BaseAir<F> for RoundAir<F> {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![self.multipliers.clone(), self.round_constants.clone()]
+    }
+}
+
+impl<AB> Air<AB> for RoundAir<AB::F>
+where
+    AB: AirBuilderWithPublicValues + PeriodicAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b) = (pis[0], pis[1]);
+        let periodic = builder.periodic_values();
+        let (m, c): (AB::Expr, AB::Expr) = (periodic[0].into(), periodic[1].into());
+
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+
+        builder.when_first_row().assert_eq(local[0], a);
+        builder
+            .when_transition()
+            .assert_eq(next[0], m * local[0] + c);
+        builder.when_last_row().assert_eq(local[0], b);
+    }
+}
+
+fn random_round_constants(len: usize) -> Vec<Val> {
+    let mut rng = thread_rng();
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn test_periodic_columns_are_degree_one() {
+    let air = RoundAir::new(random_round_constants(8));
+    assert_eq!(get_max_constraint_degree::<Val, _>(&air, 0, 2), 2);
+}
+
+#[test]
+fn test_prove_periodic() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+
+    for (period, log_height) in [(8, 3), (8, 6), (1, 4)] {
+        let air = RoundAir::new(random_round_constants(period));
+        let (trace, public_values) = air.generate_trace(log_height);
+
+        let mut challenger = Challenger::new(perm.clone());
+        let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::new(perm.clone());
+        verify(&config, &air, &mut challenger, &proof, &public_values)
+            .expect("verification failed");
+    }
+}
+
+#[test]
+fn test_verify_other_periodic_columns() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let round_constants = random_round_constants(8);
+    let air = RoundAir::new(round_constants.clone());
+    let (trace, public_values) = air.generate_trace(5);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+    let mut other_round_constants = round_constants;
+    other_round_constants[3] += Val::ONE;
+    let other_air = RoundAir::new(other_round_constants);
+    let mut challenger = Challenger::new(perm);
+    let result = verify(&config, &other_air, &mut challenger, &proof, &public_values);
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidOpeningArgument(_) | VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[test]
+fn test_reject_non_power_of_two_period() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = RoundAir::new(random_round_constants(8));
+    let (trace, public_values) = air.generate_trace(4);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+    let other_air = RoundAir::new(random_round_constants(6));
+    let mut challenger = Challenger::new(perm);
+    let result = verify(&config, &other_air, &mut challenger, &proof, &public_values);
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
+
+#[test]
+fn test_report_periodic_values() {
+    let air = RoundAir::new(random_round_constants(4));
+    let (mut trace, public_values) = air.generate_trace(4);
+    let report =
+        check_constraints_report::<_, Challenge, _>(&air, &trace, None, &[], &public_values);
+    assert!(report.is_satisfied(), "{report}");
+
+    trace.values[6] += Val::ONE;
+    let report =
+        check_constraints_report::<_, Challenge, _>(&air, &trace, None, &[], &public_values);
+    let rows = report
+        .failures
+        .iter()
+        .map(|failure| failure.row)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![5, 6]);
+    let message = report.to_string();
+    assert!(
+        message.contains(&format!(
+            "periodic[row][1] = {}",
+            air.round_constants[6 % 4]
+        )),
+        "{message}"
+    );
+}
+
+#[test]
+fn test_periodic_column_evaluations_agree() {
+    let trace_domain = TwoAdicMultiplicativeCoset {
+        log_n: 5,
+        shift: Val::ONE,
+    };
+    let quotient_domain = trace_domain.create_disjoint_domain(1 << 7);
+
+    for period in [1, 2, 8, 32] {
+        let values = random_round_constants(period);
+        // On the trace domain, the interpolant repeats the values.
+        for (i, x) in Val::two_adic_generator(5).powers().take(32).enumerate() {
+            assert_eq!(
+                trace_domain.periodic_column_at_point(&values, Challenge::from_base(x)),
+                Challenge::from_base(values[i % period])
+            );
+        }
+        // On the quotient domain, it agrees with evaluating at each point.
+        let on_coset = trace_domain.periodic_column_on_coset(&values, quotient_domain);
+        let points = Val::two_adic_generator(7)
+            .shifted_powers(quotient_domain.shift)
+            .take(1 << 7);
+        for (value, x) in on_coset.into_iter().zip(points) {
+            assert_eq!(
+                Challenge::from_base(value),
+                trace_domain.periodic_column_at_point(&values, Challenge::from_base(x))
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    prove_with_key, setup_keys, verify, verify_with_key, StarkVerifyingKey, VerificationError,
+};
+use rand::thread_rng;
+
+use crate::common::{setup_config, Challenger, MyConfig, Perm, Val};
+
+mod common;
+
+/// Checks that the single main column holds the squares of a preprocessed counter column, whose
+/// values are the row indices shifted by `offset`.
+pub struct SquaresAir {
+    log_height: usize,
+    offset: usize,
+}
+
+impl<F: Field> BaseAir<F> for SquaresAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let counter = (0..1 << self.log_height)
+            .map(|i| F::from_canonical_usize(i + self.offset))
+            .collect();
+        Some(RowMajorMatrix::new_col(counter))
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for SquaresAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let counter = preprocessed.row_slice(0)[0];
+
+        builder.assert_eq(local[0], counter * counter);
+
+        // (i + 1)^2 - i^2 = 2i + 1
+        builder
+            .when_transition()
+            .assert_eq(next[0] - local[0], counter * AB::F::TWO + AB::F::ONE);
+    }
+}
+
+fn generate_trace_rows<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let squares = (0..1 << log_height)
+        .map(|i| F::from_canonical_usize(i * i))
+        .collect();
+    RowMajorMatrix::new_col(squares)
+}
+
+#[test]
+fn test_preprocessed_columns() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = SquaresAir {
+        log_height: 4,
+        offset: 0,
+    };
+
+    let (proving_key, verifying_key) = setup_keys(&config, &air, 0);
+
+    // The same preprocessed commitment can be reused across proofs.
+    for _ in 0..2 {
+        let trace = generate_trace_rows::<Val>(4);
+        let mut challenger = Challenger::new(perm.clone());
+        let proof = prove_with_key(&config, &proving_key, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::new(perm.clone());
+        verify_with_key(
+            &config,
+            &verifying_key,
+            &air,
+            &mut challenger,
+            &proof,
+            &vec![],
+        )
+        .expect("verification failed");
+    }
+}
+
+#[test]
+fn test_serialized_verifying_key() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = SquaresAir {
+        log_height: 3,
+        offset: 0,
+    };
+
+    let (proving_key, verifying_key) = setup_keys(&config, &air, 0);
+    let serialized_vk = postcard::to_allocvec(&verifying_key).expect("unable to serialize key");
+    let verifying_key: StarkVerifyingKey<MyConfig> =
+        postcard::from_bytes(&serialized_vk).expect("unable to deserialize key");
+
+    let trace = generate_trace_rows::<Val>(3);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_with_key(&config, &proving_key, &air, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::new(perm);
+    verify_with_key(
+        &config,
+        &verifying_key,
+        &air,
+        &mut challenger,
+        &proof,
+        &vec![],
+    )
+    .expect("verification failed");
+}
+
+#[test]
+fn test_mismatched_preprocessed_height() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = SquaresAir {
+        log_height: 3,
+        offset: 0,
+    };
+    let larger_air = SquaresAir {
+        log_height: 4,
+        offset: 0,
+    };
+
+    let (proving_key, _) = setup_keys(&config, &air, 0);
+    let (_, larger_vk) = setup_keys(&config, &larger_air, 0);
+
+    let trace = generate_trace_rows::<Val>(3);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_with_key(&config, &proving_key, &air, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::new(perm);
+    let result = verify_with_key(
+        &config,
+        &larger_vk,
+        &larger_air,
+        &mut challenger,
+        &proof,
+        &vec![],
+    );
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
+
+#[test]
+fn test_different_preprocessed_values() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = SquaresAir {
+        log_height: 3,
+        offset: 0,
+    };
+    let shifted_air = SquaresAir {
+        log_height: 3,
+        offset: 1,
+    };
+
+    let (proving_key, _) = setup_keys(&config, &air, 0);
+    let (_, shifted_vk) = setup_keys(&config, &shifted_air, 0);
+
+    let trace = generate_trace_rows::<Val>(3);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_with_key(&config, &proving_key, &air, &mut challenger, trace, &vec![]);
+
+    // The preprocessed columns have the same shape but different values, so the proof must not
+    // verify against the shifted key.
+    let mut challenger = Challenger::new(perm);
+    let result = verify_with_key(
+        &config,
+        &shifted_vk,
+        &shifted_air,
+        &mut challenger,
+        &proof,
+        &vec![],
+    );
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidOpeningArgument(_) | VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[test]
+fn test_missing_preprocessed_verifier_key() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let air = SquaresAir {
+        log_height: 3,
+        offset: 0,
+    };
+
+    let (proving_key, _) = setup_keys(&config, &air, 0);
+
+    let trace = generate_trace_rows::<Val>(3);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_with_key(&config, &proving_key, &air, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::new(perm);
+    let result = verify(&config, &air, &mut challenger, &proof, &vec![]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::PreprocessedWidthMismatch)
+    ));
+}
@@ -0,0 +1,134 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_commit::ProximityRegime;
+use p3_fri::FriConfig;
+use p3_matrix::Matrix;
+use p3_uni_stark::{proven_soundness, StarkSoundness};
+use rand::thread_rng;
+
+use crate::common::{ChallengeMmcs, Dft, MyCompress, MyConfig, MyHash, Pcs, Perm, ValMmcs};
+
+mod common;
+
+/// A single column `x` with `x' = x^3`, a transition constraint of degree 3.
+pub struct CubeAir;
+
+impl<F> BaseAir<F> for CubeAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CubeAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let x = local[0];
+        builder.when_transition().assert_eq(next[0], x * x * x);
+    }
+}
+
+fn fri_config(
+    log_blowup: usize,
+    max_log_arity: usize,
+    num_queries: usize,
+) -> FriConfig<ChallengeMmcs> {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let val_mmcs = ValMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+    FriConfig {
+        log_blowup,
+        log_final_poly_len: 0,
+        max_log_arity,
+        num_queries,
+        proof_of_work_bits: 16,
+        mmcs: ChallengeMmcs::new(val_mmcs),
+    }
+}
+
+fn soundness(fri_config: FriConfig<ChallengeMmcs>, regime: ProximityRegime) -> StarkSoundness {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let val_mmcs = ValMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm));
+    let config = MyConfig::new(Pcs::new(Dft::default(), val_mmcs, fri_config));
+    proven_soundness(&config, &CubeAir, 16, 0, regime).expect("FRI has a soundness estimate")
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_unique_decoding_query_bits() {
+    // Each query catches a word at the unique decoding radius with probability `(1 - rho) / 2`.
+    let soundness = soundness(fri_config(1, 1, 100), ProximityRegime::UniqueDecoding);
+    assert_close(
+        soundness.pcs.query_phase_bits,
+        100.0 * (2.0 / 1.5f64).log2() + 16.0,
+    );
+    assert_eq!(soundness.pcs.log_list_size, 0.0);
+}
+
+#[test]
+fn test_johnson_trades_list_size_for_queries() {
+    let unique = soundness(fri_config(3, 1, 50), ProximityRegime::UniqueDecoding);
+    let johnson = soundness(fri_config(3, 1, 50), ProximityRegime::Johnson { m: 3 });
+
+    assert_close(
+        johnson.pcs.query_phase_bits,
+        50.0 * (1.5 - (7.0f64 / 6.0).log2()) + 16.0,
+    );
+    assert!(johnson.pcs.query_phase_bits > unique.pcs.query_phase_bits);
+
+    // Every out-of-domain check errs once for each codeword in the list.
+    assert_close(johnson.pcs.log_list_size, 3.5f64.log2() + 1.5);
+    assert_close(
+        unique.ood_bits - johnson.ood_bits,
+        johnson.pcs.log_list_size,
+    );
+    assert_close(
+        unique.constraint_batching_bits - johnson.constraint_batching_bits,
+        johnson.pcs.log_list_size,
+    );
+}
+
+#[test]
+fn test_higher_arity_costs_commit_phase_bits() {
+    // Folding a codeword of height 2^17 down to 2 takes 16 rounds of arity 2, or 8 of arity 4,
+    // whose errors scale with the arity less one.
+    let arity_2 = soundness(fri_config(1, 1, 100), ProximityRegime::UniqueDecoding);
+    let arity_4 = soundness(fri_config(1, 2, 100), ProximityRegime::UniqueDecoding);
+    assert_close(
+        arity_2.pcs.commit_phase_bits - arity_4.pcs.commit_phase_bits,
+        (24.0f64 / 16.0).log2(),
+    );
+    assert_close(arity_2.pcs.query_phase_bits, arity_4.pcs.query_phase_bits);
+}
+
+#[test]
+fn test_total_bits_are_bounded_by_the_weakest_step() {
+    for regime in [
+        ProximityRegime::UniqueDecoding,
+        ProximityRegime::Johnson { m: 3 },
+    ] {
+        let soundness = soundness(fri_config(2, 2, 60), regime);
+        let steps = [
+            soundness.constraint_batching_bits,
+            soundness.ood_bits,
+            soundness.pcs.batching_bits,
+            soundness.pcs.commit_phase_bits,
+            soundness.pcs.query_phase_bits,
+        ];
+        let weakest = steps.into_iter().fold(f64::INFINITY, f64::min);
+        assert!(soundness.bits() <= weakest);
+        assert_close(soundness.bits(), weakest - 5f64.log2());
+    }
+}
+
+#[test]
+fn test_proven_below_conjectured() {
+    let config = fri_config(1, 1, 100);
+    let conjectured = config.conjectured_soundness_bits() as f64;
+    let proven = soundness(config, ProximityRegime::Johnson { m: 3 });
+    assert!(proven.bits() < conjectured);
+}
@@ -0,0 +1,127 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove, verify, VerificationError};
+use rand::thread_rng;
+
+use crate::common::{generate_trace, setup_config, Challenger, Perm, Val};
+
+mod common;
+
+/// A Fibonacci sequence in a single column, starting at `(a, b)` and ending in `x`, where
+/// `[a, b, x]` are the public values.
+///
+/// Each row is the sum of the two rows before it, so the AIR needs a window of 3 rows.
+pub struct FibonacciColumnAir;
+
+impl<F> BaseAir<F> for FibonacciColumnAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn window_size(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for FibonacciColumnAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b, x) = (pis[0], pis[1], pis[2]);
+
+        let (local, next, next_next) = (main.row_slice(0), main.row_slice(1), main.row_slice(2));
+
+        builder.when_first_row().assert_eq(local[0], a);
+        builder.when_first_row().assert_eq(next[0], b);
+        builder
+            .when_transition_window(3)
+            .assert_eq(local[0] + next[0], next_next[0]);
+        builder.when_last_row().assert_eq(local[0], x);
+    }
+}
+
+/// The left column of a `FibonacciAir` trace, which is the same sequence, and its public values.
+fn generate_column_trace<F: Field>(log_height: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let (trace, _) = generate_trace(log_height);
+    let values = trace.values.into_iter().step_by(2).collect::<Vec<_>>();
+    let x = values[values.len() - 1];
+    (RowMajorMatrix::new_col(values), vec![F::ZERO, F::ONE, x])
+}
+
+#[test]
+fn test_window_of_three_rows() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (trace, public_values) = generate_column_trace::<Val>(5);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(
+        &config,
+        &FibonacciColumnAir,
+        &mut challenger,
+        trace,
+        &public_values,
+    );
+
+    let mut challenger = Challenger::new(perm);
+    verify(
+        &config,
+        &FibonacciColumnAir,
+        &mut challenger,
+        &proof,
+        &public_values,
+    )
+    .expect("verification failed");
+}
+
+#[test]
+fn test_window_of_three_rows_incorrect_public_value() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (trace, mut public_values) = generate_column_trace::<Val>(5);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(
+        &config,
+        &FibonacciColumnAir,
+        &mut challenger,
+        trace,
+        &public_values,
+    );
+
+    public_values[2] += Val::ONE;
+    let mut challenger = Challenger::new(perm);
+    let result = verify(
+        &config,
+        &FibonacciColumnAir,
+        &mut challenger,
+        &proof,
+        &public_values,
+    );
+    assert!(matches!(
+        result,
+        Err(VerificationError::InvalidOpeningArgument(_) | VerificationError::OodEvaluationMismatch)
+    ));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "constraints had nonzero value")]
+fn test_window_of_three_rows_bad_trace() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = setup_config(&perm);
+    let (mut trace, public_values) = generate_column_trace::<Val>(5);
+    // Breaks the recurrence for rows 7, 8 and 9 while keeping the public values intact.
+    trace.values[9] += Val::ONE;
+
+    let mut challenger = Challenger::new(perm);
+    prove(
+        &config,
+        &FibonacciColumnAir,
+        &mut challenger,
+        trace,
+        &public_values,
+    );
+}
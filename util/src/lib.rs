@@ -37,6 +37,38 @@ pub fn log2_strict_usize(n: usize) -> usize {
     res as usize
 }
 
+/// Computes `log_2(x)` for a positive, finite `x`, since `f64::log2` needs `std`.
+///
+/// # Panics
+/// Panics if `x` is not positive and finite.
+#[must_use]
+pub fn log2_f64(x: f64) -> f64 {
+    assert!(x > 0.0 && x.is_finite(), "log2 of {x} is undefined");
+    // Scale subnormals up so that they have an implicit leading one.
+    let (x, offset) = if x < f64::MIN_POSITIVE {
+        (x * (1u64 << 52) as f64, -52.0)
+    } else {
+        (x, 0.0)
+    };
+    // Split x into 2^exponent * mantissa, with the mantissa in [1, 2).
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mut mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+
+    // Each squaring of the mantissa shifts the next bit of its log into the integer part.
+    let mut result = exponent as f64 + offset;
+    let mut bit = 0.5;
+    for _ in 0..52 {
+        mantissa *= mantissa;
+        if mantissa >= 2.0 {
+            mantissa /= 2.0;
+            result += bit;
+        }
+        bit /= 2.0;
+    }
+    result
+}
+
 /// Returns `[0, ..., N - 1]`.
 #[must_use]
 pub const fn indices_arr<const N: usize>() -> [usize; N] {
@@ -293,4 +325,14 @@ mod tests {
         reverse_slice_index_bits(&mut input256[..]);
         assert_eq!(input256, output256);
     }
+
+    #[test]
+    fn test_log2_f64() {
+        assert_eq!(log2_f64(1.0), 0.0);
+        assert_eq!(log2_f64(1024.0), 10.0);
+        assert_eq!(log2_f64(0.125), -3.0);
+        assert_eq!(log2_f64(f64::MIN_POSITIVE / 4.0), -1024.0);
+        assert!((log2_f64(3.0) - 1.584_962_500_721_156).abs() < 1e-12);
+        assert!((log2_f64(1e-300) + 996.578_428_466_208_7).abs() < 1e-9);
+    }
 }